@@ -1,25 +1,44 @@
-use bevy::prelude::*;
+use crate::{lapis::LapisData, voices::VoiceBus};
+use bevy::{
+    prelude::*,
+    time::TimeUpdateStrategy,
+    winit::{UpdateMode, WinitSettings},
+};
 use cpal::{
-    FromSample, SizedSample, Stream, StreamConfig,
+    BufferSize, FromSample, SampleRate, SizedSample, Stream, StreamConfig,
     traits::{DeviceTrait, HostTrait, StreamTrait},
 };
 use crossbeam_channel::{Receiver, Sender, bounded};
 use fundsp::hacker::*;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
 //use assert_no_alloc::*;
 
 //#[cfg(debug_assertions)]
 //#[global_allocator]
 //static A: AllocDisabler = AllocDisabler;
 
-pub struct AudioPlugin;
+pub struct AudioPlugin {
+    /// don't open the default devices (the output is silent, renders still work)
+    pub headless: bool,
+}
 
 impl Plugin for AudioPlugin {
     fn build(&self, app: &mut App) {
+        if !self.headless {
+            app.add_systems(PreStartup, start_default_streams.after(init_audio));
+        }
         app.add_systems(PreStartup, init_audio)
             .add_observer(drop_out_stream)
             .add_observer(drop_in_stream)
             .add_observer(set_out_device)
-            .add_observer(set_in_device);
+            .add_observer(set_in_device)
+            .add_observer(record_start)
+            .add_observer(record_stop)
+            .add_systems(Last, render_scene)
+            .add_systems(Update, finish_recording);
     }
 }
 
@@ -44,6 +63,104 @@ pub struct InStreamConfig(pub Option<StreamConfig>);
 #[derive(Resource, Deref)]
 pub struct OutStreamConfig(pub Option<StreamConfig>);
 
+/// sends a tap to the output stream to start tapping it (None stops)
+#[derive(Resource, Deref)]
+pub struct OutputTap(pub Sender<Option<Tap>>);
+
+/// where the output stream sends its samples while it's tapped
+pub struct Tap {
+    samples: Sender<f32>,
+    /// frames that didn't fit because the writer fell behind
+    dropped: Arc<AtomicU64>,
+}
+
+/// a recording of the output stream in progress
+/// removed once the writer thread has finished the file
+#[derive(Resource)]
+pub struct Recording {
+    pub path: String,
+    sr: u32,
+    /// the writer's result (number of frames written)
+    done: Receiver<std::io::Result<u64>>,
+    /// frames the output stream dropped (see `Tap`)
+    dropped: Arc<AtomicU64>,
+}
+
+/// trigger this to start recording the output stream to a wav file
+/// (16-bit int, or 32-bit float if `float` is set)
+#[derive(Event)]
+pub struct RecordStart {
+    pub path: String,
+    pub float: bool,
+}
+
+/// trigger this to stop the current recording
+#[derive(Event)]
+pub struct RecordStop;
+
+/// an offline render of everything played through `AudioOutput` (and the voices)
+/// while this exists, `AudioOutput` and `VoiceBus` are new ones that aren't
+/// connected to the output stream, and it's pulled here in lockstep with virtual time.
+/// every frame is one fixed (physics) step, and frames aren't throttled,
+/// so the render runs as fast as the simulation allows
+#[derive(Resource)]
+pub struct SceneRender {
+    backend: BlockRateAdapter,
+    voices: BlockRateAdapter,
+    wave: Wave,
+    /// samples written so far
+    len: usize,
+    /// fractional samples carried over between frames
+    carry: f64,
+    path: String,
+    /// the live slot, stream config, and voice bus, put back when the render is done
+    live: Option<(Slot, Option<StreamConfig>, VoiceBus)>,
+    /// time strategy and winit settings before the render (None until the first frame)
+    restore: Option<(TimeUpdateStrategy, Option<WinitSettings>)>,
+}
+
+impl SceneRender {
+    /// swap in a fresh output slot and voice bus with the same channels and sample
+    /// rate as the output stream (2 channels at 44.1khz if there's no stream)
+    pub fn new(
+        seconds: f64,
+        path: String,
+        audio_output: &mut AudioOutput,
+        out_stream_config: &mut OutStreamConfig,
+        voice_bus: &mut VoiceBus,
+    ) -> Self {
+        let config = out_stream_config.0.clone().unwrap_or(StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(44100),
+            buffer_size: BufferSize::Default,
+        });
+        let sr = config.sample_rate.0 as f64;
+        let mut net = Net::scalar(config.channels as usize, 0.);
+        net.set_sample_rate(sr);
+        net.allocate();
+        let (slot, slot_back) = Slot::new(Box::new(net));
+        let mut backend = BlockRateAdapter::new(Box::new(slot_back));
+        backend.set_sample_rate(sr);
+        let mut bus = VoiceBus::new(config.channels as usize, sr);
+        let mut voices = BlockRateAdapter::new(Box::new(bus.backend()));
+        voices.set_sample_rate(sr);
+        let live_slot = std::mem::replace(&mut audio_output.0, slot);
+        let live_config = out_stream_config.0.replace(config.clone());
+        let mut live_bus = std::mem::replace(voice_bus, bus);
+        live_bus.silence();
+        SceneRender {
+            backend,
+            voices,
+            wave: Wave::zero(config.channels as usize, sr, seconds),
+            len: 0,
+            carry: 0.,
+            path,
+            live: Some((live_slot, live_config, live_bus)),
+            restore: None,
+        }
+    }
+}
+
 /// trigger this event to start a new input stream (ending the current one)
 /// the default (host/device/config) will be used for any field set to None
 /// use `list_in_devices` to get a list of host/device indexes
@@ -77,12 +194,17 @@ pub fn init_audio(world: &mut World) {
     world.insert_resource(AudioOutput(slot));
     world.insert_resource(InStreamConfig(None));
     world.insert_resource(OutStreamConfig(None));
+    world.insert_resource(VoiceBus::new(2, 44100.));
+    let (tap, _) = bounded(1);
+    world.insert_resource(OutputTap(tap));
     world.insert_non_send_resource(OutStream(None));
     world.insert_non_send_resource(InStream(None));
     let (_, r1) = bounded(1);
     world.insert_resource(AudioInputReceiver1(r1.clone()));
     world.insert_resource(AudioInputReceiver2(r1));
-    // start default streams
+}
+
+fn start_default_streams(world: &mut World) {
     world.trigger(SetOutDevice {
         channels: Some(2),
         ..default()
@@ -95,7 +217,17 @@ fn set_out_device(
     mut stream: NonSendMut<OutStream>,
     mut audio_output: ResMut<AudioOutput>,
     mut out_stream_config: ResMut<OutStreamConfig>,
+    mut output_tap: ResMut<OutputTap>,
+    mut voice_bus: ResMut<VoiceBus>,
+    render: Option<Res<SceneRender>>,
+    mut lapis_data: ResMut<LapisData>,
 ) -> Result {
+    // the render puts the old output back when it's done
+    if render.is_some() {
+        let msg = "\n// error: the output device can't change during a render";
+        lapis_data.buffer.push_str(msg);
+        return Ok(());
+    }
     let event = trig.event();
     let host = if let Some(h) = event.host {
         let host_id = cpal::ALL_HOSTS.get(h).ok_or("couldn't find that host")?;
@@ -126,17 +258,24 @@ fn set_out_device(
     let mut net = Net::scalar(config.channels as usize, 0.);
     net.allocate();
     let (slot, slot_back) = Slot::new(Box::new(net));
+    let (tap, tap_back) = bounded(4);
+    let mut bus = VoiceBus::new(config.channels as usize, config.sample_rate.0 as f64);
+    let bus_back = bus.backend();
 
     let s = match sample_format {
-        cpal::SampleFormat::F32 => run_out::<f32>(&device, &config, slot_back),
-        cpal::SampleFormat::I16 => run_out::<i16>(&device, &config, slot_back),
-        cpal::SampleFormat::U16 => run_out::<u16>(&device, &config, slot_back),
+        cpal::SampleFormat::F32 => run_out::<f32>(&device, &config, slot_back, bus_back, tap_back),
+        cpal::SampleFormat::I16 => run_out::<i16>(&device, &config, slot_back, bus_back, tap_back),
+        cpal::SampleFormat::U16 => run_out::<u16>(&device, &config, slot_back, bus_back, tap_back),
         format => return Err(format!("unsupported sample format: {format}").into()),
     };
     if s.is_some() {
+        // this ends a recording of the old stream (its tap is dropped with it)
         stream.0 = s;
         audio_output.0 = slot;
         out_stream_config.0 = Some(config);
+        output_tap.0 = tap;
+        // the voices are added to the new bus in the next update
+        *voice_bus = bus;
         Ok(())
     } else {
         Err(format!("couldn't start stream with given settings\n{event:?}").into())
@@ -227,27 +366,52 @@ fn run_out<T>(
     device: &cpal::Device,
     config: &cpal::StreamConfig,
     slot: SlotBackend,
+    voices: NetBackend,
+    tap_receiver: Receiver<Option<Tap>>,
 ) -> Option<Stream>
 where
     T: SizedSample + FromSample<f32>,
 {
     let mut slot = BlockRateAdapter::new(Box::new(slot));
+    let mut voices = BlockRateAdapter::new(Box::new(voices));
     let channels = config.channels as usize;
     let mut out = vec![0.; channels];
+    let mut mix = vec![0.; channels];
+    let mut tap: Option<Tap> = None;
 
     let err_fn = |err| eprintln!("an error occurred on stream: {err}");
     let stream = device.build_output_stream(
         config,
         move |data: &mut [T], _| {
+            while let Ok(new_tap) = tap_receiver.try_recv() {
+                tap = new_tap;
+            }
             for frame in data.chunks_mut(channels) {
                 slot.tick(&[], &mut out);
+                voices.tick(&[], &mut mix);
                 for i in 0..channels {
+                    out[i] += mix[i];
                     let tmp = if out[i].is_normal() {
                         out[i].clamp(-1., 1.)
                     } else {
                         0.
                     };
                     frame[i] = T::from_sample(tmp);
+                    out[i] = tmp;
+                }
+                // if the writer falls behind, whole frames are dropped (and counted)
+                if let Some(tap) = &tap {
+                    let samples = &tap.samples;
+                    if samples
+                        .capacity()
+                        .is_some_and(|c| c - samples.len() >= channels)
+                    {
+                        for &x in &out {
+                            let _ = samples.try_send(x);
+                        }
+                    } else {
+                        tap.dropped.fetch_add(1, Ordering::Relaxed);
+                    }
                 }
             }
         },
@@ -295,6 +459,227 @@ where
     None
 }
 
+fn render_scene(
+    mut commands: Commands,
+    render: Option<ResMut<SceneRender>>,
+    mut time: ResMut<Time<Virtual>>,
+    fixed: Res<Time<Fixed>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    winit: Option<ResMut<WinitSettings>>,
+    mut audio_output: ResMut<AudioOutput>,
+    mut out_stream_config: ResMut<OutStreamConfig>,
+    mut voice_bus: ResMut<VoiceBus>,
+    mut lapis_data: ResMut<LapisData>,
+) {
+    let Some(mut render) = render else {
+        return;
+    };
+    // the render started during this frame, its audio begins with the next
+    if render.restore.is_none() {
+        let step = TimeUpdateStrategy::ManualDuration(fixed.timestep());
+        let old_strategy = std::mem::replace(&mut *strategy, step);
+        let old_winit = winit.map(|mut w| {
+            let continuous = WinitSettings {
+                focused_mode: UpdateMode::Continuous,
+                unfocused_mode: UpdateMode::Continuous,
+            };
+            std::mem::replace(&mut *w, continuous)
+        });
+        render.restore = Some((old_strategy, old_winit));
+        return;
+    }
+    // pausing would stall the render forever
+    if time.is_paused() {
+        time.unpause();
+        let msg = "\n// time can't be paused during a render, it was resumed";
+        lapis_data.buffer.push_str(msg);
+    }
+    let render = &mut *render;
+    let channels = render.wave.channels();
+    let mut out = vec![0.; channels];
+    let mut mix = vec![0.; channels];
+    let samples = time.delta_secs_f64() * render.wave.sample_rate() + render.carry;
+    render.carry = samples.fract();
+    let end = (render.len + samples as usize).min(render.wave.len());
+    for i in render.len..end {
+        render.backend.tick(&[], &mut out);
+        render.voices.tick(&[], &mut mix);
+        for (channel, (&x, &y)) in out.iter().zip(&mix).enumerate() {
+            let x = x + y;
+            let x = if x.is_normal() { x.clamp(-1., 1.) } else { 0. };
+            render.wave.set(channel, i, x);
+        }
+    }
+    render.len = end;
+    if render.len < render.wave.len() {
+        return;
+    }
+    // done, put everything back
+    if let Some((slot, config, bus)) = render.live.take() {
+        audio_output.0 = slot;
+        out_stream_config.0 = config;
+        *voice_bus = bus;
+    }
+    if let Some((old_strategy, old_winit)) = render.restore.take() {
+        *strategy = old_strategy;
+        if let (Some(old_winit), Some(mut winit)) = (old_winit, winit) {
+            *winit = old_winit;
+        }
+    }
+    match render.wave.save_wav32(&render.path) {
+        Ok(()) => lapis_data.buffer.push_str(&format!(
+            "\n// rendered {}s to {}",
+            render.wave.duration(),
+            render.path
+        )),
+        Err(err) => lapis_data.buffer.push_str(&format!(
+            "\n// error: couldn't write {}: {err}",
+            render.path
+        )),
+    }
+    commands.remove_resource::<SceneRender>();
+}
+
+fn record_start(
+    trig: Trigger<RecordStart>,
+    mut commands: Commands,
+    output_tap: Res<OutputTap>,
+    out_stream_config: Res<OutStreamConfig>,
+    recording: Option<Res<Recording>>,
+    mut lapis_data: ResMut<LapisData>,
+) {
+    let event = trig.event();
+    let result = if recording.is_some() {
+        Err(String::from("already recording"))
+    } else if let Some(config) = &out_stream_config.0 {
+        std::fs::File::create(&event.path)
+            .map_err(|e| e.to_string())
+            .map(|file| (file, config))
+    } else {
+        Err(String::from("there's no output stream"))
+    };
+    let (file, config) = match result {
+        Ok(x) => x,
+        Err(err) => {
+            lapis_data.buffer.push_str(&format!(
+                "\n// error: couldn't record to {}: {err}",
+                event.path
+            ));
+            return;
+        }
+    };
+    let channels = config.channels;
+    let sr = config.sample_rate.0;
+    // about a second of audio
+    let (samples, samples_back) = bounded(sr as usize * channels as usize);
+    let (done, done_back) = bounded(1);
+    let float = event.float;
+    std::thread::spawn(move || {
+        let writer = std::io::BufWriter::new(file);
+        let _ = done.send(write_wav(writer, channels, sr, float, samples_back));
+    });
+    let dropped = Arc::new(AtomicU64::new(0));
+    let _ = output_tap.try_send(Some(Tap {
+        samples,
+        dropped: dropped.clone(),
+    }));
+    commands.insert_resource(Recording {
+        path: event.path.clone(),
+        sr,
+        done: done_back,
+        dropped,
+    });
+    lapis_data
+        .buffer
+        .push_str(&format!("\n// recording to {}", event.path));
+}
+
+fn record_stop(_: Trigger<RecordStop>, output_tap: Res<OutputTap>) {
+    // dropping the sample sender ends the writer thread
+    let _ = output_tap.try_send(None);
+}
+
+fn finish_recording(
+    mut commands: Commands,
+    recording: Option<Res<Recording>>,
+    mut lapis_data: ResMut<LapisData>,
+) {
+    let Some(recording) = recording else {
+        return;
+    };
+    let Ok(result) = recording.done.try_recv() else {
+        return;
+    };
+    let path = &recording.path;
+    match result {
+        Ok(frames) => {
+            let secs = frames as f64 / recording.sr as f64;
+            lapis_data
+                .buffer
+                .push_str(&format!("\n// recorded {secs:.2}s to {path}"));
+            let dropped = recording.dropped.load(Ordering::Relaxed);
+            if dropped > 0 {
+                let secs = dropped as f64 / recording.sr as f64;
+                lapis_data.buffer.push_str(&format!(
+                    "\n// {dropped} frames ({secs:.2}s) were dropped, writing couldn't keep up"
+                ));
+            }
+        }
+        Err(err) => lapis_data
+            .buffer
+            .push_str(&format!("\n// error: couldn't record to {path}: {err}")),
+    }
+    commands.remove_resource::<Recording>();
+}
+
+/// write samples (interleaved) to a wav file until the sender is dropped
+/// then fill in the sizes in the header. returns the number of frames written
+fn write_wav<W: std::io::Write + std::io::Seek>(
+    mut w: W,
+    channels: u16,
+    sr: u32,
+    float: bool,
+    samples: Receiver<f32>,
+) -> std::io::Result<u64> {
+    let (format, bits): (u16, u16) = if float { (3, 32) } else { (1, 16) };
+    let block_align = channels * bits / 8;
+    w.write_all(b"RIFF")?;
+    w.write_all(&0u32.to_le_bytes())?;
+    w.write_all(b"WAVEfmt ")?;
+    w.write_all(&16u32.to_le_bytes())?;
+    w.write_all(&format.to_le_bytes())?;
+    w.write_all(&channels.to_le_bytes())?;
+    w.write_all(&sr.to_le_bytes())?;
+    w.write_all(&(sr * block_align as u32).to_le_bytes())?;
+    w.write_all(&block_align.to_le_bytes())?;
+    w.write_all(&bits.to_le_bytes())?;
+    w.write_all(b"data")?;
+    w.write_all(&0u32.to_le_bytes())?;
+
+    let mut count: u64 = 0;
+    // stop before the sizes overflow
+    let max = (u32::MAX - 36) as u64 / (bits / 8) as u64;
+    for x in samples.iter() {
+        if count >= max {
+            break;
+        }
+        if float {
+            w.write_all(&x.to_le_bytes())?;
+        } else {
+            w.write_all(&((x * i16::MAX as f32) as i16).to_le_bytes())?;
+        }
+        count += 1;
+    }
+    let frames = count / channels as u64;
+    let data_size = (frames * block_align as u64) as u32;
+    w.seek(std::io::SeekFrom::Start(4))?;
+    w.write_all(&(36 + data_size).to_le_bytes())?;
+    w.seek(std::io::SeekFrom::Start(40))?;
+    w.write_all(&data_size.to_le_bytes())?;
+    w.flush()?;
+    Ok(frames)
+}
+
 /// get a list of hosts and their input devices
 pub fn list_in_devices() -> String {
     let mut s = String::new();
@@ -1,6 +1,6 @@
 use crate::{
     lapis::Lapis,
-    objects::AttractionFactor,
+    objects::{AttractionFactor, AttractionTheta},
     ui::{FontSizes, ScaleFactor},
 };
 use avian2d::prelude::Gravity;
@@ -15,9 +15,9 @@ use figment::{
 };
 use serde::{Deserialize, Serialize};
 
-pub struct ConfigPlugin;
+pub struct ConfigPlugin(pub Config);
 
-#[derive(Parser, Debug, Resource, Serialize, Deserialize)]
+#[derive(Parser, Debug, Clone, Resource, Serialize, Deserialize)]
 #[command(version, about, long_about = None)]
 pub struct Config {
     /// start with paused time
@@ -45,6 +45,10 @@ pub struct Config {
     #[arg(long, default_value_t = 0.01)]
     pub attraction: f32,
 
+    /// barnes-hut accuracy for attraction (0 = exact, ~0.5 is a good approximation)
+    #[arg(long, default_value_t = 0.0)]
+    pub attraction_theta: f32,
+
     #[arg(long, default_value_t = 1.0)]
     pub scale_factor: f32,
 
@@ -65,30 +69,49 @@ pub struct Config {
     /// output window's font size
     #[arg(long, default_value_t = 8.0)]
     pub output_font_size: f32,
+
+    /// run without a window, printing lapis output to stdout
+    #[arg(long, default_value_t = false)]
+    pub headless: bool,
+
+    /// lapis script to evaluate after init.rs (headless mode)
+    #[arg(long)]
+    pub script: Option<String>,
+
+    /// physics steps to run before exiting (headless mode, 0 = until `quit()`)
+    /// (time paused with `--pause` or `time.pause()` takes no steps)
+    #[arg(long, default_value_t = 600)]
+    pub steps: usize,
 }
 
-impl Plugin for ConfigPlugin {
-    fn build(&self, app: &mut App) {
-        let config: Config = Figment::new()
+impl Config {
+    pub fn load() -> Self {
+        Figment::new()
             .merge(Serialized::defaults(Config::parse()))
             .merge(Toml::file("config.toml"))
             .extract()
-            .unwrap();
+            .unwrap()
+    }
+}
 
-        app.insert_resource(config)
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.0.clone())
             .add_systems(PostStartup, configure);
     }
 }
 
-fn configure(
+// the window, scale factor, and font sizes don't exist in headless mode
+pub fn configure(
     config: Res<Config>,
     mut gravity: ResMut<Gravity>,
     mut attraction_factor: ResMut<AttractionFactor>,
-    mut scale_factor: ResMut<ScaleFactor>,
+    mut attraction_theta: ResMut<AttractionTheta>,
+    scale_factor: Option<ResMut<ScaleFactor>>,
     mut win: Query<&mut Window>,
     mut clear_color: ResMut<ClearColor>,
     mut lapis: Lapis,
-    mut font_sizes: ResMut<FontSizes>,
+    font_sizes: Option<ResMut<FontSizes>>,
 ) {
     if config.pause {
         lapis.time.pause();
@@ -98,15 +121,20 @@ fn configure(
     gravity.0.y = config.gravity_y;
 
     attraction_factor.0 = config.attraction;
+    attraction_theta.0 = config.attraction_theta.max(0.);
 
-    scale_factor.0 = config.scale_factor;
-    let res = &mut win.single_mut().unwrap().resolution;
-    res.set_scale_factor(config.scale_factor);
-    res.set(config.win_width, config.win_height);
-
-    if config.fullscreen {
-        win.single_mut().unwrap().mode =
-            WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current);
+    if let Some(mut scale_factor) = scale_factor {
+        scale_factor.0 = config.scale_factor;
+    }
+    if let Ok(mut win) = win.single_mut() {
+        let res = &mut win.resolution;
+        res.set_scale_factor(config.scale_factor);
+        res.set(config.win_width, config.win_height);
+
+        if config.fullscreen {
+            win.mode =
+                WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current);
+        }
     }
 
     if let Ok(color) = Srgba::hex(config.clear_color.clone()) {
@@ -116,6 +144,8 @@ fn configure(
     lapis.data.keys_active = config.lapis_keys;
     lapis.data.quiet = config.lapis_quiet;
 
-    font_sizes.0 = config.input_font_size.clamp(1., 128.);
-    font_sizes.1 = config.output_font_size.clamp(1., 128.);
+    if let Some(mut font_sizes) = font_sizes {
+        font_sizes.0 = config.input_font_size.clamp(1., 128.);
+        font_sizes.1 = config.output_font_size.clamp(1., 128.);
+    }
 }
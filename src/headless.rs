@@ -0,0 +1,90 @@
+use crate::{
+    audio::SceneRender,
+    config::{Config, configure},
+    interaction::*,
+    lapis::{Lapis, LapisData},
+};
+use avian2d::prelude::PhysicsSet;
+use bevy::{
+    app::{AppExit, ScheduleRunnerPlugin},
+    asset::AssetPlugin,
+    input::InputPlugin,
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use std::{io::Write, time::Duration};
+
+/// everything the windowed app gets from DefaultPlugins and InteractPlugin
+/// that the simulation needs, minus winit, rendering, egui, and audio devices.
+/// each update advances the clock by one fixed step (one physics step)
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            // don't wait between frames, the clock is stepped manually
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            AssetPlugin::default(),
+            TransformPlugin,
+            InputPlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1. / 60.,
+        )))
+        .insert_resource(Time::<Fixed>::from_hz(60.))
+        .insert_resource(CursorInfo::default())
+        .init_resource::<Mode>()
+        .insert_resource(EguiFocused(false))
+        .insert_resource(DrawSettings::default())
+        .insert_resource(JointSettings::default())
+        .add_systems(PostStartup, eval_script.after(configure))
+        .add_systems(
+            FixedPostUpdate,
+            count_steps.after(PhysicsSet::StepSimulation),
+        )
+        .add_systems(Last, print_buffer);
+    }
+}
+
+fn eval_script(config: Res<Config>, mut lapis: Lapis, mut exit: EventWriter<AppExit>) {
+    let Some(path) = &config.script else {
+        return;
+    };
+    match std::fs::read_to_string(path) {
+        Ok(script) => lapis.eval(&script),
+        Err(e) => {
+            eprintln!("can't read {path}: {e}");
+            exit.write(AppExit::error());
+        }
+    }
+}
+
+// stream whatever was added to the buffer since the last frame
+fn print_buffer(data: Res<LapisData>, mut printed: Local<usize>) {
+    // the buffer was cleared (or rewritten)
+    if data.buffer.len() < *printed || !data.buffer.is_char_boundary(*printed) {
+        *printed = 0;
+    }
+    if data.buffer.len() > *printed {
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(data.buffer[*printed..].as_bytes());
+        let _ = stdout.flush();
+        *printed = data.buffer.len();
+    }
+}
+
+// counts physics steps (none are taken while time is paused)
+// a running `render_scene` keeps the app going past the step limit
+fn count_steps(
+    config: Res<Config>,
+    render: Option<Res<SceneRender>>,
+    mut steps: Local<usize>,
+    mut exit: EventWriter<AppExit>,
+) {
+    *steps += 1;
+    if config.steps != 0 && *steps >= config.steps && render.is_none() {
+        exit.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,382 @@
+use crate::{
+    interaction::EguiFocused,
+    joints::JointFilter,
+    lapis::{Lapis, LapisData},
+    objects::{Code, Links},
+    scene::*,
+};
+use avian2d::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// how many edits can be undone
+const HISTORY_LEN: usize = 100;
+
+/// the most object/joint states kept in the undo history (the oldest edits go first)
+const MAX_CHANGES: usize = 10_000;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .add_observer(undo)
+            .add_observer(redo)
+            .add_systems(
+                Update,
+                undo_keys.run_if(resource_equals(EguiFocused(false))),
+            )
+            .add_systems(Last, (release_hold, commit_history).chain());
+    }
+}
+
+/// the state of an object or a joint
+#[derive(Clone, PartialEq)]
+pub enum Snapshot {
+    Body(BodyData),
+    Joint(JointData),
+}
+
+/// an entity before and after an edit (None means it didn't exist)
+struct Change {
+    entity: u64,
+    before: Option<Snapshot>,
+    after: Option<Snapshot>,
+}
+
+/// undo/redo stacks of edits to the scene
+/// an edit is everything that was touched in one frame
+/// (or for as long as `hold` is set, like while dragging)
+#[derive(Resource, Default)]
+pub struct History {
+    undo: VecDeque<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+    /// entities changed by the current edit and their state before it
+    pending: Vec<(Entity, Option<Snapshot>)>,
+    touched: HashSet<Entity>,
+    /// keep the current edit open
+    pub hold: bool,
+    /// lapis only records edits while this is set (when evaluating the input)
+    pub recording: bool,
+}
+
+impl History {
+    /// remember the state of an entity before it's changed by the current edit
+    /// (the state is taken when the commands are applied, so call this before
+    /// queueing the commands that change it)
+    pub fn touch(&self, commands: &mut Commands, e: Entity) {
+        commands.queue(move |world: &mut World| record(world, e));
+    }
+
+    /// remember the joints connected to an object before they're changed
+    pub fn touch_joints(&self, commands: &mut Commands, object: Entity) {
+        commands.queue(move |world: &mut World| {
+            let joints = world
+                .run_system_cached_with(connected_joints, object)
+                .unwrap_or_default();
+            for e in joints {
+                record(world, e);
+            }
+        });
+    }
+
+    /// record a new entity as part of the current edit
+    pub fn spawned(&mut self, e: Entity) {
+        if self.touched.insert(e) {
+            self.pending.push((e, None));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.pending.clear();
+        self.touched.clear();
+        self.hold = false;
+    }
+
+    fn push(&mut self, edit: Vec<Change>) {
+        self.redo.clear();
+        self.undo.push_back(edit);
+        while self.undo.len() > HISTORY_LEN
+            || (self.undo.len() > 1 && self.undo.iter().map(Vec::len).sum::<usize>() > MAX_CHANGES)
+        {
+            self.undo.pop_front();
+        }
+    }
+
+    /// point the recorded states at respawned entities
+    fn remap(&mut self, map: &HashMap<u64, Entity>) {
+        for edit in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            remap_edit(edit, map);
+        }
+    }
+}
+
+fn remap_edit(edit: &mut [Change], map: &HashMap<u64, Entity>) {
+    let remap = |bits: &mut u64| {
+        if let Some(e) = map.get(bits) {
+            *bits = e.to_bits();
+        }
+    };
+    for change in edit {
+        remap(&mut change.entity);
+        for snapshot in change.before.iter_mut().chain(change.after.iter_mut()) {
+            match snapshot {
+                Snapshot::Body(body) => remap(&mut body.id),
+                Snapshot::Joint(
+                    JointData::Fixed {
+                        id,
+                        entity1,
+                        entity2,
+                        ..
+                    }
+                    | JointData::Distance {
+                        id,
+                        entity1,
+                        entity2,
+                        ..
+                    }
+                    | JointData::Prismatic {
+                        id,
+                        entity1,
+                        entity2,
+                        ..
+                    }
+                    | JointData::Revolute {
+                        id,
+                        entity1,
+                        entity2,
+                        ..
+                    },
+                ) => {
+                    remap(id);
+                    remap(entity1);
+                    remap(entity2);
+                }
+            }
+        }
+    }
+}
+
+fn record(world: &mut World, e: Entity) {
+    if !world.resource_mut::<History>().touched.insert(e) {
+        return;
+    }
+    let before = world.run_system_cached_with(snapshot, e).ok().flatten();
+    world.resource_mut::<History>().pending.push((e, before));
+}
+
+fn snapshot(
+    In(e): In<Entity>,
+    lapis: Lapis,
+    links_query: Query<&Links>,
+    code_query: Query<&Code>,
+) -> Option<Snapshot> {
+    capture(e, &lapis, &links_query, &code_query)
+}
+
+fn capture(
+    e: Entity,
+    lapis: &Lapis,
+    links_query: &Query<&Links>,
+    code_query: &Query<&Code>,
+) -> Option<Snapshot> {
+    match capture_body(e, lapis, links_query, code_query) {
+        Some(body) => Some(Snapshot::Body(body)),
+        None => capture_joint(e, lapis).map(Snapshot::Joint),
+    }
+}
+
+fn connected_joints(
+    In(object): In<Entity>,
+    fixed: Query<(Entity, &FixedJoint)>,
+    distance: Query<(Entity, &DistanceJoint)>,
+    revolute: Query<(Entity, &RevoluteJoint)>,
+    prismatic: Query<(Entity, &PrismaticJoint)>,
+) -> Vec<Entity> {
+    let fixed = fixed.iter().map(|(e, j)| (e, j.entity1, j.entity2));
+    let distance = distance.iter().map(|(e, j)| (e, j.entity1, j.entity2));
+    let revolute = revolute.iter().map(|(e, j)| (e, j.entity1, j.entity2));
+    let prismatic = prismatic.iter().map(|(e, j)| (e, j.entity1, j.entity2));
+    fixed
+        .chain(distance)
+        .chain(revolute)
+        .chain(prismatic)
+        .filter(|(_, e1, e2)| *e1 == object || *e2 == object)
+        .map(|(e, _, _)| e)
+        .collect()
+}
+
+/// drags end when the mouse button is released, even if whatever started
+/// them didn't see it (the inspector was closed, the cursor was over the ui..)
+fn release_hold(mut history: ResMut<History>, mouse_button_input: Res<ButtonInput<MouseButton>>) {
+    if mouse_button_input.just_released(MouseButton::Left) {
+        history.hold = false;
+    }
+}
+
+/// compare the touched entities with their state before the edit
+fn commit_history(mut lapis: Lapis, links_query: Query<&Links>, code_query: Query<&Code>) {
+    if lapis.history.hold || lapis.history.pending.is_empty() {
+        return;
+    }
+    let pending = std::mem::take(&mut lapis.history.pending);
+    lapis.history.touched.clear();
+    let mut edit = Vec::new();
+    for (e, before) in pending {
+        let after = capture(e, &lapis, &links_query, &code_query);
+        if before != after {
+            edit.push(Change {
+                entity: e.to_bits(),
+                before,
+                after,
+            });
+        }
+    }
+    if !edit.is_empty() {
+        lapis.history.push(edit);
+    }
+}
+
+fn undo_keys(mut commands: Commands, keyboard_input: Res<ButtonInput<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::KeyZ)
+        && keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
+        if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            commands.trigger(Redo);
+        } else {
+            commands.trigger(Undo);
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct Undo;
+
+#[derive(Event)]
+pub struct Redo;
+
+fn undo(_: Trigger<Undo>, mut history: ResMut<History>, mut restore: Restore) {
+    let Some(mut edit) = history.undo.pop_back() else {
+        restore.lapis_data.buffer.push_str("\n// nothing to undo");
+        return;
+    };
+    let map = restore.apply(&edit, true);
+    remap_edit(&mut edit, &map);
+    history.remap(&map);
+    history.redo.push(edit);
+}
+
+fn redo(_: Trigger<Redo>, mut history: ResMut<History>, mut restore: Restore) {
+    let Some(mut edit) = history.redo.pop() else {
+        restore.lapis_data.buffer.push_str("\n// nothing to redo");
+        return;
+    };
+    let map = restore.apply(&edit, false);
+    remap_edit(&mut edit, &map);
+    history.remap(&map);
+    history.undo.push_back(edit);
+}
+
+#[derive(SystemParam)]
+struct Restore<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    lapis_data: ResMut<'w, LapisData>,
+    bodies: Query<'w, 's, (), With<RigidBody>>,
+    joints: Query<'w, 's, (), JointFilter>,
+    fixed: Query<'w, 's, &'static mut FixedJoint>,
+    distance: Query<'w, 's, &'static mut DistanceJoint>,
+    revolute: Query<'w, 's, &'static mut RevoluteJoint>,
+    prismatic: Query<'w, 's, &'static mut PrismaticJoint>,
+}
+
+impl Restore<'_, '_> {
+    /// put the entities of an edit back to their state before (or after) it
+    /// deleted entities are respawned, returns their old bits -> new entities
+    fn apply(&mut self, edit: &[Change], undo: bool) -> HashMap<u64, Entity> {
+        let mut map = HashMap::new();
+        let changes: Vec<&Change> = if undo {
+            edit.iter().rev().collect()
+        } else {
+            edit.iter().collect()
+        };
+        // objects first, joints need them
+        for joints_pass in [false, true] {
+            for change in &changes {
+                let (from, to) = if undo {
+                    (&change.after, &change.before)
+                } else {
+                    (&change.before, &change.after)
+                };
+                let is_joint = matches!(to.as_ref().or(from.as_ref()), Some(Snapshot::Joint(_)));
+                if is_joint != joints_pass {
+                    continue;
+                }
+                let e = Entity::from_bits(change.entity);
+                match to {
+                    None => {
+                        if let Ok(mut e) = self.commands.get_entity(e) {
+                            e.try_despawn();
+                        }
+                    }
+                    Some(Snapshot::Body(body)) => {
+                        if self.bodies.contains(e) {
+                            let mut e = self.commands.entity(e);
+                            insert_body(&mut e, &mut self.meshes, &mut self.materials, body);
+                        } else {
+                            let new = spawn_body(
+                                &mut self.commands,
+                                &mut self.meshes,
+                                &mut self.materials,
+                                body,
+                            );
+                            map.insert(change.entity, new);
+                        }
+                    }
+                    Some(Snapshot::Joint(joint)) => {
+                        let mut ends = map.clone();
+                        let (e1, e2) = joint.bodies();
+                        for bits in [e1, e2] {
+                            ends.entry(bits).or_insert_with(|| Entity::from_bits(bits));
+                        }
+                        if self.joints.contains(e) {
+                            insert_joint_data(&mut self.commands.entity(e), joint, &ends);
+                        } else if let Some(new) = spawn_joint_data(&mut self.commands, joint, &ends)
+                        {
+                            map.insert(change.entity, new);
+                        }
+                    }
+                }
+            }
+        }
+        // joints and variables that still point at the deleted versions
+        let remap = |e: &mut Entity| {
+            if let Some(new) = map.get(&e.to_bits()) {
+                *e = *new;
+            }
+        };
+        for mut j in self.fixed.iter_mut() {
+            remap(&mut j.entity1);
+            remap(&mut j.entity2);
+        }
+        for mut j in self.distance.iter_mut() {
+            remap(&mut j.entity1);
+            remap(&mut j.entity2);
+        }
+        for mut j in self.revolute.iter_mut() {
+            remap(&mut j.entity1);
+            remap(&mut j.entity2);
+        }
+        for mut j in self.prismatic.iter_mut() {
+            remap(&mut j.entity1);
+            remap(&mut j.entity2);
+        }
+        for e in self.lapis_data.entitymap.values_mut() {
+            remap(e);
+        }
+        map
+    }
+}
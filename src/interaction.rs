@@ -1,3 +1,7 @@
+use crate::{
+    history::History,
+    joints::{Breakable, JointMotor},
+};
 use avian2d::prelude::*;
 use bevy::{
     color::palettes::tailwind::{GRAY_50, GREEN_500, RED_500},
@@ -6,6 +10,7 @@ use bevy::{
 };
 use bevy_egui::EguiContexts;
 use bevy_pancam::*;
+use serde::{Deserialize, Serialize};
 use std::any::TypeId;
 use std::f32::consts::TAU;
 
@@ -68,16 +73,34 @@ pub enum Mode {
     Joint,
 }
 
-#[derive(Resource, Reflect)]
+/// what dragging in draw mode spawns
+#[derive(PartialEq, Debug, Reflect, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum DrawShape {
+    /// a regular polygon, dragging sets the radius
+    #[default]
+    Regular,
+    /// dragging sets the corners
+    Rectangle,
+    /// dragging sets the bounding box
+    Capsule,
+    /// the outline traced by the cursor (can be concave)
+    Freehand,
+    /// static line segments, click to add points and press enter to finish
+    Wall,
+}
+
+#[derive(Resource, Reflect, Clone, Serialize, Deserialize)]
 #[reflect(Resource)]
+#[serde(default)]
 pub struct DrawSettings {
+    pub shape: DrawShape,
     pub sides: u32,
     pub color: [u8; 4],
     pub rigid_body: RigidBody,
     pub collision_layer: u32,
     pub sensor: bool,
     pub links: String,
-    pub code: (String, String),
+    pub code: (String, String, String),
     pub custom_mass: bool,
     pub mass: f32,
     pub custom_inertia: bool,
@@ -93,13 +116,14 @@ pub struct DrawSettings {
 impl Default for DrawSettings {
     fn default() -> Self {
         DrawSettings {
+            shape: DrawShape::Regular,
             sides: 32,
             color: [255, 172, 171, 255],
             rigid_body: RigidBody::Dynamic,
             collision_layer: 0,
             sensor: false,
             links: String::new(),
-            code: (String::new(), String::new()),
+            code: (String::new(), String::new(), String::new()),
             custom_mass: false,
             mass: 1000.,
             custom_inertia: false,
@@ -114,7 +138,7 @@ impl Default for DrawSettings {
     }
 }
 
-#[derive(PartialEq, Debug, Reflect)]
+#[derive(PartialEq, Debug, Reflect, Clone, Copy, Serialize, Deserialize)]
 pub enum JointType {
     Fixed,
     Distance,
@@ -122,8 +146,9 @@ pub enum JointType {
     Revolute,
 }
 
-#[derive(Resource, Reflect)]
+#[derive(Resource, Reflect, Clone, Serialize, Deserialize)]
 #[reflect(Resource)]
+#[serde(default)]
 pub struct JointSettings {
     pub joint_type: JointType,
     pub compliance: f32,
@@ -222,19 +247,40 @@ fn update_indicator(
     mut gizmos: Gizmos,
     settings: Res<DrawSettings>,
     mode: Res<Mode>,
+    stroke: Res<crate::objects::Stroke>,
 ) {
+    // the points of an unfinished wall
+    if *mode == Mode::Draw && settings.shape == DrawShape::Wall {
+        gizmos.linestrip_2d(stroke.0.iter().copied(), GRAY_50);
+    }
     if mouse_button_input.pressed(MouseButton::Left)
         && !mouse_button_input.just_pressed(MouseButton::Left)
         && !keyboard_input.pressed(KeyCode::Space)
     {
         match *mode {
-            Mode::Draw => {
-                let iso = Isometry2d::from_translation(cursor.i);
-                let rad = cursor.i.distance(cursor.f);
-                gizmos
-                    .circle_2d(iso, rad, Color::WHITE)
-                    .resolution(settings.sides);
-            }
+            Mode::Draw => match settings.shape {
+                DrawShape::Regular => {
+                    let iso = Isometry2d::from_translation(cursor.i);
+                    let rad = cursor.i.distance(cursor.f);
+                    gizmos
+                        .circle_2d(iso, rad, Color::WHITE)
+                        .resolution(settings.sides);
+                }
+                DrawShape::Rectangle | DrawShape::Capsule => {
+                    let iso = Isometry2d::from_translation((cursor.i + cursor.f) / 2.);
+                    gizmos.rect_2d(iso, (cursor.f - cursor.i).abs(), Color::WHITE);
+                }
+                DrawShape::Freehand => {
+                    let points = stroke.0.iter().copied().chain([cursor.f]);
+                    gizmos.linestrip_2d(points, Color::WHITE);
+                }
+                DrawShape::Wall => {
+                    if let Some(last) = stroke.0.last() {
+                        gizmos.line_2d(*last, cursor.f, Color::WHITE);
+                    }
+                    gizmos.circle_2d(Isometry2d::from_translation(cursor.f), 3., Color::WHITE);
+                }
+            },
             Mode::Edit if clicked_on_space.0 => {
                 let iso = Isometry2d::from_translation((cursor.i + cursor.f) / 2.);
                 let size = (cursor.f - cursor.i).abs();
@@ -266,6 +312,7 @@ fn highlight_selected(selected_query: Query<&Transform, With<Selected>>, mut giz
 
 fn delete_selected(
     mut commands: Commands,
+    history: Res<History>,
     selected_query: Query<Entity, With<Selected>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     fixed: Query<(Entity, &FixedJoint)>,
@@ -278,26 +325,31 @@ fn delete_selected(
         if shift {
             for (e, j) in fixed.iter() {
                 if selected_query.contains(j.entity1) || selected_query.contains(j.entity2) {
+                    history.touch(&mut commands, e);
                     commands.entity(e).despawn();
                 }
             }
             for (e, j) in distance.iter() {
                 if selected_query.contains(j.entity1) || selected_query.contains(j.entity2) {
+                    history.touch(&mut commands, e);
                     commands.entity(e).despawn();
                 }
             }
             for (e, j) in revolute.iter() {
                 if selected_query.contains(j.entity1) || selected_query.contains(j.entity2) {
+                    history.touch(&mut commands, e);
                     commands.entity(e).despawn();
                 }
             }
             for (e, j) in prismatic.iter() {
                 if selected_query.contains(j.entity1) || selected_query.contains(j.entity2) {
+                    history.touch(&mut commands, e);
                     commands.entity(e).despawn();
                 }
             }
         } else {
             for e in selected_query.iter() {
+                history.touch(&mut commands, e);
                 commands.entity(e).despawn();
             }
         }
@@ -399,11 +451,16 @@ fn update_selection(
     }
 }
 
+// the whole drag is one edit. the first frame only records where things were
+// (that happens when the commands are applied) and its movement is added to the next
 fn move_selected(
+    mut commands: Commands,
+    mut history: ResMut<History>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     cursor: Res<CursorInfo>,
-    mut selected_query: Query<(&mut Transform, &mut LinearVelocity), With<Selected>>,
+    mut selected_query: Query<(Entity, &mut Transform, &mut LinearVelocity), With<Selected>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut drag: Local<Option<Vec2>>,
 ) {
     if mouse_button_input.pressed(MouseButton::Left)
         && !mouse_button_input.just_pressed(MouseButton::Left)
@@ -412,12 +469,25 @@ fn move_selected(
         && !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
         && !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
     {
-        for (mut t, mut v) in selected_query.iter_mut() {
+        // a drag that ended while this wasn't running isn't held anymore
+        let Some(lag) = drag.filter(|_| history.hold) else {
+            for (e, _, _) in selected_query.iter() {
+                history.touch(&mut commands, e);
+            }
+            history.hold = true;
+            *drag = Some(cursor.d);
+            return;
+        };
+        let d = cursor.d + lag;
+        for (_, mut t, mut v) in selected_query.iter_mut() {
             v.x = 0.;
             v.y = 0.;
-            t.translation.x += cursor.d.x;
-            t.translation.y += cursor.d.y;
+            t.translation.x += d.x;
+            t.translation.y += d.y;
         }
+        *drag = Some(Vec2::ZERO);
+    } else if !mouse_button_input.pressed(MouseButton::Left) && drag.take().is_some() {
+        history.hold = false;
     }
 }
 
@@ -450,6 +520,10 @@ fn copy_selection(
     lapis: crate::lapis::Lapis,
     links_query: Query<&crate::objects::Links>,
     code_query: Query<&crate::objects::Code>,
+    fixed_query: Query<(&FixedJoint, Option<&Breakable>)>,
+    distance_query: Query<(&DistanceJoint, Option<&Breakable>)>,
+    prismatic_query: Query<(&PrismaticJoint, Option<&JointMotor>, Option<&Breakable>)>,
+    revolute_query: Query<(&RevoluteJoint, Option<&JointMotor>, Option<&Breakable>)>,
 ) {
     if keyboard_input.just_pressed(KeyCode::KeyC)
         && keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
@@ -472,7 +546,7 @@ fn copy_selection(
             let mat = lapis.materials.get(mat_id).unwrap();
             let hsla: Hsla = mat.color.into();
             let (h, s, l, a) = (hsla.hue, hsla.saturation, hsla.lightness, hsla.alpha);
-            let sides = lapis.sides_query.get(e).unwrap().0;
+            let shape = lapis.shape_query.get(e).unwrap().method();
             let cm = lapis.cm_query.get(e).unwrap();
             let (cmx, cmy) = (cm.x, cm.y);
             let friction = lapis.friction_query.get(e).unwrap().dynamic_coefficient;
@@ -482,18 +556,23 @@ fn copy_selection(
             let dynamic = *lapis.body_query.get(e).unwrap() == RigidBody::Dynamic;
             let links = &links_query.get(e).unwrap().0;
             let code = code_query.get(e).unwrap();
-            let (ci, cf) = (&code.0, &code.1);
+            let (ci, cf, cc) = (&code.0, &code.1, &code.2);
+            let script = lapis
+                .script_query
+                .get(e)
+                .map(|s| format!(".code_u(\"{}\").code_u_steps({})", s.code, s.steps))
+                .unwrap_or_default();
             let line = format!(
-                "let _ = spawn({rx}).x({x}).y({y}).z({z}).ry({ry}).rot({rot}).mass({mass}).inertia({inertia}).vx({vx}).vy({vy}).va({va}).restitution({restitution}).lindamp({lindamp}).angdamp({angdamp}).h({h}).s({s}).l({l}).a({a}).sides({sides}).cmx({cmx}).cmy({cmy}).friction({friction}).tail({tail}).layer({layer}).dynamic({dynamic}).sensor({sensor}).links(\"{links}\").code_i(\"{ci}\").code_f(\"{cf}\");\n"
+                "let _ = spawn({rx}).x({x}).y({y}).z({z}).ry({ry}).rot({rot}).mass({mass}).inertia({inertia}).vx({vx}).vy({vy}).va({va}).restitution({restitution}).lindamp({lindamp}).angdamp({angdamp}).h({h}).s({s}).l({l}).a({a}).{shape}.cmx({cmx}).cmy({cmy}).friction({friction}).tail({tail}).layer({layer}).dynamic({dynamic}).sensor({sensor}).links(\"{links}\").code_i(\"{ci}\").code_f(\"{cf}\").code_c(\"{cc}\"){script};\n"
             );
             selection.push_str(&line);
         }
-        for j in lapis.fixed_query.iter() {
+        for (j, breakable) in fixed_query.iter() {
             if selected_query.contains(j.entity1) && selected_query.contains(j.entity2) {
                 let t1 = lapis.trans_query.get(j.entity1).unwrap().translation;
                 let t2 = lapis.trans_query.get(j.entity2).unwrap().translation;
                 let line = format!(
-                    "let _ = joint({},{},{},{}).joint_type(0).compliance({}).anchor1({},{}).anchor2({},{});\n",
+                    "let _ = joint({},{},{},{}).joint_type(0).compliance({}).anchor1({},{}).anchor2({},{}){};\n",
                     t1.x,
                     t1.y,
                     t2.x,
@@ -503,17 +582,18 @@ fn copy_selection(
                     j.local_anchor1.y,
                     j.local_anchor2.x,
                     j.local_anchor2.y,
+                    breakable.map(Breakable::methods).unwrap_or_default(),
                 );
                 selection.push_str(&line);
             }
         }
-        for j in lapis.distance_query.iter() {
+        for (j, breakable) in distance_query.iter() {
             if selected_query.contains(j.entity1) && selected_query.contains(j.entity2) {
                 let t1 = lapis.trans_query.get(j.entity1).unwrap().translation;
                 let t2 = lapis.trans_query.get(j.entity2).unwrap().translation;
                 let limits = j.length_limits.unwrap();
                 let line = format!(
-                    "let _ = joint({},{},{},{}).joint_type(1).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{}).rest({});\n",
+                    "let _ = joint({},{},{},{}).joint_type(1).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{}).rest({}){};\n",
                     t1.x,
                     t1.y,
                     t2.x,
@@ -526,17 +606,18 @@ fn copy_selection(
                     j.local_anchor2.x,
                     j.local_anchor2.y,
                     j.rest_length,
+                    breakable.map(Breakable::methods).unwrap_or_default(),
                 );
                 selection.push_str(&line);
             }
         }
-        for j in lapis.prismatic_query.iter() {
+        for (j, motor, breakable) in prismatic_query.iter() {
             if selected_query.contains(j.entity1) && selected_query.contains(j.entity2) {
                 let t1 = lapis.trans_query.get(j.entity1).unwrap().translation;
                 let t2 = lapis.trans_query.get(j.entity2).unwrap().translation;
                 let limits = j.free_axis_limits.unwrap();
                 let line = format!(
-                    "let _ = joint({},{},{},{}).joint_type(2).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{}).free_axis({},{});\n",
+                    "let _ = joint({},{},{},{}).joint_type(2).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{}).free_axis({},{}){}{};\n",
                     t1.x,
                     t1.y,
                     t2.x,
@@ -550,17 +631,19 @@ fn copy_selection(
                     j.local_anchor2.y,
                     j.free_axis.x,
                     j.free_axis.y,
+                    motor.map(JointMotor::methods).unwrap_or_default(),
+                    breakable.map(Breakable::methods).unwrap_or_default(),
                 );
                 selection.push_str(&line);
             }
         }
-        for j in lapis.revolute_query.iter() {
+        for (j, motor, breakable) in revolute_query.iter() {
             if selected_query.contains(j.entity1) && selected_query.contains(j.entity2) {
                 let t1 = lapis.trans_query.get(j.entity1).unwrap().translation;
                 let t2 = lapis.trans_query.get(j.entity2).unwrap().translation;
                 let limits = j.angle_limit.unwrap();
                 let line = format!(
-                    "let _ = joint({},{},{},{}).joint_type(3).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{});\n",
+                    "let _ = joint({},{},{},{}).joint_type(3).limits({},{}).compliance({}).anchor1({},{}).anchor2({},{}){}{};\n",
                     t1.x,
                     t1.y,
                     t2.x,
@@ -572,6 +655,8 @@ fn copy_selection(
                     j.local_anchor1.y,
                     j.local_anchor2.x,
                     j.local_anchor2.y,
+                    motor.map(JointMotor::methods).unwrap_or_default(),
+                    breakable.map(Breakable::methods).unwrap_or_default(),
                 );
                 selection.push_str(&line);
             }
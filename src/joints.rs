@@ -1,10 +1,19 @@
-use crate::interaction::*;
+use crate::{history::History, interaction::*, lapis::Lapis};
 use avian2d::prelude::*;
 use bevy::{math::Affine2, prelude::*, render::view::VisibleEntities};
-use std::any::TypeId;
+use serde::{Deserialize, Serialize};
+use std::{any::TypeId, f32::consts::PI};
 
 pub struct JointsPlugin;
 
+/// matches entities with any kind of joint
+pub type JointFilter = Or<(
+    With<FixedJoint>,
+    With<DistanceJoint>,
+    With<PrismaticJoint>,
+    With<RevoluteJoint>,
+)>;
+
 impl Plugin for JointsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
@@ -14,6 +23,8 @@ impl Plugin for JointsPlugin {
                 .run_if(resource_equals(EguiFocused(false)))
                 .run_if(resource_equals(Mode::Joint)),
         )
+        .add_systems(PhysicsSchedule, drive_motors.in_set(PhysicsStepSet::First))
+        .add_systems(Update, break_joints)
         .add_observer(disjoint)
         .add_observer(replace_joint)
         .add_observer(set_joint_property)
@@ -33,6 +44,7 @@ fn spawn_joint(
     egui_focused: Res<EguiFocused>,
     settings: Res<JointSettings>,
     mut src: Local<Option<(Entity, Transform)>>,
+    mut history: ResMut<History>,
 ) {
     if keyboard_input.pressed(KeyCode::Space) || egui_focused.is_changed() {
         return;
@@ -76,45 +88,245 @@ fn spawn_joint(
                     .transform_point2(cursor.f);
                 (l1, l2)
             };
-            match settings.joint_type {
-                JointType::Fixed => {
-                    commands.spawn(
+            let e = match settings.joint_type {
+                JointType::Fixed => commands
+                    .spawn(
                         FixedJoint::new(src, snk)
                             .with_compliance(settings.compliance)
                             .with_local_anchor_1(anchors.0)
                             .with_local_anchor_2(anchors.1),
-                    );
-                }
-                JointType::Distance => {
-                    commands.spawn(
+                    )
+                    .id(),
+                JointType::Distance => commands
+                    .spawn(
                         DistanceJoint::new(src, snk)
                             .with_compliance(settings.compliance)
                             .with_local_anchor_1(anchors.0)
                             .with_local_anchor_2(anchors.1)
                             .with_limits(settings.dist_limits.0, settings.dist_limits.1)
                             .with_rest_length(settings.dist_rest),
-                    );
-                }
-                JointType::Prismatic => {
-                    commands.spawn(
+                    )
+                    .id(),
+                JointType::Prismatic => commands
+                    .spawn(
                         PrismaticJoint::new(src, snk)
                             .with_compliance(settings.compliance)
                             .with_local_anchor_1(anchors.0)
                             .with_local_anchor_2(anchors.1)
                             .with_free_axis(settings.prismatic_axis)
                             .with_limits(settings.prismatic_limits.0, settings.prismatic_limits.1),
-                    );
-                }
-                JointType::Revolute => {
-                    commands.spawn(
+                    )
+                    .id(),
+                JointType::Revolute => commands
+                    .spawn(
                         RevoluteJoint::new(src, snk)
                             .with_compliance(settings.compliance)
                             .with_local_anchor_1(anchors.0)
                             .with_local_anchor_2(anchors.1)
                             .with_angle_limits(settings.angle_limits.0, settings.angle_limits.1),
-                    );
+                    )
+                    .id(),
+            };
+            history.spawned(e);
+        }
+    }
+}
+
+/// drives a revolute joint (angle, angular velocity, torque)
+/// or a prismatic joint (position along the free axis, velocity, force)
+/// the motor accelerates the second body relative to the first by
+/// `stiffness * (target_position - position) + damping * (target_velocity - velocity)`
+/// limited by `max_force`. stiffness 0 makes it a velocity motor
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct JointMotor {
+    pub target_velocity: f32,
+    pub target_position: f32,
+    pub max_force: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+}
+
+impl Default for JointMotor {
+    fn default() -> Self {
+        JointMotor {
+            target_velocity: 0.,
+            target_position: 0.,
+            max_force: f32::MAX,
+            stiffness: 0.,
+            damping: 10.,
+        }
+    }
+}
+
+impl JointMotor {
+    /// the velocity change (of body 2 relative to body 1) for this step
+    fn delta(&self, position: f32, velocity: f32, dt: f32) -> f32 {
+        let error = self.target_position - position;
+        // more than a full correction per step would overshoot
+        let damping = (self.damping * dt).clamp(0., 1.);
+        self.stiffness * error * dt + damping * (self.target_velocity - velocity)
+    }
+
+    fn set(&mut self, property: &JointProperty) {
+        match *property {
+            JointProperty::MotorVelocity(val) => self.target_velocity = val,
+            JointProperty::MotorPosition(val) => self.target_position = val,
+            JointProperty::MotorMax(val) => self.max_force = val.abs(),
+            JointProperty::MotorStiffness(val) => self.stiffness = val,
+            JointProperty::MotorDamping(val) => self.damping = val,
+            _ => {}
+        }
+    }
+
+    /// the lapis methods that set up this motor
+    pub fn methods(&self) -> String {
+        format!(
+            ".motor_velocity({}).motor_position({}).motor_max({}).motor_stiffness({}).motor_damping({})",
+            self.target_velocity,
+            self.target_position,
+            self.max_force,
+            self.stiffness,
+            self.damping
+        )
+    }
+}
+
+type MotorBody<'a> = (
+    &'a Position,
+    &'a Rotation,
+    &'a mut LinearVelocity,
+    &'a mut AngularVelocity,
+    &'a Mass,
+    &'a AngularInertia,
+    &'a RigidBody,
+);
+
+fn drive_motors(
+    revolute: Query<(&RevoluteJoint, &JointMotor)>,
+    prismatic: Query<(&PrismaticJoint, &JointMotor)>,
+    mut bodies: Query<MotorBody>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs();
+    if dt == 0. {
+        return;
+    }
+    // static (and kinematic) bodies don't get pushed around
+    let inverse = |m: f32, body: &RigidBody| {
+        if body.is_dynamic() && m > 0. {
+            1. / m
+        } else {
+            0.
+        }
+    };
+    for (j, motor) in revolute.iter() {
+        let Ok([mut b1, mut b2]) = bodies.get_many_mut([j.entity1, j.entity2]) else {
+            continue;
+        };
+        let (i1, i2) = (inverse(b1.5.0, b1.6), inverse(b2.5.0, b2.6));
+        if i1 + i2 == 0. {
+            continue;
+        }
+        let angle = b2.1.as_radians() - b1.1.as_radians();
+        // the shortest way around to the target
+        let error = (motor.target_position - angle + PI).rem_euclid(2. * PI) - PI;
+        let position = motor.target_position - error;
+        let velocity = b2.3.0 - b1.3.0;
+        let impulse = motor.delta(position, velocity, dt) / (i1 + i2);
+        let max = motor.max_force * dt;
+        let impulse = impulse.clamp(-max, max);
+        b1.3.0 -= impulse * i1;
+        b2.3.0 += impulse * i2;
+    }
+    for (j, motor) in prismatic.iter() {
+        let Ok([mut b1, mut b2]) = bodies.get_many_mut([j.entity1, j.entity2]) else {
+            continue;
+        };
+        let (m1, m2) = (inverse(b1.4.0, b1.6), inverse(b2.4.0, b2.6));
+        if m1 + m2 == 0. {
+            continue;
+        }
+        let axis = (*b1.1 * j.free_axis).normalize_or_zero();
+        let a1 = b1.0.0 + *b1.1 * j.local_anchor1;
+        let a2 = b2.0.0 + *b2.1 * j.local_anchor2;
+        let position = (a2 - a1).dot(axis);
+        let velocity = (b2.2.0 - b1.2.0).dot(axis);
+        let impulse = motor.delta(position, velocity, dt) / (m1 + m2);
+        let max = motor.max_force * dt;
+        let impulse = impulse.clamp(-max, max);
+        b1.2.0 -= axis * impulse * m1;
+        b2.2.0 += axis * impulse * m2;
+    }
+}
+
+/// a joint that despawns when its force (or torque) goes over a threshold
+/// 0 means no threshold. `code` is evaluated when it breaks (`$id` is the joint)
+#[derive(Component, Reflect, Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct Breakable {
+    pub force: f32,
+    pub torque: f32,
+    pub code: String,
+}
+
+impl Breakable {
+    fn set(&mut self, property: &JointProperty) {
+        match property {
+            JointProperty::BreakForce(val) => self.force = val.abs(),
+            JointProperty::BreakTorque(val) => self.torque = val.abs(),
+            JointProperty::OnBreak(code) => self.code = code.clone(),
+            _ => {}
+        }
+    }
+
+    /// the lapis methods that set up this threshold
+    pub fn methods(&self) -> String {
+        format!(
+            ".break_force({}).break_torque({}).on_break({:?})",
+            self.force, self.torque, self.code
+        )
+    }
+}
+
+/// the force (magnitude) and torque a joint applied in the last physics step
+/// revolute joints only apply torque at their angle limits (it's 0 without them)
+pub fn joint_force(e: Entity, lapis: &Lapis) -> Option<(f32, f32)> {
+    if let Ok(j) = lapis.fixed_query.get(e) {
+        Some((j.force.length(), j.align_torque))
+    } else if let Ok(j) = lapis.distance_query.get(e) {
+        Some((j.force.length(), 0.))
+    } else if let Ok(j) = lapis.prismatic_query.get(e) {
+        Some((j.force.length(), j.align_torque))
+    } else if let Ok(j) = lapis.revolute_query.get(e) {
+        Some((j.force.length(), j.angle_limit_torque))
+    } else {
+        None
+    }
+}
+
+fn break_joints(breakable: Query<(Entity, &Breakable)>, mut lapis: Lapis) {
+    for (e, b) in breakable.iter() {
+        let Some((force, torque)) = joint_force(e, &lapis) else {
+            continue;
+        };
+        let over_force = b.force > 0. && force > b.force;
+        let over_torque = b.torque > 0. && torque.abs() > b.torque;
+        if !over_force && !over_torque {
+            continue;
+        }
+        lapis.commands.entity(e).despawn();
+        if !b.code.is_empty() {
+            let code = b.code.replace("$id", &e.to_bits().to_string());
+            lapis.as_hook(|lapis| {
+                if lapis.data.quiet {
+                    lapis.quiet_eval(&code);
+                } else {
+                    lapis.eval(&code);
                 }
-            }
+            });
         }
     }
 }
@@ -129,16 +341,29 @@ pub enum JointProperty {
     Limits(f32, f32),
     Rest(f32),
     FreeAxis(f32, f32),
+    MotorVelocity(f32),
+    MotorPosition(f32),
+    MotorMax(f32),
+    MotorStiffness(f32),
+    MotorDamping(f32),
+    BreakForce(f32),
+    BreakTorque(f32),
+    OnBreak(String),
 }
 
 pub fn set_joint_property(
     trig: Trigger<JointProperty>,
+    mut commands: Commands,
     mut fixed: Query<&mut FixedJoint>,
     mut distance: Query<&mut DistanceJoint>,
     mut revolute: Query<&mut RevoluteJoint>,
     mut prismatic: Query<&mut PrismaticJoint>,
+    mut motors: Query<&mut JointMotor>,
+    mut breakable: Query<&mut Breakable>,
 ) {
     let e = trig.target();
+    let is_joint =
+        fixed.contains(e) || distance.contains(e) || revolute.contains(e) || prismatic.contains(e);
     match *trig.event() {
         JointProperty::Compliance(val) => {
             if let Ok(mut j) = fixed.get_mut(e) {
@@ -192,6 +417,31 @@ pub fn set_joint_property(
                 j.free_axis = Vec2::new(x, y);
             }
         }
+        JointProperty::MotorVelocity(_)
+        | JointProperty::MotorPosition(_)
+        | JointProperty::MotorMax(_)
+        | JointProperty::MotorStiffness(_)
+        | JointProperty::MotorDamping(_) => {
+            // only revolute and prismatic joints have motors
+            if let Ok(mut motor) = motors.get_mut(e) {
+                motor.set(trig.event());
+            } else if revolute.contains(e) || prismatic.contains(e) {
+                let mut motor = JointMotor::default();
+                motor.set(trig.event());
+                commands.entity(e).insert(motor);
+            }
+        }
+        JointProperty::BreakForce(_)
+        | JointProperty::BreakTorque(_)
+        | JointProperty::OnBreak(_) => {
+            if let Ok(mut b) = breakable.get_mut(e) {
+                b.set(trig.event());
+            } else if is_joint {
+                let mut b = Breakable::default();
+                b.set(trig.event());
+                commands.entity(e).insert(b);
+            }
+        }
     }
 }
 
@@ -1,126 +1,153 @@
-use super::{Lapis, entities::*, floats::*, helpers::*, ints::*, nets::*};
+use super::{Lapis, entities::*, errors::*, floats::*, helpers::*, ints::*, nets::*};
 use fundsp::hacker::*;
 use syn::*;
 
-pub fn eval_vec(expr: &Expr, lapis: &mut Lapis) -> Option<Vec<f32>> {
+pub fn eval_vec(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Vec<f32>> {
     match expr {
         Expr::Array(expr) => array_lit(expr, lapis),
         Expr::Path(_) => {
-            let k = nth_path_ident(expr, 0)?;
-            lapis.data.vmap.get(&k).cloned()
+            let k = nth_path_ident(expr, 0).or_mismatch("an array")?;
+            match lapis.data.vmap.get(&k) {
+                Some(vec) => Ok(vec.clone()),
+                None => Err(path_error(expr, lapis, "an array")),
+            }
         }
-        Expr::MethodCall(expr) => method_vec(expr, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_vec(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("an array")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn array_lit(expr: &ExprArray, lapis: &Lapis) -> Option<Vec<f32>> {
+fn array_lit(expr: &ExprArray, lapis: &Lapis) -> EvalResult<Vec<f32>> {
     let mut arr = Vec::new();
     for elem in &expr.elems {
-        if let Some(n) = eval_float_f32(elem, lapis) {
-            arr.push(n);
+        match eval_float_f32(elem, lapis) {
+            Ok(n) => arr.push(n),
+            // (other values are skipped)
+            Err(err) if err.is_specific() => return Err(err),
+            Err(_) => {}
         }
     }
-    Some(arr)
+    Ok(arr)
 }
 
-fn method_vec(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<Vec<f32>> {
+fn method_vec(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<Vec<f32>> {
     match expr.method.to_string().as_str() {
         "channel" => {
-            let arg = expr.args.first()?;
-            let chan = eval_usize(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get(&k)?;
+            let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a wave")?;
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            let Some(wave) = lapis.data.wmap.get(&k) else {
+                return Err(path_error(&expr.receiver, lapis, "a wave"));
+            };
             if chan < wave.channels() {
-                Some(wave.channel(chan).clone())
+                Ok(wave.channel(chan).clone())
             } else {
-                None
+                let kind = ErrorKind::OutOfRange {
+                    what: "channel",
+                    index: chan,
+                    len: wave.channels(),
+                };
+                Err(EvalError::new(expr, kind))
             }
         }
         "clone" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            lapis.data.vmap.get(&k).cloned()
+            let k = nth_path_ident(&expr.receiver, 0).or_mismatch("an array")?;
+            match lapis.data.vmap.get(&k) {
+                Some(vec) => Ok(vec.clone()),
+                None => Err(EvalError::unknown_method().or(path_error(
+                    &expr.receiver,
+                    lapis,
+                    "an array",
+                ))),
+            }
         }
         "tick" => {
-            let input = expr.args.first()?;
+            let input = arg(expr, 0)?;
             let in_arr = eval_vec(input, lapis)?;
-            let mut output = Vec::new();
             if let Some(k) = nth_path_ident(&expr.receiver, 0) {
-                if let Some(g) = lapis.data.gmap.get_mut(&k) {
-                    if g.inputs() != in_arr.len() {
-                        return None;
-                    }
-                    output.resize(g.outputs(), 0.);
-                    g.tick(&in_arr, &mut output);
-                    return Some(output);
-                }
-            } else if let Some(mut g) = eval_net(&expr.receiver, lapis) {
-                if g.inputs() != in_arr.len() {
-                    return None;
+                match lapis.data.gmap.get_mut(&k) {
+                    Some(g) => tick(g, input, &in_arr),
+                    None => Err(EvalError::unknown_method().or(path_error(
+                        &expr.receiver,
+                        lapis,
+                        "a net",
+                    ))),
                 }
-                output.resize(g.outputs(), 0.);
-                g.tick(&in_arr, &mut output);
-                return Some(output);
+            } else {
+                let mut g = eval_net(&expr.receiver, lapis)?;
+                tick(&mut g, input, &in_arr)
             }
-            None
         }
         "to_floats" => {
-            let e = path_lit_entity(&expr.receiver, lapis)?;
+            let e = path_lit_entity(&expr.receiver, lapis).or_mismatch("an entity")?;
             let bits = e.to_bits();
             let h = (bits >> u32::BITS) as u32;
             let l = bits as u32;
             let h = f32::from_bits(h);
             let l = f32::from_bits(l);
-            Some(vec![h, l])
+            Ok(vec![h, l])
         }
-        _ => None,
+        _ => Err(EvalError::unknown_method()),
     }
 }
 
-pub fn vec_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<()> {
-    match expr.method.to_string().as_str() {
+fn tick(g: &mut Net, input: &Expr, in_arr: &[f32]) -> EvalResult<Vec<f32>> {
+    if g.inputs() != in_arr.len() {
+        let err = EvalError::new(input, ErrorKind::Invalid);
+        return Err(err.suggest(format!("the net has {} inputs", g.inputs())));
+    }
+    let mut output = vec![0.; g.outputs()];
+    g.tick(in_arr, &mut output);
+    Ok(output)
+}
+
+pub fn vec_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<()> {
+    let method = expr.method.to_string();
+    if !matches!(
+        method.as_str(),
+        "push" | "pop" | "insert" | "remove" | "resize" | "clear"
+    ) {
+        return Err(EvalError::unknown_method());
+    }
+    let k = nth_path_ident(&expr.receiver, 0).or_mismatch("an array")?;
+    if !lapis.data.vmap.contains_key(&k) {
+        return Err(EvalError::unknown_method().or(path_error(&expr.receiver, lapis, "an array")));
+    }
+    match method.as_str() {
         "push" => {
-            let arg = expr.args.first()?;
-            let v = eval_float_f32(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+            let v = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             vec.push(v);
         }
         "pop" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             vec.pop();
         }
         "insert" => {
-            let index = eval_usize(expr.args.first()?, lapis)?;
-            let val = eval_float_f32(expr.args.get(1)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+            let index = eval_usize(arg(expr, 0)?, lapis)?;
+            let val = eval_float_f32(arg(expr, 1)?, lapis)?;
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             if index < vec.len() {
                 vec.insert(index, val);
             }
         }
         "remove" => {
-            let index = eval_usize(expr.args.first()?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+            let index = eval_usize(arg(expr, 0)?, lapis)?;
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             if index < vec.len() {
                 vec.remove(index);
             }
         }
         "resize" => {
-            let new_len = eval_usize(expr.args.first()?, lapis)?;
-            let val = eval_float_f32(expr.args.get(1)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+            let new_len = eval_usize(arg(expr, 0)?, lapis)?;
+            let val = eval_float_f32(arg(expr, 1)?, lapis)?;
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             vec.resize(new_len, val);
         }
-        "clear" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let vec = lapis.data.vmap.get_mut(&k)?;
+        _ => {
+            let vec = lapis.data.vmap.get_mut(&k).or_invalid()?;
             vec.clear();
         }
-        _ => {}
     }
-    None
+    Ok(())
 }
@@ -1,56 +1,69 @@
-use super::{Lapis, arrays::*, floats::*, helpers::*, ints::*};
+use super::{Lapis, arrays::*, errors::*, floats::*, helpers::*, ints::*};
 use fundsp::hacker::*;
 use syn::*;
 
-pub fn eval_shared(expr: &Expr, lapis: &Lapis) -> Option<Shared> {
+pub fn eval_shared(expr: &Expr, lapis: &Lapis) -> EvalResult<Shared> {
     match expr {
-        Expr::Call(expr) => call_shared(expr, lapis),
-        Expr::Path(expr) => path_shared(&expr.path, lapis),
+        Expr::Call(call) => call_shared(call, lapis).map_err(|e| e.within(call)),
+        Expr::Path(_) => path_shared(expr, lapis),
         Expr::Reference(expr) => eval_shared(&expr.expr, lapis),
-        _ => None,
+        _ => Err(EvalError::mismatch("a shared")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn path_shared(expr: &Path, lapis: &Lapis) -> Option<Shared> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.smap.get(&k).cloned()
+fn path_shared(expr: &Expr, lapis: &Lapis) -> EvalResult<Shared> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a shared")?;
+    match lapis.data.smap.get(&k) {
+        Some(s) => Ok(s.clone()),
+        None => Err(path_error(expr, lapis, "a shared")),
+    }
 }
 
-fn call_shared(expr: &ExprCall, lapis: &Lapis) -> Option<Shared> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn call_shared(expr: &ExprCall, lapis: &Lapis) -> EvalResult<Shared> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("a shared")?;
     if func == "shared" {
-        let arg = expr.args.first()?;
-        let val = eval_float_f32(arg, lapis)?;
-        Some(shared(val))
+        let val = eval_float_f32(arg(expr, 0)?, lapis)?;
+        Ok(shared(val))
     } else {
-        None
+        Err(EvalError::unknown_function())
     }
 }
 
-pub fn shared_methods(expr: &ExprMethodCall, lapis: &Lapis) -> Option<()> {
+pub fn shared_methods(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<()> {
     if expr.method == "set" || expr.method == "set_value" {
-        let k = nth_path_ident(&expr.receiver, 0)?;
+        let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a shared")?;
         if let Some(shared) = lapis.data.smap.get(&k) {
-            let value = eval_float_f32(expr.args.first()?, lapis)?;
+            let value = eval_float_f32(arg(expr, 0)?, lapis)?;
             shared.set(value);
+            return Ok(());
         } else if let Some(table) = lapis.data.atomic_table_map.get(&k) {
-            let i = eval_usize(expr.args.first()?, lapis)?;
-            let value = eval_float_f32(expr.args.get(1)?, lapis)?;
+            let i = eval_usize(arg(expr, 0)?, lapis)?;
+            let value = eval_float_f32(arg(expr, 1)?, lapis)?;
             table.set(i, value);
+            return Ok(());
         }
     }
-    None
+    Err(EvalError::unknown_method())
+}
+
+pub fn eval_atomic_table(expr: &Expr, lapis: &mut Lapis) -> EvalResult<AtomicTable> {
+    match expr {
+        Expr::Call(call) => call_atomic_table(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("an atomic table")),
+    }
 }
 
-pub fn eval_atomic_table(expr: &Expr, lapis: &mut Lapis) -> Option<AtomicTable> {
-    if let Expr::Call(expr) = expr {
-        let func = nth_path_ident(&expr.func, 0)?;
-        if func == "atomic_table" || func == "AtomicTable" {
-            let wave = eval_vec(expr.args.first()?, lapis)?;
-            if wave.len().is_power_of_two() {
-                return Some(AtomicTable::new(&wave));
-            }
+fn call_atomic_table(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<AtomicTable> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("an atomic table")?;
+    if func == "atomic_table" || func == "AtomicTable" {
+        let arg = arg(expr, 0)?;
+        let wave = eval_vec(arg, lapis)?;
+        if !wave.len().is_power_of_two() {
+            let err = EvalError::new(arg, ErrorKind::Invalid);
+            return Err(err.suggest("the length has to be a power of two"));
         }
+        return Ok(AtomicTable::new(&wave));
     }
-    None
+    Err(EvalError::unknown_function())
 }
@@ -1,84 +1,98 @@
-use super::{Lapis, entities::*, floats::*, helpers::*};
+use super::timers::TimerId;
+use super::{Lapis, entities::*, errors::*, floats::*, helpers::*};
 use avian2d::prelude::*;
 use syn::*;
 
-pub fn eval_bool(expr: &Expr, lapis: &Lapis) -> Option<bool> {
+pub fn eval_bool(expr: &Expr, lapis: &Lapis) -> EvalResult<bool> {
     match expr {
         Expr::Lit(expr) => lit_bool(&expr.lit),
         Expr::Binary(expr) => bin_expr_bool(expr, lapis),
         Expr::Paren(expr) => eval_bool(&expr.expr, lapis),
-        Expr::Path(expr) => path_bool(&expr.path, lapis),
+        Expr::Path(_) => path_bool(expr, lapis),
         Expr::Unary(expr) => unary_bool(expr, lapis),
         Expr::Field(expr) => field_bool(expr, lapis),
-        Expr::MethodCall(expr) => method_bool(expr, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_bool(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("a bool")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn method_bool(expr: &ExprMethodCall, lapis: &Lapis) -> Option<bool> {
+fn method_bool(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<bool> {
     if let Some(k) = nth_path_ident(&expr.receiver, 0)
         && k == "time"
         && expr.method == "is_paused"
     {
-        return Some(lapis.time.is_paused());
+        return Ok(lapis.time.is_paused());
     }
-    None
+    if expr.method == "is_active" {
+        let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a timer")?;
+        let Some(id) = lapis.data.timer_map.get(&k) else {
+            return Err(path_error(&expr.receiver, lapis, "a timer"));
+        };
+        let id: TimerId = *id;
+        return Ok(lapis.data.timers.is_active(id));
+    }
+    Err(EvalError::unknown_method())
 }
 
-fn field_bool(expr: &ExprField, lapis: &Lapis) -> Option<bool> {
-    let e = path_lit_entity(&expr.base, lapis)?;
+fn field_bool(expr: &ExprField, lapis: &Lapis) -> EvalResult<bool> {
+    let e = path_lit_entity(&expr.base, lapis).or_mismatch("a bool")?;
     if let Member::Named(ident) = &expr.member {
         if ident == "sensor" {
-            return Some(lapis.sensor_query.contains(e));
+            return Ok(lapis.sensor_query.contains(e));
         } else if ident == "dynamic" {
-            let body = lapis.body_query.get(e).ok()?;
-            return Some(*body == RigidBody::Dynamic);
+            let body = lapis.body_query.get(e).or_invalid()?;
+            return Ok(*body == RigidBody::Dynamic);
         }
     }
-    None
+    Err(EvalError::mismatch("a bool"))
 }
 
-fn lit_bool(expr: &Lit) -> Option<bool> {
+fn lit_bool(expr: &Lit) -> EvalResult<bool> {
     match expr {
-        Lit::Bool(expr) => Some(expr.value),
-        _ => None,
+        Lit::Bool(expr) => Ok(expr.value),
+        _ => Err(EvalError::mismatch("a bool")),
     }
 }
 
-fn bin_expr_bool(expr: &ExprBinary, lapis: &Lapis) -> Option<bool> {
-    let left_bool = eval_bool(&expr.left, lapis);
-    let right_bool = eval_bool(&expr.right, lapis);
-    let left_float = eval_float(&expr.left, lapis);
-    let right_float = eval_float(&expr.right, lapis);
-    if let (Some(left), Some(right)) = (left_bool, right_bool) {
-        match expr.op {
-            BinOp::And(_) => Some(left && right),
-            BinOp::Or(_) => Some(left || right),
-            _ => None,
+fn bin_expr_bool(expr: &ExprBinary, lapis: &Lapis) -> EvalResult<bool> {
+    match expr.op {
+        BinOp::And(_) | BinOp::Or(_) => {
+            let left = eval_bool(&expr.left, lapis)?;
+            // the right side is only evaluated if the left doesn't decide
+            match expr.op {
+                BinOp::And(_) if !left => Ok(false),
+                BinOp::Or(_) if left => Ok(true),
+                _ => eval_bool(&expr.right, lapis),
+            }
         }
-    } else if let (Some(left), Some(right)) = (left_float, right_float) {
-        match expr.op {
-            BinOp::Eq(_) => Some(left == right),
-            BinOp::Ne(_) => Some(left != right),
-            BinOp::Lt(_) => Some(left < right),
-            BinOp::Gt(_) => Some(left > right),
-            BinOp::Le(_) => Some(left <= right),
-            BinOp::Ge(_) => Some(left >= right),
-            _ => None,
+        BinOp::Eq(_) | BinOp::Ne(_) | BinOp::Lt(_) | BinOp::Gt(_) | BinOp::Le(_) | BinOp::Ge(_) => {
+            let left = eval_float(&expr.left, lapis)?;
+            let right = eval_float(&expr.right, lapis)?;
+            match expr.op {
+                BinOp::Eq(_) => Ok(left == right),
+                BinOp::Ne(_) => Ok(left != right),
+                BinOp::Lt(_) => Ok(left < right),
+                BinOp::Gt(_) => Ok(left > right),
+                BinOp::Le(_) => Ok(left <= right),
+                _ => Ok(left >= right),
+            }
         }
-    } else {
-        None
+        _ => Err(EvalError::mismatch("a bool")),
     }
 }
 
-fn path_bool(expr: &Path, lapis: &Lapis) -> Option<bool> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.bmap.get(&k).copied()
+fn path_bool(expr: &Expr, lapis: &Lapis) -> EvalResult<bool> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a bool")?;
+    match lapis.data.bmap.get(&k) {
+        Some(b) => Ok(*b),
+        None => Err(path_error(expr, lapis, "a bool")),
+    }
 }
 
-fn unary_bool(expr: &ExprUnary, lapis: &Lapis) -> Option<bool> {
+fn unary_bool(expr: &ExprUnary, lapis: &Lapis) -> EvalResult<bool> {
     match expr.op {
-        UnOp::Not(_) => Some(!eval_bool(&expr.expr, lapis)?),
-        _ => None,
+        UnOp::Not(_) => Ok(!eval_bool(&expr.expr, lapis)?),
+        _ => Err(EvalError::mismatch("a bool")),
     }
 }
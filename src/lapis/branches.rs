@@ -0,0 +1,189 @@
+use super::{
+    Lapis, Stash, bools::*, errors::*, floats::*, functions::*, statements::*, strings::*,
+};
+use syn::*;
+
+/// a value that can be matched against patterns
+#[derive(Clone, Debug)]
+enum MatchValue {
+    Float(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl MatchValue {
+    fn eval(expr: &Expr, lapis: &Lapis) -> EvalResult<MatchValue> {
+        let mut err = None;
+        if let Some(f) = attempt(eval_float(expr, lapis), &mut err) {
+            Ok(MatchValue::Float(f))
+        } else if let Some(b) = attempt(eval_bool(expr, lapis), &mut err) {
+            Ok(MatchValue::Bool(b))
+        } else if let Some(s) = attempt(eval_string(expr, lapis), &mut err) {
+            Ok(MatchValue::Str(s))
+        } else {
+            Err(err.unwrap_or_else(EvalError::invalid))
+        }
+    }
+
+    fn bind(self, k: String, lapis: &mut Lapis) {
+        match self {
+            MatchValue::Float(f) => {
+                lapis.data.fmap.insert(k, f);
+            }
+            MatchValue::Bool(b) => {
+                lapis.data.bmap.insert(k, b);
+            }
+            MatchValue::Str(s) => {
+                lapis.data.string_map.insert(k, s);
+            }
+        }
+    }
+}
+
+fn pat_matches(pat: &Pat, value: &MatchValue, lapis: &Lapis) -> bool {
+    match pat {
+        Pat::Wild(_) | Pat::Ident(_) => true,
+        Pat::Paren(pat) => pat_matches(&pat.pat, value, lapis),
+        Pat::Or(pat) => pat.cases.iter().any(|p| pat_matches(p, value, lapis)),
+        Pat::Lit(pat) => match (&pat.lit, value) {
+            (Lit::Int(l), MatchValue::Float(f)) => l.base10_parse::<f64>().is_ok_and(|l| l == *f),
+            (Lit::Float(l), MatchValue::Float(f)) => l.base10_parse::<f64>().is_ok_and(|l| l == *f),
+            (Lit::Bool(l), MatchValue::Bool(b)) => l.value == *b,
+            (Lit::Str(l), MatchValue::Str(s)) => l.value() == *s,
+            _ => false,
+        },
+        Pat::Range(pat) => {
+            let MatchValue::Float(f) = value else {
+                return false;
+            };
+            if let Some(start) = &pat.start {
+                let Ok(s) = eval_float(start, lapis) else {
+                    return false;
+                };
+                if *f < s {
+                    return false;
+                }
+            }
+            if let Some(end) = &pat.end {
+                let Ok(e) = eval_float(end, lapis) else {
+                    return false;
+                };
+                return match pat.limits {
+                    RangeLimits::HalfOpen(_) => *f < e,
+                    RangeLimits::Closed(_) => *f <= e,
+                };
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// a name bound by a match arm, with whatever it shadowed
+type Binding = Option<(String, Stash)>;
+
+/// find the first arm whose pattern (and guard) accepts the scrutinee
+/// `n => ...` and `n if ...` bind the value to `n` for the guard and body
+/// the binding must be released with `unbind` once the arm is done
+fn select_arm<'a>(
+    expr: &'a ExprMatch,
+    lapis: &mut Lapis,
+    buffer: &mut String,
+) -> Option<(&'a Arm, Binding)> {
+    let value = with_expanded(&expr.expr, lapis, buffer, |e, lapis, buffer| {
+        let r = MatchValue::eval(e, lapis);
+        or_report(r, e, Some("a float, bool, or string"), lapis, buffer)
+    })?;
+    for arm in &expr.arms {
+        if !pat_matches(&arm.pat, &value, lapis) {
+            continue;
+        }
+        let binding = if let Pat::Ident(pat) = &arm.pat {
+            let k = pat.ident.to_string();
+            let old = lapis.data.take(&k);
+            value.clone().bind(k.clone(), lapis);
+            Some((k, old))
+        } else {
+            None
+        };
+        if let Some((_, guard)) = &arm.guard {
+            let pass = with_expanded(guard, lapis, buffer, |e, lapis, buffer| {
+                or_report(eval_bool(e, lapis), e, Some("a bool"), lapis, buffer)
+            });
+            if pass != Some(true) {
+                unbind(binding, lapis);
+                continue;
+            }
+        }
+        return Some((arm, binding));
+    }
+    None
+}
+
+fn unbind(binding: Binding, lapis: &mut Lapis) {
+    if let Some((k, old)) = binding {
+        lapis.data.put(&k, old);
+    }
+}
+
+/// `match` in statement position
+pub fn eval_match(expr: &ExprMatch, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    let Some((arm, binding)) = select_arm(expr, lapis, buffer) else {
+        return Flow::Next;
+    };
+    let flow = eval_expr_stmt(&arm.body, lapis, buffer);
+    unbind(binding, lapis);
+    flow
+}
+
+/// evaluate an `if` or `match` in expression position
+/// the value of the branch taken is stored in a temporary variable
+/// and its name is returned (None if the branch gave no value)
+pub fn branch_value(expr: &Expr, lapis: &mut Lapis, buffer: &mut String) -> Option<String> {
+    match expr {
+        Expr::If(expr) => {
+            let cond = with_expanded(&expr.cond, lapis, buffer, |e, lapis, buffer| {
+                or_report(eval_bool(e, lapis), e, Some("a bool"), lapis, buffer)
+            })?;
+            if cond {
+                block_value(&expr.then_branch, lapis, buffer)
+            } else {
+                let (_, else_branch) = expr.else_branch.as_ref()?;
+                branch_value(else_branch, lapis, buffer)
+            }
+        }
+        Expr::Match(expr) => {
+            let (arm, binding) = select_arm(expr, lapis, buffer)?;
+            let ret = branch_value(&arm.body, lapis, buffer);
+            unbind(binding, lapis);
+            ret
+        }
+        Expr::Block(expr) => block_value(&expr.block, lapis, buffer),
+        expr => {
+            let tmp = lapis.data.temp_name();
+            let slot = tmp.clone();
+            with_expanded(expr, lapis, buffer, |e, lapis, buffer| {
+                let r = assign_value(slot, e, lapis);
+                or_report(r, e, None, lapis, buffer)
+            })?;
+            Some(tmp)
+        }
+    }
+}
+
+/// run all statements of a block, the trailing expression is its value
+fn block_value(block: &Block, lapis: &mut Lapis, buffer: &mut String) -> Option<String> {
+    let stmts = &block.stmts;
+    let tail = match stmts.last() {
+        Some(Stmt::Expr(tail, None)) if matches!(tail, Expr::If(_) | Expr::Block(_)) => tail,
+        Some(Stmt::Expr(tail, None)) if is_value(tail) => tail,
+        _ => {
+            eval_stmts(stmts, lapis, buffer);
+            return None;
+        }
+    };
+    if eval_stmts(&stmts[..stmts.len() - 1], lapis, buffer) != Flow::Next {
+        return None;
+    }
+    branch_value(tail, lapis, buffer)
+}
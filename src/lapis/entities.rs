@@ -1,23 +1,24 @@
-use super::{Lapis, arrays::*, bools::*, floats::*, helpers::*};
+use super::{Lapis, arrays::*, bools::*, errors::*, floats::*, helpers::*, nets::*};
 use crate::interaction::*;
-use crate::{joints::*, objects::*};
+use crate::{joints::*, objects::*, shapes::Shape, voices::*};
 use bevy::prelude::*;
 use syn::*;
 
-pub fn eval_entity(expr: &Expr, lapis: &mut Lapis) -> Option<Entity> {
+pub fn eval_entity(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Entity> {
     match expr {
-        Expr::Call(expr) => call_entity(expr, lapis),
-        Expr::Lit(expr) => lit_entity(&expr.lit),
-        Expr::Path(expr) => path_entity(&expr.path, lapis),
-        Expr::MethodCall(expr) => method_entity(expr, lapis),
-        _ => None,
+        Expr::Call(call) => call_entity(call, lapis).map_err(|e| e.within(call)),
+        Expr::Lit(expr) => lit_entity(&expr.lit).or_mismatch("an entity"),
+        Expr::Path(_) => path_entity(expr, lapis),
+        Expr::MethodCall(call) => method_entity(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("an entity")),
     }
+    .map_err(|e| e.at(expr))
 }
 
 pub fn path_lit_entity(expr: &Expr, lapis: &Lapis) -> Option<Entity> {
     match expr {
         Expr::Lit(expr) => lit_entity(&expr.lit),
-        Expr::Path(expr) => path_entity(&expr.path, lapis),
+        Expr::Path(_) => path_entity(expr, lapis).ok(),
         _ => None,
     }
 }
@@ -29,80 +30,140 @@ fn lit_entity(expr: &Lit) -> Option<Entity> {
     }
 }
 
-fn path_entity(expr: &Path, lapis: &Lapis) -> Option<Entity> {
-    let k = expr.segments.first()?.ident.to_string();
-    if k == "Entity" && expr.segments.get(1)?.ident == "PLACEHOLDER" {
-        return Some(Entity::PLACEHOLDER);
+fn path_entity(expr: &Expr, lapis: &Lapis) -> EvalResult<Entity> {
+    let k = nth_path_ident(expr, 0).or_mismatch("an entity")?;
+    if k == "Entity" && nth_path_ident(expr, 1).as_deref() == Some("PLACEHOLDER") {
+        return Ok(Entity::PLACEHOLDER);
+    }
+    match lapis.data.entitymap.get(&k) {
+        Some(e) => Ok(*e),
+        None => Err(path_error(expr, lapis, "an entity")),
     }
-    lapis.data.entitymap.get(&k).copied()
 }
 
-fn call_entity(expr: &ExprCall, lapis: &mut Lapis) -> Option<Entity> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn call_entity(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<Entity> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("an entity")?;
     match func.as_str() {
         "Entity" => {
-            let f = nth_path_ident(&expr.func, 1)?;
+            let f = nth_path_ident(&expr.func, 1).or_invalid()?;
             match f.as_str() {
-                "from_bits" => {
-                    if let Expr::Lit(expr) = expr.args.first()? {
-                        return lit_entity(&expr.lit);
-                    }
-                    None
-                }
+                "from_bits" => path_lit_entity(arg(expr, 0)?, lapis).or_invalid(),
                 "from_floats" => {
-                    let arr = eval_vec(expr.args.first()?, lapis)?;
-                    let h = arr.first()?;
-                    let l = arr.get(1)?;
+                    let arr = eval_vec(arg(expr, 0)?, lapis)?;
+                    let (Some(h), Some(l)) = (arr.first(), arr.get(1)) else {
+                        return Err(EvalError::invalid().suggest("the array needs 2 floats"));
+                    };
                     let h = h.to_bits();
                     let l = l.to_bits();
                     let bits = ((h as u64) << u32::BITS) | (l as u64);
-                    Entity::try_from_bits(bits).ok()
+                    Entity::try_from_bits(bits).or_invalid()
                 }
-                _ => None,
+                _ => Err(EvalError::unknown_function()),
             }
         }
         "spawn" => {
-            let r = eval_float_f32(expr.args.first()?, lapis)?;
+            let r = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let e = lapis.commands.spawn_empty().id();
+            lapis.spawned(e);
+            lapis.commands.trigger_targets(InsertDefaults(r), e);
+            Ok(e)
+        }
+        "spawn_poly" => {
+            let points = eval_points(arg(expr, 0)?, lapis)?;
+            let (shape, center) = Shape::polygon(&points).ok_or_else(bad_outline)?;
+            // mass and inertia from the farthest vertex (like the radius)
+            let Shape::Polygon(v) = &shape else {
+                return Err(bad_outline());
+            };
+            let r = v.iter().map(|p| p.length()).fold(1., f32::max);
             let e = lapis.commands.spawn_empty().id();
+            lapis.spawned(e);
             lapis.commands.trigger_targets(InsertDefaults(r), e);
-            Some(e)
+            lapis.commands.trigger_targets(Property::Rx(1.), e);
+            lapis.commands.trigger_targets(Property::Ry(1.), e);
+            lapis.commands.trigger_targets(Property::Shape(shape), e);
+            lapis.commands.trigger_targets(Property::X(center.x), e);
+            lapis.commands.trigger_targets(Property::Y(center.y), e);
+            Ok(e)
+        }
+        "spawn_wall" => {
+            let points = eval_points(arg(expr, 0)?, lapis)?;
+            let (shape, center) = Shape::wall(&points).ok_or_else(bad_outline)?;
+            let e = lapis.commands.spawn_empty().id();
+            lapis.spawned(e);
+            lapis.commands.trigger_targets(InsertDefaults(1.), e);
+            lapis.commands.trigger_targets(Property::Dynamic(false), e);
+            lapis.commands.trigger_targets(Property::Shape(shape), e);
+            lapis.commands.trigger_targets(Property::X(center.x), e);
+            lapis.commands.trigger_targets(Property::Y(center.y), e);
+            Ok(e)
         }
         "joint" => {
-            let e1 = eval_entity(expr.args.first()?, lapis);
-            let e2 = eval_entity(expr.args.get(1)?, lapis);
-            if let (Some(e1), Some(e2)) = (e1, e2) {
+            let e1 = eval_entity(arg(expr, 0)?, lapis);
+            let e2 = eval_entity(arg(expr, 1)?, lapis);
+            if let (Ok(e1), Ok(e2)) = (e1, e2) {
                 let e = lapis.commands.spawn_empty().id();
+                lapis.spawned(e);
                 lapis.commands.trigger_targets(JointEntities(e1, e2), e);
-                Some(e)
+                Ok(e)
             } else {
-                let x1 = eval_float_f32(expr.args.first()?, lapis)?;
-                let y1 = eval_float_f32(expr.args.get(1)?, lapis)?;
-                let x2 = eval_float_f32(expr.args.get(2)?, lapis)?;
-                let y2 = eval_float_f32(expr.args.get(3)?, lapis)?;
+                let x1 = eval_float_f32(arg(expr, 0)?, lapis)?;
+                let y1 = eval_float_f32(arg(expr, 1)?, lapis)?;
+                let x2 = eval_float_f32(arg(expr, 2)?, lapis)?;
+                let y2 = eval_float_f32(arg(expr, 3)?, lapis)?;
                 let e = lapis.commands.spawn_empty().id();
+                lapis.spawned(e);
                 let i = Vec2::new(x1, y1);
                 let f = Vec2::new(x2, y2);
                 lapis.commands.trigger_targets(JointPoints(i, f), e);
-                Some(e)
+                Ok(e)
             }
         }
-        _ => None,
+        _ => Err(EvalError::unknown_function()),
     }
 }
 
-fn method_entity(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<Entity> {
+fn method_entity(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<Entity> {
     let e = eval_entity(&expr.receiver, lapis)?;
     // this being here allows some nonsense like
     // let var = entity.despawn();
     // which doesn't assign anything to var but does despawn entity
+    lapis.touch(e);
     if expr.method == "despawn" {
-        lapis.commands.get_entity(e).ok()?.try_despawn();
-        return None;
+        lapis.commands.get_entity(e).or_invalid()?.try_despawn();
+        return Err(EvalError::new(expr, ErrorKind::NoValue));
     } else if expr.method == "disjoint" {
+        lapis.touch_joints(e);
         lapis.commands.trigger_targets(Disjoint, e);
-        return None;
+        return Err(EvalError::new(expr, ErrorKind::NoValue));
+    } else if expr.method == "rectangle" {
+        lapis
+            .commands
+            .trigger_targets(Property::Shape(Shape::Rectangle), e);
+        return Ok(e);
+    } else if expr.method == "poly" {
+        let points = eval_points(arg(expr, 0)?, lapis)?;
+        let (shape, _) = Shape::polygon(&points).ok_or_else(bad_outline)?;
+        lapis.commands.trigger_targets(Property::Shape(shape), e);
+        return Ok(e);
+    } else if expr.method == "wall" {
+        let points = eval_points(arg(expr, 0)?, lapis)?;
+        let (shape, _) = Shape::wall(&points).ok_or_else(bad_outline)?;
+        lapis.commands.trigger_targets(Property::Shape(shape), e);
+        return Ok(e);
+    } else if expr.method == "voice" {
+        let net = eval_net_cloned(arg(expr, 0)?, lapis)?;
+        if !Voice::fits(&net) {
+            let help = "a voice has 1 output and at most 3 inputs";
+            return Err(EvalError::invalid().suggest(help));
+        }
+        lapis.commands.trigger_targets(VoiceProperty::Net(net), e);
+        return Ok(e);
+    } else if expr.method == "no_voice" {
+        lapis.commands.trigger_targets(VoiceProperty::Remove, e);
+        return Ok(e);
     }
-    let val = eval_float_f32(expr.args.first()?, lapis);
+    let val = arg(expr, 0).and_then(|arg| eval_float_f32(arg, lapis));
     let cmd = &mut lapis.commands;
     match expr.method.to_string().as_str() {
         "x" => cmd.trigger_targets(Property::X(val?), e),
@@ -124,75 +185,95 @@ fn method_entity(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<Entity> {
         "l" => cmd.trigger_targets(Property::L(val?), e),
         "a" => cmd.trigger_targets(Property::A(val?), e),
         "sides" => cmd.trigger_targets(Property::Sides(val? as u32), e),
+        "capsule" => cmd.trigger_targets(Property::Shape(Shape::Capsule(val?.max(0.))), e),
         "cmx" => cmd.trigger_targets(Property::Cmx(val?), e),
         "cmy" => cmd.trigger_targets(Property::Cmy(val?), e),
         "friction" => cmd.trigger_targets(Property::Friction(val?), e),
         "tail" => cmd.trigger_targets(Property::Tail(val? as usize), e),
         "layer" => cmd.trigger_targets(Property::Layer(val? as u32), e),
+        "voice_gain" => cmd.trigger_targets(VoiceProperty::Gain(val?), e),
         "dynamic" => {
-            let b = eval_bool(expr.args.first()?, lapis)?;
+            let b = eval_bool(arg(expr, 0)?, lapis)?;
             lapis.commands.trigger_targets(Property::Dynamic(b), e);
         }
         "sensor" => {
-            let b = eval_bool(expr.args.first()?, lapis)?;
+            let b = eval_bool(arg(expr, 0)?, lapis)?;
             lapis.commands.trigger_targets(Property::Sensor(b), e);
         }
-        "links" => {
-            if let Expr::Lit(expr) = expr.args.first()?
-                && let Lit::Str(expr) = &expr.lit
-            {
-                cmd.trigger_targets(Property::Links(expr.value()), e);
-            }
-        }
-        "code_i" => {
-            if let Expr::Lit(expr) = expr.args.first()?
-                && let Lit::Str(expr) = &expr.lit
-            {
-                cmd.trigger_targets(Property::CodeI(expr.value()), e);
-            }
-        }
-        "code_f" => {
-            if let Expr::Lit(expr) = expr.args.first()?
-                && let Lit::Str(expr) = &expr.lit
-            {
-                cmd.trigger_targets(Property::CodeF(expr.value()), e);
-            }
-        }
+        "links" => cmd.trigger_targets(Property::Links(lit_string(arg(expr, 0)?)?), e),
+        "code_i" => cmd.trigger_targets(Property::CodeI(lit_string(arg(expr, 0)?)?), e),
+        "code_f" => cmd.trigger_targets(Property::CodeF(lit_string(arg(expr, 0)?)?), e),
+        "code_c" => cmd.trigger_targets(Property::CodeC(lit_string(arg(expr, 0)?)?), e),
+        "code_u" => cmd.trigger_targets(Property::CodeU(lit_string(arg(expr, 0)?)?), e),
+        "code_u_steps" => cmd.trigger_targets(Property::CodeUSteps(val?.max(0.) as u32), e),
         // joint methods
         "joint_type" => match val?.trunc() {
             0. => cmd.trigger_targets(ReplaceJoint(JointType::Fixed), e),
             1. => cmd.trigger_targets(ReplaceJoint(JointType::Distance), e),
             2. => cmd.trigger_targets(ReplaceJoint(JointType::Prismatic), e),
             3. => cmd.trigger_targets(ReplaceJoint(JointType::Revolute), e),
-            _ => {}
+            _ => {
+                let help =
+                    "the joint types are 0 (fixed), 1 (distance), 2 (prismatic) and 3 (revolute)";
+                return Err(EvalError::invalid().suggest(help));
+            }
         },
         "compliance" => cmd.trigger_targets(JointProperty::Compliance(val?), e),
         "anchor1" => {
-            let val2 = eval_float_f32(expr.args.get(1)?, lapis)?;
+            let val2 = eval_float_f32(arg(expr, 1)?, lapis)?;
             lapis
                 .commands
                 .trigger_targets(JointProperty::Anchor1(val?, val2), e);
         }
         "anchor2" => {
-            let val2 = eval_float_f32(expr.args.get(1)?, lapis)?;
+            let val2 = eval_float_f32(arg(expr, 1)?, lapis)?;
             lapis
                 .commands
                 .trigger_targets(JointProperty::Anchor2(val?, val2), e);
         }
         "limits" => {
-            let val2 = eval_float_f32(expr.args.get(1)?, lapis)?;
+            let val2 = eval_float_f32(arg(expr, 1)?, lapis)?;
             lapis
                 .commands
                 .trigger_targets(JointProperty::Limits(val?, val2), e);
         }
         "rest" => cmd.trigger_targets(JointProperty::Rest(val?), e),
         "free_axis" => {
-            let val2 = eval_float_f32(expr.args.get(1)?, lapis)?;
+            let val2 = eval_float_f32(arg(expr, 1)?, lapis)?;
             lapis
                 .commands
                 .trigger_targets(JointProperty::FreeAxis(val?, val2), e);
         }
-        _ => return None,
+        "motor_velocity" => cmd.trigger_targets(JointProperty::MotorVelocity(val?), e),
+        "motor_position" => cmd.trigger_targets(JointProperty::MotorPosition(val?), e),
+        "motor_max" => cmd.trigger_targets(JointProperty::MotorMax(val?), e),
+        "motor_stiffness" => cmd.trigger_targets(JointProperty::MotorStiffness(val?), e),
+        "motor_damping" => cmd.trigger_targets(JointProperty::MotorDamping(val?), e),
+        "break_force" => cmd.trigger_targets(JointProperty::BreakForce(val?), e),
+        "break_torque" => cmd.trigger_targets(JointProperty::BreakTorque(val?), e),
+        "on_break" => cmd.trigger_targets(JointProperty::OnBreak(lit_string(arg(expr, 0)?)?), e),
+        _ => return Err(EvalError::unknown_method()),
+    }
+    Ok(e)
+}
+
+/// an array of x, y pairs as points (a trailing odd value is ignored)
+fn eval_points(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Vec<Vec2>> {
+    let arr = eval_vec(expr, lapis)?;
+    Ok(arr.chunks_exact(2).map(|p| Vec2::new(p[0], p[1])).collect())
+}
+
+/// the value of a string literal
+fn lit_string(expr: &Expr) -> EvalResult<String> {
+    if let Expr::Lit(lit) = expr
+        && let Lit::Str(s) = &lit.lit
+    {
+        return Ok(s.value());
     }
-    Some(e)
+    let err = EvalError::new(expr, ErrorKind::Invalid);
+    Err(err.suggest("code is given as a string literal"))
+}
+
+fn bad_outline() -> EvalError {
+    EvalError::invalid().suggest("the points don't make an outline")
 }
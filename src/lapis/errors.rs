@@ -0,0 +1,596 @@
+use super::{Lapis, floats::*, helpers::*};
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::fmt;
+use syn::{punctuated::Punctuated, *};
+
+pub type EvalResult<T> = std::result::Result<T, EvalError>;
+
+/// what went wrong while evaluating
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// a name that isn't a variable
+    UnknownVariable(String),
+    /// a name that isn't a function
+    UnknownFunction(String),
+    /// a value of one type where another was expected
+    /// (`found` is None when it isn't known)
+    WrongType {
+        expected: &'static str,
+        found: Option<&'static str>,
+    },
+    /// a function called with the wrong number of arguments
+    Arity {
+        name: String,
+        expected: usize,
+        given: usize,
+    },
+    /// a function called without enough arguments
+    TooFewArguments {
+        name: String,
+        needed: usize,
+    },
+    /// nets that can't be combined with an operator
+    /// (the operator and the (inputs, outputs) of each side)
+    Channels {
+        op: &'static str,
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+    /// an index (or channel) past the end
+    OutOfRange {
+        what: &'static str,
+        index: usize,
+        len: usize,
+    },
+    UnknownMethod {
+        ty: Option<&'static str>,
+        method: String,
+    },
+    UnknownField(String),
+    /// variables of this type can't be reassigned
+    Unassignable(&'static str),
+    /// something that was done but doesn't give a value (like `e.despawn()`)
+    NoValue,
+    /// nothing more specific is known
+    Invalid,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnknownVariable(k) => write!(f, "unknown variable `{k}`"),
+            ErrorKind::UnknownFunction(k) => write!(f, "unknown function `{k}`"),
+            ErrorKind::WrongType {
+                expected,
+                found: Some(found),
+            } => write!(f, "expected {expected}, found {found}"),
+            ErrorKind::WrongType {
+                expected,
+                found: None,
+            } => write!(f, "expected {expected}"),
+            ErrorKind::Arity {
+                name,
+                expected,
+                given,
+            } => write!(
+                f,
+                "`{name}` takes {expected} arguments but {given} were given"
+            ),
+            ErrorKind::TooFewArguments { name, needed } => {
+                write!(f, "`{name}` needs at least {needed} arguments")
+            }
+            ErrorKind::Channels { op, left, right } => write!(
+                f,
+                "can't `{op}` a net with {} inputs and {} outputs and a net with {} inputs and {} outputs",
+                left.0, left.1, right.0, right.1
+            ),
+            ErrorKind::OutOfRange { what, index, len } => {
+                write!(f, "{what} {index} is out of range (there are {len})")
+            }
+            ErrorKind::UnknownMethod {
+                ty: Some(ty),
+                method,
+            } => write!(f, "no method `{method}` for {ty}"),
+            ErrorKind::UnknownMethod { ty: None, method } => {
+                write!(f, "unknown method `{method}`")
+            }
+            ErrorKind::UnknownField(field) => write!(f, "no field `{field}` for entities"),
+            ErrorKind::Unassignable(ty) => write!(f, "can't assign to {ty}"),
+            ErrorKind::NoValue => write!(f, "this doesn't have a value"),
+            ErrorKind::Invalid => write!(f, "couldn't evaluate this"),
+        }
+    }
+}
+
+/// an evaluation error, pointing at the code that caused it
+#[derive(Clone, Debug)]
+pub struct EvalError {
+    /// the offending code (located in the source when rendering)
+    /// None until it's known
+    pub code: Option<TokenStream>,
+    pub kind: ErrorKind,
+    pub suggestion: Option<String>,
+}
+
+impl EvalError {
+    pub fn new(code: &impl ToTokens, kind: ErrorKind) -> Self {
+        EvalError {
+            code: Some(code.to_token_stream()),
+            kind,
+            suggestion: None,
+        }
+    }
+
+    /// the expression isn't of the type an evaluator handles
+    /// (it's cheap, since evaluators are tried one after the other)
+    pub fn mismatch(expected: &'static str) -> Self {
+        EvalError {
+            code: None,
+            kind: ErrorKind::WrongType {
+                expected,
+                found: None,
+            },
+            suggestion: None,
+        }
+    }
+
+    /// something went wrong in code that is located later
+    pub fn invalid() -> Self {
+        EvalError {
+            code: None,
+            kind: ErrorKind::Invalid,
+            suggestion: None,
+        }
+    }
+
+    /// the function isn't one the evaluator knows
+    /// (the name is filled in by `within`)
+    pub fn unknown_function() -> Self {
+        EvalError {
+            code: None,
+            kind: ErrorKind::UnknownFunction(String::new()),
+            suggestion: None,
+        }
+    }
+
+    /// the method isn't one the evaluator knows (for the receiver's type)
+    pub fn unknown_method() -> Self {
+        EvalError {
+            code: None,
+            kind: ErrorKind::UnknownMethod {
+                ty: None,
+                method: String::new(),
+            },
+            suggestion: None,
+        }
+    }
+
+    pub fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// how much this error says about what went wrong
+    /// (mismatches and unknown names that aren't located yet
+    /// are expected while trying evaluators one after the other)
+    fn rank(&self) -> u8 {
+        match (&self.kind, &self.code) {
+            (ErrorKind::WrongType { found: None, .. }, _) => 0,
+            (ErrorKind::UnknownFunction(_) | ErrorKind::UnknownMethod { .. }, None) => 0,
+            (ErrorKind::Invalid, _) => 1,
+            _ => 2,
+        }
+    }
+
+    /// whether this is more than a type mismatch or a generic failure
+    pub fn is_specific(&self) -> bool {
+        self.rank() == 2
+    }
+
+    /// whether every evaluator just found the code isn't of its type
+    pub fn is_mismatch(&self) -> bool {
+        self.rank() == 0
+    }
+
+    /// the more useful of two errors (the first one if they're as useful)
+    pub fn or(self, other: EvalError) -> Self {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// point the error at `code` unless it already points somewhere
+    /// (mismatches aren't located since another evaluator might handle the code)
+    pub fn at(mut self, code: &impl ToTokens) -> Self {
+        if self.code.is_none() && self.rank() > 0 {
+            self.code = Some(code.to_token_stream());
+        }
+        self
+    }
+
+    /// an error from evaluating `call`
+    /// if the function is known, a mismatch in its arguments makes the call invalid
+    /// and unknown functions in the arguments are located
+    pub fn within(mut self, call: &impl Call) -> Self {
+        if self.code.is_none()
+            && let ErrorKind::UnknownFunction(name) | ErrorKind::UnknownMethod { method: name, .. } =
+                &mut self.kind
+        {
+            if name.is_empty() {
+                *name = call.name();
+            } else if *name != call.name() {
+                self.code = name.parse().ok();
+            }
+            return self;
+        }
+        if self.rank() == 0 {
+            EvalError::new(call, ErrorKind::Invalid)
+        } else {
+            self.at(call)
+        }
+    }
+
+    /// format the error as comments, quoting the offending line of `source`
+    /// with the code underlined (if it can be found)
+    pub fn render(&self, source: &str) -> String {
+        let mut s = format!("\n// error: {}", self.kind);
+        if let Some(code) = &self.code
+            && let Some((start, end)) = locate(source, code)
+        {
+            let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[start..]
+                .find('\n')
+                .map_or(source.len(), |i| start + i);
+            let line = &source[line_start..line_end];
+            let number = source[..start].matches('\n').count() + 1;
+            // keep tabs so the carets line up
+            let pad: String = source[line_start..start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let len = source[start..end.min(line_end)].chars().count().max(1);
+            s.push_str(&format!("\n// | {number}: {line}"));
+            let margin = " ".repeat(number.to_string().len() + 2);
+            s.push_str(&format!("\n// | {margin}{pad}{}", "^".repeat(len)));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            s.push_str(&format!("\n// help: {suggestion}"));
+        }
+        s
+    }
+}
+
+/// `?` for options (and other results) in evaluators
+pub trait OrInvalid<T> {
+    /// the value, or an error to be located by the evaluator
+    fn or_invalid(self) -> EvalResult<T>;
+    /// the value, or a mismatch (for when the code isn't of the expected type)
+    fn or_mismatch(self, expected: &'static str) -> EvalResult<T>;
+}
+
+impl<T> OrInvalid<T> for Option<T> {
+    fn or_invalid(self) -> EvalResult<T> {
+        self.ok_or_else(EvalError::invalid)
+    }
+    fn or_mismatch(self, expected: &'static str) -> EvalResult<T> {
+        self.ok_or_else(|| EvalError::mismatch(expected))
+    }
+}
+
+impl<T, E> OrInvalid<T> for std::result::Result<T, E> {
+    fn or_invalid(self) -> EvalResult<T> {
+        self.map_err(|_| EvalError::invalid())
+    }
+    fn or_mismatch(self, expected: &'static str) -> EvalResult<T> {
+        self.map_err(|_| EvalError::mismatch(expected))
+    }
+}
+
+/// the value of `r`, keeping its error in `err` if it's the most useful so far
+/// (for trying evaluators one after the other)
+pub fn attempt<T>(r: EvalResult<T>, err: &mut Option<EvalError>) -> Option<T> {
+    match r {
+        Ok(v) => Some(v),
+        Err(e) => {
+            *err = Some(match err.take() {
+                Some(old) => old.or(e),
+                None => e,
+            });
+            None
+        }
+    }
+}
+
+/// calls and method calls
+pub trait Call: ToTokens {
+    fn name(&self) -> String;
+    fn args(&self) -> &Punctuated<Expr, Token![,]>;
+}
+
+impl Call for ExprCall {
+    fn name(&self) -> String {
+        self.func.to_token_stream().to_string().replace(' ', "")
+    }
+    fn args(&self) -> &Punctuated<Expr, Token![,]> {
+        &self.args
+    }
+}
+
+impl Call for ExprMethodCall {
+    fn name(&self) -> String {
+        self.method.to_string()
+    }
+    fn args(&self) -> &Punctuated<Expr, Token![,]> {
+        &self.args
+    }
+}
+
+/// the `n`th argument of a call
+pub fn arg(call: &impl Call, n: usize) -> EvalResult<&Expr> {
+    call.args().get(n).ok_or_else(|| too_few(call, n))
+}
+
+fn too_few(call: &impl Call, n: usize) -> EvalError {
+    let kind = ErrorKind::TooFewArguments {
+        name: call.name(),
+        needed: n + 1,
+    };
+    EvalError::new(call, kind)
+}
+
+/// the `n`th of the float arguments of a call (see `accumulate_args`)
+/// if it's missing, the error is in one of the arguments or there aren't enough
+pub fn float_arg<T: Copy>(args: &[T], n: usize, call: &impl Call, lapis: &Lapis) -> EvalResult<T> {
+    if let Some(arg) = args.get(n) {
+        return Ok(*arg);
+    }
+    for arg in call.args() {
+        if let Err(err) = eval_float(arg, lapis)
+            && err.is_specific()
+        {
+            return Err(err);
+        }
+    }
+    Err(too_few(call, n))
+}
+
+/// add the rendered error to the output
+pub fn report(err: &EvalError, lapis: &Lapis, buffer: &mut String) {
+    let mut err = err.clone();
+    // only looked for when reported, since the error might not be
+    if let ErrorKind::UnknownVariable(k) = &err.kind
+        && err.suggestion.is_none()
+    {
+        err.suggestion = Some(match closest(k, var_names(lapis)) {
+            Some(n) => format!("did you mean `{n}`?"),
+            None => format!("declare it first with `let {k} = ...;`"),
+        });
+    }
+    buffer.push_str(&err.render(&lapis.data.source));
+}
+
+/// report the error (if any) and turn the result into an option
+pub fn or_report<T>(
+    r: EvalResult<T>,
+    expr: &Expr,
+    expected: Option<&'static str>,
+    lapis: &Lapis,
+    buffer: &mut String,
+) -> Option<T> {
+    match r {
+        Ok(v) => Some(v),
+        Err(err) => {
+            let err = finish(err, expr, lapis, expected);
+            report(&err, lapis, buffer);
+            None
+        }
+    }
+}
+
+/// make an error that no evaluator could explain say something about `expr`
+/// `expected` is the type that was needed, if there's one
+pub fn finish(
+    mut err: EvalError,
+    expr: &Expr,
+    lapis: &Lapis,
+    expected: Option<&'static str>,
+) -> EvalError {
+    if err.rank() > 0 {
+        return err.at(expr);
+    }
+    match (&mut err.kind, expr) {
+        (_, Expr::Paren(e)) => return finish(err, &e.expr, lapis, expected),
+        // no evaluator knows the function
+        (ErrorKind::UnknownFunction(name), expr) => {
+            err.code = match expr {
+                Expr::Call(call) if *name == call.name() => Some(call.func.to_token_stream()),
+                _ => name.parse().ok(),
+            };
+            return err;
+        }
+        (ErrorKind::UnknownMethod { ty, method }, expr) => {
+            if let Expr::MethodCall(call) = expr
+                && call.method == method
+            {
+                if let Expr::Path(path) = &*call.receiver
+                    && let Some(k) = path.path.get_ident().map(|k| k.to_string())
+                    && !lapis.data.contains(&k)
+                    && !SPECIAL_NAMES.contains(&k.as_str())
+                {
+                    return EvalError::new(path, ErrorKind::UnknownVariable(k));
+                }
+                *ty = type_of(&call.receiver, lapis);
+                err.code = Some(call.method.to_token_stream());
+            } else {
+                err.code = method.parse().ok();
+            }
+            return err;
+        }
+        (_, Expr::Field(field)) => {
+            if let Member::Named(member) = &field.member
+                && type_of(&field.base, lapis) == Some("an entity")
+            {
+                let kind = ErrorKind::UnknownField(member.to_string());
+                return EvalError::new(member, kind);
+            }
+        }
+        (_, Expr::Binary(bin)) => {
+            // values that can't be mixed
+            if let (Some(l), Some(r)) = (type_of(&bin.left, lapis), type_of(&bin.right, lapis)) {
+                let mixable = ["a float", "a net"];
+                if l != r && !(mixable.contains(&l) && mixable.contains(&r)) {
+                    let kind = ErrorKind::WrongType {
+                        expected: l,
+                        found: Some(r),
+                    };
+                    return EvalError::new(&bin.right, kind);
+                }
+            }
+        }
+        _ => {}
+    }
+    match (expected, type_of(expr, lapis)) {
+        (Some(expected), Some(found)) if expected != found => {
+            let kind = ErrorKind::WrongType {
+                expected,
+                found: Some(found),
+            };
+            EvalError::new(expr, kind)
+        }
+        _ => EvalError::new(expr, ErrorKind::Invalid),
+    }
+}
+
+/// the error for a path that isn't a variable of the expected type
+pub fn path_error(expr: &Expr, lapis: &Lapis, expected: &'static str) -> EvalError {
+    if let Expr::Path(path) = expr
+        && let Some(k) = path.path.get_ident().map(|k| k.to_string())
+        && !lapis.data.contains(&k)
+        && !SPECIAL_NAMES.contains(&k.as_str())
+        && constant_float(&k).is_none()
+    {
+        return EvalError::new(path, ErrorKind::UnknownVariable(k));
+    }
+    EvalError::mismatch(expected)
+}
+
+/// find the byte range of `code` in `source`, ignoring whitespace between tokens
+fn locate(source: &str, code: &TokenStream) -> Option<(usize, usize)> {
+    let mut pieces = Vec::new();
+    flatten(code.clone(), &mut pieces);
+    let first = pieces.first()?;
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    'search: for (start, _) in source.match_indices(first.as_str()) {
+        // don't match part of a longer name
+        if source[..start].ends_with(word) && first.starts_with(word) {
+            continue;
+        }
+        let mut pos = start;
+        for piece in &pieces {
+            pos += source[pos..].len() - source[pos..].trim_start().len();
+            if !source[pos..].starts_with(piece.as_str()) {
+                continue 'search;
+            }
+            pos += piece.len();
+            if piece.ends_with(word) && source[pos..].starts_with(word) {
+                continue 'search;
+            }
+        }
+        return Some((start, pos));
+    }
+    None
+}
+
+fn flatten(stream: TokenStream, pieces: &mut Vec<String>) {
+    for tt in stream {
+        match tt {
+            TokenTree::Group(g) => {
+                let (open, close) = match g.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    pieces.push(open.into());
+                }
+                flatten(g.stream(), pieces);
+                if !close.is_empty() {
+                    pieces.push(close.into());
+                }
+            }
+            TokenTree::Punct(p) => pieces.push(p.as_char().to_string()),
+            tt => pieces.push(tt.to_string()),
+        }
+    }
+}
+
+/// names that aren't variables but are valid on their own
+const SPECIAL_NAMES: &[&str] = &["time", "in_stream", "out_stream"];
+
+/// the closest name to `k` (if any is close enough to be a likely typo)
+fn closest<'a>(k: &str, names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max = (k.chars().count() / 3).max(1);
+    names
+        .filter(|n| *n != k)
+        .map(|n| (edit_distance(k, n), n))
+        .filter(|(d, _)| *d <= max)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, n)| n)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let up = row[j + 1];
+            row[j + 1] = (diag + (ca != *cb) as usize).min(up + 1).min(row[j] + 1);
+            diag = up;
+        }
+    }
+    row[b.len()]
+}
+
+fn var_names<'a>(lapis: &'a Lapis) -> impl Iterator<Item = &'a str> {
+    let d = &lapis.data;
+    d.fmap
+        .keys()
+        .chain(d.vmap.keys())
+        .chain(d.gmap.keys())
+        .chain(d.idmap.keys())
+        .chain(d.bmap.keys())
+        .chain(d.smap.keys())
+        .chain(d.wmap.keys())
+        .chain(d.seqmap.keys())
+        .chain(d.eventmap.keys())
+        .chain(d.srcmap.keys())
+        .chain(d.entitymap.keys())
+        .chain(d.atomic_table_map.keys())
+        .chain(d.string_map.keys())
+        .chain(d.fnmap.keys())
+        .chain(d.timer_map.keys())
+        .map(|k| k.as_str())
+        // temporaries aren't something to suggest
+        .filter(|k| !k.starts_with("__tmp"))
+}
+
+/// the type of a literal or variable (without evaluating anything)
+fn type_of(expr: &Expr, lapis: &Lapis) -> Option<&'static str> {
+    match expr {
+        Expr::Lit(expr) => match expr.lit {
+            Lit::Int(_) | Lit::Float(_) => Some("a float"),
+            Lit::Bool(_) => Some("a bool"),
+            Lit::Str(_) => Some("a string"),
+            _ => None,
+        },
+        Expr::Paren(expr) => type_of(&expr.expr, lapis),
+        Expr::Path(_) => lapis.data.var_type(&nth_path_ident(expr, 0)?),
+        _ => None,
+    }
+}
@@ -1,28 +1,30 @@
-use super::{Lapis, entities::*, helpers::*, ints::*, nets::*};
+use super::{Lapis, entities::*, errors::*, helpers::*, ints::*, nets::*};
+use crate::joints::joint_force;
 use bevy::prelude::*;
 use fundsp::hacker::*;
 use syn::*;
 
-pub fn eval_float_f32(expr: &Expr, lapis: &Lapis) -> Option<f32> {
-    Some(eval_float(expr, lapis)? as f32)
+pub fn eval_float_f32(expr: &Expr, lapis: &Lapis) -> EvalResult<f32> {
+    Ok(eval_float(expr, lapis)? as f32)
 }
 
-pub fn eval_float(expr: &Expr, lapis: &Lapis) -> Option<f64> {
+pub fn eval_float(expr: &Expr, lapis: &Lapis) -> EvalResult<f64> {
     match expr {
-        Expr::Call(expr) => call_float(expr, lapis),
+        Expr::Call(call) => call_float(call, lapis).map_err(|e| e.within(call)),
         Expr::Lit(expr) => lit_float(&expr.lit),
         Expr::Binary(expr) => bin_expr_float(expr, lapis),
         Expr::Paren(expr) => eval_float(&expr.expr, lapis),
-        Expr::Path(expr) => path_float(&expr.path, lapis),
+        Expr::Path(_) => path_float(expr, lapis),
         Expr::Unary(expr) => unary_float(expr, lapis),
-        Expr::MethodCall(expr) => method_float(expr, lapis),
+        Expr::MethodCall(call) => method_float(call, lapis).map_err(|e| e.within(call)),
         Expr::Index(expr) => index_float(expr, lapis),
         Expr::Field(expr) => field_float(expr, lapis),
-        _ => None,
+        _ => Err(EvalError::mismatch("a float")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn field_float(expr: &ExprField, lapis: &Lapis) -> Option<f64> {
+fn field_float(expr: &ExprField, lapis: &Lapis) -> EvalResult<f64> {
     if let Some(mut e) = path_lit_entity(&expr.base, lapis) {
         if e == Entity::PLACEHOLDER
             && let Ok(selected) = lapis.selected_query.single()
@@ -32,523 +34,569 @@ fn field_float(expr: &ExprField, lapis: &Lapis) -> Option<f64> {
         if let Member::Named(ident) = &expr.member {
             let trans = &lapis.trans_query;
             let f = match ident.to_string().as_str() {
-                "x" => Some(trans.get(e).ok()?.translation.x),
-                "y" => Some(trans.get(e).ok()?.translation.y),
-                "z" => Some(trans.get(e).ok()?.translation.z),
-                "rx" => Some(trans.get(e).ok()?.scale.x),
-                "ry" => Some(trans.get(e).ok()?.scale.y),
-                "rot" => Some(trans.get(e).ok()?.rotation.to_euler(EulerRot::XYZ).2),
-                "mass" => Some(lapis.mass_query.get(e).ok()?.0),
-                "vx" => Some(lapis.lin_velocity_query.get(e).ok()?.x),
-                "vy" => Some(lapis.lin_velocity_query.get(e).ok()?.y),
-                "va" => Some(lapis.ang_velocity_query.get(e).ok()?.0),
-                "restitution" => Some(lapis.restitution_query.get(e).ok()?.coefficient),
-                "lindamp" => Some(lapis.lin_damp_query.get(e).ok()?.0),
-                "angdamp" => Some(lapis.ang_damp_query.get(e).ok()?.0),
-                "inertia" => Some(lapis.inertia_query.get(e).ok()?.0),
+                "x" => trans.get(e).or_invalid()?.translation.x,
+                "y" => trans.get(e).or_invalid()?.translation.y,
+                "z" => trans.get(e).or_invalid()?.translation.z,
+                "rx" => trans.get(e).or_invalid()?.scale.x,
+                "ry" => trans.get(e).or_invalid()?.scale.y,
+                "rot" => {
+                    trans
+                        .get(e)
+                        .or_invalid()?
+                        .rotation
+                        .to_euler(EulerRot::XYZ)
+                        .2
+                }
+                "mass" => lapis.mass_query.get(e).or_invalid()?.0,
+                "vx" => lapis.lin_velocity_query.get(e).or_invalid()?.x,
+                "vy" => lapis.lin_velocity_query.get(e).or_invalid()?.y,
+                "va" => lapis.ang_velocity_query.get(e).or_invalid()?.0,
+                "restitution" => lapis.restitution_query.get(e).or_invalid()?.coefficient,
+                "lindamp" => lapis.lin_damp_query.get(e).or_invalid()?.0,
+                "angdamp" => lapis.ang_damp_query.get(e).or_invalid()?.0,
+                "inertia" => lapis.inertia_query.get(e).or_invalid()?.0,
                 "h" => {
-                    let mat_id = lapis.material_ids.get(e).ok()?;
-                    let mat = lapis.materials.get(mat_id)?;
+                    let mat_id = lapis.material_ids.get(e).or_invalid()?;
+                    let mat = lapis.materials.get(mat_id).or_invalid()?;
                     let hsla: Hsla = mat.color.into();
-                    Some(hsla.hue)
+                    hsla.hue
                 }
                 "s" => {
-                    let mat_id = lapis.material_ids.get(e).ok()?;
-                    let mat = lapis.materials.get(mat_id)?;
+                    let mat_id = lapis.material_ids.get(e).or_invalid()?;
+                    let mat = lapis.materials.get(mat_id).or_invalid()?;
                     let hsla: Hsla = mat.color.into();
-                    Some(hsla.saturation)
+                    hsla.saturation
                 }
                 "l" => {
-                    let mat_id = lapis.material_ids.get(e).ok()?;
-                    let mat = lapis.materials.get(mat_id)?;
+                    let mat_id = lapis.material_ids.get(e).or_invalid()?;
+                    let mat = lapis.materials.get(mat_id).or_invalid()?;
                     let hsla: Hsla = mat.color.into();
-                    Some(hsla.lightness)
+                    hsla.lightness
                 }
                 "a" => {
-                    let mat_id = lapis.material_ids.get(e).ok()?;
-                    let mat = lapis.materials.get(mat_id)?;
+                    let mat_id = lapis.material_ids.get(e).or_invalid()?;
+                    let mat = lapis.materials.get(mat_id).or_invalid()?;
                     let hsla: Hsla = mat.color.into();
-                    Some(hsla.alpha)
+                    hsla.alpha
+                }
+                "sides" => lapis
+                    .shape_query
+                    .get(e)
+                    .or_invalid()?
+                    .sides()
+                    .or_invalid()? as f32,
+                "cmx" => lapis.cm_query.get(e).or_invalid()?.x,
+                "cmy" => lapis.cm_query.get(e).or_invalid()?.y,
+                "friction" => {
+                    lapis
+                        .friction_query
+                        .get(e)
+                        .or_invalid()?
+                        .dynamic_coefficient
                 }
-                "sides" => Some(lapis.sides_query.get(e).ok()?.0 as f32),
-                "cmx" => Some(lapis.cm_query.get(e).ok()?.x),
-                "cmy" => Some(lapis.cm_query.get(e).ok()?.y),
-                "friction" => Some(lapis.friction_query.get(e).ok()?.dynamic_coefficient),
-                "tail" => Some(lapis.tail_query.get(e).ok()?.len as f32),
-                "layer" => Some(lapis.layer_query.get(e).ok()?.memberships.0.ilog2() as f32),
+                "tail" => lapis.tail_query.get(e).or_invalid()?.len as f32,
+                "layer" => lapis.layer_query.get(e).or_invalid()?.memberships.0.ilog2() as f32,
+                "voice_gain" => lapis.voice_query.get(e).or_invalid()?.gain,
+                "code_u_steps" => lapis.script_query.get(e).or_invalid()?.steps as f32,
                 // joint fields
                 "joint_type" => {
                     if lapis.fixed_query.contains(e) {
-                        Some(0.)
+                        0.
                     } else if lapis.distance_query.contains(e) {
-                        Some(1.)
+                        1.
                     } else if lapis.prismatic_query.contains(e) {
-                        Some(2.)
+                        2.
                     } else if lapis.revolute_query.contains(e) {
-                        Some(3.)
+                        3.
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "compliance" => {
                     if let Ok(j) = lapis.fixed_query.get(e) {
-                        Some(j.compliance)
+                        j.compliance
                     } else if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.compliance)
+                        j.compliance
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.compliance)
+                        j.compliance
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.compliance)
+                        j.compliance
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "anchor1x" => {
                     if let Ok(j) = lapis.fixed_query.get(e) {
-                        Some(j.local_anchor1.x)
+                        j.local_anchor1.x
                     } else if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.local_anchor1.x)
+                        j.local_anchor1.x
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.local_anchor1.x)
+                        j.local_anchor1.x
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.local_anchor1.x)
+                        j.local_anchor1.x
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "anchor1y" => {
                     if let Ok(j) = lapis.fixed_query.get(e) {
-                        Some(j.local_anchor1.y)
+                        j.local_anchor1.y
                     } else if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.local_anchor1.y)
+                        j.local_anchor1.y
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.local_anchor1.y)
+                        j.local_anchor1.y
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.local_anchor1.y)
+                        j.local_anchor1.y
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "anchor2x" => {
                     if let Ok(j) = lapis.fixed_query.get(e) {
-                        Some(j.local_anchor2.x)
+                        j.local_anchor2.x
                     } else if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.local_anchor2.x)
+                        j.local_anchor2.x
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.local_anchor2.x)
+                        j.local_anchor2.x
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.local_anchor2.x)
+                        j.local_anchor2.x
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "anchor2y" => {
                     if let Ok(j) = lapis.fixed_query.get(e) {
-                        Some(j.local_anchor2.y)
+                        j.local_anchor2.y
                     } else if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.local_anchor2.y)
+                        j.local_anchor2.y
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.local_anchor2.y)
+                        j.local_anchor2.y
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.local_anchor2.y)
+                        j.local_anchor2.y
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "min" => {
                     if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.length_limits?.min)
+                        j.length_limits.or_invalid()?.min
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.free_axis_limits?.min)
+                        j.free_axis_limits.or_invalid()?.min
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.angle_limit?.min)
+                        j.angle_limit.or_invalid()?.min
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
                 "max" => {
                     if let Ok(j) = lapis.distance_query.get(e) {
-                        Some(j.length_limits?.max)
+                        j.length_limits.or_invalid()?.max
                     } else if let Ok(j) = lapis.prismatic_query.get(e) {
-                        Some(j.free_axis_limits?.max)
+                        j.free_axis_limits.or_invalid()?.max
                     } else if let Ok(j) = lapis.revolute_query.get(e) {
-                        Some(j.angle_limit?.max)
+                        j.angle_limit.or_invalid()?.max
                     } else {
-                        None
+                        return Err(EvalError::invalid());
                     }
                 }
-                "rest" => Some(lapis.distance_query.get(e).ok()?.rest_length),
-                "axis_x" => Some(lapis.prismatic_query.get(e).ok()?.free_axis.x),
-                "axis_y" => Some(lapis.prismatic_query.get(e).ok()?.free_axis.y),
-                _ => None,
+                "rest" => lapis.distance_query.get(e).or_invalid()?.rest_length,
+                "axis_x" => lapis.prismatic_query.get(e).or_invalid()?.free_axis.x,
+                "axis_y" => lapis.prismatic_query.get(e).or_invalid()?.free_axis.y,
+                "motor_velocity" => lapis.motor_query.get(e).or_invalid()?.target_velocity,
+                "motor_position" => lapis.motor_query.get(e).or_invalid()?.target_position,
+                "motor_max" => lapis.motor_query.get(e).or_invalid()?.max_force,
+                "motor_stiffness" => lapis.motor_query.get(e).or_invalid()?.stiffness,
+                "motor_damping" => lapis.motor_query.get(e).or_invalid()?.damping,
+                "force" => joint_force(e, lapis).or_invalid()?.0,
+                "torque" => joint_force(e, lapis).or_invalid()?.1,
+                "break_force" => lapis.breakable_query.get(e).or_invalid()?.force,
+                "break_torque" => lapis.breakable_query.get(e).or_invalid()?.torque,
+                // (bool fields and unknown ones)
+                _ => return Err(EvalError::mismatch("a float")),
             };
-            return f.map(|x| x as f64);
+            return Ok(f as f64);
         }
     } else {
-        let base = nth_path_ident(&expr.base, 0)?;
+        let base = nth_path_ident(&expr.base, 0).or_mismatch("a float")?;
         if let Member::Named(ident) = &expr.member {
             let config = if base == "out_stream" {
-                lapis.out_stream_config.0.as_ref()?
+                lapis.out_stream_config.0.as_ref().or_invalid()?
             } else if base == "in_stream" {
-                lapis.in_stream_config.0.as_ref()?
+                lapis.in_stream_config.0.as_ref().or_invalid()?
             } else {
-                return None;
+                return Err(path_error(&expr.base, lapis, "a float"));
             };
             return match ident.to_string().as_str() {
-                "sr" => Some(config.sample_rate.0 as f64),
-                "chan" => Some(config.channels as f64),
+                "sr" => Ok(config.sample_rate.0 as f64),
+                "chan" => Ok(config.channels as f64),
                 "buffer" => {
                     if let cpal::BufferSize::Fixed(size) = config.buffer_size {
-                        return Some(size as f64);
+                        return Ok(size as f64);
                     }
-                    None
+                    Err(EvalError::invalid())
                 }
-                _ => None,
+                _ => Err(EvalError::mismatch("a float")),
             };
         }
     }
-    None
+    Err(EvalError::mismatch("a float"))
 }
 
-fn index_float(expr: &ExprIndex, lapis: &Lapis) -> Option<f64> {
-    let k = nth_path_ident(&expr.expr, 0)?;
+fn index_float(expr: &ExprIndex, lapis: &Lapis) -> EvalResult<f64> {
+    let k = nth_path_ident(&expr.expr, 0).or_mismatch("a float")?;
     let index = eval_usize(&expr.index, lapis)?;
-    Some(*lapis.data.vmap.get(&k)?.get(index)? as f64)
+    let Some(vec) = lapis.data.vmap.get(&k) else {
+        return Err(path_error(&expr.expr, lapis, "an array"));
+    };
+    let Some(v) = vec.get(index) else {
+        let kind = ErrorKind::OutOfRange {
+            what: "index",
+            index,
+            len: vec.len(),
+        };
+        return Err(EvalError::new(expr, kind));
+    };
+    Ok(*v as f64)
 }
 
-fn method_float(expr: &ExprMethodCall, lapis: &Lapis) -> Option<f64> {
-    if let Some(f) = eval_float(&expr.receiver, lapis) {
+fn method_float(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<f64> {
+    let receiver = eval_float(&expr.receiver, lapis);
+    if let Ok(f) = receiver {
         match expr.method.to_string().as_str() {
-            "floor" => Some(f.floor()),
-            "ceil" => Some(f.ceil()),
-            "round" => Some(f.round()),
-            "trunc" => Some(f.trunc()),
-            "fract" => Some(f.fract()),
-            "abs" => Some(f.abs()),
-            "signum" => Some(f.signum()),
+            "floor" => Ok(f.floor()),
+            "ceil" => Ok(f.ceil()),
+            "round" => Ok(f.round()),
+            "trunc" => Ok(f.trunc()),
+            "fract" => Ok(f.fract()),
+            "abs" => Ok(f.abs()),
+            "signum" => Ok(f.signum()),
             "copysign" => {
-                let sign = eval_float(expr.args.first()?, lapis)?;
-                Some(f.copysign(sign))
+                let sign = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.copysign(sign))
             }
             "div_euclid" => {
-                let rhs = eval_float(expr.args.first()?, lapis)?;
-                Some(f.div_euclid(rhs))
+                let rhs = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.div_euclid(rhs))
             }
             "rem_euclid" => {
-                let rhs = eval_float(expr.args.first()?, lapis)?;
-                Some(f.rem_euclid(rhs))
+                let rhs = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.rem_euclid(rhs))
             }
             "powi" => {
-                let n = eval_i32(expr.args.first()?, lapis)?;
-                Some(f.powi(n))
+                let n = eval_i32(arg(expr, 0)?, lapis)?;
+                Ok(f.powi(n))
             }
             "powf" => {
-                let n = eval_float(expr.args.first()?, lapis)?;
-                Some(f.powf(n))
+                let n = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.powf(n))
             }
-            "sqrt" => Some(f.sqrt()),
-            "exp" => Some(f.exp()),
-            "exp2" => Some(f.exp2()),
-            "ln" => Some(f.ln()),
+            "sqrt" => Ok(f.sqrt()),
+            "exp" => Ok(f.exp()),
+            "exp2" => Ok(f.exp2()),
+            "ln" => Ok(f.ln()),
             "log" => {
-                let base = eval_float(expr.args.first()?, lapis)?;
-                Some(f.log(base))
+                let base = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.log(base))
             }
-            "log2" => Some(f.log2()),
-            "log10" => Some(f.log10()),
-            "cbrt" => Some(f.cbrt()),
+            "log2" => Ok(f.log2()),
+            "log10" => Ok(f.log10()),
+            "cbrt" => Ok(f.cbrt()),
             "hypot" => {
-                let other = eval_float(expr.args.first()?, lapis)?;
-                Some(f.hypot(other))
+                let other = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.hypot(other))
             }
-            "sin" => Some(f.sin()),
-            "cos" => Some(f.cos()),
-            "tan" => Some(f.tan()),
-            "asin" => Some(f.asin()),
-            "acos" => Some(f.acos()),
-            "atan" => Some(f.atan()),
-            "sinh" => Some(f.sinh()),
-            "cosh" => Some(f.cosh()),
-            "tanh" => Some(f.tanh()),
-            "asinh" => Some(f.asinh()),
-            "acosh" => Some(f.acosh()),
-            "atanh" => Some(f.atanh()),
+            "sin" => Ok(f.sin()),
+            "cos" => Ok(f.cos()),
+            "tan" => Ok(f.tan()),
+            "asin" => Ok(f.asin()),
+            "acos" => Ok(f.acos()),
+            "atan" => Ok(f.atan()),
+            "sinh" => Ok(f.sinh()),
+            "cosh" => Ok(f.cosh()),
+            "tanh" => Ok(f.tanh()),
+            "asinh" => Ok(f.asinh()),
+            "acosh" => Ok(f.acosh()),
+            "atanh" => Ok(f.atanh()),
             "atan2" => {
-                let other = eval_float(expr.args.first()?, lapis)?;
-                Some(f.atan2(other))
+                let other = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.atan2(other))
             }
-            "recip" => Some(f.recip()),
-            "to_degrees" => Some(f.to_degrees()),
-            "to_radians" => Some(f.to_radians()),
+            "recip" => Ok(f.recip()),
+            "to_degrees" => Ok(f.to_degrees()),
+            "to_radians" => Ok(f.to_radians()),
             "max" => {
-                let other = eval_float(expr.args.first()?, lapis)?;
-                Some(f.max(other))
+                let other = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.max(other))
             }
             "min" => {
-                let other = eval_float(expr.args.first()?, lapis)?;
-                Some(f.min(other))
+                let other = eval_float(arg(expr, 0)?, lapis)?;
+                Ok(f.min(other))
             }
-            _ => None,
+            _ => Err(EvalError::unknown_method()),
         }
     } else if let Some(k) = nth_path_ident(&expr.receiver, 0) {
+        if k == "time" {
+            return match expr.method.to_string().as_str() {
+                "delta" => Ok(lapis.time.delta_secs_f64()),
+                "elapsed_wrapped" => Ok(lapis.time.elapsed_secs_wrapped_f64()),
+                "elapsed" => Ok(lapis.time.elapsed_secs_f64()),
+                _ => Err(EvalError::unknown_method()),
+            };
+        } else if !lapis.data.contains(&k) {
+            let err = path_error(&expr.receiver, lapis, "a float");
+            return Err(EvalError::unknown_method().or(err));
+        }
         match expr.method.to_string().as_str() {
             "value" => {
-                let shared = &mut lapis.data.smap.get(&k)?;
-                Some(shared.value() as f64)
-            }
-            "delta" => {
-                if k == "time" {
-                    return Some(lapis.time.delta_secs_f64());
-                }
-                None
-            }
-            "elapsed_wrapped" => {
-                if k == "time" {
-                    return Some(lapis.time.elapsed_secs_wrapped_f64());
-                }
-                None
-            }
-            "elapsed" => {
-                if k == "time" {
-                    return Some(lapis.time.elapsed_secs_f64());
-                }
-                None
+                let shared = lapis.data.smap.get(&k).or_mismatch("a shared")?;
+                Ok(shared.value() as f64)
             }
             "at" => {
                 if let Some(wave) = lapis.data.wmap.get(&k) {
-                    let arg0 = expr.args.first()?;
-                    let arg1 = expr.args.get(1)?;
-                    let chan = eval_usize(arg0, lapis)?;
-                    let index = eval_usize(arg1, lapis)?;
-                    if chan < wave.channels() && index < wave.len() {
-                        return Some(wave.at(chan, index) as f64);
+                    let chan = eval_usize(arg(expr, 0)?, lapis)?;
+                    let index = eval_usize(arg(expr, 1)?, lapis)?;
+                    if chan >= wave.channels() {
+                        let kind = ErrorKind::OutOfRange {
+                            what: "channel",
+                            index: chan,
+                            len: wave.channels(),
+                        };
+                        return Err(EvalError::new(expr, kind));
+                    } else if index >= wave.len() {
+                        let kind = ErrorKind::OutOfRange {
+                            what: "index",
+                            index,
+                            len: wave.len(),
+                        };
+                        return Err(EvalError::new(expr, kind));
                     }
+                    Ok(wave.at(chan, index) as f64)
                 } else if let Some(table) = lapis.data.atomic_table_map.get(&k) {
-                    let i = eval_usize(expr.args.first()?, lapis)?;
-                    if i < table.len() {
-                        return Some(table.at(i) as f64);
+                    let i = eval_usize(arg(expr, 0)?, lapis)?;
+                    if i >= table.len() {
+                        let kind = ErrorKind::OutOfRange {
+                            what: "index",
+                            index: i,
+                            len: table.len(),
+                        };
+                        return Err(EvalError::new(expr, kind));
                     }
+                    Ok(table.at(i) as f64)
+                } else {
+                    Err(EvalError::unknown_method())
                 }
-                None
             }
             "sample_rate" => {
-                let wave = lapis.data.wmap.get(&k)?;
-                Some(wave.sample_rate())
+                let wave = lapis.data.wmap.get(&k).or_mismatch("a wave")?;
+                Ok(wave.sample_rate())
             }
             "channels" => {
-                let wave = lapis.data.wmap.get(&k)?;
-                Some(wave.channels() as f64)
+                let wave = lapis.data.wmap.get(&k).or_mismatch("a wave")?;
+                Ok(wave.channels() as f64)
             }
             "len" | "length" => {
                 if let Some(wave) = lapis.data.wmap.get(&k) {
-                    Some(wave.len() as f64)
+                    Ok(wave.len() as f64)
                 } else if let Some(table) = lapis.data.atomic_table_map.get(&k) {
-                    Some(table.len() as f64)
+                    Ok(table.len() as f64)
                 } else {
-                    let vec = lapis.data.vmap.get(&k)?;
-                    Some(vec.len() as f64)
+                    let vec = lapis.data.vmap.get(&k).or_mismatch("an array")?;
+                    Ok(vec.len() as f64)
                 }
             }
             "duration" => {
-                let wave = lapis.data.wmap.get(&k)?;
-                Some(wave.duration())
+                let wave = lapis.data.wmap.get(&k).or_mismatch("a wave")?;
+                Ok(wave.duration())
             }
             "amplitude" => {
-                let wave = lapis.data.wmap.get(&k)?;
-                Some(wave.amplitude() as f64)
+                let wave = lapis.data.wmap.get(&k).or_mismatch("a wave")?;
+                Ok(wave.amplitude() as f64)
             }
             "size" => {
-                let net = lapis.data.gmap.get(&k)?;
-                Some(net.size() as f64)
+                let net = lapis.data.gmap.get(&k).or_mismatch("a net")?;
+                Ok(net.size() as f64)
             }
             "inputs" => {
-                let net = lapis.data.gmap.get(&k)?;
-                Some(net.inputs() as f64)
+                let net = lapis.data.gmap.get(&k).or_mismatch("a net")?;
+                Ok(net.inputs() as f64)
             }
             "outputs" => {
-                let net = lapis.data.gmap.get(&k)?;
-                Some(net.outputs() as f64)
+                let net = lapis.data.gmap.get(&k).or_mismatch("a net")?;
+                Ok(net.outputs() as f64)
             }
             "inputs_in" => {
-                let net = lapis.data.gmap.get(&k)?;
-                let id = eval_path_nodeid(expr.args.first()?, lapis)?;
-                if net.contains(id) {
-                    Some(net.inputs_in(id) as f64)
-                } else {
-                    None
-                }
+                let net = lapis.data.gmap.get(&k).or_mismatch("a net")?;
+                let id = eval_path_nodeid(arg(expr, 0)?, lapis)?;
+                net.contains(id)
+                    .then(|| net.inputs_in(id) as f64)
+                    .or_invalid()
             }
             "outputs_in" => {
-                let net = lapis.data.gmap.get(&k)?;
-                let id = eval_path_nodeid(expr.args.first()?, lapis)?;
-                if net.contains(id) {
-                    Some(net.outputs_in(id) as f64)
-                } else {
-                    None
-                }
+                let net = lapis.data.gmap.get(&k).or_mismatch("a net")?;
+                let id = eval_path_nodeid(arg(expr, 0)?, lapis)?;
+                net.contains(id)
+                    .then(|| net.outputs_in(id) as f64)
+                    .or_invalid()
             }
             "first" => {
-                let vec = &mut lapis.data.vmap.get(&k)?;
-                Some(*vec.first()? as f64)
+                let vec = lapis.data.vmap.get(&k).or_mismatch("an array")?;
+                Ok(*vec.first().or_invalid()? as f64)
             }
             "last" => {
-                let vec = &mut lapis.data.vmap.get(&k)?;
-                Some(*vec.last()? as f64)
+                let vec = lapis.data.vmap.get(&k).or_mismatch("an array")?;
+                Ok(*vec.last().or_invalid()? as f64)
             }
             "get" => {
-                let index = eval_usize(expr.args.first()?, lapis)?;
-                let vec = &mut lapis.data.vmap.get(&k)?;
-                Some(*vec.get(index)? as f64)
+                let index = eval_usize(arg(expr, 0)?, lapis)?;
+                let vec = lapis.data.vmap.get(&k).or_mismatch("an array")?;
+                Ok(*vec.get(index).or_invalid()? as f64)
             }
-            _ => None,
+            _ => Err(EvalError::unknown_method()),
         }
     } else {
-        None
+        // an error in the receiver is the one to report if it says more
+        let err = EvalError::unknown_method();
+        Err(match receiver {
+            Err(e) => err.or(e),
+            Ok(_) => err,
+        })
     }
 }
 
-fn lit_float(expr: &Lit) -> Option<f64> {
+fn lit_float(expr: &Lit) -> EvalResult<f64> {
     match expr {
-        Lit::Float(expr) => expr.base10_parse::<f64>().ok(),
-        Lit::Int(expr) => expr.base10_parse::<f64>().ok(),
-        _ => None,
+        Lit::Float(expr) => expr.base10_parse::<f64>().or_invalid(),
+        Lit::Int(expr) => expr.base10_parse::<f64>().or_invalid(),
+        _ => Err(EvalError::mismatch("a float")),
     }
 }
 
-fn bin_expr_float(expr: &ExprBinary, lapis: &Lapis) -> Option<f64> {
+fn bin_expr_float(expr: &ExprBinary, lapis: &Lapis) -> EvalResult<f64> {
     let left = eval_float(&expr.left, lapis)?;
     let right = eval_float(&expr.right, lapis)?;
     match expr.op {
-        BinOp::Sub(_) => Some(left - right),
-        BinOp::Div(_) => Some(left / right),
-        BinOp::Mul(_) => Some(left * right),
-        BinOp::Add(_) => Some(left + right),
-        BinOp::Rem(_) => Some(left % right),
-        _ => None,
+        BinOp::Sub(_) => Ok(left - right),
+        BinOp::Div(_) => Ok(left / right),
+        BinOp::Mul(_) => Ok(left * right),
+        BinOp::Add(_) => Ok(left + right),
+        BinOp::Rem(_) => Ok(left % right),
+        _ => Err(EvalError::mismatch("a float")),
     }
 }
 
-fn path_float(expr: &Path, lapis: &Lapis) -> Option<f64> {
-    let k = expr.segments.first()?.ident.to_string();
+fn path_float(expr: &Expr, lapis: &Lapis) -> EvalResult<f64> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a float")?;
     if let Some(c) = constant_float(&k) {
-        Some(c)
+        Ok(c)
+    } else if let Some(f) = lapis.data.fmap.get(&k) {
+        Ok(*f)
     } else {
-        lapis.data.fmap.get(&k).copied()
+        Err(path_error(expr, lapis, "a float"))
     }
 }
 
-fn unary_float(expr: &ExprUnary, lapis: &Lapis) -> Option<f64> {
+fn unary_float(expr: &ExprUnary, lapis: &Lapis) -> EvalResult<f64> {
     match expr.op {
-        UnOp::Neg(_) => Some(-eval_float(&expr.expr, lapis)?),
-        _ => None,
+        UnOp::Neg(_) => Ok(-eval_float(&expr.expr, lapis)?),
+        _ => Err(EvalError::mismatch("a float")),
     }
 }
 
-fn call_float(expr: &ExprCall, lapis: &Lapis) -> Option<f64> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn call_float(expr: &ExprCall, lapis: &Lapis) -> EvalResult<f64> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("a float")?;
     if func == "time" {
         let epoch = std::time::UNIX_EPOCH;
         let now = std::time::SystemTime::now();
-        return Some(now.duration_since(epoch).ok()?.as_millis() as f64);
+        return Ok(now.duration_since(epoch).or_invalid()?.as_millis() as f64);
     }
     let args = accumulate_args_f64(&expr.args, lapis);
+    let a = |n| float_arg(&args, n, expr, lapis);
     match func.as_str() {
-        "a_weight" => Some(a_weight(*args.first()?)),
-        "abs" => Some(abs(*args.first()?)),
-        "amp_db" => Some(amp_db(*args.first()?)),
-        "atan" => Some(atan(*args.first()?)),
-        "bpm_hz" => Some(bpm_hz(*args.first()?)),
-        "ceil" => Some(ceil(*args.first()?)),
-        "clamp" => Some(clamp(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "clamp01" => Some(clamp01(*args.first()?)),
-        "clamp11" => Some(clamp11(*args.first()?)),
-        "cos" => Some(cos(*args.first()?)),
-        "cos_hz" => Some(cos_hz(*args.first()?, *args.get(1)?)),
-        "cubed" => Some(cubed(*args.first()?)),
-        "db_amp" => Some(db_amp(*args.first()?)),
-        "delerp" => Some(delerp(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "delerp11" => Some(delerp11(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "dexerp" => Some(dexerp(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "dexerp11" => Some(dexerp11(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "dissonance" => Some(dissonance(*args.first()?, *args.get(1)?)),
-        "dissonance_max" => Some(dissonance_max(*args.first()?)),
-        "downarc" => Some(downarc(*args.first()?)),
-        "ease_noise" => None, //TODO
-        "exp" => Some(exp(*args.first()?)),
-        "exp2" => Some(exp2(*args.first()?)),
-        "exp10" => Some(exp10(*args.first()?)),
-        "floor" => Some(floor(*args.first()?)),
-        "fractal_ease_noise" => None, //TODO
+        "tempo" if args.is_empty() => Ok(lapis.data.timers.tempo),
+        "a_weight" => Ok(a_weight(a(0)?)),
+        "abs" => Ok(abs(a(0)?)),
+        "amp_db" => Ok(amp_db(a(0)?)),
+        "atan" => Ok(atan(a(0)?)),
+        "bpm_hz" => Ok(bpm_hz(a(0)?)),
+        "ceil" => Ok(ceil(a(0)?)),
+        "clamp" => Ok(clamp(a(0)?, a(1)?, a(2)?)),
+        "clamp01" => Ok(clamp01(a(0)?)),
+        "clamp11" => Ok(clamp11(a(0)?)),
+        "cos" => Ok(cos(a(0)?)),
+        "cos_hz" => Ok(cos_hz(a(0)?, a(1)?)),
+        "cubed" => Ok(cubed(a(0)?)),
+        "db_amp" => Ok(db_amp(a(0)?)),
+        "delerp" => Ok(delerp(a(0)?, a(1)?, a(2)?)),
+        "delerp11" => Ok(delerp11(a(0)?, a(1)?, a(2)?)),
+        "dexerp" => Ok(dexerp(a(0)?, a(1)?, a(2)?)),
+        "dexerp11" => Ok(dexerp11(a(0)?, a(1)?, a(2)?)),
+        "dissonance" => Ok(dissonance(a(0)?, a(1)?)),
+        "dissonance_max" => Ok(dissonance_max(a(0)?)),
+        "downarc" => Ok(downarc(a(0)?)),
+        "ease_noise" => Err(EvalError::invalid()), //TODO
+        "exp" => Ok(exp(a(0)?)),
+        "exp2" => Ok(exp2(a(0)?)),
+        "exp10" => Ok(exp10(a(0)?)),
+        "floor" => Ok(floor(a(0)?)),
+        "fractal_ease_noise" => Err(EvalError::invalid()), //TODO
         "fractal_noise" => {
-            let seed = eval_u64(expr.args.first()?, lapis)?;
-            let octaves = eval_i64(expr.args.get(1)?, lapis)?;
-            let roughness = eval_float(expr.args.get(2)?, lapis)?;
-            let x = eval_float(expr.args.get(3)?, lapis)?;
-            Some(fractal_noise(seed, octaves, roughness, x))
+            let seed = eval_u64(arg(expr, 0)?, lapis)?;
+            let octaves = eval_i64(arg(expr, 1)?, lapis)?;
+            let roughness = eval_float(arg(expr, 2)?, lapis)?;
+            let x = eval_float(arg(expr, 3)?, lapis)?;
+            Ok(fractal_noise(seed, octaves, roughness, x))
         }
-        "hash1" | "hash2" => None, //TODO
-        "identity" => None,        //TODO not useful here
-        "lerp" => Some(lerp(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "lerp11" => Some(lerp11(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "log" => Some(log(*args.first()?)),
-        "log2" => Some(log2(*args.first()?)),
-        "log10" => Some(log10(*args.first()?)),
-        "m_weight" => Some(m_weight(*args.first()?)),
-        "max" => Some(max(*args.first()?, *args.get(1)?)),
-        "midi_hz" => Some(midi_hz(*args.first()?)),
-        "min" => Some(min(*args.first()?, *args.get(1)?)),
-        "pow" => Some(pow(*args.first()?, *args.get(1)?)),
-        "rnd1" => Some(rnd1(eval_u64(expr.args.first()?, lapis)?)),
-        "rnd2" => Some(rnd2(eval_u64(expr.args.first()?, lapis)?)),
-        "round" => Some(round(*args.first()?)),
-        "semitone_ratio" => Some(semitone_ratio(*args.first()?)),
-        "signum" => Some(signum(*args.first()?)),
-        "sin" => Some(sin(*args.first()?)),
-        "sin_hz" => Some(sin_hz(*args.first()?, *args.get(1)?)),
-        "sine_ease" => Some(sine_ease(*args.first()?)),
-        "smooth3" => Some(smooth3(*args.first()?)),
-        "smooth5" => Some(smooth5(*args.first()?)),
-        "smooth7" => Some(smooth7(*args.first()?)),
-        "smooth9" => Some(smooth9(*args.first()?)),
-        "softexp" => Some(softexp(*args.first()?)),
-        "softmix" => Some(softmix(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "softsign" => Some(softsign(*args.first()?)),
-        "spline" => Some(spline(
-            *args.first()?,
-            *args.get(1)?,
-            *args.get(2)?,
-            *args.get(3)?,
-            *args.get(4)?,
-        )),
-        "spline_mono" => Some(spline_mono(
-            *args.first()?,
-            *args.get(1)?,
-            *args.get(2)?,
-            *args.get(3)?,
-            *args.get(4)?,
-        )),
+        "hash1" | "hash2" => Err(EvalError::invalid()), //TODO
+        "identity" => Err(EvalError::invalid()),        //TODO not useful here
+        "lerp" => Ok(lerp(a(0)?, a(1)?, a(2)?)),
+        "lerp11" => Ok(lerp11(a(0)?, a(1)?, a(2)?)),
+        "log" => Ok(log(a(0)?)),
+        "log2" => Ok(log2(a(0)?)),
+        "log10" => Ok(log10(a(0)?)),
+        "m_weight" => Ok(m_weight(a(0)?)),
+        "max" => Ok(max(a(0)?, a(1)?)),
+        "midi_hz" => Ok(midi_hz(a(0)?)),
+        "min" => Ok(min(a(0)?, a(1)?)),
+        "pow" => Ok(pow(a(0)?, a(1)?)),
+        "rnd1" => Ok(rnd1(eval_u64(arg(expr, 0)?, lapis)?)),
+        "rnd2" => Ok(rnd2(eval_u64(arg(expr, 0)?, lapis)?)),
+        "round" => Ok(round(a(0)?)),
+        "semitone_ratio" => Ok(semitone_ratio(a(0)?)),
+        "signum" => Ok(signum(a(0)?)),
+        "sin" => Ok(sin(a(0)?)),
+        "sin_hz" => Ok(sin_hz(a(0)?, a(1)?)),
+        "sine_ease" => Ok(sine_ease(a(0)?)),
+        "smooth3" => Ok(smooth3(a(0)?)),
+        "smooth5" => Ok(smooth5(a(0)?)),
+        "smooth7" => Ok(smooth7(a(0)?)),
+        "smooth9" => Ok(smooth9(a(0)?)),
+        "softexp" => Ok(softexp(a(0)?)),
+        "softmix" => Ok(softmix(a(0)?, a(1)?, a(2)?)),
+        "softsign" => Ok(softsign(a(0)?)),
+        "spline" => Ok(spline(a(0)?, a(1)?, a(2)?, a(3)?, a(4)?)),
+        "spline_mono" => Ok(spline_mono(a(0)?, a(1)?, a(2)?, a(3)?, a(4)?)),
         "spline_noise" => {
-            let seed = eval_u64(expr.args.first()?, lapis)?;
-            let x = eval_float(expr.args.get(1)?, lapis)?;
-            Some(spline_noise(seed, x))
+            let seed = eval_u64(arg(expr, 0)?, lapis)?;
+            let x = eval_float(arg(expr, 1)?, lapis)?;
+            Ok(spline_noise(seed, x))
         }
-        "sqr_hz" => Some(sqr_hz(*args.first()?, *args.get(1)?)),
-        "sqrt" => Some(sqrt(*args.first()?)),
-        "sqared" => Some(squared(*args.first()?)),
-        "tan" => Some(tan(*args.first()?)),
-        "tanh" => Some(tanh(*args.first()?)),
-        "tri_hz" => Some(tri_hz(*args.first()?, *args.get(1)?)),
-        "uparc" => Some(uparc(*args.first()?)),
-        "xerp" => Some(xerp(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "xerp11" => Some(xerp11(*args.first()?, *args.get(1)?, *args.get(2)?)),
-        "wrap" => Some(wrap(*args.first()?)),
-        "mirror" => Some(mirror(*args.first()?)),
-        "Some" => Some(*args.first()?),
-        _ => None,
+        "sqr_hz" => Ok(sqr_hz(a(0)?, a(1)?)),
+        "sqrt" => Ok(sqrt(a(0)?)),
+        "sqared" => Ok(squared(a(0)?)),
+        "tan" => Ok(tan(a(0)?)),
+        "tanh" => Ok(tanh(a(0)?)),
+        "tri_hz" => Ok(tri_hz(a(0)?, a(1)?)),
+        "uparc" => Ok(uparc(a(0)?)),
+        "xerp" => Ok(xerp(a(0)?, a(1)?, a(2)?)),
+        "xerp11" => Ok(xerp11(a(0)?, a(1)?, a(2)?)),
+        "wrap" => Ok(wrap(a(0)?)),
+        "mirror" => Ok(mirror(a(0)?)),
+        "Some" => Ok(a(0)?),
+        _ => Err(EvalError::unknown_function()),
     }
 }
 
-fn constant_float(s: &str) -> Option<f64> {
+pub fn constant_float(s: &str) -> Option<f64> {
     match s {
         "E" => Some(std::f64::consts::E),
         "FRAC_1_PI" => Some(std::f64::consts::FRAC_1_PI),
@@ -580,16 +628,43 @@ fn constant_float(s: &str) -> Option<f64> {
     }
 }
 
-pub fn float_bin_assign(expr: &ExprBinary, lapis: &mut Lapis) -> Option<()> {
-    let right = eval_float(&expr.right, lapis)?;
-    let k = nth_path_ident(&expr.left, 0)?;
+pub fn float_bin_assign(expr: &ExprBinary, lapis: &mut Lapis) -> EvalResult<()> {
+    if !matches!(
+        expr.op,
+        BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+    ) {
+        return Err(EvalError::mismatch("a float"));
+    }
+    let k = nth_path_ident(&expr.left, 0).or_mismatch("a float")?;
+    if !lapis.data.fmap.contains_key(&k) {
+        let err = match lapis.data.var_type(&k) {
+            Some(found) => EvalError::new(
+                &expr.left,
+                ErrorKind::WrongType {
+                    expected: "a float",
+                    found: Some(found),
+                },
+            ),
+            None => path_error(&expr.left, lapis, "a float"),
+        };
+        return Err(err);
+    }
+    let right = eval_float(&expr.right, lapis)
+        .map_err(|e| finish(e, &expr.right, lapis, Some("a float")))?;
+    let Some(left) = lapis.data.fmap.get_mut(&k) else {
+        return Err(EvalError::invalid());
+    };
     match expr.op {
-        BinOp::AddAssign(_) => *lapis.data.fmap.get_mut(&k)? += right,
-        BinOp::SubAssign(_) => *lapis.data.fmap.get_mut(&k)? -= right,
-        BinOp::MulAssign(_) => *lapis.data.fmap.get_mut(&k)? *= right,
-        BinOp::DivAssign(_) => *lapis.data.fmap.get_mut(&k)? /= right,
-        BinOp::RemAssign(_) => *lapis.data.fmap.get_mut(&k)? %= right,
+        BinOp::AddAssign(_) => *left += right,
+        BinOp::SubAssign(_) => *left -= right,
+        BinOp::MulAssign(_) => *left *= right,
+        BinOp::DivAssign(_) => *left /= right,
+        BinOp::RemAssign(_) => *left %= right,
         _ => {}
     }
-    None
+    Ok(())
 }
@@ -0,0 +1,440 @@
+use super::{Lapis, bools::*, branches::*, errors::*, helpers::*, statements::*};
+use proc_macro2::{Delimiter, Group, Punct, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::{collections::HashMap, rc::Rc};
+use syn::{visit::Visit, visit_mut::VisitMut, *};
+
+/// calls nested deeper than this are refused (runaway recursion)
+pub const CALL_DEPTH_LIMIT: usize = 256;
+
+/// a user defined function
+/// the body is kept as source (it's shown and saved with scenes),
+/// the parsed body is kept in `FnCache`
+#[derive(Clone, Debug)]
+pub struct LapisFn {
+    pub params: Vec<String>,
+    pub body: String,
+}
+
+impl LapisFn {
+    /// the source of this function's definition
+    pub fn source(&self, name: &str) -> String {
+        format!("fn {name}({}) {}", self.params.join(", "), self.body)
+    }
+}
+
+/// parsed function bodies (with the source they were parsed from), by name
+/// (syn trees aren't `Send`, so this is a non-send resource)
+#[derive(Default)]
+pub struct FnCache(HashMap<String, (String, Rc<Block>)>);
+
+impl FnCache {
+    /// the parsed body of `name`, parsing it again if its source changed
+    /// (e.g. when it's loaded with a scene)
+    fn body(&mut self, name: &str, source: &str) -> Option<Rc<Block>> {
+        if let Some((cached, block)) = self.0.get(name)
+            && cached == source
+        {
+            return Some(block.clone());
+        }
+        let block = Rc::new(parse_str::<Block>(source).ok()?);
+        self.0
+            .insert(name.to_string(), (source.to_string(), block.clone()));
+        Some(block)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+        self.0.shrink_to_fit();
+    }
+}
+
+pub fn define_fn(item: &ItemFn, lapis: &mut Lapis, buffer: &mut String) {
+    let mut params = Vec::new();
+    for arg in &item.sig.inputs {
+        if let FnArg::Typed(arg) = arg
+            && let Some(p) = pat_ident(&arg.pat)
+        {
+            params.push(p);
+        } else {
+            buffer.push_str("\n// error: function parameters must be plain names");
+            return;
+        }
+    }
+    let k = item.sig.ident.to_string();
+    let body = item.block.to_token_stream().to_string();
+    let block = Rc::new((*item.block).clone());
+    lapis.fn_cache.0.insert(k.clone(), (body.clone(), block));
+    lapis.drop(&k);
+    lapis.data.fnmap.insert(k, LapisFn { params, body });
+}
+
+pub fn eval_return(expr: &ExprReturn, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    if let Some(e) = &expr.expr
+        && let Some(slot) = lapis.data.fn_returns.last().cloned()
+    {
+        with_expanded(e, lapis, buffer, |e, lapis, buffer| {
+            assign_return(slot, e, lapis, buffer);
+        });
+    }
+    Flow::Return
+}
+
+/// store the value of `expr` in the return slot
+/// (things without a value, like `e.despawn()`, just don't return anything)
+fn assign_return(slot: String, expr: &Expr, lapis: &mut Lapis, buffer: &mut String) {
+    match assign_value(slot, expr, lapis) {
+        Err(err) if err.kind == ErrorKind::NoValue => {}
+        // not a value, but maybe something to do (like `tempo(120)`)
+        Err(err) if err.is_mismatch() => {
+            eval_expr_stmt(expr, lapis, buffer);
+        }
+        Err(err) => {
+            let err = finish(err, expr, lapis, None);
+            report(&err, lapis, buffer);
+        }
+        Ok(()) => {}
+    }
+}
+
+/// run a user function, returns the name of the temporary variable holding
+/// its return value (None if it didn't return anything)
+fn call_fn(call: &ExprCall, name: &str, lapis: &mut Lapis, buffer: &mut String) -> Option<String> {
+    let f = lapis.data.fnmap.get(name)?.clone();
+    let args = &call.args;
+    if args.len() != f.params.len() {
+        let kind = ErrorKind::Arity {
+            name: name.to_string(),
+            expected: f.params.len(),
+            given: args.len(),
+        };
+        let params = f.params.join(", ");
+        let err = EvalError::new(call, kind);
+        report(
+            &err.suggest(format!("it's defined as `fn {name}({params})`")),
+            lapis,
+            buffer,
+        );
+        return None;
+    }
+    if lapis.data.call_depth >= CALL_DEPTH_LIMIT {
+        buffer.push_str(&format!(
+            "\n// error: call depth limit ({CALL_DEPTH_LIMIT}) reached in `{name}`"
+        ));
+        return None;
+    }
+    let block = lapis.fn_cache.body(name, &f.body)?;
+    // evaluate all arguments before binding any of them
+    let mut values: Vec<String> = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        let tmp = lapis.data.temp_name();
+        let slot = tmp.clone();
+        let r = assign_value(slot, arg, lapis);
+        if or_report(r, arg, None, lapis, buffer).is_none() {
+            buffer.push_str(&format!("\n// (in argument {} of `{name}`)", i + 1));
+            for tmp in values {
+                lapis.drop(&tmp);
+            }
+            return None;
+        }
+        values.push(tmp);
+    }
+    // bind the arguments (saving whatever had the same names)
+    let mut saved = Vec::new();
+    for (param, tmp) in f.params.iter().zip(values) {
+        saved.push(lapis.data.take(param));
+        let value = lapis.data.take(&tmp);
+        lapis.data.put(param, value);
+    }
+    let ret = lapis.data.temp_name();
+    lapis.data.fn_returns.push(ret.clone());
+    lapis.data.fn_locals.push(Vec::new());
+    lapis.data.call_depth += 1;
+    // errors in the body quote the body
+    let caller = std::mem::replace(&mut lapis.data.source, f.body.clone());
+    // a trailing expression (without a semicolon) is the return value
+    let stmts = &block.stmts;
+    match stmts.last() {
+        Some(Stmt::Expr(tail, None)) if is_value(tail) => {
+            if eval_stmts(&stmts[..stmts.len() - 1], lapis, buffer) == Flow::Next {
+                let slot = ret.clone();
+                with_expanded(tail, lapis, buffer, |e, lapis, buffer| {
+                    assign_return(slot, e, lapis, buffer);
+                });
+            }
+        }
+        _ => {
+            eval_stmts(stmts, lapis, buffer);
+        }
+    }
+    lapis.data.source = caller;
+    lapis.data.call_depth -= 1;
+    lapis.data.fn_returns.pop();
+    // what `let` shadowed, then what the parameters did
+    let locals = lapis.data.fn_locals.pop().unwrap_or_default();
+    for (k, old) in locals.into_iter().rev() {
+        lapis.data.put(&k, old);
+    }
+    for (param, old) in f.params.iter().zip(saved) {
+        lapis.data.put(param, old);
+    }
+    lapis.data.contains(&ret).then_some(ret)
+}
+
+/// whether a trailing expression gives a value (rather than being a statement)
+pub fn is_value(expr: &Expr) -> bool {
+    !matches!(
+        expr,
+        Expr::If(_)
+            | Expr::Block(_)
+            | Expr::ForLoop(_)
+            | Expr::While(_)
+            | Expr::Loop(_)
+            | Expr::Assign(_)
+            | Expr::Break(_)
+            | Expr::Continue(_)
+            | Expr::Return(_)
+    )
+}
+
+/// replaces calls to user functions, and `if`/`match` expressions,
+/// with the variables holding their results
+struct Expander<'a, 'w, 's> {
+    lapis: &'a mut Lapis<'w, 's>,
+    buffer: &'a mut String,
+    temps: Vec<String>,
+}
+
+impl VisitMut for Expander<'_, '_, '_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        // only the branch taken is evaluated
+        if matches!(expr, Expr::If(_) | Expr::Match(_)) {
+            let tmp = branch_value(expr, self.lapis, self.buffer);
+            *expr = self.temp_path(tmp);
+            return;
+        }
+        // these are evaluated lazily (if at all) so calls in them are expanded later
+        if matches!(
+            expr,
+            Expr::Block(_) | Expr::ForLoop(_) | Expr::While(_) | Expr::Loop(_) | Expr::Closure(_)
+        ) {
+            return;
+        }
+        // the right side of `&&`/`||` is only expanded (and so run) if the left doesn't decide
+        if let Expr::Binary(bin) = expr
+            && let BinOp::And(_) | BinOp::Or(_) = bin.op
+        {
+            self.visit_expr_mut(&mut bin.left);
+            let and = matches!(bin.op, BinOp::And(_));
+            if eval_bool(&bin.left, self.lapis).is_ok_and(|left| left == and) {
+                self.visit_expr_mut(&mut bin.right);
+            }
+            return;
+        }
+        // arguments first
+        visit_mut::visit_expr_mut(self, expr);
+        if let Expr::Call(call) = expr
+            && let Some(name) = nth_path_ident(&call.func, 0)
+            && nth_path_ident(&call.func, 1).is_none()
+            && self.lapis.data.fnmap.contains_key(&name)
+        {
+            let tmp = call_fn(&call.clone(), &name, self.lapis, self.buffer);
+            *expr = self.temp_path(tmp);
+        }
+    }
+}
+
+impl Expander<'_, '_, '_> {
+    /// a path to the temporary variable (or `()` if there's nothing)
+    fn temp_path(&mut self, tmp: Option<String>) -> Expr {
+        match tmp {
+            Some(tmp) => {
+                let path = parse_str::<Expr>(&tmp).unwrap();
+                self.temps.push(tmp);
+                path
+            }
+            // evaluates to nothing
+            None => parse_str::<Expr>("()").unwrap(),
+        }
+    }
+}
+
+/// looks for what the expander would replace
+/// (`if`/`match` expressions and calls to user functions)
+struct ExpandFinder<'a> {
+    fns: &'a HashMap<String, LapisFn>,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for ExpandFinder<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if self.found {
+            return;
+        }
+        match expr {
+            Expr::If(_) | Expr::Match(_) => self.found = true,
+            Expr::Call(call)
+                if nth_path_ident(&call.func, 1).is_none()
+                    && nth_path_ident(&call.func, 0).is_some_and(|k| self.fns.contains_key(&k)) =>
+            {
+                self.found = true
+            }
+            // nothing inside
+            Expr::Lit(_) | Expr::Path(_) => {}
+            Expr::Block(_)
+            | Expr::ForLoop(_)
+            | Expr::While(_)
+            | Expr::Loop(_)
+            | Expr::Closure(_) => {}
+            _ => visit::visit_expr(self, expr),
+        }
+    }
+}
+
+/// run `f` on `expr` after calling any user functions in it and evaluating
+/// any `if`/`match` expressions in it
+/// (the temporary variables holding their results are dropped afterwards)
+pub fn with_expanded<'w, 's, R>(
+    expr: &Expr,
+    lapis: &mut Lapis<'w, 's>,
+    buffer: &mut String,
+    f: impl FnOnce(&Expr, &mut Lapis<'w, 's>, &mut String) -> R,
+) -> R {
+    let mut finder = ExpandFinder {
+        fns: &lapis.data.fnmap,
+        found: false,
+    };
+    finder.visit_expr(expr);
+    // most statements have nothing to expand, they aren't copied
+    if !finder.found {
+        return f(expr, lapis, buffer);
+    }
+    let mut expanded = expr.clone();
+    let mut expander = Expander {
+        lapis,
+        buffer,
+        temps: Vec::new(),
+    };
+    expander.visit_expr_mut(&mut expanded);
+    let Expander {
+        lapis,
+        buffer,
+        temps,
+    } = expander;
+    let r = f(&expanded, lapis, buffer);
+    for tmp in temps {
+        lapis.drop(&tmp);
+    }
+    r
+}
+
+/// rust requires types for function parameters, lapis doesn't
+/// this turns `fn f(a, b)` into `fn f(a: _, b: _)` so it can be parsed
+pub fn untyped_params(stream: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut after_fn = 0;
+    for tt in stream {
+        match tt {
+            TokenTree::Ident(i) if i == "fn" => {
+                after_fn = 1;
+                out.push(TokenTree::Ident(i));
+            }
+            TokenTree::Ident(i) if after_fn == 1 => {
+                after_fn = 2;
+                out.push(TokenTree::Ident(i));
+            }
+            TokenTree::Group(g) if after_fn == 2 && g.delimiter() == Delimiter::Parenthesis => {
+                after_fn = 0;
+                let mut group = Group::new(Delimiter::Parenthesis, type_params(g.stream()));
+                group.set_span(g.span());
+                out.push(TokenTree::Group(group));
+            }
+            TokenTree::Group(g) => {
+                after_fn = 0;
+                let mut group = Group::new(g.delimiter(), untyped_params(g.stream()));
+                group.set_span(g.span());
+                out.push(TokenTree::Group(group));
+            }
+            tt => {
+                after_fn = 0;
+                out.push(tt);
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// add `: _` to every parameter that's just a name
+fn type_params(stream: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut param = Vec::new();
+    let mut tokens = stream.into_iter().peekable();
+    while let Some(tt) = tokens.next() {
+        let end = matches!(&tt, TokenTree::Punct(p) if p.as_char() == ',');
+        if !end {
+            param.push(tt.clone());
+        }
+        if end || tokens.peek().is_none() {
+            let untyped = matches!(param.as_slice(), [TokenTree::Ident(_)]);
+            out.append(&mut param);
+            if untyped {
+                out.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+                out.push(TokenTree::Ident(proc_macro2::Ident::new(
+                    "_",
+                    proc_macro2::Span::call_site(),
+                )));
+            }
+            if end {
+                out.push(tt);
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio::init_audio,
+        history::History,
+        lapis::{LapisData, timers::TimerCache},
+    };
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        prelude::{Assets, ColorMaterial, Time, Virtual, World},
+    };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<LapisData>();
+        world.init_resource::<Time<Virtual>>();
+        world.init_resource::<Assets<ColorMaterial>>();
+        world.init_resource::<History>();
+        world.init_non_send_resource::<FnCache>();
+        world.init_non_send_resource::<TimerCache>();
+        init_audio(&mut world);
+        world
+    }
+
+    fn eval(world: &mut World, code: &'static str) {
+        world
+            .run_system_once(move |mut lapis: Lapis| lapis.quiet_eval(code))
+            .unwrap();
+    }
+
+    #[test]
+    fn short_circuit() {
+        let mut world = world();
+        eval(&mut world, "let n = 0;");
+        eval(&mut world, "fn bump() { n = n + 1; true }");
+        eval(&mut world, "let a = false && bump();");
+        eval(&mut world, "let b = true || bump();");
+        eval(&mut world, "let c = (1 > 2 && bump()) || bump();");
+        let data = world.resource::<LapisData>();
+        assert_eq!(data.fmap["n"], 1.);
+        assert!(!data.bmap["a"]);
+        assert!(data.bmap["b"]);
+        assert!(data.bmap["c"]);
+    }
+}
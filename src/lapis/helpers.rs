@@ -1,4 +1,4 @@
-use super::{Lapis, floats::*, ints::*};
+use super::{Lapis, errors::*, floats::*, ints::*};
 use bevy_egui::egui::{Key, Modifiers};
 use fundsp::hacker::*;
 use syn::{punctuated::Punctuated, *};
@@ -34,6 +34,29 @@ pub fn parse_shortcut(mut k: String) -> Option<(Modifiers, Key, bool)> {
     Some((modifiers, key, !release))
 }
 
+/// the inverse of parse_shortcut
+pub fn shortcut_string(shortcut: &(Modifiers, Key, bool)) -> String {
+    let (modifiers, key, pressed) = shortcut;
+    let mut s = String::new();
+    if !pressed {
+        s.push('!');
+    }
+    if modifiers.ctrl {
+        s.push_str("Ctrl+");
+    }
+    if modifiers.alt {
+        s.push_str("Alt+");
+    }
+    if modifiers.shift {
+        s.push_str("Shift+");
+    }
+    if modifiers.command && !modifiers.ctrl {
+        s.push_str("Command+");
+    }
+    s.push_str(key.name());
+    s
+}
+
 pub fn path_fade(expr: &Expr) -> Option<Fade> {
     let f = nth_path_ident(expr, 0)?;
     let c = nth_path_ident(expr, 1)?;
@@ -47,33 +70,32 @@ pub fn path_fade(expr: &Expr) -> Option<Fade> {
     None
 }
 
-pub fn eval_meter(expr: &Expr, lapis: &Lapis) -> Option<Meter> {
+pub fn eval_meter(expr: &Expr, lapis: &Lapis) -> EvalResult<Meter> {
     match expr {
         Expr::Call(expr) => {
-            let seg0 = nth_path_ident(&expr.func, 0)?;
-            let seg1 = nth_path_ident(&expr.func, 1)?;
-            let arg = expr.args.first()?;
-            let val = eval_float(arg, lapis)?;
+            let seg0 = nth_path_ident(&expr.func, 0).or_mismatch("a meter")?;
+            let seg1 = nth_path_ident(&expr.func, 1).or_mismatch("a meter")?;
             if seg0 == "Meter" {
+                let val = eval_float(arg(expr, 0)?, lapis)?;
                 match seg1.as_str() {
-                    "Peak" => Some(Meter::Peak(val)),
-                    "Rms" => Some(Meter::Rms(val)),
-                    _ => None,
+                    "Peak" => Ok(Meter::Peak(val)),
+                    "Rms" => Ok(Meter::Rms(val)),
+                    _ => Err(EvalError::mismatch("a meter")),
                 }
             } else {
-                None
+                Err(EvalError::mismatch("a meter"))
             }
         }
-        Expr::Path(expr) => {
-            let seg0 = &expr.path.segments.first()?.ident;
-            let seg1 = &expr.path.segments.get(1)?.ident;
-            if seg0 == "Meter" && seg1 == "Sample" {
-                Some(Meter::Sample)
+        Expr::Path(_) => {
+            let seg0 = nth_path_ident(expr, 0);
+            let seg1 = nth_path_ident(expr, 1);
+            if seg0.as_deref() == Some("Meter") && seg1.as_deref() == Some("Sample") {
+                Ok(Meter::Sample)
             } else {
-                None
+                Err(EvalError::mismatch("a meter"))
             }
         }
-        _ => None,
+        _ => Err(EvalError::mismatch("a meter")),
     }
 }
 
@@ -86,19 +108,19 @@ pub fn pat_ident(pat: &Pat) -> Option<String> {
     }
 }
 
-pub fn range_bounds(expr: &Expr, lapis: &Lapis) -> Option<(i32, i32)> {
+pub fn range_bounds(expr: &Expr, lapis: &Lapis) -> EvalResult<(i32, i32)> {
     match expr {
         Expr::Range(expr) => {
-            let start = expr.start.clone()?;
-            let end = expr.end.clone()?;
-            let s = eval_i32(&start, lapis)?;
-            let mut e = eval_i32(&end, lapis)?;
+            let start = expr.start.as_ref().or_invalid()?;
+            let end = expr.end.as_ref().or_invalid()?;
+            let s = eval_i32(start, lapis)?;
+            let mut e = eval_i32(end, lapis)?;
             if let RangeLimits::Closed(_) = expr.limits {
                 e += 1;
             }
-            Some((s, e))
+            Ok((s, e))
         }
-        _ => None,
+        _ => Err(EvalError::mismatch("a range")),
     }
 }
 
@@ -128,7 +150,7 @@ pub fn nth_path_generic(expr: &Expr, n: usize) -> Option<String> {
 pub fn accumulate_args_f64(args: &Punctuated<Expr, Token!(,)>, lapis: &Lapis) -> Vec<f64> {
     let mut vec = Vec::new();
     for arg in args {
-        if let Some(n) = eval_float(arg, lapis) {
+        if let Ok(n) = eval_float(arg, lapis) {
             vec.push(n);
         }
     }
@@ -138,7 +160,7 @@ pub fn accumulate_args_f64(args: &Punctuated<Expr, Token!(,)>, lapis: &Lapis) ->
 pub fn accumulate_args(args: &Punctuated<Expr, Token!(,)>, lapis: &Lapis) -> Vec<f32> {
     let mut vec = Vec::new();
     for arg in args {
-        if let Some(n) = eval_float_f32(arg, lapis) {
+        if let Ok(n) = eval_float_f32(arg, lapis) {
             vec.push(n);
         }
     }
@@ -171,22 +193,23 @@ impl Shape for ShapeEnum {
     }
 }
 
-pub fn call_shape(expr: &Expr, lapis: &Lapis) -> Option<ShapeEnum> {
+pub fn call_shape(expr: &Expr, lapis: &Lapis) -> EvalResult<ShapeEnum> {
     match expr {
         Expr::Call(expr) => {
-            let ident = nth_path_ident(&expr.func, 0)?;
+            let ident = nth_path_ident(&expr.func, 0).or_mismatch("a shape")?;
             let args = accumulate_args(&expr.args, lapis);
+            let a = |n| float_arg(&args, n, expr, lapis);
             match ident.as_str() {
-                "Atan" => Some(ShapeEnum::Atan(Atan(*args.first()?))),
-                "Clip" => Some(ShapeEnum::Clip(Clip(*args.first()?))),
-                "ClipTo" => Some(ShapeEnum::ClipTo(ClipTo(*args.first()?, *args.get(1)?))),
-                "Crush" => Some(ShapeEnum::Crush(Crush(*args.first()?))),
-                "SoftCrush" => Some(ShapeEnum::SoftCrush(SoftCrush(*args.first()?))),
-                "Softsign" => Some(ShapeEnum::Softsign(Softsign(*args.first()?))),
-                "Tanh" => Some(ShapeEnum::Tanh(Tanh(*args.first()?))),
-                _ => None,
+                "Atan" => Ok(ShapeEnum::Atan(Atan(a(0)?))),
+                "Clip" => Ok(ShapeEnum::Clip(Clip(a(0)?))),
+                "ClipTo" => Ok(ShapeEnum::ClipTo(ClipTo(a(0)?, a(1)?))),
+                "Crush" => Ok(ShapeEnum::Crush(Crush(a(0)?))),
+                "SoftCrush" => Ok(ShapeEnum::SoftCrush(SoftCrush(a(0)?))),
+                "Softsign" => Ok(ShapeEnum::Softsign(Softsign(a(0)?))),
+                "Tanh" => Ok(ShapeEnum::Tanh(Tanh(a(0)?))),
+                _ => Err(EvalError::mismatch("a shape")),
             }
         }
-        _ => None,
+        _ => Err(EvalError::mismatch("a shape")),
     }
 }
@@ -1,17 +1,17 @@
-use super::{Lapis, floats::*};
+use super::{Lapis, errors::*, floats::*};
 use syn::*;
 
-pub fn eval_i32(expr: &Expr, lapis: &Lapis) -> Option<i32> {
+pub fn eval_i32(expr: &Expr, lapis: &Lapis) -> EvalResult<i32> {
     let i = match expr {
         Expr::Lit(expr) => lit_i32(&expr.lit),
-        Expr::Paren(expr) => eval_i32(&expr.expr, lapis),
+        Expr::Paren(expr) => eval_i32(&expr.expr, lapis).ok(),
         Expr::Unary(expr) => unary_i32(expr, lapis),
         _ => None,
     };
-    if i.is_some() {
-        i
+    if let Some(i) = i {
+        Ok(i)
     } else {
-        Some(eval_float(expr, lapis)? as i32)
+        Ok(eval_float(expr, lapis)? as i32)
     }
 }
 
@@ -24,22 +24,22 @@ fn lit_i32(expr: &Lit) -> Option<i32> {
 
 fn unary_i32(expr: &ExprUnary, lapis: &Lapis) -> Option<i32> {
     match expr.op {
-        UnOp::Neg(_) => Some(-eval_i32(&expr.expr, lapis)?),
+        UnOp::Neg(_) => Some(-eval_i32(&expr.expr, lapis).ok()?),
         _ => None,
     }
 }
 
-pub fn eval_i64(expr: &Expr, lapis: &Lapis) -> Option<i64> {
+pub fn eval_i64(expr: &Expr, lapis: &Lapis) -> EvalResult<i64> {
     let i = match expr {
         Expr::Lit(expr) => lit_i64(&expr.lit),
-        Expr::Paren(expr) => eval_i64(&expr.expr, lapis),
+        Expr::Paren(expr) => eval_i64(&expr.expr, lapis).ok(),
         Expr::Unary(expr) => unary_i64(expr, lapis),
         _ => None,
     };
-    if i.is_some() {
-        i
+    if let Some(i) = i {
+        Ok(i)
     } else {
-        Some(eval_float(expr, lapis)? as i64)
+        Ok(eval_float(expr, lapis)? as i64)
     }
 }
 
@@ -52,12 +52,12 @@ fn lit_i64(expr: &Lit) -> Option<i64> {
 
 fn unary_i64(expr: &ExprUnary, lapis: &Lapis) -> Option<i64> {
     match expr.op {
-        UnOp::Neg(_) => Some(-eval_i64(&expr.expr, lapis)?),
+        UnOp::Neg(_) => Some(-eval_i64(&expr.expr, lapis).ok()?),
         _ => None,
     }
 }
 
-pub fn eval_u64(expr: &Expr, lapis: &Lapis) -> Option<u64> {
+pub fn eval_u64(expr: &Expr, lapis: &Lapis) -> EvalResult<u64> {
     let i = match expr {
         Expr::Lit(expr) => match &expr.lit {
             Lit::Int(expr) => expr.base10_parse::<u64>().ok(),
@@ -65,14 +65,14 @@ pub fn eval_u64(expr: &Expr, lapis: &Lapis) -> Option<u64> {
         },
         _ => None,
     };
-    if i.is_some() {
-        i
+    if let Some(i) = i {
+        Ok(i)
     } else {
-        Some(eval_float(expr, lapis)? as u64)
+        Ok(eval_float(expr, lapis)? as u64)
     }
 }
 
-pub fn eval_usize(expr: &Expr, lapis: &Lapis) -> Option<usize> {
+pub fn eval_usize(expr: &Expr, lapis: &Lapis) -> EvalResult<usize> {
     let i = match expr {
         Expr::Lit(expr) => match &expr.lit {
             Lit::Int(expr) => expr.base10_parse::<usize>().ok(),
@@ -80,14 +80,14 @@ pub fn eval_usize(expr: &Expr, lapis: &Lapis) -> Option<usize> {
         },
         _ => None,
     };
-    if i.is_some() {
-        i
+    if let Some(i) = i {
+        Ok(i)
     } else {
-        Some(eval_float(expr, lapis)? as usize)
+        Ok(eval_float(expr, lapis)? as usize)
     }
 }
 
-pub fn eval_isize(expr: &Expr, lapis: &Lapis) -> Option<isize> {
+pub fn eval_isize(expr: &Expr, lapis: &Lapis) -> EvalResult<isize> {
     let i = match expr {
         Expr::Lit(expr) => match &expr.lit {
             Lit::Int(expr) => expr.base10_parse::<isize>().ok(),
@@ -95,9 +95,9 @@ pub fn eval_isize(expr: &Expr, lapis: &Lapis) -> Option<isize> {
         },
         _ => None,
     };
-    if i.is_some() {
-        i
+    if let Some(i) = i {
+        Ok(i)
     } else {
-        Some(eval_float(expr, lapis)? as isize)
+        Ok(eval_float(expr, lapis)? as isize)
     }
 }
@@ -1,32 +1,51 @@
-use crate::{audio::*, interaction::Selected, objects::*};
+use crate::{
+    audio::*,
+    history::History,
+    interaction::Selected,
+    joints::{Breakable, JointMotor},
+    midi::*,
+    objects::*,
+    shapes::Shape,
+    voices::{Voice, VoiceBus},
+};
 use avian2d::prelude::*;
 use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_egui::egui::{Key, Modifiers};
 use fundsp::hacker::*;
+use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
 use std::{collections::HashMap, sync::Arc};
-use syn::{Stmt, parse_str};
+use syn::{Stmt, parse_str, parse2};
 
 mod arrays;
 mod atomics;
 mod bools;
+mod branches;
 mod entities;
+mod errors;
 pub mod floats;
-mod helpers;
+pub mod functions;
+pub mod helpers;
 mod ints;
 mod nets;
 mod sequencers;
 mod sources;
 mod statements;
 mod strings;
+mod timers;
 mod waves;
+use functions::*;
 use statements::*;
+use timers::*;
 
 pub struct LapisPlugin;
 
 impl Plugin for LapisPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<LapisData>();
+        app.init_non_send_resource::<FnCache>();
+        app.init_non_send_resource::<TimerCache>();
         app.add_systems(Startup, init_eval);
+        app.add_systems(Update, run_timers);
     }
 }
 
@@ -46,6 +65,8 @@ pub struct LapisData {
     pub idmap: HashMap<String, NodeId>,
     pub bmap: HashMap<String, bool>,
     pub smap: HashMap<String, Shared>,
+    /// bumped whenever a shared is added or removed (so links can re-resolve)
+    pub smap_version: u64,
     pub wmap: HashMap<String, Arc<Wave>>,
     pub seqmap: HashMap<String, Sequencer>,
     pub eventmap: HashMap<String, EventId>,
@@ -53,13 +74,242 @@ pub struct LapisData {
     pub entitymap: HashMap<String, Entity>,
     pub atomic_table_map: HashMap<String, Arc<AtomicTable>>,
     pub string_map: HashMap<String, String>,
+    pub fnmap: HashMap<String, LapisFn>,
+    pub timer_map: HashMap<String, TimerId>,
+    pub timers: Timers,
     // (modifiers, key, pressed)
     pub keys: HashMap<(Modifiers, Key, bool), String>,
     pub keys_active: bool,
+    pub midi: HashMap<MidiBinding, RemoteAction>,
+    /// osc address -> action
+    pub osc: HashMap<String, RemoteAction>,
+    /// loop iterations of the current evaluation (see `LOOP_LIMIT`)
+    pub loop_iterations: usize,
+    /// whether the code being evaluated runs on its own (see `HOOK_LOOP_LIMIT`)
+    pub hook: bool,
+    /// how deep in nested `eval` calls we are
+    pub eval_depth: usize,
+    /// how deep in nested user function calls we are
+    pub call_depth: usize,
+    /// the variables that `return` assigns to (one per active call)
+    pub fn_returns: Vec<String>,
+    /// what the `let`s of each active call shadowed (restored when it ends)
+    pub fn_locals: Vec<Vec<(String, Stash)>>,
+    /// the code being evaluated (errors quote it)
+    pub source: String,
+    temp_counter: usize,
     pub keys_repeat: bool,
     pub quiet: bool,
     pub about: bool,
     pub help: bool,
+    pub vars: bool,
+}
+
+impl LapisData {
+    pub fn drop(&mut self, k: &str) {
+        self.fmap.remove(k);
+        self.vmap.remove(k);
+        self.gmap.remove(k);
+        self.idmap.remove(k);
+        self.bmap.remove(k);
+        if self.smap.remove(k).is_some() {
+            self.smap_version = self.smap_version.wrapping_add(1);
+        }
+        self.wmap.remove(k);
+        self.seqmap.remove(k);
+        self.eventmap.remove(k);
+        self.srcmap.remove(k);
+        self.entitymap.remove(k);
+        self.atomic_table_map.remove(k);
+        self.string_map.remove(k);
+        self.fnmap.remove(k);
+        self.timer_map.remove(k);
+    }
+    /// whether any map has a variable with this name
+    pub fn contains(&self, k: &str) -> bool {
+        self.fmap.contains_key(k)
+            || self.vmap.contains_key(k)
+            || self.gmap.contains_key(k)
+            || self.idmap.contains_key(k)
+            || self.bmap.contains_key(k)
+            || self.smap.contains_key(k)
+            || self.wmap.contains_key(k)
+            || self.seqmap.contains_key(k)
+            || self.eventmap.contains_key(k)
+            || self.srcmap.contains_key(k)
+            || self.entitymap.contains_key(k)
+            || self.atomic_table_map.contains_key(k)
+            || self.string_map.contains_key(k)
+            || self.fnmap.contains_key(k)
+            || self.timer_map.contains_key(k)
+    }
+    /// what type of variable `k` is (as it reads in error messages)
+    pub fn var_type(&self, k: &str) -> Option<&'static str> {
+        if self.fmap.contains_key(k) {
+            Some("a float")
+        } else if self.vmap.contains_key(k) {
+            Some("an array")
+        } else if self.gmap.contains_key(k) {
+            Some("a net")
+        } else if self.idmap.contains_key(k) {
+            Some("a node id")
+        } else if self.bmap.contains_key(k) {
+            Some("a bool")
+        } else if self.smap.contains_key(k) {
+            Some("a shared")
+        } else if self.wmap.contains_key(k) {
+            Some("a wave")
+        } else if self.seqmap.contains_key(k) {
+            Some("a sequencer")
+        } else if self.eventmap.contains_key(k) {
+            Some("an event id")
+        } else if self.srcmap.contains_key(k) {
+            Some("a source")
+        } else if self.entitymap.contains_key(k) {
+            Some("an entity")
+        } else if self.atomic_table_map.contains_key(k) {
+            Some("an atomic table")
+        } else if self.string_map.contains_key(k) {
+            Some("a string")
+        } else if self.fnmap.contains_key(k) {
+            Some("a function")
+        } else if self.timer_map.contains_key(k) {
+            Some("a timer")
+        } else {
+            None
+        }
+    }
+    /// remove a variable (from whichever map it's in) and return it
+    pub fn take(&mut self, k: &str) -> Stash {
+        let s = self.smap.remove(k);
+        if s.is_some() {
+            self.smap_version = self.smap_version.wrapping_add(1);
+        }
+        Stash {
+            f: self.fmap.remove(k),
+            v: self.vmap.remove(k),
+            g: self.gmap.remove(k),
+            id: self.idmap.remove(k),
+            b: self.bmap.remove(k),
+            s,
+            w: self.wmap.remove(k),
+            seq: self.seqmap.remove(k),
+            event: self.eventmap.remove(k),
+            src: self.srcmap.remove(k),
+            entity: self.entitymap.remove(k),
+            table: self.atomic_table_map.remove(k),
+            string: self.string_map.remove(k),
+            func: self.fnmap.remove(k),
+            timer: self.timer_map.remove(k),
+        }
+    }
+    /// make room for a new variable `k`
+    /// (inside a function call, what had that name is kept until the call ends)
+    pub fn shadow(&mut self, k: &str) {
+        let Some(locals) = self.fn_locals.last() else {
+            self.drop(k);
+            return;
+        };
+        if locals.iter().any(|(name, _)| name == k) {
+            self.drop(k);
+        } else {
+            let old = self.take(k);
+            if let Some(locals) = self.fn_locals.last_mut() {
+                locals.push((k.to_string(), old));
+            }
+        }
+    }
+    /// replace a variable with a previously taken one
+    pub fn put(&mut self, k: &str, stash: Stash) {
+        self.drop(k);
+        let k = k.to_string();
+        if let Some(x) = stash.f {
+            self.fmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.v {
+            self.vmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.g {
+            self.gmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.id {
+            self.idmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.b {
+            self.bmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.s {
+            self.insert_shared(k.clone(), x);
+        }
+        if let Some(x) = stash.w {
+            self.wmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.seq {
+            self.seqmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.event {
+            self.eventmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.src {
+            self.srcmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.entity {
+            self.entitymap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.table {
+            self.atomic_table_map.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.string {
+            self.string_map.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.func {
+            self.fnmap.insert(k.clone(), x);
+        }
+        if let Some(x) = stash.timer {
+            self.timer_map.insert(k, x);
+        }
+    }
+    /// links look their shared up again when the shareds change
+    pub fn insert_shared(&mut self, k: String, s: Shared) {
+        self.smap.insert(k, s);
+        self.smap_version = self.smap_version.wrapping_add(1);
+    }
+    /// a fresh name for a temporary variable
+    pub fn temp_name(&mut self) -> String {
+        self.temp_counter = self.temp_counter.wrapping_add(1);
+        format!("__tmp{}", self.temp_counter)
+    }
+}
+
+/// a variable taken out of the maps (see `LapisData::take`)
+#[derive(Default)]
+pub struct Stash {
+    f: Option<f64>,
+    v: Option<Vec<f32>>,
+    g: Option<Net>,
+    id: Option<NodeId>,
+    b: Option<bool>,
+    s: Option<Shared>,
+    w: Option<Arc<Wave>>,
+    seq: Option<Sequencer>,
+    event: Option<EventId>,
+    src: Option<Source>,
+    entity: Option<Entity>,
+    table: Option<Arc<AtomicTable>>,
+    string: Option<String>,
+    func: Option<LapisFn>,
+    timer: Option<TimerId>,
+}
+
+/// parse lapis code (a series of statements) into a block statement
+pub fn parse_input(input: &str) -> syn::Result<Stmt> {
+    parse_tokens(parse_str::<TokenStream>(input)?)
+}
+
+/// like `parse_input` but for already tokenized code
+pub fn parse_tokens(stream: TokenStream) -> syn::Result<Stmt> {
+    let block = Group::new(Delimiter::Brace, untyped_params(stream));
+    parse2::<Stmt>(TokenTree::Group(block).into())
 }
 
 #[derive(SystemParam)]
@@ -74,7 +324,7 @@ pub struct Lapis<'w, 's> {
     pub lin_damp_query: Query<'w, 's, &'static LinearDamping>,
     pub ang_damp_query: Query<'w, 's, &'static AngularDamping>,
     pub inertia_query: Query<'w, 's, &'static AngularInertia>,
-    pub sides_query: Query<'w, 's, &'static Sides>,
+    pub shape_query: Query<'w, 's, &'static Shape>,
     pub material_ids: Query<'w, 's, &'static MeshMaterial2d<ColorMaterial>>,
     pub materials: Res<'w, Assets<ColorMaterial>>,
     pub cm_query: Query<'w, 's, &'static CenterOfMass>,
@@ -87,34 +337,64 @@ pub struct Lapis<'w, 's> {
     pub distance_query: Query<'w, 's, &'static DistanceJoint>,
     pub revolute_query: Query<'w, 's, &'static RevoluteJoint>,
     pub prismatic_query: Query<'w, 's, &'static PrismaticJoint>,
+    pub motor_query: Query<'w, 's, &'static JointMotor>,
+    pub breakable_query: Query<'w, 's, &'static Breakable>,
+    pub voice_query: Query<'w, 's, &'static Voice>,
+    pub script_query: Query<'w, 's, &'static Script>,
     pub time: ResMut<'w, Time<Virtual>>,
     pub selected_query: Query<'w, 's, Entity, With<Selected>>,
     pub audio_out: ResMut<'w, AudioOutput>,
     pub input_receiver: Res<'w, AudioInputReceiver1>,
     pub in_stream_config: Res<'w, InStreamConfig>,
-    pub out_stream_config: Res<'w, OutStreamConfig>,
+    pub out_stream_config: ResMut<'w, OutStreamConfig>,
+    pub scene_render: Option<Res<'w, SceneRender>>,
+    pub voice_bus: ResMut<'w, VoiceBus>,
+    pub history: ResMut<'w, History>,
+    pub fn_cache: NonSendMut<'w, FnCache>,
+    pub timer_cache: NonSendMut<'w, TimerCache>,
 }
 
 impl Lapis<'_, '_> {
+    /// record the state of an entity (or the selection) before the input changes it
+    pub fn touch(&mut self, e: Entity) {
+        if !self.history.recording {
+            return;
+        }
+        if e == Entity::PLACEHOLDER {
+            for e in self.selected_query.iter() {
+                self.history.touch(&mut self.commands, e);
+            }
+        } else {
+            self.history.touch(&mut self.commands, e);
+        }
+    }
+    /// record the joints connected to an object before the input changes them
+    pub fn touch_joints(&mut self, e: Entity) {
+        if self.history.recording {
+            self.history.touch_joints(&mut self.commands, e);
+        }
+    }
+    /// record a new entity as part of the input's edit
+    pub fn spawned(&mut self, e: Entity) {
+        if self.history.recording {
+            self.history.spawned(e);
+        }
+    }
     pub fn drop(&mut self, k: &str) {
-        self.data.fmap.remove(k);
-        self.data.vmap.remove(k);
-        self.data.gmap.remove(k);
-        self.data.idmap.remove(k);
-        self.data.bmap.remove(k);
-        self.data.smap.remove(k);
-        self.data.wmap.remove(k);
-        self.data.seqmap.remove(k);
-        self.data.eventmap.remove(k);
-        self.data.srcmap.remove(k);
-        self.data.entitymap.remove(k);
-        self.data.atomic_table_map.remove(k);
-        self.data.string_map.remove(k);
+        self.data.drop(k);
     }
     pub fn clear_keys(&mut self) {
         self.data.keys.clear();
         self.data.keys.shrink_to_fit();
     }
+    pub fn clear_midi(&mut self) {
+        self.data.midi.clear();
+        self.data.midi.shrink_to_fit();
+    }
+    pub fn clear_osc(&mut self) {
+        self.data.osc.clear();
+        self.data.osc.shrink_to_fit();
+    }
     pub fn clear_maps(&mut self) {
         self.data.fmap.clear();
         self.data.fmap.shrink_to_fit();
@@ -127,6 +407,7 @@ impl Lapis<'_, '_> {
         self.data.bmap.clear();
         self.data.bmap.shrink_to_fit();
         self.data.smap.clear();
+        self.data.smap_version = self.data.smap_version.wrapping_add(1);
         self.data.smap.shrink_to_fit();
         self.data.wmap.clear();
         self.data.wmap.shrink_to_fit();
@@ -140,14 +421,19 @@ impl Lapis<'_, '_> {
         self.data.atomic_table_map.shrink_to_fit();
         self.data.string_map.clear();
         self.data.string_map.shrink_to_fit();
+        self.data.fnmap.clear();
+        self.data.fnmap.shrink_to_fit();
+        self.fn_cache.clear();
+        self.data.timer_map.clear();
+        self.data.timer_map.shrink_to_fit();
     }
     pub fn eval(&mut self, input: &str) {
         if !input.is_empty() {
             self.data.buffer.push('\n');
             self.data.buffer.push_str(input);
-            match parse_str::<Stmt>(&format!("{{{input}\n}}")) {
+            match parse_input(input) {
                 Ok(stmt) => {
-                    let out = eval_stmt(stmt, self);
+                    let out = self.run(input, &stmt);
                     self.data.buffer.push_str(&out);
                 }
                 Err(err) => {
@@ -158,12 +444,15 @@ impl Lapis<'_, '_> {
     }
     pub fn eval_input(&mut self) {
         if !self.data.input.is_empty() {
-            match parse_str::<Stmt>(&format!("{{{}\n}}", self.data.input)) {
+            match parse_input(&self.data.input) {
                 Ok(stmt) => {
                     self.data.buffer.push('\n');
                     let input = std::mem::take(&mut self.data.input);
                     self.data.buffer.push_str(&input);
-                    let out = eval_stmt(stmt, self);
+                    // only what's typed in is undoable
+                    self.history.recording = true;
+                    let out = self.run(&input, &stmt);
+                    self.history.recording = false;
                     self.data.buffer.push_str(&out);
                 }
                 Err(err) => {
@@ -173,8 +462,41 @@ impl Lapis<'_, '_> {
         }
     }
     pub fn quiet_eval(&mut self, input: &str) {
-        if let Ok(stmt) = parse_str::<Stmt>(&format!("{{{input}\n}}")) {
-            eval_stmt(stmt, self);
+        if let Ok(stmt) = parse_input(input) {
+            self.run(input, &stmt);
+        }
+    }
+    /// like `eval` but for an already parsed statement
+    /// `input` is only used for display
+    pub fn eval_parsed(&mut self, input: &str, stmt: &Stmt) {
+        if !input.is_empty() {
+            self.data.buffer.push('\n');
+            self.data.buffer.push_str(input);
+            let out = self.run(input, stmt);
+            self.data.buffer.push_str(&out);
+        }
+    }
+    pub fn quiet_eval_parsed(&mut self, stmt: &Stmt) {
+        self.run("", stmt);
+    }
+    /// run `f` with the lower loop limit of code that runs on its own
+    pub fn as_hook(&mut self, f: impl FnOnce(&mut Self)) {
+        let hook = std::mem::replace(&mut self.data.hook, true);
+        f(self);
+        self.data.hook = hook;
+    }
+    /// evaluate a statement (parsed from `source`), returning its output
+    /// (the loop limit applies to the outermost evaluation, including nested `eval` calls)
+    fn run(&mut self, source: &str, stmt: &Stmt) -> String {
+        if self.data.eval_depth == 0 {
+            self.data.loop_iterations = 0;
         }
+        self.data.eval_depth += 1;
+        let outer = std::mem::replace(&mut self.data.source, source.to_string());
+        let mut out = String::new();
+        eval_stmt(stmt, self, &mut out);
+        self.data.source = outer;
+        self.data.eval_depth -= 1;
+        out
     }
 }
@@ -1,909 +1,987 @@
 use super::{
-    Lapis, arrays::*, atomics::*, bools::*, floats::*, helpers::*, ints::*, sources::*, strings::*,
-    waves::*,
+    Lapis, arrays::*, atomics::*, bools::*, errors::*, floats::*, helpers::*, ints::*, sources::*,
+    strings::*, waves::*,
 };
 use fundsp::hacker::*;
 use fundsp::maps;
 use fundsp::sound::*;
+use quote::ToTokens;
 use syn::*;
 
-pub fn eval_net(expr: &Expr, lapis: &mut Lapis) -> Option<Net> {
+pub fn eval_net(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Net> {
     match expr {
-        Expr::Call(expr) => call_net(expr, lapis),
+        Expr::Call(call) => call_net(call, lapis).map_err(|e| e.within(call)),
         Expr::Binary(expr) => bin_expr_net(expr, lapis),
         Expr::Paren(expr) => eval_net(&expr.expr, lapis),
-        Expr::Path(expr) => path_net(&expr.path, lapis),
+        Expr::Path(_) => path_net(expr, lapis),
         Expr::Unary(expr) => unary_net(expr, lapis),
-        Expr::MethodCall(expr) => method_net(expr, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_net(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("a net")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-pub fn eval_net_cloned(expr: &Expr, lapis: &mut Lapis) -> Option<Net> {
+pub fn eval_net_cloned(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Net> {
     match expr {
-        Expr::Call(expr) => call_net(expr, lapis),
-        Expr::Binary(expr) => bin_expr_net(expr, lapis),
-        Expr::Paren(expr) => eval_net(&expr.expr, lapis),
-        Expr::Path(expr) => path_net_cloned(&expr.path, lapis),
-        Expr::Unary(expr) => unary_net(expr, lapis),
-        Expr::MethodCall(expr) => method_net(expr, lapis),
-        _ => None,
+        Expr::Paren(expr) => eval_net_cloned(&expr.expr, lapis),
+        Expr::Path(_) => path_net_cloned(expr, lapis).map_err(|e| e.at(expr)),
+        _ => eval_net(expr, lapis),
     }
 }
 
-fn method_net(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<Net> {
+/// the name of the net a method is called on
+/// (or the error if the receiver isn't one)
+fn net_receiver(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<String> {
+    let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a net")?;
+    if lapis.data.gmap.contains_key(&k) {
+        Ok(k)
+    } else {
+        let err = path_error(&expr.receiver, lapis, "a net");
+        Err(EvalError::unknown_method().or(err))
+    }
+}
+
+/// an error if the net doesn't have the node (`arg` is where it came from)
+fn check_node(net: &Net, id: NodeId, arg: &Expr) -> EvalResult<()> {
+    if net.contains(id) {
+        Ok(())
+    } else {
+        let err = EvalError::new(arg, ErrorKind::Invalid);
+        Err(err.suggest("the node isn't in this net"))
+    }
+}
+
+/// an error if the port isn't one of the `len` there are
+fn check_port(what: &'static str, index: usize, len: usize, arg: &Expr) -> EvalResult<()> {
+    if index < len {
+        Ok(())
+    } else {
+        Err(EvalError::new(
+            arg,
+            ErrorKind::OutOfRange { what, index, len },
+        ))
+    }
+}
+
+/// an error if a unit can't replace the node
+fn check_replacement(net: &Net, id: NodeId, unit: &Net) -> EvalResult<()> {
+    if unit.inputs() == net.inputs_in(id) && unit.outputs() == net.outputs_in(id) {
+        Ok(())
+    } else {
+        Err(needs(net.inputs_in(id), net.outputs_in(id)))
+    }
+}
+
+fn method_net(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<Net> {
     match expr.method.to_string().as_str() {
         "backend" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            if let Some(seq) = lapis.data.seqmap.get_mut(&k) {
+            let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a net")?;
+            let has_backend = if let Some(seq) = lapis.data.seqmap.get_mut(&k) {
                 if !seq.has_backend() {
-                    return Some(Net::wrap(Box::new(seq.backend())));
+                    return Ok(Net::wrap(Box::new(seq.backend())));
                 }
-            } else if let Some(g) = lapis.data.gmap.get_mut(&k)
-                && !g.has_backend()
-            {
-                return Some(Net::wrap(Box::new(g.backend())));
+                true
+            } else if let Some(g) = lapis.data.gmap.get_mut(&k) {
+                if !g.has_backend() {
+                    return Ok(Net::wrap(Box::new(g.backend())));
+                }
+                true
+            } else {
+                false
+            };
+            if has_backend {
+                Err(EvalError::invalid().suggest(format!("`{k}` already has a backend")))
+            } else {
+                let err = path_error(&expr.receiver, lapis, "a net");
+                Err(EvalError::unknown_method().or(err))
             }
-            None
         }
         "clone" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            lapis.data.gmap.get(&k).cloned()
+            let k = net_receiver(expr, lapis)?;
+            lapis.data.gmap.get(&k).cloned().or_invalid()
         }
         "remove" => {
-            let arg = expr.args.first()?;
+            let k = net_receiver(expr, lapis)?;
+            let arg = arg(expr, 0)?;
             let id = eval_nodeid(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) {
-                Some(Net::wrap(net.remove(id)))
-            } else {
-                None
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg)?;
+            Ok(Net::wrap(net.remove(id)))
         }
         "remove_link" => {
-            let arg = expr.args.first()?;
+            let k = net_receiver(expr, lapis)?;
+            let arg = arg(expr, 0)?;
             let id = eval_nodeid(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) && net.inputs_in(id) == net.outputs_in(id) {
-                return Some(Net::wrap(net.remove_link(id)));
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg)?;
+            if net.inputs_in(id) != net.outputs_in(id) {
+                let help = "the node needs as many inputs as outputs";
+                return Err(EvalError::invalid().suggest(help));
             }
-            None
+            Ok(Net::wrap(net.remove_link(id)))
         }
         "replace" => {
-            let arg0 = expr.args.first()?;
+            let k = net_receiver(expr, lapis)?;
+            let arg0 = arg(expr, 0)?;
             let id = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
-            let unit = eval_net(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id)
-                && unit.inputs() == net.inputs_in(id)
-                && unit.outputs() == net.outputs_in(id)
-            {
-                return Some(Net::wrap(net.replace(id, Box::new(unit))));
-            }
-            None
+            let unit = eval_net(arg(expr, 1)?, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg0)?;
+            check_replacement(net, id, &unit)?;
+            Ok(Net::wrap(net.replace(id, Box::new(unit))))
         }
         "phase" => {
-            let p = eval_float_f32(expr.args.first()?, lapis)?;
-            let mut net = eval_net(&expr.receiver, lapis)?;
+            let p = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let mut net =
+                eval_net(&expr.receiver, lapis).map_err(|e| EvalError::unknown_method().or(e))?;
             // bad amy
             for i in 0..net.ids().len() {
-                net.set(Setting::phase(p).node(*net.ids().nth(i)?).right());
+                net.set(
+                    Setting::phase(p)
+                        .node(*net.ids().nth(i).or_invalid()?)
+                        .right(),
+                );
             }
             net.reset();
-            Some(net)
+            Ok(net)
         }
         "seed" => {
-            let s = eval_u64(expr.args.first()?, lapis)?;
-            let mut net = eval_net(&expr.receiver, lapis)?;
+            let s = eval_u64(arg(expr, 0)?, lapis)?;
+            let mut net =
+                eval_net(&expr.receiver, lapis).map_err(|e| EvalError::unknown_method().or(e))?;
             // really bad amy
             for i in 0..net.ids().len() {
-                net.set(Setting::seed(s).node(*net.ids().nth(i)?).left());
+                net.set(
+                    Setting::seed(s)
+                        .node(*net.ids().nth(i).or_invalid()?)
+                        .left(),
+                );
             }
             net.reset();
-            Some(net)
+            Ok(net)
         }
-        _ => None,
+        _ => Err(EvalError::unknown_method()),
     }
 }
 
-fn bin_expr_net(expr: &ExprBinary, lapis: &mut Lapis) -> Option<Net> {
+fn bin_expr_net(expr: &ExprBinary, lapis: &mut Lapis) -> EvalResult<Net> {
+    let Some(op) = net_op(&expr.op) else {
+        return Err(EvalError::mismatch("a net"));
+    };
     let left_net = eval_net(&expr.left, lapis);
     let right_net = eval_net(&expr.right, lapis);
-    let left_float = eval_float_f32(&expr.left, lapis);
-    let right_float = eval_float_f32(&expr.right, lapis);
-    match (left_net, right_net, left_float, right_float) {
-        (Some(left), Some(right), _, _) => {
-            let (li, lo) = (left.inputs(), left.outputs());
-            let (ri, ro) = (right.inputs(), right.outputs());
-            match expr.op {
-                BinOp::BitAnd(_) if li == ri && lo == ro => Some(left & right),
-                BinOp::BitOr(_) => Some(left | right),
-                BinOp::BitXor(_) if li == ri => Some(left ^ right),
-                BinOp::Shr(_) if lo == ri => Some(left >> right),
-                BinOp::Sub(_) if lo == ro => Some(left - right),
-                BinOp::Mul(_) if lo == ro => Some(left * right),
-                BinOp::Add(_) if lo == ro => Some(left + right),
-                _ => None,
+    match (left_net, right_net) {
+        (Ok(left), Ok(right)) => {
+            if let Err(err) = check_channels(expr, op, io(&left), io(&right)) {
+                restore(&expr.left, left, lapis);
+                restore(&expr.right, right, lapis);
+                return Err(err);
+            }
+            Ok(match expr.op {
+                BinOp::BitAnd(_) => left & right,
+                BinOp::BitOr(_) => left | right,
+                BinOp::BitXor(_) => left ^ right,
+                BinOp::Shr(_) => left >> right,
+                BinOp::Sub(_) => left - right,
+                BinOp::Mul(_) => left * right,
+                _ => left + right,
+            })
+        }
+        (Ok(left), Err(err)) => match eval_float_f32(&expr.right, lapis) {
+            Ok(right) => match expr.op {
+                BinOp::Sub(_) => Ok(left - right),
+                BinOp::Mul(_) => Ok(left * right),
+                BinOp::Add(_) => Ok(left + right),
+                _ => {
+                    restore(&expr.left, left, lapis);
+                    Err(EvalError::mismatch("a net"))
+                }
+            },
+            Err(e) => {
+                restore(&expr.left, left, lapis);
+                Err(err.or(e))
             }
-        }
-        (Some(left), _, _, Some(right)) => match expr.op {
-            BinOp::Sub(_) => Some(left - right),
-            BinOp::Mul(_) => Some(left * right),
-            BinOp::Add(_) => Some(left + right),
-            _ => None,
         },
-        (_, Some(right), Some(left), _) => match expr.op {
-            BinOp::Sub(_) => Some(left - right),
-            BinOp::Mul(_) => Some(left * right),
-            BinOp::Add(_) => Some(left + right),
-            _ => None,
+        (Err(err), Ok(right)) => match eval_float_f32(&expr.left, lapis) {
+            Ok(left) => match expr.op {
+                BinOp::Sub(_) => Ok(left - right),
+                BinOp::Mul(_) => Ok(left * right),
+                BinOp::Add(_) => Ok(left + right),
+                _ => {
+                    restore(&expr.right, right, lapis);
+                    Err(EvalError::mismatch("a net"))
+                }
+            },
+            Err(e) => {
+                restore(&expr.right, right, lapis);
+                Err(err.or(e))
+            }
         },
-        _ => None,
+        (Err(left), Err(right)) => Err(left.or(right)),
     }
 }
 
-fn unary_net(expr: &ExprUnary, lapis: &mut Lapis) -> Option<Net> {
+fn unary_net(expr: &ExprUnary, lapis: &mut Lapis) -> EvalResult<Net> {
     match expr.op {
-        UnOp::Neg(_) => Some(-eval_net(&expr.expr, lapis)?),
-        UnOp::Not(_) => Some(!eval_net(&expr.expr, lapis)?),
-        _ => None,
+        UnOp::Neg(_) => Ok(-eval_net(&expr.expr, lapis)?),
+        UnOp::Not(_) => Ok(!eval_net(&expr.expr, lapis)?),
+        _ => Err(EvalError::mismatch("a net")),
     }
 }
 
-fn path_net(expr: &Path, lapis: &mut Lapis) -> Option<Net> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.gmap.remove(&k)
+fn path_net(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Net> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a net")?;
+    match lapis.data.gmap.remove(&k) {
+        Some(g) => Ok(g),
+        None => Err(path_error(expr, lapis, "a net")),
+    }
 }
 
-fn path_net_cloned(expr: &Path, lapis: &Lapis) -> Option<Net> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.gmap.get(&k).cloned()
+fn path_net_cloned(expr: &Expr, lapis: &Lapis) -> EvalResult<Net> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a net")?;
+    match lapis.data.gmap.get(&k) {
+        Some(g) => Ok(g.clone()),
+        None => Err(path_error(expr, lapis, "a net")),
+    }
 }
 
-pub fn net_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<()> {
-    match expr.method.to_string().as_str() {
+pub fn net_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<()> {
+    let method = expr.method.to_string();
+    if !matches!(
+        method.as_str(),
+        "remove"
+            | "remove_link"
+            | "replace"
+            | "crossfade"
+            | "connect"
+            | "disconnect"
+            | "connect_input"
+            | "pipe_input"
+            | "connect_output"
+            | "disconnect_output"
+            | "pipe_output"
+            | "pass_through"
+            | "pipe_all"
+            | "set_source"
+            | "set_output_source"
+            | "commit"
+            | "set_sample_rate"
+            | "reset"
+    ) {
+        return Err(EvalError::unknown_method());
+    }
+    let k = net_receiver(expr, lapis)?;
+    match method.as_str() {
         "remove" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let id = eval_nodeid(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) {
-                net.remove(id);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg)?;
+            net.remove(id);
         }
         "remove_link" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let id = eval_nodeid(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) && net.inputs_in(id) == net.outputs_in(id) {
-                net.remove_link(id);
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg)?;
+            if net.inputs_in(id) != net.outputs_in(id) {
+                let help = "the node needs as many inputs as outputs";
+                return Err(EvalError::invalid().suggest(help));
             }
+            net.remove_link(id);
         }
         "replace" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let id = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
-            let unit = eval_net(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id)
-                && unit.inputs() == net.inputs_in(id)
-                && unit.outputs() == net.outputs_in(id)
-            {
-                net.replace(id, Box::new(unit));
-            }
+            let unit = eval_net(arg(expr, 1)?, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg0)?;
+            check_replacement(net, id, &unit)?;
+            net.replace(id, Box::new(unit));
         }
         "crossfade" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let id = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
-            let fade = path_fade(arg1)?;
-            let arg2 = expr.args.get(2)?;
-            let time = eval_float_f32(arg2, lapis)?;
-            let arg3 = expr.args.get(3)?;
-            let unit = eval_net(arg3, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id)
-                && unit.inputs() == net.inputs_in(id)
-                && unit.outputs() == net.outputs_in(id)
-            {
-                net.crossfade(id, fade, time, Box::new(unit));
-            }
+            let fade = path_fade(arg(expr, 1)?).or_invalid()?;
+            let time = eval_float_f32(arg(expr, 2)?, lapis)?;
+            let unit = eval_net(arg(expr, 3)?, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg0)?;
+            check_replacement(net, id, &unit)?;
+            net.crossfade(id, fade, time, Box::new(unit));
         }
         "connect" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1, arg2, arg3) =
+                (arg(expr, 0)?, arg(expr, 1)?, arg(expr, 2)?, arg(expr, 3)?);
             let src = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let src_port = eval_usize(arg1, lapis)?;
-            let arg2 = expr.args.get(2)?;
             let snk = eval_nodeid(arg2, lapis)?;
-            if src == snk {
-                return None;
-            }
-            let arg3 = expr.args.get(3)?;
             let snk_port = eval_usize(arg3, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(src) && net.contains(snk) {
-                let src_outs = net.outputs_in(src);
-                let snk_ins = net.inputs_in(snk);
-                if src_port < src_outs && snk_port < snk_ins {
-                    net.connect(src, src_port, snk, snk_port);
-                }
+            if src == snk {
+                let help = "a node can't be connected to itself";
+                return Err(EvalError::invalid().suggest(help));
             }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, src, arg0)?;
+            check_node(net, snk, arg2)?;
+            check_port("output", src_port, net.outputs_in(src), arg1)?;
+            check_port("input", snk_port, net.inputs_in(snk), arg3)?;
+            net.connect(src, src_port, snk, snk_port);
         }
         "disconnect" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1) = (arg(expr, 0)?, arg(expr, 1)?);
             let id = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let port = eval_usize(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) && port < net.inputs_in(id) {
-                net.disconnect(id, port);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg0)?;
+            check_port("input", port, net.inputs_in(id), arg1)?;
+            net.disconnect(id, port);
         }
         "connect_input" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1, arg2) = (arg(expr, 0)?, arg(expr, 1)?, arg(expr, 2)?);
             let global_in = eval_usize(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let snk = eval_nodeid(arg1, lapis)?;
-            let arg2 = expr.args.get(2)?;
             let snk_port = eval_usize(arg2, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if global_in < net.inputs() && net.contains(snk) && snk_port < net.inputs_in(snk) {
-                net.connect_input(global_in, snk, snk_port);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_port("input", global_in, net.inputs(), arg0)?;
+            check_node(net, snk, arg1)?;
+            check_port("input", snk_port, net.inputs_in(snk), arg2)?;
+            net.connect_input(global_in, snk, snk_port);
         }
         "pipe_input" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let snk = eval_nodeid(arg0, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(snk) {
-                net.pipe_input(snk);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, snk, arg0)?;
+            net.pipe_input(snk);
         }
         "connect_output" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1, arg2) = (arg(expr, 0)?, arg(expr, 1)?, arg(expr, 2)?);
             let src = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let src_port = eval_usize(arg1, lapis)?;
-            let arg2 = expr.args.get(2)?;
             let global_out = eval_usize(arg2, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if global_out < net.outputs() && net.contains(src) && src_port < net.outputs_in(src) {
-                net.connect_output(src, src_port, global_out);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, src, arg0)?;
+            check_port("output", src_port, net.outputs_in(src), arg1)?;
+            check_port("output", global_out, net.outputs(), arg2)?;
+            net.connect_output(src, src_port, global_out);
         }
         "disconnect_output" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let out = eval_usize(arg0, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if out < net.outputs() {
-                net.disconnect_output(out);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_port("output", out, net.outputs(), arg0)?;
+            net.disconnect_output(out);
         }
         "pipe_output" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let src = eval_nodeid(arg0, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(src) {
-                net.pipe_output(src);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, src, arg0)?;
+            net.pipe_output(src);
         }
         "pass_through" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1) = (arg(expr, 0)?, arg(expr, 1)?);
             let input = eval_usize(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let output = eval_usize(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if input < net.inputs() && output < net.outputs() {
-                net.pass_through(input, output);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_port("input", input, net.inputs(), arg0)?;
+            check_port("output", output, net.outputs(), arg1)?;
+            net.pass_through(input, output);
         }
         "pipe_all" => {
-            let arg0 = expr.args.first()?;
+            let (arg0, arg1) = (arg(expr, 0)?, arg(expr, 1)?);
             let src = eval_nodeid(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
             let snk = eval_nodeid(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(src) && net.contains(snk) {
-                net.pipe_all(src, snk);
-            }
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, src, arg0)?;
+            check_node(net, snk, arg1)?;
+            net.pipe_all(src, snk);
         }
         "set_source" => {
-            let id = eval_nodeid(expr.args.first()?, lapis)?;
-            let chan = eval_usize(expr.args.get(1)?, lapis)?;
-            let source = eval_source(expr.args.get(2)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.contains(id) && chan < net.inputs_in(id) {
-                if let Source::Local(src_id, src_chan) = source {
-                    if id != src_id && net.contains(src_id) && src_chan < net.outputs_in(src_id) {
-                        net.set_source(id, chan, source);
-                    }
-                } else if let Source::Global(g_chan) = source {
-                    if g_chan < net.inputs() {
-                        net.set_source(id, chan, source);
-                    }
-                } else {
-                    net.set_source(id, chan, source);
-                }
+            let (arg0, arg1, arg2) = (arg(expr, 0)?, arg(expr, 1)?, arg(expr, 2)?);
+            let id = eval_nodeid(arg0, lapis)?;
+            let chan = eval_usize(arg1, lapis)?;
+            let source = eval_source(arg2, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_node(net, id, arg0)?;
+            check_port("input", chan, net.inputs_in(id), arg1)?;
+            check_source(net, &source, arg2)?;
+            if let Source::Local(src_id, _) = source
+                && src_id == id
+            {
+                let help = "a node can't be its own source";
+                return Err(EvalError::new(arg2, ErrorKind::Invalid).suggest(help));
             }
+            net.set_source(id, chan, source);
         }
         "set_output_source" => {
-            let chan = eval_usize(expr.args.first()?, lapis)?;
-            let source = eval_source(expr.args.get(1)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if chan < net.outputs() {
-                if let Source::Local(src_id, src_chan) = source {
-                    if net.contains(src_id) && src_chan < net.outputs_in(src_id) {
-                        net.set_output_source(chan, source);
-                    }
-                } else if let Source::Global(i) = source {
-                    if i < net.inputs() {
-                        net.set_output_source(chan, source);
-                    }
-                } else {
-                    net.set_output_source(chan, source);
-                }
-            }
+            let (arg0, arg1) = (arg(expr, 0)?, arg(expr, 1)?);
+            let chan = eval_usize(arg0, lapis)?;
+            let source = eval_source(arg1, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            check_port("output", chan, net.outputs(), arg0)?;
+            check_source(net, &source, arg1)?;
+            net.set_output_source(chan, source);
         }
         "commit" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
-            if net.has_backend() {
-                net.commit();
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            if !net.has_backend() {
+                let help = format!("`{k}` has no backend to commit to");
+                return Err(EvalError::invalid().suggest(help));
             }
+            net.commit();
         }
         "set_sample_rate" => {
-            let arg = expr.args.first()?;
-            let sr = eval_float(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
+            let sr = eval_float(arg(expr, 0)?, lapis)?;
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
             net.set_sample_rate(sr);
         }
-        "reset" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let net = lapis.data.gmap.get_mut(&k)?;
+        _ => {
+            let net = lapis.data.gmap.get_mut(&k).or_invalid()?;
             net.reset();
         }
-        _ => {}
     }
-    None
+    Ok(())
 }
 
-pub fn eval_nodeid(expr: &Expr, lapis: &mut Lapis) -> Option<NodeId> {
+/// an error if the source isn't in the net
+fn check_source(net: &Net, source: &Source, arg: &Expr) -> EvalResult<()> {
+    match *source {
+        Source::Local(id, chan) => {
+            check_node(net, id, arg)?;
+            check_port("output", chan, net.outputs_in(id), arg)
+        }
+        Source::Global(chan) => check_port("input", chan, net.inputs(), arg),
+        Source::Zero => Ok(()),
+    }
+}
+
+pub fn eval_nodeid(expr: &Expr, lapis: &mut Lapis) -> EvalResult<NodeId> {
     match expr {
-        Expr::MethodCall(expr) => method_nodeid(expr, lapis),
-        Expr::Path(expr) => path_nodeid(&expr.path, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_nodeid(call, lapis).map_err(|e| e.within(call)),
+        Expr::Path(_) => path_nodeid(expr, lapis),
+        _ => Err(EvalError::mismatch("a node id")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn method_nodeid(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<NodeId> {
+fn method_nodeid(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<NodeId> {
     match expr.method.to_string().as_str() {
         "push" => {
-            let arg = expr.args.first()?;
-            let node = eval_net(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let g = lapis.data.gmap.get_mut(&k)?;
-            Some(g.push(Box::new(node)))
+            let k = net_receiver(expr, lapis)?;
+            let node = eval_net(arg(expr, 0)?, lapis)?;
+            let g = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            Ok(g.push(Box::new(node)))
         }
         "chain" => {
-            let arg = expr.args.first()?;
-            let node = eval_net(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let g = lapis.data.gmap.get_mut(&k)?;
-            Some(g.chain(Box::new(node)))
+            let k = net_receiver(expr, lapis)?;
+            let node = eval_net(arg(expr, 0)?, lapis)?;
+            let g = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            Ok(g.chain(Box::new(node)))
         }
         "fade_in" => {
-            let fade = path_fade(expr.args.first()?)?;
-            let fade_time = eval_float_f32(expr.args.get(1)?, lapis)?;
-            let unit = Box::new(eval_net(expr.args.get(2)?, lapis)?);
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let g = lapis.data.gmap.get_mut(&k)?;
-            Some(g.fade_in(fade, fade_time, unit))
+            let k = net_receiver(expr, lapis)?;
+            let fade = path_fade(arg(expr, 0)?).or_invalid()?;
+            let fade_time = eval_float_f32(arg(expr, 1)?, lapis)?;
+            let unit = Box::new(eval_net(arg(expr, 2)?, lapis)?);
+            let g = lapis.data.gmap.get_mut(&k).or_invalid()?;
+            Ok(g.fade_in(fade, fade_time, unit))
         }
         "nth" => {
-            let index = eval_usize(expr.args.first()?, lapis)?;
-            if let Expr::MethodCall(ref expr) = *expr.receiver
-                && expr.method == "ids"
-            {
-                let k = nth_path_ident(&expr.receiver, 0)?;
-                let g = &lapis.data.gmap.get(&k)?;
-                return g.ids().nth(index).copied();
+            let Expr::MethodCall(ids) = &*expr.receiver else {
+                return Err(EvalError::unknown_method());
+            };
+            if ids.method != "ids" {
+                return Err(EvalError::unknown_method());
+            }
+            let k = net_receiver(ids, lapis)?;
+            let index = eval_usize(arg(expr, 0)?, lapis)?;
+            let g = lapis.data.gmap.get(&k).or_invalid()?;
+            match g.ids().nth(index) {
+                Some(id) => Ok(*id),
+                None => {
+                    let kind = ErrorKind::OutOfRange {
+                        what: "node",
+                        index,
+                        len: g.ids().len(),
+                    };
+                    Err(EvalError::new(expr, kind))
+                }
             }
-            None
         }
-        _ => None,
+        _ => Err(EvalError::unknown_method()),
     }
 }
 
-pub fn eval_path_nodeid(expr: &Expr, lapis: &Lapis) -> Option<NodeId> {
-    if let Expr::Path(expr) = expr {
-        path_nodeid(&expr.path, lapis)
-    } else {
-        None
+pub fn eval_path_nodeid(expr: &Expr, lapis: &Lapis) -> EvalResult<NodeId> {
+    match expr {
+        Expr::Path(_) => path_nodeid(expr, lapis).map_err(|e| e.at(expr)),
+        _ => Err(EvalError::mismatch("a node id")),
     }
 }
 
-fn path_nodeid(expr: &Path, lapis: &Lapis) -> Option<NodeId> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.idmap.get(&k).copied()
+fn path_nodeid(expr: &Expr, lapis: &Lapis) -> EvalResult<NodeId> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a node id")?;
+    match lapis.data.idmap.get(&k) {
+        Some(id) => Ok(*id),
+        None => Err(path_error(expr, lapis, "a node id")),
+    }
 }
 
 macro_rules! tuple_call_match {
     ( $func:ident, $p:expr ) => {{
         match $p.len() {
-            1 => Some(Net::wrap(Box::new($func($p[0])))),
-            2 => Some(Net::wrap(Box::new($func(($p[0], $p[1]))))),
-            3 => Some(Net::wrap(Box::new($func(($p[0], $p[1], $p[2]))))),
-            4 => Some(Net::wrap(Box::new($func(($p[0], $p[1], $p[2], $p[3]))))),
-            5 => Some(Net::wrap(Box::new($func((
+            1 => Ok(Net::wrap(Box::new($func($p[0])))),
+            2 => Ok(Net::wrap(Box::new($func(($p[0], $p[1]))))),
+            3 => Ok(Net::wrap(Box::new($func(($p[0], $p[1], $p[2]))))),
+            4 => Ok(Net::wrap(Box::new($func(($p[0], $p[1], $p[2], $p[3]))))),
+            5 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4],
             ))))),
-            6 => Some(Net::wrap(Box::new($func((
+            6 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4], $p[5],
             ))))),
-            7 => Some(Net::wrap(Box::new($func((
+            7 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4], $p[5], $p[6],
             ))))),
-            8 => Some(Net::wrap(Box::new($func((
+            8 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4], $p[5], $p[6], $p[7],
             ))))),
-            9 => Some(Net::wrap(Box::new($func((
+            9 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4], $p[5], $p[6], $p[7], $p[8],
             ))))),
-            10 => Some(Net::wrap(Box::new($func((
+            10 => Ok(Net::wrap(Box::new($func((
                 $p[0], $p[1], $p[2], $p[3], $p[4], $p[5], $p[6], $p[7], $p[8], $p[9],
             ))))),
-            _ => None,
+            _ => Err(EvalError::invalid().suggest("a tuple of 1 to 10 floats")),
         }
     }};
 }
 
-fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<Net> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("a net")?;
     let args = accumulate_args(&expr.args, lapis);
+    let a = |n| float_arg(&args, n, expr, lapis);
     match func.as_str() {
         "Net" => {
-            let f = nth_path_ident(&expr.func, 1)?;
+            let f = nth_path_ident(&expr.func, 1).or_invalid()?;
             match f.as_str() {
                 "new" => {
-                    let ins = args.first()?;
-                    let outs = args.get(1)?;
-                    Some(Net::new(*ins as usize, *outs as usize))
+                    let ins = &a(0)?;
+                    let outs = &a(1)?;
+                    Ok(Net::new(*ins as usize, *outs as usize))
                 }
                 "scalar" => {
-                    let arg0 = expr.args.first()?;
-                    let arg1 = expr.args.get(1)?;
+                    let arg0 = arg(expr, 0)?;
+                    let arg1 = arg(expr, 1)?;
                     let chans = eval_usize(arg0, lapis)?;
                     let val = eval_float_f32(arg1, lapis)?;
-                    Some(Net::scalar(chans, val))
+                    Ok(Net::scalar(chans, val))
                 }
-                _ => None,
+                _ => Err(EvalError::unknown_function()),
             }
         }
         "Box" => {
-            if nth_path_ident(&expr.func, 1)? == "new" {
-                return eval_net(expr.args.first()?, lapis);
+            if nth_path_ident(&expr.func, 1).or_invalid()? == "new" {
+                return eval_net(arg(expr, 0)?, lapis);
             }
-            None
+            Err(EvalError::unknown_function())
         }
         "add" => {
-            let tuple = expr.args.first()?;
+            let tuple = arg(expr, 0)?;
             if let Expr::Tuple(expr) = tuple {
                 let p = accumulate_args(&expr.elems, lapis);
                 tuple_call_match!(add, p)
             } else {
                 match args.len() {
-                    1 => Some(Net::wrap(Box::new(add(args[0])))),
-                    _ => None,
+                    1 => Ok(Net::wrap(Box::new(add(args[0])))),
+                    _ => Err(one_or_tuple(&args, expr, lapis)),
                 }
             }
         }
         "adsr_live" => {
-            let a = args.first()?;
-            let d = args.get(1)?;
-            let s = args.get(2)?;
-            let r = args.get(3)?;
-            Some(Net::wrap(Box::new(adsr_live(*a, *d, *s, *r))))
+            let attack = &a(0)?;
+            let d = &a(1)?;
+            let s = &a(2)?;
+            let r = &a(3)?;
+            Ok(Net::wrap(Box::new(adsr_live(*attack, *d, *s, *r))))
         }
         "afollow" => {
-            let attack = args.first()?;
-            let release = args.get(1)?;
-            Some(Net::wrap(Box::new(afollow(*attack, *release))))
+            let attack = &a(0)?;
+            let release = &a(1)?;
+            Ok(Net::wrap(Box::new(afollow(*attack, *release))))
         }
         "allnest" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let net = eval_net(arg, lapis)?;
             if net.inputs() != 1 || net.outputs() != 1 {
-                return None;
+                return Err(needs(1, 1));
             }
             let node = Unit::<U1, U1>::new(Box::new(net));
-            Some(Net::wrap(Box::new(allnest(An(node)))))
+            Ok(Net::wrap(Box::new(allnest(An(node)))))
         }
         "allnest_c" => {
-            let coeff = args.first()?;
-            let arg = expr.args.get(1)?;
+            let coeff = &a(0)?;
+            let arg = arg(expr, 1)?;
             let net = eval_net(arg, lapis)?;
             if net.inputs() != 1 || net.outputs() != 1 {
-                return None;
+                return Err(needs(1, 1));
             }
             let node = Unit::<U1, U1>::new(Box::new(net));
-            Some(Net::wrap(Box::new(allnest_c(*coeff, An(node)))))
+            Ok(Net::wrap(Box::new(allnest_c(*coeff, An(node)))))
         }
-        "allpass" => Some(Net::wrap(Box::new(allpass()))),
+        "allpass" => Ok(Net::wrap(Box::new(allpass()))),
         "allpass_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(allpass_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(allpass_hz(*f, *q))))
         }
         "allpass_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(allpass_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(allpass_q(*q))))
         }
-        "allpole" => Some(Net::wrap(Box::new(allpole()))),
+        "allpole" => Ok(Net::wrap(Box::new(allpole()))),
         "allpole_delay" => {
-            let delay = args.first()?;
-            Some(Net::wrap(Box::new(allpole_delay(delay.max(0.0000001)))))
+            let delay = &a(0)?;
+            Ok(Net::wrap(Box::new(allpole_delay(delay.max(0.0000001)))))
         }
-        "bandpass" => Some(Net::wrap(Box::new(bandpass()))),
+        "bandpass" => Ok(Net::wrap(Box::new(bandpass()))),
         "bandpass_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(bandpass_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(bandpass_hz(*f, *q))))
         }
         "bandpass_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(bandpass_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(bandpass_q(*q))))
         }
-        "bandrez" => Some(Net::wrap(Box::new(bandrez()))),
+        "bandrez" => Ok(Net::wrap(Box::new(bandrez()))),
         "bandrez_hz" => {
-            let center = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(bandrez_hz(*center, *q))))
+            let center = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(bandrez_hz(*center, *q))))
         }
         "bandrez_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(bandrez_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(bandrez_q(*q))))
         }
         "bassdrum" => {
-            let sharpness = args.first()?;
-            let pitch0 = args.get(1)?;
-            let pitch1 = args.get(2)?;
-            Some(Net::wrap(Box::new(bassdrum(*sharpness, *pitch0, *pitch1))))
+            let sharpness = &a(0)?;
+            let pitch0 = &a(1)?;
+            let pitch1 = &a(2)?;
+            Ok(Net::wrap(Box::new(bassdrum(*sharpness, *pitch0, *pitch1))))
         }
-        "bell" => Some(Net::wrap(Box::new(bell()))),
+        "bell" => Ok(Net::wrap(Box::new(bell()))),
         "bell_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            let gain = args.get(2)?;
-            Some(Net::wrap(Box::new(bell_hz(*f, *q, *gain))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            let gain = &a(2)?;
+            Ok(Net::wrap(Box::new(bell_hz(*f, *q, *gain))))
         }
         "bell_q" => {
-            let q = args.first()?;
-            let gain = args.get(1)?;
-            Some(Net::wrap(Box::new(bell_q(*q, *gain))))
+            let q = &a(0)?;
+            let gain = &a(1)?;
+            Ok(Net::wrap(Box::new(bell_q(*q, *gain))))
         }
         "biquad" => {
-            let a1 = args.first()?;
-            let a2 = args.get(1)?;
-            let b0 = args.get(2)?;
-            let b1 = args.get(3)?;
-            let b2 = args.get(4)?;
-            Some(Net::wrap(Box::new(biquad(*a1, *a2, *b0, *b1, *b2))))
-        }
-        "biquad_bank" => None, // TODO
+            let a1 = &a(0)?;
+            let a2 = &a(1)?;
+            let b0 = &a(2)?;
+            let b1 = &a(3)?;
+            let b2 = &a(4)?;
+            Ok(Net::wrap(Box::new(biquad(*a1, *a2, *b0, *b1, *b2))))
+        }
+        "biquad_bank" => Err(EvalError::invalid()), // TODO
         "branch" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            if x.inputs() == y.inputs() {
-                Some(x ^ y)
-            } else {
-                None
-            }
+            check_channels(expr, "^", io(&x), io(&y))?;
+            Ok(x ^ y)
         }
-        "branchf" | "branchi" => None, //TODO
-        "brown" => Some(Net::wrap(Box::new(brown()))),
+        "branchf" | "branchi" => Err(EvalError::invalid()), //TODO
+        "brown" => Ok(Net::wrap(Box::new(brown()))),
         "bus" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            if x.outputs() == y.outputs() && x.inputs() == y.inputs() {
-                Some(x & y)
-            } else {
-                None
-            }
+            check_channels(expr, "&", io(&x), io(&y))?;
+            Ok(x & y)
         }
-        "busf" | "busi" => None, //TODO
-        "butterpass" => Some(Net::wrap(Box::new(butterpass()))),
+        "busf" | "busi" => Err(EvalError::invalid()), //TODO
+        "butterpass" => Ok(Net::wrap(Box::new(butterpass()))),
         "butterpass_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(butterpass_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(butterpass_hz(*f))))
         }
         "chorus" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let seed = eval_u64(arg, lapis)?;
-            let seperation = args.get(1)?;
-            let variation = args.get(2)?;
-            let mod_freq = args.get(3)?;
-            Some(Net::wrap(Box::new(chorus(
+            let seperation = &a(1)?;
+            let variation = &a(2)?;
+            let mod_freq = &a(3)?;
+            Ok(Net::wrap(Box::new(chorus(
                 seed,
                 *seperation,
                 *variation,
                 *mod_freq,
             ))))
         }
-        "clip" => Some(Net::wrap(Box::new(clip()))),
+        "clip" => Ok(Net::wrap(Box::new(clip()))),
         "clip_to" => {
-            let min = args.first()?;
-            let max = args.get(1)?;
-            Some(Net::wrap(Box::new(clip_to(min.min(*max), max.max(*min)))))
+            let min = &a(0)?;
+            let max = &a(1)?;
+            Ok(Net::wrap(Box::new(clip_to(min.min(*max), max.max(*min)))))
         }
         "cymbal" => {
-            let seed = eval_i64(expr.args.first()?, lapis)?;
-            Some(Net::wrap(Box::new(cymbal(seed))))
+            let seed = eval_i64(arg(expr, 0)?, lapis)?;
+            Ok(Net::wrap(Box::new(cymbal(seed))))
         }
         "dbell" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(dbell(shape))))
+            Ok(Net::wrap(Box::new(dbell(shape))))
         }
         "dbell_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let center = args.first()?;
-            let q = args.get(1)?;
-            let gain = args.get(2)?;
-            Some(Net::wrap(Box::new(dbell_hz(shape, *center, *q, *gain))))
+            let center = &a(0)?;
+            let q = &a(1)?;
+            let gain = &a(2)?;
+            Ok(Net::wrap(Box::new(dbell_hz(shape, *center, *q, *gain))))
         }
         "dc" | "constant" => {
-            let tuple = expr.args.first()?;
+            let tuple = arg(expr, 0)?;
             if let Expr::Tuple(expr) = tuple {
                 let p = accumulate_args(&expr.elems, lapis);
                 tuple_call_match!(dc, p)
             } else {
                 match args.len() {
-                    1 => Some(Net::wrap(Box::new(dc(args[0])))),
-                    _ => None,
+                    1 => Ok(Net::wrap(Box::new(dc(args[0])))),
+                    _ => Err(one_or_tuple(&args, expr, lapis)),
                 }
             }
         }
-        "dcblock" => Some(Net::wrap(Box::new(dcblock()))),
+        "dcblock" => Ok(Net::wrap(Box::new(dcblock()))),
         "dcblock_hz" => {
-            let cutoff = args.first()?;
-            Some(Net::wrap(Box::new(dcblock_hz(*cutoff))))
+            let cutoff = &a(0)?;
+            Ok(Net::wrap(Box::new(dcblock_hz(*cutoff))))
         }
-        "declick" => Some(Net::wrap(Box::new(declick()))),
+        "declick" => Ok(Net::wrap(Box::new(declick()))),
         "declick_s" => {
-            let t = args.first()?;
-            Some(Net::wrap(Box::new(declick_s(*t))))
+            let t = &a(0)?;
+            Ok(Net::wrap(Box::new(declick_s(*t))))
         }
         "delay" => {
-            let t = args.first()?;
-            Some(Net::wrap(Box::new(delay(t.max(0.)))))
+            let t = &a(0)?;
+            Ok(Net::wrap(Box::new(delay(t.max(0.)))))
         }
         "dhighpass" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(dhighpass(shape))))
+            Ok(Net::wrap(Box::new(dhighpass(shape))))
         }
         "dhighpass_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let cutoff = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(dhighpass_hz(shape, *cutoff, *q))))
+            let cutoff = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(dhighpass_hz(shape, *cutoff, *q))))
         }
         "dlowpass" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(dlowpass(shape))))
+            Ok(Net::wrap(Box::new(dlowpass(shape))))
         }
         "dlowpass_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let cutoff = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(dlowpass_hz(shape, *cutoff, *q))))
+            let cutoff = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(dlowpass_hz(shape, *cutoff, *q))))
         }
         "dresonator" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(dresonator(shape))))
+            Ok(Net::wrap(Box::new(dresonator(shape))))
         }
         "dresonator_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let center = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(dresonator_hz(shape, *center, *q))))
+            let center = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(dresonator_hz(shape, *center, *q))))
         }
-        "dsf_saw" => Some(Net::wrap(Box::new(dsf_saw()))),
+        "dsf_saw" => Ok(Net::wrap(Box::new(dsf_saw()))),
         "dsf_saw_r" => {
-            let roughness = args.first()?;
-            Some(Net::wrap(Box::new(dsf_saw_r(*roughness))))
+            let roughness = &a(0)?;
+            Ok(Net::wrap(Box::new(dsf_saw_r(*roughness))))
         }
-        "dsf_square" => Some(Net::wrap(Box::new(dsf_square()))),
+        "dsf_square" => Ok(Net::wrap(Box::new(dsf_square()))),
         "dsf_square_r" => {
-            let roughness = args.first()?;
-            Some(Net::wrap(Box::new(dsf_square_r(*roughness))))
+            let roughness = &a(0)?;
+            Ok(Net::wrap(Box::new(dsf_square_r(*roughness))))
         }
-        "envelope" | "envelope2" | "envelope3" | "envelope_in" => None, //TODO
-        "lfo" | "lfo2" | "lfo3" | "lfo_in" => None,                     //TODO
+        "envelope" | "envelope2" | "envelope3" | "envelope_in" => Err(EvalError::invalid()), //TODO
+        "lfo" | "lfo2" | "lfo3" | "lfo_in" => Err(EvalError::invalid()),                     //TODO
         "fbell" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(fbell(shape))))
+            Ok(Net::wrap(Box::new(fbell(shape))))
         }
         "fbell_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let center = args.first()?;
-            let q = args.get(1)?;
-            let gain = args.get(2)?;
-            Some(Net::wrap(Box::new(fbell_hz(shape, *center, *q, *gain))))
+            let center = &a(0)?;
+            let q = &a(1)?;
+            let gain = &a(2)?;
+            Ok(Net::wrap(Box::new(fbell_hz(shape, *center, *q, *gain))))
         }
-        "fdn" | "fdn2" => None, //TODO
+        "fdn" | "fdn2" => Err(EvalError::invalid()), //TODO
         "feedback" => {
-            let arg = expr.args.get(0)?;
+            let arg = arg(expr, 0)?;
             let net = eval_net(arg, lapis)?;
             if net.inputs() != net.outputs() {
-                return None;
+                let help = "the net needs as many inputs as outputs";
+                return Err(EvalError::invalid().suggest(help));
             }
-            Some(Net::wrap(Box::new(FeedbackUnit::new(0., Box::new(net)))))
+            Ok(Net::wrap(Box::new(FeedbackUnit::new(0., Box::new(net)))))
         }
-        "feedback2" => None, //TODO
+        "feedback2" => Err(EvalError::invalid()), //TODO
         "rfft" => {
-            let n = eval_usize(expr.args.first()?, lapis)?;
-            let offset = eval_usize(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(Rfft::new(n, offset)))))
+            let n = eval_usize(arg(expr, 0)?, lapis)?;
+            let offset = eval_usize(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(Rfft::new(n, offset)))))
         }
         "ifft" => {
-            let n = eval_usize(expr.args.first()?, lapis)?;
-            let offset = eval_usize(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(Ifft::new(n, offset)))))
+            let n = eval_usize(arg(expr, 0)?, lapis)?;
+            let offset = eval_usize(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(Ifft::new(n, offset)))))
         }
         "fhighpass" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(fhighpass(shape))))
+            Ok(Net::wrap(Box::new(fhighpass(shape))))
         }
         "fhighpass_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let cutoff = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(fhighpass_hz(shape, *cutoff, *q))))
+            let cutoff = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(fhighpass_hz(shape, *cutoff, *q))))
         }
         "fir" => {
-            let tuple = expr.args.first()?;
+            let tuple = arg(expr, 0)?;
             if let Expr::Tuple(expr) = tuple {
                 let p = accumulate_args(&expr.elems, lapis);
                 tuple_call_match!(fir, p)
             } else {
                 match args.len() {
-                    1 => Some(Net::wrap(Box::new(fir(args[0])))),
-                    _ => None,
+                    1 => Ok(Net::wrap(Box::new(fir(args[0])))),
+                    _ => Err(one_or_tuple(&args, expr, lapis)),
                 }
             }
         }
         "fir3" => {
-            let gain = args.first()?;
-            Some(Net::wrap(Box::new(fir3(*gain))))
+            let gain = &a(0)?;
+            Ok(Net::wrap(Box::new(fir3(*gain))))
         }
         "flanger" => {
-            let feedback_amount = eval_float_f32(expr.args.first()?, lapis)?;
-            let min_delay = eval_float_f32(expr.args.get(1)?, lapis)?;
-            let max_delay = eval_float_f32(expr.args.get(2)?, lapis)?;
+            let feedback_amount = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let min_delay = eval_float_f32(arg(expr, 1)?, lapis)?;
+            let max_delay = eval_float_f32(arg(expr, 2)?, lapis)?;
             let node = (pass() | pass())
                 & feedback2(
                     tap(min_delay, max_delay) | zero(),
                     shape(Tanh(feedback_amount)) | pass(),
                 );
             let node = node >> (pass() | sink());
-            Some(Net::wrap(Box::new(node)))
+            Ok(Net::wrap(Box::new(node)))
         }
         "flowpass" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(flowpass(shape))))
+            Ok(Net::wrap(Box::new(flowpass(shape))))
         }
         "flowpass_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let cutoff = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(flowpass_hz(shape, *cutoff, *q))))
+            let cutoff = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(flowpass_hz(shape, *cutoff, *q))))
         }
         "follow" => {
-            let response_time = args.first()?;
-            Some(Net::wrap(Box::new(follow(*response_time))))
+            let response_time = &a(0)?;
+            Ok(Net::wrap(Box::new(follow(*response_time))))
         }
         "fresonator" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(fresonator(shape))))
+            Ok(Net::wrap(Box::new(fresonator(shape))))
         }
         "fresonator_hz" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shape = call_shape(arg, lapis)?;
-            let center = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(fresonator_hz(shape, *center, *q))))
+            let center = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(fresonator_hz(shape, *center, *q))))
         }
-        "hammond" => Some(Net::wrap(Box::new(hammond()))),
+        "hammond" => Ok(Net::wrap(Box::new(hammond()))),
         "hammond_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(hammond_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(hammond_hz(*f))))
         }
-        "highpass" => Some(Net::wrap(Box::new(highpass()))),
+        "highpass" => Ok(Net::wrap(Box::new(highpass()))),
         "highpass_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(highpass_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(highpass_hz(*f, *q))))
         }
         "highpass_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(highpass_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(highpass_q(*q))))
         }
-        "highpole" => Some(Net::wrap(Box::new(highpole()))),
+        "highpole" => Ok(Net::wrap(Box::new(highpole()))),
         "highpole_hz" => {
-            let cutoff = args.first()?;
-            Some(Net::wrap(Box::new(highpole_hz(*cutoff))))
+            let cutoff = &a(0)?;
+            Ok(Net::wrap(Box::new(highpole_hz(*cutoff))))
         }
-        "highshelf" => Some(Net::wrap(Box::new(highshelf()))),
+        "highshelf" => Ok(Net::wrap(Box::new(highshelf()))),
         "highshelf_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            let gain = args.get(2)?;
-            Some(Net::wrap(Box::new(highshelf_hz(*f, *q, *gain))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            let gain = &a(2)?;
+            Ok(Net::wrap(Box::new(highshelf_hz(*f, *q, *gain))))
         }
         "highshelf_q" => {
-            let q = args.first()?;
-            let gain = args.get(1)?;
-            Some(Net::wrap(Box::new(highshelf_q(*q, *gain))))
+            let q = &a(0)?;
+            let gain = &a(1)?;
+            Ok(Net::wrap(Box::new(highshelf_q(*q, *gain))))
         }
         "hold" => {
-            let variability = args.first()?;
-            Some(Net::wrap(Box::new(hold(*variability))))
+            let variability = &a(0)?;
+            Ok(Net::wrap(Box::new(hold(*variability))))
         }
         "hold_hz" => {
-            let f = args.first()?;
-            let variability = args.get(1)?;
-            Some(Net::wrap(Box::new(hold_hz(*f, *variability))))
+            let f = &a(0)?;
+            let variability = &a(1)?;
+            Ok(Net::wrap(Box::new(hold_hz(*f, *variability))))
         }
         "impulse" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
+            let n = generic_usize(expr, 0)?;
             let impulse = Net::wrap(Box::new(impulse::<U1>()));
             let split = Net::wrap(Box::new(MultiSplitUnit::new(1, n)));
-            Some(Net::wrap(Box::new(impulse >> split)))
+            Ok(Net::wrap(Box::new(impulse >> split)))
         }
         "input" => {
             let r = lapis.input_receiver.clone();
-            let channels = lapis.in_stream_config.0.as_ref()?.channels;
+            let Some(config) = lapis.in_stream_config.0.as_ref() else {
+                return Err(EvalError::invalid().suggest("there's no input stream"));
+            };
+            let channels = config.channels;
             if let (Some(i1), Some(i2)) = (args.first(), args.get(1)) {
                 let i1 = *i1 as usize;
                 let i2 = *i2 as usize;
@@ -922,7 +1000,7 @@ fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
                     }
                     out
                 });
-                return Some(Net::wrap(Box::new(node)));
+                return Ok(Net::wrap(Box::new(node)));
             } else if let Some(i) = args.first() {
                 let i = *i as usize;
                 let node = map(move |_: &Frame<f32, U0>| {
@@ -936,319 +1014,292 @@ fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
                     }
                     out
                 });
-                return Some(Net::wrap(Box::new(node)));
+                return Ok(Net::wrap(Box::new(node)));
             }
-            None
+            Err(a(0).err().unwrap_or_else(EvalError::invalid))
         }
         "join" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            Some(Net::wrap(Box::new(MultiJoinUnit::new(1, n))))
+            let n = generic_usize(expr, 0)?;
+            Ok(Net::wrap(Box::new(MultiJoinUnit::new(1, n))))
         }
         "limiter" => {
-            let attack = args.first()?;
-            let release = args.get(1)?;
-            Some(Net::wrap(Box::new(limiter(*attack, *release))))
+            let attack = &a(0)?;
+            let release = &a(1)?;
+            Ok(Net::wrap(Box::new(limiter(*attack, *release))))
         }
         "limiter_stereo" => {
-            let attack = args.first()?;
-            let release = args.get(1)?;
-            Some(Net::wrap(Box::new(limiter_stereo(*attack, *release))))
+            let attack = &a(0)?;
+            let release = &a(1)?;
+            Ok(Net::wrap(Box::new(limiter_stereo(*attack, *release))))
         }
-        "lorenz" => Some(Net::wrap(Box::new(lorenz()))),
-        "lowpass" => Some(Net::wrap(Box::new(lowpass()))),
+        "lorenz" => Ok(Net::wrap(Box::new(lorenz()))),
+        "lowpass" => Ok(Net::wrap(Box::new(lowpass()))),
         "lowpass_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(lowpass_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(lowpass_hz(*f, *q))))
         }
         "lowpass_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(lowpass_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(lowpass_q(*q))))
         }
-        "lowpole" => Some(Net::wrap(Box::new(lowpole()))),
+        "lowpole" => Ok(Net::wrap(Box::new(lowpole()))),
         "lowpole_hz" => {
-            let cutoff = args.first()?;
-            Some(Net::wrap(Box::new(lowpole_hz(*cutoff))))
+            let cutoff = &a(0)?;
+            Ok(Net::wrap(Box::new(lowpole_hz(*cutoff))))
         }
-        "lowrez" => Some(Net::wrap(Box::new(lowrez()))),
+        "lowrez" => Ok(Net::wrap(Box::new(lowrez()))),
         "lowrez_hz" => {
-            let cutoff = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(lowrez_hz(*cutoff, *q))))
+            let cutoff = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(lowrez_hz(*cutoff, *q))))
         }
         "lowrez_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(lowrez_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(lowrez_q(*q))))
         }
-        "lowshelf" => Some(Net::wrap(Box::new(lowshelf()))),
+        "lowshelf" => Ok(Net::wrap(Box::new(lowshelf()))),
         "lowshelf_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            let gain = args.get(2)?;
-            Some(Net::wrap(Box::new(lowshelf_hz(*f, *q, *gain))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            let gain = &a(2)?;
+            Ok(Net::wrap(Box::new(lowshelf_hz(*f, *q, *gain))))
         }
         "lowshelf_q" => {
-            let q = args.first()?;
-            let gain = args.get(1)?;
-            Some(Net::wrap(Box::new(lowshelf_q(*q, *gain))))
+            let q = &a(0)?;
+            let gain = &a(1)?;
+            Ok(Net::wrap(Box::new(lowshelf_q(*q, *gain))))
         }
-        "map" => None, //TODO i'll be seeing you...
+        "map" => Err(EvalError::invalid()), //TODO i'll be seeing you...
         "f" => {
-            let f = eval_string(expr.args.first()?, lapis)?;
+            let f = eval_string(arg(expr, 0)?, lapis)?;
             match f.as_str() {
-                "rise" => Some(Net::wrap(Box::new(maps::rise()))),
-                "fall" => Some(Net::wrap(Box::new(maps::fall()))),
-                ">" => Some(Net::wrap(Box::new(maps::gt()))),
-                "<" => Some(Net::wrap(Box::new(maps::lt()))),
-                "==" => Some(Net::wrap(Box::new(maps::eq()))),
-                "!=" => Some(Net::wrap(Box::new(maps::neq()))),
-                ">=" => Some(Net::wrap(Box::new(maps::ge()))),
-                "<=" => Some(Net::wrap(Box::new(maps::le()))),
-                "min" => Some(Net::wrap(Box::new(maps::min()))),
-                "max" => Some(Net::wrap(Box::new(maps::max()))),
-                "pow" => Some(Net::wrap(Box::new(maps::pow()))),
-                "rem" => Some(Net::wrap(Box::new(maps::rem()))),
-                "rem_euclid" => Some(Net::wrap(Box::new(maps::rem_euclid()))),
-                "rem2" => Some(Net::wrap(Box::new(maps::rem2()))),
-                "log" => Some(Net::wrap(Box::new(maps::log()))),
-                "bitand" => Some(Net::wrap(Box::new(maps::bitand()))),
-                "bitor" => Some(Net::wrap(Box::new(maps::bitor()))),
-                "bitxor" => Some(Net::wrap(Box::new(maps::bitxor()))),
-                "shl" => Some(Net::wrap(Box::new(maps::shl()))),
-                "shr" => Some(Net::wrap(Box::new(maps::shr()))),
-                "lerp" => Some(Net::wrap(Box::new(maps::lerp()))),
-                "lerp11" => Some(Net::wrap(Box::new(maps::lerp11()))),
-                "delerp" => Some(Net::wrap(Box::new(maps::delerp()))),
-                "delerp11" => Some(Net::wrap(Box::new(maps::delerp11()))),
-                "xerp" => Some(Net::wrap(Box::new(maps::xerp()))),
-                "xerp11" => Some(Net::wrap(Box::new(maps::xerp11()))),
-                "dexerp" => Some(Net::wrap(Box::new(maps::dexerp()))),
-                "dexerp11" => Some(Net::wrap(Box::new(maps::dexerp11()))),
-                "abs" => Some(Net::wrap(Box::new(maps::abs()))),
-                "signum" => Some(Net::wrap(Box::new(maps::signum()))),
-                "floor" => Some(Net::wrap(Box::new(maps::floor()))),
-                "fract" => Some(Net::wrap(Box::new(maps::fract()))),
-                "ceil" => Some(Net::wrap(Box::new(maps::ceil()))),
-                "round" => Some(Net::wrap(Box::new(maps::round()))),
-                "sqrt" => Some(Net::wrap(Box::new(maps::sqrt()))),
-                "exp" => Some(Net::wrap(Box::new(maps::exp()))),
-                "exp2" => Some(Net::wrap(Box::new(maps::exp2()))),
-                "exp10" => Some(Net::wrap(Box::new(maps::exp10()))),
-                "exp_m1" => Some(Net::wrap(Box::new(maps::exp_m1()))),
-                "ln_1p" => Some(Net::wrap(Box::new(maps::ln_1p()))),
-                "ln" => Some(Net::wrap(Box::new(maps::ln()))),
-                "log2" => Some(Net::wrap(Box::new(maps::log2()))),
-                "log10" => Some(Net::wrap(Box::new(maps::log10()))),
-                "hypot" => Some(Net::wrap(Box::new(maps::hypot()))),
-                "atan2" => Some(Net::wrap(Box::new(maps::atan2()))),
-                "to_pol" => Some(Net::wrap(Box::new(maps::to_pol()))),
-                "to_car" => Some(Net::wrap(Box::new(maps::to_car()))),
-                "to_deg" => Some(Net::wrap(Box::new(maps::to_deg()))),
-                "to_rad" => Some(Net::wrap(Box::new(maps::to_rad()))),
-                "sin" => Some(Net::wrap(Box::new(maps::sin()))),
-                "cos" => Some(Net::wrap(Box::new(maps::cos()))),
-                "tan" => Some(Net::wrap(Box::new(maps::tan()))),
-                "asin" => Some(Net::wrap(Box::new(maps::asin()))),
-                "acos" => Some(Net::wrap(Box::new(maps::acos()))),
-                "atan" => Some(Net::wrap(Box::new(maps::atan()))),
-                "sinh" => Some(Net::wrap(Box::new(maps::sinh()))),
-                "cosh" => Some(Net::wrap(Box::new(maps::cosh()))),
-                "tanh" => Some(Net::wrap(Box::new(maps::tanh()))),
-                "asinh" => Some(Net::wrap(Box::new(maps::asinh()))),
-                "acosh" => Some(Net::wrap(Box::new(maps::acosh()))),
-                "atanh" => Some(Net::wrap(Box::new(maps::atanh()))),
-                "squared" => Some(Net::wrap(Box::new(maps::squared()))),
-                "cubed" => Some(Net::wrap(Box::new(maps::cubed()))),
-                "dissonance" => Some(Net::wrap(Box::new(maps::dissonance()))),
-                "dissonance_max" => Some(Net::wrap(Box::new(maps::dissonance_max()))),
-                "db_amp" => Some(Net::wrap(Box::new(maps::db_amp()))),
-                "amp_db" => Some(Net::wrap(Box::new(maps::amp_db()))),
-                "a_weight" => Some(Net::wrap(Box::new(maps::a_weight()))),
-                "m_weight" => Some(Net::wrap(Box::new(maps::m_weight()))),
-                "spline" => Some(Net::wrap(Box::new(maps::spline()))),
-                "spline_mono" => Some(Net::wrap(Box::new(maps::spline_mono()))),
-                "softsign" => Some(Net::wrap(Box::new(maps::softsign()))),
-                "softexp" => Some(Net::wrap(Box::new(maps::softexp()))),
-                "softmix" => Some(Net::wrap(Box::new(maps::softmix()))),
-                "smooth3" => Some(Net::wrap(Box::new(maps::smooth3()))),
-                "smooth5" => Some(Net::wrap(Box::new(maps::smooth5()))),
-                "smooth7" => Some(Net::wrap(Box::new(maps::smooth7()))),
-                "smooth9" => Some(Net::wrap(Box::new(maps::smooth9()))),
-                "uparc" => Some(Net::wrap(Box::new(maps::uparc()))),
-                "downarc" => Some(Net::wrap(Box::new(maps::downarc()))),
-                "sine_ease" => Some(Net::wrap(Box::new(maps::sine_ease()))),
-                "sin_hz" => Some(Net::wrap(Box::new(maps::sin_hz()))),
-                "cos_hz" => Some(Net::wrap(Box::new(maps::cos_hz()))),
-                "sqr_hz" => Some(Net::wrap(Box::new(maps::sqr_hz()))),
-                "tri_hz" => Some(Net::wrap(Box::new(maps::tri_hz()))),
-                "rnd1" => Some(Net::wrap(Box::new(maps::rnd1()))),
-                "rnd2" => Some(Net::wrap(Box::new(maps::rnd2()))),
-                "spline_noise" => Some(Net::wrap(Box::new(maps::spline_noise()))),
-                "fractal_noise" => Some(Net::wrap(Box::new(maps::fractal_noise()))),
-                "recip" => Some(Net::wrap(Box::new(maps::recip()))),
-                "normal" => Some(Net::wrap(Box::new(maps::normal()))),
-                "wrap" => Some(Net::wrap(Box::new(maps::wrap()))),
-                "mirror" => Some(Net::wrap(Box::new(maps::mirror()))),
-                _ => None,
+                "rise" => Ok(Net::wrap(Box::new(maps::rise()))),
+                "fall" => Ok(Net::wrap(Box::new(maps::fall()))),
+                ">" => Ok(Net::wrap(Box::new(maps::gt()))),
+                "<" => Ok(Net::wrap(Box::new(maps::lt()))),
+                "==" => Ok(Net::wrap(Box::new(maps::eq()))),
+                "!=" => Ok(Net::wrap(Box::new(maps::neq()))),
+                ">=" => Ok(Net::wrap(Box::new(maps::ge()))),
+                "<=" => Ok(Net::wrap(Box::new(maps::le()))),
+                "min" => Ok(Net::wrap(Box::new(maps::min()))),
+                "max" => Ok(Net::wrap(Box::new(maps::max()))),
+                "pow" => Ok(Net::wrap(Box::new(maps::pow()))),
+                "rem" => Ok(Net::wrap(Box::new(maps::rem()))),
+                "rem_euclid" => Ok(Net::wrap(Box::new(maps::rem_euclid()))),
+                "rem2" => Ok(Net::wrap(Box::new(maps::rem2()))),
+                "log" => Ok(Net::wrap(Box::new(maps::log()))),
+                "bitand" => Ok(Net::wrap(Box::new(maps::bitand()))),
+                "bitor" => Ok(Net::wrap(Box::new(maps::bitor()))),
+                "bitxor" => Ok(Net::wrap(Box::new(maps::bitxor()))),
+                "shl" => Ok(Net::wrap(Box::new(maps::shl()))),
+                "shr" => Ok(Net::wrap(Box::new(maps::shr()))),
+                "lerp" => Ok(Net::wrap(Box::new(maps::lerp()))),
+                "lerp11" => Ok(Net::wrap(Box::new(maps::lerp11()))),
+                "delerp" => Ok(Net::wrap(Box::new(maps::delerp()))),
+                "delerp11" => Ok(Net::wrap(Box::new(maps::delerp11()))),
+                "xerp" => Ok(Net::wrap(Box::new(maps::xerp()))),
+                "xerp11" => Ok(Net::wrap(Box::new(maps::xerp11()))),
+                "dexerp" => Ok(Net::wrap(Box::new(maps::dexerp()))),
+                "dexerp11" => Ok(Net::wrap(Box::new(maps::dexerp11()))),
+                "abs" => Ok(Net::wrap(Box::new(maps::abs()))),
+                "signum" => Ok(Net::wrap(Box::new(maps::signum()))),
+                "floor" => Ok(Net::wrap(Box::new(maps::floor()))),
+                "fract" => Ok(Net::wrap(Box::new(maps::fract()))),
+                "ceil" => Ok(Net::wrap(Box::new(maps::ceil()))),
+                "round" => Ok(Net::wrap(Box::new(maps::round()))),
+                "sqrt" => Ok(Net::wrap(Box::new(maps::sqrt()))),
+                "exp" => Ok(Net::wrap(Box::new(maps::exp()))),
+                "exp2" => Ok(Net::wrap(Box::new(maps::exp2()))),
+                "exp10" => Ok(Net::wrap(Box::new(maps::exp10()))),
+                "exp_m1" => Ok(Net::wrap(Box::new(maps::exp_m1()))),
+                "ln_1p" => Ok(Net::wrap(Box::new(maps::ln_1p()))),
+                "ln" => Ok(Net::wrap(Box::new(maps::ln()))),
+                "log2" => Ok(Net::wrap(Box::new(maps::log2()))),
+                "log10" => Ok(Net::wrap(Box::new(maps::log10()))),
+                "hypot" => Ok(Net::wrap(Box::new(maps::hypot()))),
+                "atan2" => Ok(Net::wrap(Box::new(maps::atan2()))),
+                "to_pol" => Ok(Net::wrap(Box::new(maps::to_pol()))),
+                "to_car" => Ok(Net::wrap(Box::new(maps::to_car()))),
+                "to_deg" => Ok(Net::wrap(Box::new(maps::to_deg()))),
+                "to_rad" => Ok(Net::wrap(Box::new(maps::to_rad()))),
+                "sin" => Ok(Net::wrap(Box::new(maps::sin()))),
+                "cos" => Ok(Net::wrap(Box::new(maps::cos()))),
+                "tan" => Ok(Net::wrap(Box::new(maps::tan()))),
+                "asin" => Ok(Net::wrap(Box::new(maps::asin()))),
+                "acos" => Ok(Net::wrap(Box::new(maps::acos()))),
+                "atan" => Ok(Net::wrap(Box::new(maps::atan()))),
+                "sinh" => Ok(Net::wrap(Box::new(maps::sinh()))),
+                "cosh" => Ok(Net::wrap(Box::new(maps::cosh()))),
+                "tanh" => Ok(Net::wrap(Box::new(maps::tanh()))),
+                "asinh" => Ok(Net::wrap(Box::new(maps::asinh()))),
+                "acosh" => Ok(Net::wrap(Box::new(maps::acosh()))),
+                "atanh" => Ok(Net::wrap(Box::new(maps::atanh()))),
+                "squared" => Ok(Net::wrap(Box::new(maps::squared()))),
+                "cubed" => Ok(Net::wrap(Box::new(maps::cubed()))),
+                "dissonance" => Ok(Net::wrap(Box::new(maps::dissonance()))),
+                "dissonance_max" => Ok(Net::wrap(Box::new(maps::dissonance_max()))),
+                "db_amp" => Ok(Net::wrap(Box::new(maps::db_amp()))),
+                "amp_db" => Ok(Net::wrap(Box::new(maps::amp_db()))),
+                "a_weight" => Ok(Net::wrap(Box::new(maps::a_weight()))),
+                "m_weight" => Ok(Net::wrap(Box::new(maps::m_weight()))),
+                "spline" => Ok(Net::wrap(Box::new(maps::spline()))),
+                "spline_mono" => Ok(Net::wrap(Box::new(maps::spline_mono()))),
+                "softsign" => Ok(Net::wrap(Box::new(maps::softsign()))),
+                "softexp" => Ok(Net::wrap(Box::new(maps::softexp()))),
+                "softmix" => Ok(Net::wrap(Box::new(maps::softmix()))),
+                "smooth3" => Ok(Net::wrap(Box::new(maps::smooth3()))),
+                "smooth5" => Ok(Net::wrap(Box::new(maps::smooth5()))),
+                "smooth7" => Ok(Net::wrap(Box::new(maps::smooth7()))),
+                "smooth9" => Ok(Net::wrap(Box::new(maps::smooth9()))),
+                "uparc" => Ok(Net::wrap(Box::new(maps::uparc()))),
+                "downarc" => Ok(Net::wrap(Box::new(maps::downarc()))),
+                "sine_ease" => Ok(Net::wrap(Box::new(maps::sine_ease()))),
+                "sin_hz" => Ok(Net::wrap(Box::new(maps::sin_hz()))),
+                "cos_hz" => Ok(Net::wrap(Box::new(maps::cos_hz()))),
+                "sqr_hz" => Ok(Net::wrap(Box::new(maps::sqr_hz()))),
+                "tri_hz" => Ok(Net::wrap(Box::new(maps::tri_hz()))),
+                "rnd1" => Ok(Net::wrap(Box::new(maps::rnd1()))),
+                "rnd2" => Ok(Net::wrap(Box::new(maps::rnd2()))),
+                "spline_noise" => Ok(Net::wrap(Box::new(maps::spline_noise()))),
+                "fractal_noise" => Ok(Net::wrap(Box::new(maps::fractal_noise()))),
+                "recip" => Ok(Net::wrap(Box::new(maps::recip()))),
+                "normal" => Ok(Net::wrap(Box::new(maps::normal()))),
+                "wrap" => Ok(Net::wrap(Box::new(maps::wrap()))),
+                "mirror" => Ok(Net::wrap(Box::new(maps::mirror()))),
+                _ => Err(EvalError::new(arg(expr, 0)?, ErrorKind::Invalid)),
             }
         }
         "meter" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let m = eval_meter(arg, lapis)?;
-            Some(Net::wrap(Box::new(meter(m))))
+            Ok(Net::wrap(Box::new(meter(m))))
         }
-        "mls" => Some(Net::wrap(Box::new(mls()))),
+        "mls" => Ok(Net::wrap(Box::new(mls()))),
         "mls_bits" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let n = eval_u64(arg, lapis)?;
-            Some(Net::wrap(Box::new(mls_bits(n))))
+            Ok(Net::wrap(Box::new(mls_bits(n))))
         }
         "monitor" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let shared = eval_shared(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let meter = eval_meter(arg1, lapis)?;
-            Some(Net::wrap(Box::new(monitor(&shared, meter))))
+            Ok(Net::wrap(Box::new(monitor(&shared, meter))))
         }
-        "moog" => Some(Net::wrap(Box::new(moog()))),
+        "moog" => Ok(Net::wrap(Box::new(moog()))),
         "moog_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(moog_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(moog_hz(*f, *q))))
         }
         "moog_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(moog_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(moog_q(*q))))
         }
-        "morph" => Some(Net::wrap(Box::new(morph()))),
+        "morph" => Ok(Net::wrap(Box::new(morph()))),
         "morph_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            let morph = args.get(2)?;
-            Some(Net::wrap(Box::new(lowshelf_hz(*f, *q, *morph))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            let morph = &a(2)?;
+            Ok(Net::wrap(Box::new(lowshelf_hz(*f, *q, *morph))))
         }
         "mul" => {
-            let tuple = expr.args.first()?;
+            let tuple = arg(expr, 0)?;
             if let Expr::Tuple(expr) = tuple {
                 let p = accumulate_args(&expr.elems, lapis);
                 tuple_call_match!(mul, p)
             } else {
                 match args.len() {
-                    1 => Some(Net::wrap(Box::new(mul(args[0])))),
-                    _ => None,
+                    1 => Ok(Net::wrap(Box::new(mul(args[0])))),
+                    _ => Err(one_or_tuple(&args, expr, lapis)),
                 }
             }
         }
         "multijoin" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            let m = nth_path_generic(&expr.func, 1)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            Some(Net::wrap(Box::new(MultiJoinUnit::new(n, m))))
+            let n = generic_usize(expr, 0)?;
+            let m = generic_usize(expr, 1)?;
+            Ok(Net::wrap(Box::new(MultiJoinUnit::new(n, m))))
         }
         "multipass" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
+            let n = generic_usize(expr, 0)?;
             let mut g = Net::new(0, 0);
             for _ in 0..n {
                 g = g | pass();
             }
-            Some(Net::wrap(Box::new(g)))
+            Ok(Net::wrap(Box::new(g)))
         }
         "multisink" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
+            let n = generic_usize(expr, 0)?;
             let mut g = Net::new(0, 0);
             for _ in 0..n {
                 g = g | sink();
             }
-            Some(Net::wrap(Box::new(g)))
+            Ok(Net::wrap(Box::new(g)))
         }
         "multisplit" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            let m = nth_path_generic(&expr.func, 1)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            Some(Net::wrap(Box::new(MultiSplitUnit::new(n, m))))
-        }
-        "multitap" | "multitap_linear" => None, //TODO
+            let n = generic_usize(expr, 0)?;
+            let m = generic_usize(expr, 1)?;
+            Ok(Net::wrap(Box::new(MultiSplitUnit::new(n, m))))
+        }
+        "multitap" | "multitap_linear" => Err(EvalError::invalid()), //TODO
         "multitick" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
+            let n = generic_usize(expr, 0)?;
             let mut g = Net::new(0, 0);
             for _ in 0..n {
                 g = g | tick();
             }
-            Some(Net::wrap(Box::new(g)))
+            Ok(Net::wrap(Box::new(g)))
         }
         "multizero" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
+            let n = generic_usize(expr, 0)?;
             let mut g = Net::new(0, 0);
             for _ in 0..n {
                 g = g | zero();
             }
-            Some(Net::wrap(Box::new(g)))
+            Ok(Net::wrap(Box::new(g)))
         }
-        "noise" => Some(Net::wrap(Box::new(noise()))),
-        "notch" => Some(Net::wrap(Box::new(notch()))),
+        "noise" => Ok(Net::wrap(Box::new(noise()))),
+        "notch" => Ok(Net::wrap(Box::new(notch()))),
         "notch_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(notch_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(notch_hz(*f, *q))))
         }
         "notch_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(notch_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(notch_q(*q))))
         }
-        "organ" => Some(Net::wrap(Box::new(organ()))),
+        "organ" => Ok(Net::wrap(Box::new(organ()))),
         "organ_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(organ_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(organ_hz(*f))))
         }
-        "oversample" => None, //TODO
+        "oversample" => Err(EvalError::invalid()), //TODO
         "pan" => {
-            let p = args.first()?;
-            Some(Net::wrap(Box::new(pan(*p))))
+            let p = &a(0)?;
+            Ok(Net::wrap(Box::new(pan(*p))))
         }
-        "panner" => Some(Net::wrap(Box::new(panner()))),
-        "pass" => Some(Net::wrap(Box::new(pass()))),
-        "peak" => Some(Net::wrap(Box::new(peak()))),
+        "panner" => Ok(Net::wrap(Box::new(panner()))),
+        "pass" => Ok(Net::wrap(Box::new(pass()))),
+        "peak" => Ok(Net::wrap(Box::new(peak()))),
         "peak_hz" => {
-            let f = args.first()?;
-            let q = args.get(1)?;
-            Some(Net::wrap(Box::new(peak_hz(*f, *q))))
+            let f = &a(0)?;
+            let q = &a(1)?;
+            Ok(Net::wrap(Box::new(peak_hz(*f, *q))))
         }
         "peak_q" => {
-            let q = args.first()?;
-            Some(Net::wrap(Box::new(peak_q(*q))))
+            let q = &a(0)?;
+            Ok(Net::wrap(Box::new(peak_q(*q))))
         }
         "pebbles" => {
-            let speed = eval_float_f32(expr.args.first()?, lapis)?;
-            let seed = eval_u64(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(pebbles(speed, seed))))
+            let speed = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let seed = eval_u64(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(pebbles(speed, seed))))
         }
         "phaser" => {
-            let feedback_amount = eval_float_f32(expr.args.first()?, lapis)?;
+            let feedback_amount = eval_float_f32(arg(expr, 0)?, lapis)?;
             let node = (pass() | pass())
                 & feedback(
                     pipei::<U10, _, _>(|_i| add((0.0, 0.1)) >> !allpole())
@@ -1257,75 +1308,69 @@ fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
             let node = (pass() | map(|i: &Frame<f32, U1>| lerp(2.0, 20.0, clamp01(i[0]))))
                 >> node
                 >> (pass() | sink());
-            Some(Net::wrap(Box::new(node)))
+            Ok(Net::wrap(Box::new(node)))
         }
-        "pink" => Some(Net::wrap(Box::new(pink()))),
-        "pinkpass" => Some(Net::wrap(Box::new(pinkpass()))),
+        "pink" => Ok(Net::wrap(Box::new(pink()))),
+        "pinkpass" => Ok(Net::wrap(Box::new(pinkpass()))),
         "pipe" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            if x.outputs() == y.inputs() {
-                Some(x >> y)
-            } else {
-                None
-            }
+            check_channels(expr, ">>", io(&x), io(&y))?;
+            Ok(x >> y)
         }
-        "pipef" | "pipei" => None, //TODO
+        "pipef" | "pipei" => Err(EvalError::invalid()), //TODO
         "pluck" => {
-            let freq = args.first()?;
-            let gain_per_sec = args.get(1)?;
-            let hf_damp = args.get(2)?;
-            Some(Net::wrap(Box::new(pluck(*freq, *gain_per_sec, *hf_damp))))
+            let freq = &a(0)?;
+            let gain_per_sec = &a(1)?;
+            let hf_damp = &a(2)?;
+            Ok(Net::wrap(Box::new(pluck(*freq, *gain_per_sec, *hf_damp))))
         }
-        "poly_saw" => Some(Net::wrap(Box::new(poly_saw()))),
+        "poly_saw" => Ok(Net::wrap(Box::new(poly_saw()))),
         "poly_saw_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(poly_saw_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(poly_saw_hz(*f))))
         }
-        "poly_square" => Some(Net::wrap(Box::new(poly_square()))),
+        "poly_square" => Ok(Net::wrap(Box::new(poly_square()))),
         "poly_square_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(poly_square_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(poly_square_hz(*f))))
         }
         "product" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            if x.outputs() == y.outputs() {
-                Some(x * y)
-            } else {
-                None
-            }
+            check_channels(expr, "*", io(&x), io(&y))?;
+            Ok(x * y)
         }
-        "pulse" => Some(Net::wrap(Box::new(pulse()))),
-        "ramp" => Some(Net::wrap(Box::new(ramp()))),
+        "pulse" => Ok(Net::wrap(Box::new(pulse()))),
+        "ramp" => Ok(Net::wrap(Box::new(ramp()))),
         "ramp_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(ramp_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(ramp_hz(*f))))
         }
-        "resample" => None, //TODO
-        "resonator" => Some(Net::wrap(Box::new(resonator()))),
+        "resample" => Err(EvalError::invalid()), //TODO
+        "resonator" => Ok(Net::wrap(Box::new(resonator()))),
         "resonator_hz" => {
-            let center = args.first()?;
-            let bandwidth = args.get(1)?;
-            Some(Net::wrap(Box::new(resonator_hz(*center, *bandwidth))))
+            let center = &a(0)?;
+            let bandwidth = &a(1)?;
+            Ok(Net::wrap(Box::new(resonator_hz(*center, *bandwidth))))
         }
-        "resynth" => None, //TODO
+        "resynth" => Err(EvalError::invalid()), //TODO
         "reverb2_stereo" => {
-            let room = args.first()?;
-            let time = args.get(1)?;
-            let diffusion = args.get(2)?;
-            let modulation = args.get(3)?;
-            let arg = expr.args.get(4)?;
+            let room = &a(0)?;
+            let time = &a(1)?;
+            let diffusion = &a(2)?;
+            let modulation = &a(3)?;
+            let arg = arg(expr, 4)?;
             let net = eval_net(arg, lapis)?;
             if net.inputs() != 1 || net.outputs() != 1 {
-                return None;
+                return Err(needs(1, 1));
             }
             let node = An(Unit::<U1, U1>::new(Box::new(net)));
-            Some(Net::wrap(Box::new(reverb2_stereo(
+            Ok(Net::wrap(Box::new(reverb2_stereo(
                 *room,
                 *time,
                 *diffusion,
@@ -1334,294 +1379,259 @@ fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
             ))))
         }
         "reverb3_stereo" => {
-            let time = args.first()?;
-            let diffusion = args.get(1)?;
-            let arg = expr.args.get(2)?;
+            let time = &a(0)?;
+            let diffusion = &a(1)?;
+            let arg = arg(expr, 2)?;
             let net = eval_net(arg, lapis)?;
             if net.inputs() != 1 || net.outputs() != 1 {
-                return None;
+                return Err(needs(1, 1));
             }
             let node = An(Unit::<U1, U1>::new(Box::new(net)));
-            Some(Net::wrap(Box::new(reverb3_stereo(*time, *diffusion, node))))
+            Ok(Net::wrap(Box::new(reverb3_stereo(*time, *diffusion, node))))
         }
         "reverb4_stereo" => {
-            let room = args.first()?;
-            let time = args.get(1)?;
-            Some(Net::wrap(Box::new(reverb4_stereo(*room, *time))))
+            let room = &a(0)?;
+            let time = &a(1)?;
+            Ok(Net::wrap(Box::new(reverb4_stereo(*room, *time))))
         }
         "reverb4_stereo_delays" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
+            let time = &a(0)?;
             let mut delays = eval_vec(arg, lapis)?;
-            let time = args.first()?;
             if delays.len() != 32 {
-                return None;
+                let err = EvalError::new(arg, ErrorKind::Invalid);
+                return Err(err.suggest(format!("there are {} delays, not 32", delays.len())));
             }
             for d in delays.iter_mut() {
                 *d = d.max(0.);
             }
-            Some(Net::wrap(Box::new(reverb4_stereo_delays(&delays, *time))))
+            Ok(Net::wrap(Box::new(reverb4_stereo_delays(&delays, *time))))
         }
         "reverb_stereo" => {
-            let room = args.first()?;
-            let time = args.get(1)?;
-            let damp = args.get(2)?;
-            Some(Net::wrap(Box::new(reverb_stereo(*room, *time, *damp))))
+            let room = &a(0)?;
+            let time = &a(1)?;
+            let damp = &a(2)?;
+            Ok(Net::wrap(Box::new(reverb_stereo(*room, *time, *damp))))
         }
         "reverse" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            Some(Net::wrap(Box::new(ReverseUnit::new(n))))
+            let n = generic_usize(expr, 0)?;
+            Ok(Net::wrap(Box::new(ReverseUnit::new(n))))
         }
         "risset_glissando" => {
-            let up = eval_bool(expr.args.first()?, lapis)?;
-            Some(Net::wrap(Box::new(risset_glissando(up))))
+            let up = eval_bool(arg(expr, 0)?, lapis)?;
+            Ok(Net::wrap(Box::new(risset_glissando(up))))
         }
-        "rossler" => Some(Net::wrap(Box::new(rossler()))),
+        "rossler" => Ok(Net::wrap(Box::new(rossler()))),
         "rotate" => {
-            let angle = args.first()?;
-            let gain = args.get(1)?;
-            Some(Net::wrap(Box::new(rotate(*angle, *gain))))
+            let angle = &a(0)?;
+            let gain = &a(1)?;
+            Ok(Net::wrap(Box::new(rotate(*angle, *gain))))
         }
-        "saw" => Some(Net::wrap(Box::new(saw()))),
+        "saw" => Ok(Net::wrap(Box::new(saw()))),
         "saw_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(saw_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(saw_hz(*f))))
         }
         "shape" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shp = call_shape(arg, lapis)?;
-            Some(Net::wrap(Box::new(shape(shp))))
+            Ok(Net::wrap(Box::new(shape(shp))))
         }
-        "shape_fn" => None, //TODO
-        "sine" => Some(Net::wrap(Box::new(sine()))),
+        "shape_fn" => Err(EvalError::invalid()), //TODO
+        "sine" => Ok(Net::wrap(Box::new(sine()))),
         "sine_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(sine_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(sine_hz(*f))))
         }
-        "sink" => Some(Net::wrap(Box::new(sink()))),
+        "sink" => Ok(Net::wrap(Box::new(sink()))),
         "snaredrum" => {
-            let seed = eval_i64(expr.args.first()?, lapis)?;
-            let sharpness = eval_float_f32(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(snaredrum(seed, sharpness))))
+            let seed = eval_i64(arg(expr, 0)?, lapis)?;
+            let sharpness = eval_float_f32(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(snaredrum(seed, sharpness))))
         }
-        "snoop" => None, // TODO you shouldn't be here..
-        "soft_saw" => Some(Net::wrap(Box::new(soft_saw()))),
+        "snoop" => Err(EvalError::invalid()), // TODO you shouldn't be here..
+        "soft_saw" => Ok(Net::wrap(Box::new(soft_saw()))),
         "soft_saw_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(soft_saw_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(soft_saw_hz(*f))))
         }
         "split" => {
-            let n = nth_path_generic(&expr.func, 0)?
-                .get(1..)?
-                .parse::<usize>()
-                .ok()?;
-            Some(Net::wrap(Box::new(MultiSplitUnit::new(1, n))))
+            let n = generic_usize(expr, 0)?;
+            Ok(Net::wrap(Box::new(MultiSplitUnit::new(1, n))))
         }
-        "square" => Some(Net::wrap(Box::new(square()))),
+        "square" => Ok(Net::wrap(Box::new(square()))),
         "square_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(square_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(square_hz(*f))))
         }
         "stack" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            Some(x | y)
+            Ok(x | y)
         }
-        "stackf" | "stacki" => None, //TODO
+        "stackf" | "stacki" => Err(EvalError::invalid()), //TODO
         "sub" => {
-            let tuple = expr.args.first()?;
+            let tuple = arg(expr, 0)?;
             if let Expr::Tuple(expr) = tuple {
                 let p = accumulate_args(&expr.elems, lapis);
                 tuple_call_match!(sub, p)
             } else {
                 match args.len() {
-                    1 => Some(Net::wrap(Box::new(sub(args[0])))),
-                    _ => None,
+                    1 => Ok(Net::wrap(Box::new(sub(args[0])))),
+                    _ => Err(one_or_tuple(&args, expr, lapis)),
                 }
             }
         }
         "sum" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            let arg1 = expr.args.get(1)?;
+            let arg1 = arg(expr, 1)?;
             let y = eval_net(arg1, lapis)?;
-            if x.outputs() == y.outputs() {
-                Some(x + y)
-            } else {
-                None
-            }
+            check_channels(expr, "+", io(&x), io(&y))?;
+            Ok(x + y)
         }
-        "sumf" | "sumi" => None, //TODO
-        "t" => Some(Net::wrap(Box::new(lfo(|t| t)))),
+        "sumf" | "sumi" => Err(EvalError::invalid()), //TODO
+        "t" => Ok(Net::wrap(Box::new(lfo(|t| t)))),
         "tap" => {
-            let min = args.first()?;
-            let max = args.get(1)?;
-            Some(Net::wrap(Box::new(tap(*min, *max))))
+            let min = &a(0)?;
+            let max = &a(1)?;
+            Ok(Net::wrap(Box::new(tap(*min, *max))))
         }
         "tap_linear" => {
-            let min = args.first()?;
-            let max = args.get(1)?;
-            Some(Net::wrap(Box::new(tap_linear(*min, *max))))
+            let min = &a(0)?;
+            let max = &a(1)?;
+            Ok(Net::wrap(Box::new(tap_linear(*min, *max))))
         }
         "thru" => {
-            let arg0 = expr.args.first()?;
+            let arg0 = arg(expr, 0)?;
             let x = eval_net(arg0, lapis)?;
-            Some(!x)
+            Ok(!x)
         }
-        "tick" => Some(Net::wrap(Box::new(tick()))),
+        "tick" => Ok(Net::wrap(Box::new(tick()))),
         "timer" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shared = eval_shared(arg, lapis)?;
-            Some(Net::wrap(Box::new(timer(&shared))))
+            Ok(Net::wrap(Box::new(timer(&shared))))
         }
-        "triangle" => Some(Net::wrap(Box::new(triangle()))),
+        "triangle" => Ok(Net::wrap(Box::new(triangle()))),
         "triangle_hz" => {
-            let f = args.first()?;
-            Some(Net::wrap(Box::new(triangle_hz(*f))))
+            let f = &a(0)?;
+            Ok(Net::wrap(Box::new(triangle_hz(*f))))
         }
-        "unit" => None,   //TODO return the input net?
-        "update" => None, //TODO
+        "unit" => Err(EvalError::invalid()), //TODO return the input net?
+        "update" => Err(EvalError::invalid()), //TODO
         "var" => {
-            let arg = expr.args.first()?;
+            let arg = arg(expr, 0)?;
             let shared = eval_shared(arg, lapis)?;
-            Some(Net::wrap(Box::new(var(&shared))))
+            Ok(Net::wrap(Box::new(var(&shared))))
         }
-        "var_fn" => None, // TODO
+        "var_fn" => Err(EvalError::invalid()), // TODO
         "wavech" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
+            let arg0 = arg(expr, 0)?;
+            let arg1 = arg(expr, 1)?;
             let arg2 = expr.args.get(2);
             let wave = eval_wave(arg0, lapis)?;
             let chan = eval_usize(arg1, lapis)?;
-            if chan < wave.channels() {
-                let loop_point = if let Some(arg) = arg2 {
-                    eval_usize(arg, lapis)
-                } else {
-                    None
-                };
-                Some(Net::wrap(Box::new(wavech(&wave, chan, loop_point))))
-            } else {
-                None
-            }
+            check_wave_channel(expr, &wave, chan)?;
+            let loop_point = match arg2 {
+                Some(arg) => Some(eval_usize(arg, lapis)?),
+                None => None,
+            };
+            Ok(Net::wrap(Box::new(wavech(&wave, chan, loop_point))))
         }
         "wavech_at" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let arg3 = expr.args.get(3)?;
+            let arg0 = arg(expr, 0)?;
+            let arg1 = arg(expr, 1)?;
+            let arg2 = arg(expr, 2)?;
+            let arg3 = arg(expr, 3)?;
             let arg4 = expr.args.get(4);
             let wave = eval_wave(arg0, lapis)?;
             let chan = eval_usize(arg1, lapis)?;
             let start = eval_usize(arg2, lapis)?;
             let end = eval_usize(arg3, lapis)?;
-            if chan < wave.channels() && end <= wave.len() {
-                let loop_point = if let Some(arg) = arg4 {
-                    eval_usize(arg, lapis)
-                } else {
-                    None
-                };
-                Some(Net::wrap(Box::new(wavech_at(
-                    &wave, chan, start, end, loop_point,
-                ))))
-            } else {
-                None
-            }
+            check_wave_channel(expr, &wave, chan)?;
+            if end > wave.len() {
+                let help = format!("the wave is {} samples long", wave.len());
+                return Err(EvalError::new(arg3, ErrorKind::Invalid).suggest(help));
+            }
+            let loop_point = match arg4 {
+                Some(arg) => Some(eval_usize(arg, lapis)?),
+                None => None,
+            };
+            Ok(Net::wrap(Box::new(wavech_at(
+                &wave, chan, start, end, loop_point,
+            ))))
         }
-        "white" => Some(Net::wrap(Box::new(white()))),
-        "zero" => Some(Net::wrap(Box::new(zero()))),
+        "white" => Ok(Net::wrap(Box::new(white()))),
+        "zero" => Ok(Net::wrap(Box::new(zero()))),
         "select" => {
-            let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
-            for arg in &expr.args {
-                if let Some(unit) = eval_net(arg, lapis)
-                    && unit.inputs() == 0
-                    && unit.outputs() == 1
-                {
-                    units.push(Box::new(unit));
-                }
-            }
-            Some(Net::wrap(Box::new(An(Select::new(units)))))
+            let units = eval_units(expr, 0, 1, lapis)?;
+            Ok(Net::wrap(Box::new(An(Select::new(units)))))
         }
         "fade_select" => {
-            let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
-            for arg in &expr.args {
-                if let Some(unit) = eval_net(arg, lapis)
-                    && unit.inputs() == 0
-                    && unit.outputs() == 1
-                {
-                    units.push(Box::new(unit));
-                }
-            }
-            Some(Net::wrap(Box::new(An(FadeSelect::new(units)))))
+            let units = eval_units(expr, 0, 1, lapis)?;
+            Ok(Net::wrap(Box::new(An(FadeSelect::new(units)))))
         }
         "seq" => {
-            let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
-            for arg in &expr.args {
-                if let Some(unit) = eval_net(arg, lapis)
-                    && unit.inputs() == 0
-                    && unit.outputs() == 1
-                {
-                    units.push(Box::new(unit));
-                }
-            }
-            Some(Net::wrap(Box::new(An(Seq::new(units)))))
+            let units = eval_units(expr, 0, 1, lapis)?;
+            Ok(Net::wrap(Box::new(An(Seq::new(units)))))
         }
-        "shift_reg" => Some(Net::wrap(Box::new(An(ShiftReg::new())))),
+        "shift_reg" => Ok(Net::wrap(Box::new(An(ShiftReg::new())))),
         "quantizer" => {
-            let vec = eval_vec(expr.args.first()?, lapis)?;
-            let range = vec.last()? - vec.first()?;
-            Some(Net::wrap(Box::new(An(Quantizer::new(vec, range)))))
+            let vec = eval_vec(arg(expr, 0)?, lapis)?;
+            let (Some(first), Some(last)) = (vec.first(), vec.last()) else {
+                return Err(EvalError::invalid().suggest("the array can't be empty"));
+            };
+            let range = last - first;
+            Ok(Net::wrap(Box::new(An(Quantizer::new(vec, range)))))
         }
         "kr" => {
-            let unit = Box::new(eval_net(expr.args.first()?, lapis)?);
-            let n = eval_usize(expr.args.get(1)?, lapis)?;
+            let unit = Box::new(eval_net(arg(expr, 0)?, lapis)?);
+            let n = eval_usize(arg(expr, 1)?, lapis)?;
             let mut preserve_time = true;
-            if let Some(arg) = expr.args.get(2)
-                && let Some(b) = eval_bool(arg, lapis)
-            {
-                preserve_time = b;
+            if let Some(arg) = expr.args.get(2) {
+                preserve_time = eval_bool(arg, lapis)?;
             }
-            Some(Net::wrap(Box::new(Kr::new(unit, n, preserve_time))))
+            Ok(Net::wrap(Box::new(Kr::new(unit, n, preserve_time))))
         }
         "reset" => {
-            let unit = Box::new(eval_net(expr.args.first()?, lapis)?);
+            let unit = Box::new(eval_net(arg(expr, 0)?, lapis)?);
             if unit.inputs() != 0 || unit.outputs() != 1 {
-                return None;
+                return Err(needs(0, 1));
             }
-            let dur = eval_float_f32(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(Reset::new(unit, dur)))))
+            let dur = eval_float_f32(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(Reset::new(unit, dur)))))
         }
         "trig_reset" => {
-            let unit = Box::new(eval_net(expr.args.first()?, lapis)?);
+            let unit = Box::new(eval_net(arg(expr, 0)?, lapis)?);
             if unit.inputs() != 0 || unit.outputs() != 1 {
-                return None;
+                return Err(needs(0, 1));
             }
-            Some(Net::wrap(Box::new(An(TrigReset::new(unit)))))
+            Ok(Net::wrap(Box::new(An(TrigReset::new(unit)))))
         }
         "reset_v" => {
-            let unit = Box::new(eval_net(expr.args.first()?, lapis)?);
+            let unit = Box::new(eval_net(arg(expr, 0)?, lapis)?);
             if unit.inputs() != 0 || unit.outputs() != 1 {
-                return None;
+                return Err(needs(0, 1));
             }
-            Some(Net::wrap(Box::new(An(ResetV::new(unit)))))
+            Ok(Net::wrap(Box::new(An(ResetV::new(unit)))))
         }
-        "snh" => Some(Net::wrap(Box::new(An(SnH::new())))),
-        "euclid" => Some(Net::wrap(Box::new(An(EuclidSeq::new())))),
+        "snh" => Ok(Net::wrap(Box::new(An(SnH::new())))),
+        "euclid" => Ok(Net::wrap(Box::new(An(EuclidSeq::new())))),
         "resample1" => {
-            let unit = Box::new(eval_net(expr.args.first()?, lapis)?);
+            let unit = Box::new(eval_net(arg(expr, 0)?, lapis)?);
             if unit.inputs() != 0 || unit.outputs() != 1 {
-                return None;
+                return Err(needs(0, 1));
             }
             let node = Unit::<U0, U1>::new(unit);
-            Some(Net::wrap(Box::new(resample(An(node)))))
+            Ok(Net::wrap(Box::new(resample(An(node)))))
         }
-        "bitcrush" => Some(Net::wrap(Box::new(maps::bitcrush()))),
-        "gate" => Some(Net::wrap(Box::new(An(Gate::new(*args.first()? as f64))))),
+        "bitcrush" => Ok(Net::wrap(Box::new(maps::bitcrush()))),
+        "gate" => Ok(Net::wrap(Box::new(An(Gate::new(a(0)? as f64))))),
         "phase_synth" => {
-            let table = eval_string(expr.args.first()?, lapis)?;
+            let table = eval_string(arg(expr, 0)?, lapis)?;
             let table = match table.as_str() {
                 "hammond" => hammond_table(),
                 "organ" => organ_table(),
@@ -1630,104 +1640,218 @@ fn call_net(expr: &ExprCall, lapis: &mut Lapis) -> Option<Net> {
                 "square" => square_table(),
                 "triangle" => triangle_table(),
                 "sine" => sine_table(),
-                _ => return None,
+                _ => {
+                    let err = EvalError::new(arg(expr, 0)?, ErrorKind::Invalid);
+                    let help =
+                        "the tables are hammond, organ, saw, soft_saw, square, triangle and sine";
+                    return Err(err.suggest(help));
+                }
             };
-            Some(Net::wrap(Box::new(An(PhaseSynth::new(table)))))
+            Ok(Net::wrap(Box::new(An(PhaseSynth::new(table)))))
         }
         "unsteady" => {
-            let times = eval_vec(expr.args.first()?, lapis)?;
-            let looping = eval_bool(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(Unsteady::new(times, looping)))))
+            let times = eval_vec(arg(expr, 0)?, lapis)?;
+            let looping = eval_bool(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(Unsteady::new(times, looping)))))
         }
         "unsteady_no_reset" | "unsteady_nr" => {
-            let times = eval_vec(expr.args.first()?, lapis)?;
-            let looping = eval_bool(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(
+            let times = eval_vec(arg(expr, 0)?, lapis)?;
+            let looping = eval_bool(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(
                 Unsteady::new(times, looping).no_reset()
             ))))
         }
         "unsteady_ramp" => {
-            let times = eval_vec(expr.args.first()?, lapis)?;
-            let looping = eval_bool(expr.args.get(1)?, lapis)?;
-            Some(Net::wrap(Box::new(An(UnsteadyRamp::new(times, looping)))))
+            let times = eval_vec(arg(expr, 0)?, lapis)?;
+            let looping = eval_bool(arg(expr, 1)?, lapis)?;
+            Ok(Net::wrap(Box::new(An(UnsteadyRamp::new(times, looping)))))
         }
         "atomic_synth" => {
-            let k = nth_path_ident(expr.args.first()?, 0)?;
-            if let Some(table) = lapis.data.atomic_table_map.get(&k) {
-                let mut synth = AtomicSynth::<f32>::new(table.clone());
-                if let Some(arg1) = expr.args.get(1)
-                    && let Some(interp) = eval_string(arg1, lapis)
-                {
-                    if interp == "linear" {
-                        synth.set_interpolation(Interpolation::Linear);
-                    } else if interp == "cubic" {
-                        synth.set_interpolation(Interpolation::Cubic);
-                    }
+            let k = nth_path_ident(arg(expr, 0)?, 0).or_invalid()?;
+            let Some(table) = lapis.data.atomic_table_map.get(&k) else {
+                return Err(path_error(arg(expr, 0)?, lapis, "an atomic table"));
+            };
+            let mut synth = AtomicSynth::<f32>::new(table.clone());
+            if let Some(arg1) = expr.args.get(1) {
+                let interp = eval_string(arg1, lapis)?;
+                if interp == "linear" {
+                    synth.set_interpolation(Interpolation::Linear);
+                } else if interp == "cubic" {
+                    synth.set_interpolation(Interpolation::Cubic);
                 }
-                return Some(Net::wrap(Box::new(An(synth))));
             }
-            None
+            Ok(Net::wrap(Box::new(An(synth))))
         }
         "ahr" => {
-            let a = eval_float_f32(expr.args.first()?, lapis)?;
-            let h = eval_float_f32(expr.args.get(1)?, lapis)?;
-            let r = eval_float_f32(expr.args.get(2)?, lapis)?;
-            Some(Net::wrap(Box::new(ahr(a, h, r))))
+            let a = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let h = eval_float_f32(arg(expr, 1)?, lapis)?;
+            let r = eval_float_f32(arg(expr, 2)?, lapis)?;
+            Ok(Net::wrap(Box::new(ahr(a, h, r))))
         }
         "step" => {
-            let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
-            for arg in &expr.args {
-                if let Some(unit) = eval_net(arg, lapis)
-                    && unit.inputs() == 0
-                    && unit.outputs() == 1
-                {
-                    units.push(Box::new(unit));
-                }
-            }
-            Some(Net::wrap(Box::new(An(Step::new(units)))))
+            let units = eval_units(expr, 0, 1, lapis)?;
+            Ok(Net::wrap(Box::new(An(Step::new(units)))))
         }
         "filter_step" => {
-            let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
-            for arg in &expr.args {
-                if let Some(unit) = eval_net(arg, lapis)
-                    && unit.inputs() == 1
-                    && unit.outputs() == 1
-                {
-                    units.push(Box::new(unit));
-                }
-            }
-            Some(Net::wrap(Box::new(An(FilterStep::new(units)))))
+            let units = eval_units(expr, 1, 1, lapis)?;
+            Ok(Net::wrap(Box::new(An(FilterStep::new(units)))))
         }
         "atomic_phase" => {
-            let k = nth_path_ident(expr.args.first()?, 0)?;
-            let table = lapis.data.atomic_table_map.get(&k)?;
+            let k = nth_path_ident(arg(expr, 0)?, 0).or_invalid()?;
+            let Some(table) = lapis.data.atomic_table_map.get(&k) else {
+                return Err(path_error(arg(expr, 0)?, lapis, "an atomic table"));
+            };
             let mut interp = Interpolation::Nearest;
-            if let Some(arg1) = expr.args.get(1)
-                && let Some(i) = eval_string(arg1, lapis)
-            {
+            if let Some(arg1) = expr.args.get(1) {
+                let i = eval_string(arg1, lapis)?;
                 if i == "linear" {
                     interp = Interpolation::Linear;
                 } else if i == "cubic" {
                     interp = Interpolation::Cubic;
                 }
             }
-            Some(Net::wrap(Box::new(maps::atomic_phase(
+            Ok(Net::wrap(Box::new(maps::atomic_phase(
                 table.clone(),
                 interp,
             ))))
         }
         "wave_at" => {
-            let wave = eval_wave(expr.args.first()?, lapis)?;
-            Some(Net::wrap(Box::new(maps::wave_at(wave))))
+            let wave = eval_wave(arg(expr, 0)?, lapis)?;
+            Ok(Net::wrap(Box::new(maps::wave_at(wave))))
         }
         "wave_set" => {
-            let wave = eval_wave(expr.args.first()?, lapis)?;
-            Some(Net::wrap(Box::new(maps::wave_set(wave))))
+            let wave = eval_wave(arg(expr, 0)?, lapis)?;
+            Ok(Net::wrap(Box::new(maps::wave_set(wave))))
         }
         "wave_mix" => {
-            let wave = eval_wave(expr.args.first()?, lapis)?;
-            Some(Net::wrap(Box::new(maps::wave_mix(wave))))
+            let wave = eval_wave(arg(expr, 0)?, lapis)?;
+            Ok(Net::wrap(Box::new(maps::wave_mix(wave))))
         }
+        _ => Err(EvalError::unknown_function()),
+    }
+}
+
+/// the number in the `n`th generic argument of a function (like the 4 in `U4`)
+fn generic_usize(expr: &ExprCall, n: usize) -> EvalResult<usize> {
+    let g = nth_path_generic(&expr.func, n).or_invalid()?;
+    let n = g.get(1..).and_then(|n| n.parse().ok());
+    n.ok_or_else(|| EvalError::invalid().suggest("sizes are given as types, like `U4`"))
+}
+
+/// the error for a call that takes one float or a tuple of them
+fn one_or_tuple(args: &[f32], expr: &ExprCall, lapis: &Lapis) -> EvalError {
+    match float_arg(args, 0, expr, lapis) {
+        Err(err) => err,
+        Ok(_) => EvalError::invalid().suggest("one float or a tuple of them"),
+    }
+}
+
+/// the error for a net argument that doesn't have these inputs and outputs
+fn needs(ins: usize, outs: usize) -> EvalError {
+    let s = |n| if n == 1 { "" } else { "s" };
+    let help = format!(
+        "the net needs {ins} input{} and {outs} output{}",
+        s(ins),
+        s(outs)
+    );
+    EvalError::invalid().suggest(help)
+}
+
+/// the (inputs, outputs) of a net
+fn io(net: &Net) -> (usize, usize) {
+    (net.inputs(), net.outputs())
+}
+
+/// the symbol of an operator that combines nets
+fn net_op(op: &BinOp) -> Option<&'static str> {
+    match op {
+        BinOp::BitAnd(_) => Some("&"),
+        BinOp::BitOr(_) => Some("|"),
+        BinOp::BitXor(_) => Some("^"),
+        BinOp::Shr(_) => Some(">>"),
+        BinOp::Sub(_) => Some("-"),
+        BinOp::Mul(_) => Some("*"),
+        BinOp::Add(_) => Some("+"),
         _ => None,
     }
 }
+
+/// an error if nets with these (inputs, outputs) can't be combined with `op`
+fn check_channels(
+    code: &impl ToTokens,
+    op: &'static str,
+    left: (usize, usize),
+    right: (usize, usize),
+) -> EvalResult<()> {
+    let (ok, suggestion) = match op {
+        "|" => return Ok(()),
+        "&" => (
+            left == right,
+            "both sides need the same inputs and outputs".into(),
+        ),
+        "^" => (
+            left.0 == right.0,
+            "both sides need the same number of inputs".into(),
+        ),
+        ">>" => (
+            left.1 == right.0,
+            format!(
+                "the left side has {} outputs, the right {} inputs",
+                left.1, right.0
+            ),
+        ),
+        _ => (
+            left.1 == right.1,
+            "both sides need the same number of outputs".into(),
+        ),
+    };
+    if ok {
+        return Ok(());
+    }
+    let kind = ErrorKind::Channels { op, left, right };
+    Err(EvalError::new(code, kind).suggest(suggestion))
+}
+
+fn check_wave_channel(expr: &ExprCall, wave: &Wave, chan: usize) -> EvalResult<()> {
+    if chan < wave.channels() {
+        return Ok(());
+    }
+    let kind = ErrorKind::OutOfRange {
+        what: "channel",
+        index: chan,
+        len: wave.channels(),
+    };
+    Err(EvalError::new(expr, kind))
+}
+
+/// the arguments of a call as units with these inputs and outputs
+fn eval_units(
+    expr: &ExprCall,
+    ins: usize,
+    outs: usize,
+    lapis: &mut Lapis,
+) -> EvalResult<Vec<Box<dyn AudioUnit>>> {
+    let mut units: Vec<Box<dyn AudioUnit>> = Vec::new();
+    for arg in &expr.args {
+        let unit = eval_net(arg, lapis)?;
+        if unit.inputs() != ins || unit.outputs() != outs {
+            return Err(needs(ins, outs).at(arg));
+        }
+        units.push(Box::new(unit));
+    }
+    Ok(units)
+}
+
+/// put a net taken from a variable back
+/// (so a failed expression doesn't consume the nets it uses directly)
+fn restore(expr: &Expr, net: Net, lapis: &mut Lapis) {
+    match expr {
+        Expr::Paren(expr) => restore(&expr.expr, net, lapis),
+        Expr::Path(_) => {
+            if let Some(k) = nth_path_ident(expr, 0) {
+                lapis.data.gmap.insert(k, net);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,86 +1,106 @@
-use super::{Lapis, bools::*, floats::*, helpers::*, ints::*, nets::*};
+use super::{Lapis, bools::*, errors::*, floats::*, helpers::*, ints::*, nets::*};
 use fundsp::hacker::*;
 use syn::*;
 
-pub fn call_seq(expr: &Expr, lapis: &Lapis) -> Option<Sequencer> {
+pub fn call_seq(expr: &Expr, lapis: &Lapis) -> EvalResult<Sequencer> {
     match expr {
-        Expr::Call(expr) => {
-            let seg0 = nth_path_ident(&expr.func, 0)?;
-            if seg0 == "Sequencer" {
-                let seg1 = nth_path_ident(&expr.func, 1)?;
-                if seg1 == "new" {
-                    let replay = eval_bool(expr.args.first()?, lapis)?;
-                    let outputs = eval_usize(expr.args.get(1)?, lapis)?;
-                    return Some(Sequencer::new(replay, outputs));
-                } else if seg1 == "io" {
-                    let inputs = eval_usize(expr.args.get(0)?, lapis)?;
-                    let outputs = eval_usize(expr.args.get(1)?, lapis)?;
-                    return Some(Sequencer::new(false, outputs).with_inputs(inputs));
-                }
-            }
-            None
+        Expr::Call(call) => new_seq(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("a sequencer")),
+    }
+    .map_err(|e| e.at(expr))
+}
+
+fn new_seq(expr: &ExprCall, lapis: &Lapis) -> EvalResult<Sequencer> {
+    let seg0 = nth_path_ident(&expr.func, 0).or_mismatch("a sequencer")?;
+    if seg0 == "Sequencer" {
+        let seg1 = nth_path_ident(&expr.func, 1).or_invalid()?;
+        if seg1 == "new" {
+            let replay = eval_bool(arg(expr, 0)?, lapis)?;
+            let outputs = eval_usize(arg(expr, 1)?, lapis)?;
+            return Ok(Sequencer::new(replay, outputs));
+        } else if seg1 == "io" {
+            let inputs = eval_usize(arg(expr, 0)?, lapis)?;
+            let outputs = eval_usize(arg(expr, 1)?, lapis)?;
+            return Ok(Sequencer::new(false, outputs).with_inputs(inputs));
         }
-        _ => None,
     }
+    Err(EvalError::unknown_function())
 }
 
-pub fn seq_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<()> {
-    match expr.method.to_string().as_str() {
+/// the name of the sequencer a method is called on
+/// (or the error if the receiver isn't one)
+fn seq_receiver(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<String> {
+    let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a sequencer")?;
+    if lapis.data.seqmap.contains_key(&k) {
+        Ok(k)
+    } else {
+        let err = path_error(&expr.receiver, lapis, "a sequencer");
+        Err(EvalError::unknown_method().or(err))
+    }
+}
+
+pub fn seq_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<()> {
+    let method = expr.method.to_string();
+    if !matches!(
+        method.as_str(),
+        "edit"
+            | "edit_relative"
+            | "set_sample_rate"
+            | "reset"
+            | "set_loop"
+            | "set_time"
+            | "set_replay_events"
+            | "clear"
+    ) {
+        return Err(EvalError::unknown_method());
+    }
+    let k = seq_receiver(expr, lapis)?;
+    match method.as_str() {
         "edit" => {
-            let id = eval_eventid(expr.args.first()?, lapis)?;
-            let end_time = eval_float(expr.args.get(1)?, lapis)?;
-            let fade_out = eval_float(expr.args.get(2)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let id = eval_eventid(arg(expr, 0)?, lapis)?;
+            let end_time = eval_float(arg(expr, 1)?, lapis)?;
+            let fade_out = eval_float(arg(expr, 2)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.edit(id, end_time, fade_out);
         }
         "edit_relative" => {
-            let id = eval_eventid(expr.args.first()?, lapis)?;
-            let end_time = eval_float(expr.args.get(1)?, lapis)?;
-            let fade_out = eval_float(expr.args.get(2)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let id = eval_eventid(arg(expr, 0)?, lapis)?;
+            let end_time = eval_float(arg(expr, 1)?, lapis)?;
+            let fade_out = eval_float(arg(expr, 2)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.edit_relative(id, end_time, fade_out);
         }
         "set_sample_rate" => {
-            let arg = expr.args.first()?;
-            let sr = eval_float(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let sr = eval_float(arg(expr, 0)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.set_sample_rate(sr);
         }
         "reset" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.reset();
         }
         "set_loop" => {
-            let start = eval_float(expr.args.get(0)?, lapis)?;
-            let end = eval_float(expr.args.get(1)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let start = eval_float(arg(expr, 0)?, lapis)?;
+            let end = eval_float(arg(expr, 1)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.set_loop(start, end);
         }
         "set_time" => {
-            let t = eval_float(expr.args.get(0)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let t = eval_float(arg(expr, 0)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.set_time(t);
         }
         "set_replay_events" => {
-            let keep = eval_bool(expr.args.get(0)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+            let keep = eval_bool(arg(expr, 0)?, lapis)?;
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.set_replay_events(keep);
         }
-        "clear" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
+        _ => {
+            let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
             seq.clear();
         }
-        _ => {}
     }
-    None
+    Ok(())
 }
 
 pub fn path_seq<'a>(expr: &'a Expr, lapis: &'a Lapis) -> Option<&'a Sequencer> {
@@ -93,77 +113,52 @@ pub fn path_seq<'a>(expr: &'a Expr, lapis: &'a Lapis) -> Option<&'a Sequencer> {
     }
 }
 
-pub fn eval_eventid(expr: &Expr, lapis: &mut Lapis) -> Option<EventId> {
+pub fn eval_eventid(expr: &Expr, lapis: &mut Lapis) -> EvalResult<EventId> {
     match expr {
-        Expr::MethodCall(expr) => method_eventid(expr, lapis),
-        Expr::Path(expr) => path_eventid(&expr.path, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_eventid(call, lapis).map_err(|e| e.within(call)),
+        Expr::Path(_) => path_eventid(expr, lapis),
+        _ => Err(EvalError::mismatch("an event id")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn method_eventid(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<EventId> {
-    match expr.method.to_string().as_str() {
-        "push" => {
-            let start_time = eval_float(expr.args.first()?, lapis)?;
-            let end_time = eval_float(expr.args.get(1)?, lapis)?;
-            let fade = path_fade(expr.args.get(2)?)?;
-            let fade_in = eval_float(expr.args.get(3)?, lapis)?;
-            let fade_out = eval_float(expr.args.get(4)?, lapis)?;
-            let unit = Box::new(eval_net(expr.args.get(5)?, lapis)?);
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
-            let duration = end_time - start_time;
-            if unit.inputs() != 0
-                || unit.outputs() != seq.outputs()
-                || fade_in > duration
-                || fade_out > duration
-            {
-                return None;
-            }
-            Some(seq.push(start_time, end_time, fade, fade_in, fade_out, unit))
-        }
-        "push_relative" => {
-            let start_time = eval_float(expr.args.first()?, lapis)?;
-            let end_time = eval_float(expr.args.get(1)?, lapis)?;
-            let fade = path_fade(expr.args.get(2)?)?;
-            let fade_in = eval_float(expr.args.get(3)?, lapis)?;
-            let fade_out = eval_float(expr.args.get(4)?, lapis)?;
-            let unit = Box::new(eval_net(expr.args.get(5)?, lapis)?);
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
-            let duration = end_time - start_time;
-            if unit.inputs() != 0
-                || unit.outputs() != seq.outputs()
-                || fade_in > duration
-                || fade_out > duration
-            {
-                return None;
-            }
-            Some(seq.push_relative(start_time, end_time, fade, fade_in, fade_out, unit))
-        }
-        "push_duration" => {
-            let start_time = eval_float(expr.args.first()?, lapis)?;
-            let duration = eval_float(expr.args.get(1)?, lapis)?;
-            let fade = path_fade(expr.args.get(2)?)?;
-            let fade_in = eval_float(expr.args.get(3)?, lapis)?;
-            let fade_out = eval_float(expr.args.get(4)?, lapis)?;
-            let unit = Box::new(eval_net(expr.args.get(5)?, lapis)?);
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let seq = lapis.data.seqmap.get_mut(&k)?;
-            if unit.inputs() != 0
-                || unit.outputs() != seq.outputs()
-                || fade_in > duration
-                || fade_out > duration
-            {
-                return None;
-            }
-            Some(seq.push_duration(start_time, duration, fade, fade_in, fade_out, unit))
-        }
-        _ => None,
+fn method_eventid(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<EventId> {
+    let method = expr.method.to_string();
+    if !matches!(method.as_str(), "push" | "push_relative" | "push_duration") {
+        return Err(EvalError::unknown_method());
+    }
+    let k = seq_receiver(expr, lapis)?;
+    let start_time = eval_float(arg(expr, 0)?, lapis)?;
+    // the end time (or the duration for push_duration)
+    let time = eval_float(arg(expr, 1)?, lapis)?;
+    let fade = path_fade(arg(expr, 2)?).or_invalid()?;
+    let fade_in = eval_float(arg(expr, 3)?, lapis)?;
+    let fade_out = eval_float(arg(expr, 4)?, lapis)?;
+    let unit = Box::new(eval_net(arg(expr, 5)?, lapis)?);
+    let seq = lapis.data.seqmap.get_mut(&k).or_invalid()?;
+    let duration = if method == "push_duration" {
+        time
+    } else {
+        time - start_time
+    };
+    if unit.inputs() != 0 || unit.outputs() != seq.outputs() {
+        let help = format!("the net needs 0 inputs and {} outputs", seq.outputs());
+        return Err(EvalError::invalid().suggest(help));
     }
+    if fade_in > duration || fade_out > duration {
+        return Err(EvalError::invalid().suggest("the fades can't be longer than the event"));
+    }
+    Ok(match method.as_str() {
+        "push" => seq.push(start_time, time, fade, fade_in, fade_out, unit),
+        "push_relative" => seq.push_relative(start_time, time, fade, fade_in, fade_out, unit),
+        _ => seq.push_duration(start_time, time, fade, fade_in, fade_out, unit),
+    })
 }
 
-fn path_eventid(expr: &Path, lapis: &Lapis) -> Option<EventId> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.eventmap.get(&k).copied()
+fn path_eventid(expr: &Expr, lapis: &Lapis) -> EvalResult<EventId> {
+    let k = nth_path_ident(expr, 0).or_mismatch("an event id")?;
+    match lapis.data.eventmap.get(&k) {
+        Some(id) => Ok(*id),
+        None => Err(path_error(expr, lapis, "an event id")),
+    }
 }
@@ -1,81 +1,75 @@
-use super::{Lapis, helpers::*, ints::*, nets::*};
+use super::{Lapis, errors::*, helpers::*, ints::*, nets::*};
 use fundsp::hacker::*;
 use syn::*;
 
-fn method_source(expr: &ExprMethodCall, lapis: &Lapis) -> Option<Source> {
+fn method_source(expr: &ExprMethodCall, lapis: &Lapis) -> EvalResult<Source> {
     match expr.method.to_string().as_str() {
         "source" => {
-            if let Some(k) = nth_path_ident(&expr.receiver, 0)
-                && let Some(g) = &mut lapis.data.gmap.get(&k)
-            {
-                let arg0 = expr.args.first();
-                let arg1 = expr.args.get(1);
-                if let (Some(arg0), Some(arg1)) = (arg0, arg1) {
-                    let id = eval_path_nodeid(arg0, lapis);
-                    let chan = eval_usize(arg1, lapis);
-                    if let (Some(id), Some(chan)) = (id, chan)
-                        && g.contains(id)
-                        && chan < g.inputs_in(id)
-                    {
-                        return Some(g.source(id, chan));
-                    }
-                }
+            let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a net")?;
+            let Some(g) = lapis.data.gmap.get(&k) else {
+                return Err(EvalError::unknown_method().or(path_error(
+                    &expr.receiver,
+                    lapis,
+                    "a net",
+                )));
+            };
+            let id = eval_path_nodeid(arg(expr, 0)?, lapis)?;
+            let chan = eval_usize(arg(expr, 1)?, lapis)?;
+            if g.contains(id) && chan < g.inputs_in(id) {
+                Ok(g.source(id, chan))
+            } else {
+                Err(EvalError::invalid())
             }
-            None
         }
         "output_source" => {
-            if let Some(k) = nth_path_ident(&expr.receiver, 0)
-                && let Some(g) = &mut lapis.data.gmap.get(&k)
-            {
-                let arg0 = expr.args.first();
-                if let Some(arg0) = arg0 {
-                    let chan = eval_usize(arg0, lapis);
-                    if let Some(chan) = chan {
-                        return Some(g.output_source(chan));
-                    }
-                }
-            }
-            None
+            let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a net")?;
+            let Some(g) = lapis.data.gmap.get(&k) else {
+                return Err(EvalError::unknown_method().or(path_error(
+                    &expr.receiver,
+                    lapis,
+                    "a net",
+                )));
+            };
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            Ok(g.output_source(chan))
         }
-        _ => None,
+        _ => Err(EvalError::unknown_method()),
     }
 }
 
-pub fn eval_source(expr: &Expr, lapis: &Lapis) -> Option<Source> {
+pub fn eval_source(expr: &Expr, lapis: &Lapis) -> EvalResult<Source> {
     match expr {
-        Expr::Call(expr) => {
-            let seg0 = nth_path_ident(&expr.func, 0)?;
-            let seg1 = nth_path_ident(&expr.func, 1)?;
-            if seg0 == "Source" {
-                if seg1 == "Local" {
-                    let arg0 = expr.args.first()?;
-                    let arg1 = expr.args.get(1)?;
-                    let id = eval_path_nodeid(arg0, lapis)?;
-                    let index = eval_usize(arg1, lapis)?;
-                    Some(Source::Local(id, index))
-                } else if seg1 == "Global" {
-                    let arg0 = expr.args.first()?;
-                    let index = eval_usize(arg0, lapis)?;
-                    Some(Source::Global(index))
-                } else {
-                    None
-                }
+        Expr::Call(call) => {
+            let seg0 = nth_path_ident(&call.func, 0).or_mismatch("a source")?;
+            let seg1 = nth_path_ident(&call.func, 1);
+            if seg0 == "Source" && seg1.as_deref() == Some("Local") {
+                let id = eval_path_nodeid(arg(call, 0)?, lapis)?;
+                let index = eval_usize(arg(call, 1)?, lapis)?;
+                Ok(Source::Local(id, index))
+            } else if seg0 == "Source" && seg1.as_deref() == Some("Global") {
+                let index = eval_usize(arg(call, 0)?, lapis)?;
+                Ok(Source::Global(index))
             } else {
-                None
+                Err(EvalError::unknown_function())
             }
+            .map_err(|e| e.within(call))
         }
-        Expr::Path(expr) => {
-            let seg0 = &expr.path.segments.first()?.ident;
-            if let Some(seg1) = &expr.path.segments.get(1) {
+        Expr::Path(path) => {
+            let seg0 = &path.path.segments.first().or_mismatch("a source")?.ident;
+            if let Some(seg1) = &path.path.segments.get(1) {
                 if seg0 == "Source" && seg1.ident == "Zero" {
-                    return Some(Source::Zero);
+                    return Ok(Source::Zero);
                 }
-                None
+                Err(EvalError::mismatch("a source"))
             } else {
-                lapis.data.srcmap.get(&seg0.to_string()).copied()
+                match lapis.data.srcmap.get(&seg0.to_string()) {
+                    Some(source) => Ok(*source),
+                    None => Err(path_error(expr, lapis, "a source")),
+                }
             }
         }
-        Expr::MethodCall(expr) => method_source(expr, lapis),
-        _ => None,
+        Expr::MethodCall(call) => method_source(call, lapis).map_err(|e| e.within(call)),
+        _ => Err(EvalError::mismatch("a source")),
     }
+    .map_err(|e| e.at(expr))
 }
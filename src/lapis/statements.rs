@@ -1,9 +1,14 @@
 use super::{
-    Lapis, arrays::*, atomics::*, bools::*, entities::*, floats::*, helpers::*, ints::*, nets::*,
-    sequencers::*, sources::*, strings::*, waves::*,
+    Lapis, arrays::*, atomics::*, bools::*, branches::*, entities::*, errors::*, floats::*,
+    functions::*, helpers::*, ints::*, nets::*, sequencers::*, sources::*, strings::*, timers::*,
+    waves::*,
 };
 use crate::audio::*;
+use crate::history::{Redo, Undo};
+use crate::midi::*;
 use crate::objects::*;
+use crate::osc::*;
+use crate::scene::*;
 use avian2d::prelude::*;
 use bevy::prelude::*;
 use crossbeam_channel::bounded;
@@ -11,42 +16,104 @@ use fundsp::hacker::*;
 use std::{sync::Arc, thread, time::Duration};
 use syn::*;
 
-pub fn eval_stmt(s: Stmt, lapis: &mut Lapis) -> String {
-    let mut buffer = String::new();
+/// the most iterations (of all loops combined) a single evaluation can run
+pub const LOOP_LIMIT: usize = 1_000_000;
+/// the limit for code that runs on its own (collision code, scripts, timers, bindings)
+/// since it can run many times every frame
+pub const HOOK_LOOP_LIMIT: usize = 10_000;
+
+/// control flow signal returned by statement evaluation
+#[derive(Debug, PartialEq)]
+pub enum Flow {
+    /// carry on to the next statement
+    Next,
+    /// break out of the innermost loop (or the one with this label)
+    Break(Option<String>),
+    /// skip to the next iteration of the innermost loop (or the one with this label)
+    Continue(Option<String>),
+    /// return from the current function
+    Return,
+    /// stop evaluating entirely (loop limit reached)
+    Halt,
+}
+
+pub fn eval_stmt(s: &Stmt, lapis: &mut Lapis, buffer: &mut String) -> Flow {
     match s {
         Stmt::Local(expr) => {
-            if eval_local(&expr, lapis).is_none() {
-                buffer.push_str("\n// error: assignment error");
+            if let Err(err) = eval_local(expr, lapis, buffer) {
+                report(&err, lapis, buffer);
             }
         }
-        Stmt::Expr(expr, _) => match expr {
-            Expr::Assign(expr) => eval_assign(&expr, lapis),
-            Expr::ForLoop(expr) => eval_for_loop(&expr, lapis, &mut buffer),
-            Expr::Block(expr) => eval_block(expr, lapis, &mut buffer),
-            Expr::If(expr) => eval_if(expr, lapis, &mut buffer),
-            expr => eval_expr(expr, lapis, &mut buffer),
-        },
+        Stmt::Item(Item::Fn(item)) => define_fn(item, lapis, buffer),
+        Stmt::Expr(expr, _) => return eval_expr_stmt(expr, lapis, buffer),
         _ => {}
     }
-    buffer
+    Flow::Next
 }
 
-fn eval_expr(expr: Expr, lapis: &mut Lapis, buffer: &mut String) {
-    if let Some(n) = eval_float(&expr, lapis) {
+/// evaluate an expression statement
+pub fn eval_expr_stmt(expr: &Expr, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    match expr {
+        Expr::Assign(_) => with_expanded(expr, lapis, buffer, |expr, lapis, buffer| {
+            if let Expr::Assign(expr) = expr
+                && let Err(err) = eval_assign(expr, lapis, buffer)
+            {
+                report(&err, lapis, buffer);
+            }
+        }),
+        Expr::ForLoop(expr) => return eval_for_loop(expr, lapis, buffer),
+        Expr::While(expr) => return eval_while(expr, lapis, buffer),
+        Expr::Loop(expr) => return eval_loop(expr, lapis, buffer),
+        Expr::Block(expr) => return eval_block(expr, lapis, buffer),
+        Expr::If(expr) => return eval_if(expr, lapis, buffer),
+        Expr::Match(expr) => return eval_match(expr, lapis, buffer),
+        Expr::Break(expr) => {
+            let label = expr.label.as_ref().map(|l| l.ident.to_string());
+            return Flow::Break(label);
+        }
+        Expr::Continue(expr) => {
+            let label = expr.label.as_ref().map(|l| l.ident.to_string());
+            return Flow::Continue(label);
+        }
+        Expr::Return(expr) => return eval_return(expr, lapis, buffer),
+        expr => with_expanded(expr, lapis, buffer, |expr, lapis, buffer| {
+            let r = eval_expr(expr, lapis, buffer);
+            or_report(r, expr, None, lapis, buffer);
+        }),
+    }
+    Flow::Next
+}
+
+/// evaluate statements until one of them changes the flow
+pub fn eval_stmts(stmts: &[Stmt], lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    for stmt in stmts {
+        let flow = eval_stmt(stmt, lapis, buffer);
+        if flow != Flow::Next {
+            return flow;
+        }
+    }
+    Flow::Next
+}
+
+/// show the value of `expr`, or do what it says
+/// (things that are done without giving a value, like `e.despawn()`, count as done)
+fn eval_expr(expr: &Expr, lapis: &mut Lapis, buffer: &mut String) -> EvalResult<()> {
+    let mut err = None;
+    if let Some(n) = attempt(eval_float(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {n:?}"));
-    } else if let Some(arr) = eval_vec(&expr, lapis) {
+    } else if let Some(arr) = attempt(eval_vec(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {arr:?}"));
-    } else if let Some(mut g) = eval_net_cloned(&expr, lapis) {
+    } else if let Some(mut g) = attempt(eval_net_cloned(expr, lapis), &mut err) {
         let info = g.display().replace('\n', "\n// ");
         buffer.push_str(&format!("\n// {info}"));
         buffer.push_str(&format!("Size           : {}", g.size()));
-    } else if let Some(id) = eval_nodeid(&expr, lapis) {
+    } else if let Some(id) = attempt(eval_nodeid(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {id:?}"));
-    } else if let Some(b) = eval_bool(&expr, lapis) {
+    } else if let Some(b) = attempt(eval_bool(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {b:?}"));
-    } else if let Some(s) = eval_shared(&expr, lapis) {
+    } else if let Some(s) = attempt(eval_shared(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// Shared({})", s.value()));
-    } else if let Some(w) = eval_wave(&expr, lapis) {
+    } else if let Some(w) = attempt(eval_wave(expr, lapis), &mut err) {
         buffer.push_str(&format!(
             "\n// Wave(ch:{}, sr:{}, len:{}, dur:{}, arcs:{})",
             w.channels(),
@@ -55,270 +122,363 @@ fn eval_expr(expr: Expr, lapis: &mut Lapis, buffer: &mut String) {
             w.duration(),
             Arc::strong_count(&w) - 1
         ));
-    } else if let Some(seq) = path_seq(&expr, lapis).or(call_seq(&expr, lapis).as_ref()) {
-        let info = format!(
-            "\n// Sequencer(outs: {}, ins: {}, has_backend: {}, replay: {}, loop: ({}, {}))",
-            seq.outputs(),
-            seq.inputs(),
-            seq.has_backend(),
-            seq.replay_events(),
-            seq.loop_start(),
-            seq.loop_end(),
-        );
-        buffer.push_str(&info);
-    } else if let Some(source) = eval_source(&expr, lapis) {
+    } else if let Some(seq) = path_seq(expr, lapis) {
+        buffer.push_str(&seq_info(seq));
+    } else if let Some(seq) = attempt(call_seq(expr, lapis), &mut err) {
+        buffer.push_str(&seq_info(&seq));
+    } else if let Some(source) = attempt(eval_source(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {source:?}"));
-    } else if let Some(event) = eval_eventid(&expr, lapis) {
+    } else if let Some(event) = attempt(eval_eventid(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {event:?}"));
-    } else if let Some(entity) = eval_entity(&expr, lapis) {
+    } else if let Some(timer) = attempt(eval_timer(expr, lapis), &mut err) {
+        buffer.push_str(&format!("\n// {timer:?}"));
+    } else if let Some(entity) = attempt(eval_entity(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n// {entity:?}"));
-    } else if let Some(string) = eval_string(&expr, lapis) {
+    } else if let Some(string) = attempt(eval_string(expr, lapis), &mut err) {
         buffer.push_str(&format!("\n/* \"{}\" */", string));
-    } else if let Some(k) = nth_path_ident(&expr, 0)
+    } else if let Some(k) = nth_path_ident(expr, 0)
         && let Some(t) = lapis.data.atomic_table_map.get(&k)
     {
         buffer.push_str(&format!("\n// AtomicTable(len:{})", t.len()));
-    } else if let Expr::Binary(expr) = expr {
-        float_bin_assign(&expr, lapis);
-    } else if let Expr::Call(expr) = expr {
-        gravity_commands(&expr, lapis);
-        function_calls(&expr, lapis, buffer);
-    } else if let Expr::Break(_) = expr {
-        buffer.push_str("#B");
-    } else if let Expr::Continue(_) = expr {
-        buffer.push_str("#C");
-    } else if let Expr::MethodCall(expr) = expr {
-        match expr.method.to_string().as_str() {
-            "play" => {
-                if let Some(mut g) = eval_net(&expr.receiver, lapis) {
-                    let slot_outputs = lapis.audio_out.outputs();
-                    if g.inputs() == 0
-                        && g.outputs() == slot_outputs
-                        && let Some(config) = &lapis.out_stream_config.0
-                    {
-                        g.allocate();
-                        g.set_sample_rate(config.sample_rate.0 as f64);
-                        lapis.audio_out.set(Fade::Smooth, 0.01, Box::new(g));
-                    }
-                }
+    } else if let Some(k) = nth_path_ident(expr, 0)
+        && let Some(f) = lapis.data.fnmap.get(&k)
+    {
+        buffer.push_str(&format!("\n// {}", f.source(&k)));
+    } else {
+        let done = match expr {
+            Expr::Binary(expr) => float_bin_assign(expr, lapis),
+            Expr::Call(expr) => function_calls(expr, lapis, buffer).map_err(|e| e.within(expr)),
+            Expr::MethodCall(expr) => {
+                method_statements(expr, lapis, buffer).map_err(|e| e.within(expr))
             }
-            "drop" => {
-                if let Some(k) = nth_path_ident(&expr.receiver, 0) {
-                    lapis.drop(&k);
-                }
+            _ => Err(EvalError::mismatch("a statement")),
+        };
+        if let Err(e) = done {
+            attempt::<()>(Err(e), &mut err);
+            let err = err.unwrap_or_else(EvalError::invalid);
+            if err.kind != ErrorKind::NoValue {
+                return Err(err);
             }
-            "error" => {
-                if let Some(k) = nth_path_ident(&expr.receiver, 0)
-                    && let Some(g) = lapis.data.gmap.get_mut(&k)
-                {
-                    let error = format!("\n// {:?}", g.error());
-                    buffer.push_str(&error);
-                }
+        }
+    }
+    Ok(())
+}
+
+fn seq_info(seq: &Sequencer) -> String {
+    format!(
+        "\n// Sequencer(outs: {}, ins: {}, has_backend: {}, replay: {}, loop: ({}, {}))",
+        seq.outputs(),
+        seq.inputs(),
+        seq.has_backend(),
+        seq.replay_events(),
+        seq.loop_start(),
+        seq.loop_end(),
+    )
+}
+
+/// method calls that don't give a value
+fn method_statements(
+    expr: &ExprMethodCall,
+    lapis: &mut Lapis,
+    buffer: &mut String,
+) -> EvalResult<()> {
+    let receiver = &expr.receiver;
+    match expr.method.to_string().as_str() {
+        "play" => {
+            let mut g = eval_net(receiver, lapis).map_err(|e| EvalError::unknown_method().or(e))?;
+            let slot_outputs = lapis.audio_out.outputs();
+            if g.inputs() != 0 || g.outputs() != slot_outputs {
+                let err = EvalError::new(receiver, ErrorKind::Invalid);
+                let help = format!("the net needs 0 inputs and {slot_outputs} outputs to play");
+                return Err(err.suggest(help));
             }
-            "pause" => {
-                if let Some(k) = nth_path_ident(&expr.receiver, 0)
-                    && k == "time"
-                {
-                    lapis.time.pause();
-                }
+            let Some(config) = &lapis.out_stream_config.0 else {
+                return Err(EvalError::invalid().suggest("there's no output stream"));
+            };
+            g.allocate();
+            g.set_sample_rate(config.sample_rate.0 as f64);
+            lapis.audio_out.set(Fade::Smooth, 0.01, Box::new(g));
+        }
+        "drop" => match nth_path_ident(receiver, 0) {
+            Some(k) if lapis.data.contains(&k) => lapis.drop(&k),
+            _ => {
+                let err = path_error(receiver, lapis, "a variable");
+                return Err(EvalError::unknown_method().or(err));
             }
-            "resume" | "unpause" => {
-                if let Some(k) = nth_path_ident(&expr.receiver, 0)
-                    && k == "time"
-                {
-                    lapis.time.unpause();
-                }
+        },
+        "cancel" => {
+            let id = path_timer(receiver, lapis).map_err(|e| EvalError::unknown_method().or(e))?;
+            lapis.data.timers.cancel(id);
+        }
+        "error" => {
+            let k = nth_path_ident(receiver, 0).unwrap_or_default();
+            let Some(g) = lapis.data.gmap.get_mut(&k) else {
+                let err = path_error(receiver, lapis, "a net");
+                return Err(EvalError::unknown_method().or(err));
+            };
+            let error = format!("\n// {:?}", g.error());
+            buffer.push_str(&error);
+        }
+        "pause" | "resume" | "unpause" => {
+            if nth_path_ident(receiver, 0).as_deref() != Some("time") {
+                return Err(EvalError::unknown_method());
             }
-            _ => {
-                wave_methods(&expr, lapis);
-                net_methods(&expr, lapis);
-                vec_methods(&expr, lapis);
-                shared_methods(&expr, lapis);
-                seq_methods(&expr, lapis);
+            if expr.method == "pause" {
+                lapis.time.pause();
+            } else {
+                lapis.time.unpause();
             }
         }
+        _ => {
+            let mut err = None;
+            let handlers: [fn(&ExprMethodCall, &mut Lapis) -> EvalResult<()>; 5] = [
+                wave_methods,
+                net_methods,
+                vec_methods,
+                |expr, lapis| shared_methods(expr, lapis),
+                seq_methods,
+            ];
+            for handler in handlers {
+                if attempt(handler(expr, lapis), &mut err).is_some() {
+                    return Ok(());
+                }
+            }
+            return Err(err.unwrap_or_else(EvalError::unknown_method));
+        }
     }
+    Ok(())
 }
 
-fn eval_if(expr: ExprIf, lapis: &mut Lapis, buffer: &mut String) {
-    if let Some(cond) = eval_bool(&expr.cond, lapis) {
+fn eval_if(expr: &ExprIf, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    let cond = with_expanded(&expr.cond, lapis, buffer, eval_cond);
+    if let Some(cond) = cond {
         if cond {
-            let expr = Expr::Block(ExprBlock {
-                attrs: Vec::new(),
-                label: None,
-                block: expr.then_branch,
-            });
-            let s = eval_stmt(Stmt::Expr(expr, None), lapis);
-            buffer.push_str(&s);
-        } else if let Some((_, else_branch)) = expr.else_branch {
-            let s = eval_stmt(Stmt::Expr(*else_branch, None), lapis);
-            buffer.push_str(&s);
+            return eval_stmts(&expr.then_branch.stmts, lapis, buffer);
+        } else if let Some((_, else_branch)) = &expr.else_branch {
+            match &**else_branch {
+                Expr::Block(expr) => return eval_block(expr, lapis, buffer),
+                Expr::If(expr) => return eval_if(expr, lapis, buffer),
+                _ => {}
+            }
         }
     }
+    Flow::Next
+}
+
+fn eval_block(expr: &ExprBlock, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    let flow = eval_stmts(&expr.block.stmts, lapis, buffer);
+    // `break 'label` out of a labeled block
+    if let (Flow::Break(Some(l)), Some(label)) = (&flow, &expr.label)
+        && *l == label.name.ident.to_string()
+    {
+        return Flow::Next;
+    }
+    flow
 }
 
-fn eval_block(expr: ExprBlock, lapis: &mut Lapis, buffer: &mut String) {
-    for stmt in expr.block.stmts {
-        buffer.push_str(&eval_stmt(stmt, lapis));
+/// evaluate `expr` and store it in whichever map fits its type
+pub fn assign_value(k: String, expr: &Expr, lapis: &mut Lapis) -> EvalResult<()> {
+    let mut err = None;
+    if let Some(v) = attempt(eval_float(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.fmap.insert(k, v);
+    } else if let Some(v) = attempt(eval_net(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.gmap.insert(k, v);
+    } else if let Some(arr) = attempt(eval_vec(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.vmap.insert(k, arr);
+    } else if let Some(table) = attempt(eval_atomic_table(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.atomic_table_map.insert(k, Arc::new(table));
+    } else if let Some(id) = attempt(eval_nodeid(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.idmap.insert(k, id);
+    } else if let Some(b) = attempt(eval_bool(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.bmap.insert(k, b);
+    } else if let Some(s) = attempt(eval_shared(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.insert_shared(k, s);
+    } else if let Some(w) = attempt(eval_wave(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.wmap.insert(k, w);
+    } else if let Some(seq) = attempt(call_seq(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.seqmap.insert(k, seq);
+    } else if let Some(source) = attempt(eval_source(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.srcmap.insert(k, source);
+    } else if let Some(event) = attempt(eval_eventid(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.eventmap.insert(k, event);
+    } else if let Some(timer) = attempt(eval_timer(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.timer_map.insert(k, timer);
+    } else if let Some(entity) = attempt(eval_entity(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.entitymap.insert(k, entity);
+    } else if let Some(string) = attempt(eval_string(expr, lapis), &mut err) {
+        lapis.drop(&k);
+        lapis.data.string_map.insert(k, string);
+    } else {
+        return Err(err.unwrap_or_else(EvalError::invalid));
     }
+    Ok(())
 }
 
-fn eval_local(expr: &syn::Local, lapis: &mut Lapis) -> Option<()> {
-    let init = expr.init.as_ref()?;
+fn eval_local(expr: &syn::Local, lapis: &mut Lapis, buffer: &mut String) -> EvalResult<()> {
+    let Some(init) = &expr.init else {
+        let err = EvalError::new(expr, ErrorKind::Invalid);
+        return Err(err.suggest("give it a value with `let x = ...;`"));
+    };
     if let Some(k) = pat_ident(&expr.pat) {
-        if let Some(v) = eval_float(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.fmap.insert(k, v);
-        } else if let Some(v) = eval_net(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.gmap.insert(k, v);
-        } else if let Some(arr) = eval_vec(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.vmap.insert(k, arr);
-        } else if let Some(table) = eval_atomic_table(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.atomic_table_map.insert(k, Arc::new(table));
-        } else if let Some(id) = eval_nodeid(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.idmap.insert(k, id);
-        } else if let Some(b) = eval_bool(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.bmap.insert(k, b);
-        } else if let Some(s) = eval_shared(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.smap.insert(k, s);
-        } else if let Some(w) = eval_wave(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.wmap.insert(k, w);
-        } else if let Some(seq) = call_seq(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.seqmap.insert(k, seq);
-        } else if let Some(source) = eval_source(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.srcmap.insert(k, source);
-        } else if let Some(event) = eval_eventid(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.eventmap.insert(k, event);
-        } else if let Some(entity) = eval_entity(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.entitymap.insert(k, entity);
-        } else if let Some(string) = eval_string(&init.expr, lapis) {
-            lapis.drop(&k);
-            lapis.data.string_map.insert(k, string);
-        } else {
-            return None;
-        }
+        with_expanded(&init.expr, lapis, buffer, |expr, lapis, buffer| {
+            if lapis.data.fn_locals.is_empty() {
+                let r = assign_value(k, expr, lapis);
+                or_report(r, expr, None, lapis, buffer);
+                return;
+            }
+            // in a function, `k` only shadows what it replaces once the value is made
+            // (`let x = x + 1;` uses the outer `x`)
+            let tmp = lapis.data.temp_name();
+            let r = assign_value(tmp.clone(), expr, lapis);
+            if or_report(r, expr, None, lapis, buffer).is_some() {
+                let value = lapis.data.take(&tmp);
+                lapis.data.shadow(&k);
+                lapis.data.put(&k, value);
+            }
+        });
+        Ok(())
     } else if let Pat::Tuple(pat) = &expr.pat
         && let Expr::Call(call) = &*init.expr
     {
-        let p0 = pat_ident(pat.elems.first()?)?;
-        let p1 = pat_ident(pat.elems.get(1)?)?;
-        let f = nth_path_ident(&call.func, 0)?;
-        if f == "bounded" {
-            let cap = eval_usize(call.args.first()?, lapis)?;
-            let (s, r) = bounded(cap.clamp(0, 1000000));
-            let s = Net::wrap(Box::new(An(BuffIn::new(s))));
-            let r = Net::wrap(Box::new(An(BuffOut::new(r))));
-            lapis.drop(&p0);
-            lapis.data.gmap.insert(p0, s);
-            lapis.drop(&p1);
-            lapis.data.gmap.insert(p1, r);
-        } else if f == "buffer" {
-            let cap = eval_usize(call.args.first()?, lapis)?;
-            let (s, r) = fundsp::misc_nodes::buffer(cap.clamp(0, 1000000));
-            let s = Net::wrap(Box::new(s));
-            let r = Net::wrap(Box::new(r));
-            lapis.drop(&p0);
-            lapis.data.gmap.insert(p0, s);
-            lapis.drop(&p1);
-            lapis.data.gmap.insert(p1, r);
-        } else if f == "Net" && nth_path_ident(&call.func, 1)? == "wrap_id" {
-            let initial = eval_net(call.args.first()?, lapis)?;
-            let (net, id) = Net::wrap_id(Box::new(initial));
-            lapis.drop(&p0);
-            lapis.data.gmap.insert(p0, net);
-            lapis.drop(&p1);
-            lapis.data.idmap.insert(p1, id);
-        } else {
-            return None;
-        }
+        local_pair(pat, call, lapis)
+            .map_err(|e| e.within(call))
+            .map_err(|e| finish(e, &init.expr, lapis, None))
     } else {
-        return None;
+        let err = EvalError::new(&expr.pat, ErrorKind::Invalid);
+        Err(err.suggest("only a name or a pair from `bounded`, `buffer` or `Net::wrap_id`"))
     }
-    Some(())
 }
 
-#[allow(clippy::map_entry)]
-fn eval_assign(expr: &ExprAssign, lapis: &mut Lapis) {
+/// `let (a, b) = ...;` for the functions that give two things
+fn local_pair(pat: &PatTuple, call: &ExprCall, lapis: &mut Lapis) -> EvalResult<()> {
+    let p0 = pat.elems.first().and_then(pat_ident);
+    let p1 = pat.elems.get(1).and_then(pat_ident);
+    let (Some(p0), Some(p1), 2) = (p0, p1, pat.elems.len()) else {
+        return Err(EvalError::new(pat, ErrorKind::Invalid).suggest("this needs two names"));
+    };
+    let f = nth_path_ident(&call.func, 0).or_invalid()?;
+    if f == "bounded" {
+        let cap = eval_usize(arg(call, 0)?, lapis)?;
+        let (s, r) = bounded(cap.clamp(0, 1000000));
+        let s = Net::wrap(Box::new(An(BuffIn::new(s))));
+        let r = Net::wrap(Box::new(An(BuffOut::new(r))));
+        lapis.data.shadow(&p0);
+        lapis.data.gmap.insert(p0, s);
+        lapis.data.shadow(&p1);
+        lapis.data.gmap.insert(p1, r);
+    } else if f == "buffer" {
+        let cap = eval_usize(arg(call, 0)?, lapis)?;
+        let (s, r) = fundsp::misc_nodes::buffer(cap.clamp(0, 1000000));
+        let s = Net::wrap(Box::new(s));
+        let r = Net::wrap(Box::new(r));
+        lapis.data.shadow(&p0);
+        lapis.data.gmap.insert(p0, s);
+        lapis.data.shadow(&p1);
+        lapis.data.gmap.insert(p1, r);
+    } else if f == "Net" && nth_path_ident(&call.func, 1).as_deref() == Some("wrap_id") {
+        let initial = eval_net(arg(call, 0)?, lapis)?;
+        let (net, id) = Net::wrap_id(Box::new(initial));
+        lapis.data.shadow(&p0);
+        lapis.data.gmap.insert(p0, net);
+        lapis.data.shadow(&p1);
+        lapis.data.idmap.insert(p1, id);
+    } else {
+        return Err(EvalError::unknown_function());
+    }
+    Ok(())
+}
+
+fn eval_assign(expr: &ExprAssign, lapis: &mut Lapis, buffer: &mut String) -> EvalResult<()> {
     match &*expr.left {
         Expr::Path(_) => {
             let Some(ident) = nth_path_ident(&expr.left, 0) else {
-                return;
+                return Ok(());
             };
-            if let Some(f) = eval_float(&expr.right, lapis) {
-                if let Some(var) = lapis.data.fmap.get_mut(&ident) {
-                    *var = f;
-                }
-            } else if lapis.data.gmap.contains_key(&ident) {
-                if let Some(g) = eval_net(&expr.right, lapis) {
-                    lapis.data.gmap.insert(ident, g);
-                }
-            } else if lapis.data.vmap.contains_key(&ident) {
-                if let Some(a) = eval_vec(&expr.right, lapis) {
-                    lapis.data.vmap.insert(ident, a);
-                }
-            } else if let Some(id) = eval_nodeid(&expr.right, lapis) {
-                if let Some(var) = lapis.data.idmap.get_mut(&ident) {
-                    *var = id;
-                }
-            } else if let Some(b) = eval_bool(&expr.right, lapis) {
-                if let Some(var) = lapis.data.bmap.get_mut(&ident) {
-                    *var = b;
-                }
-            } else if let Some(s) = eval_shared(&expr.right, lapis) {
-                if let Some(var) = lapis.data.smap.get_mut(&ident) {
-                    *var = s;
-                }
-            } else if let Some(s) = eval_source(&expr.right, lapis) {
-                if let Some(var) = lapis.data.srcmap.get_mut(&ident) {
-                    *var = s;
-                }
-            } else if let Some(event) = eval_eventid(&expr.right, lapis) {
-                if let Some(var) = lapis.data.eventmap.get_mut(&ident) {
-                    *var = event;
-                }
-            } else if let Some(entity) = eval_entity(&expr.right, lapis) {
-                if let Some(var) = lapis.data.entitymap.get_mut(&ident) {
-                    *var = entity;
-                }
-            } else if let Some(string) = eval_string(&expr.right, lapis)
-                && let Some(var) = lapis.data.string_map.get_mut(&ident)
-            {
-                *var = string;
+            let Some(ty) = lapis.data.var_type(&ident) else {
+                let err = path_error(&expr.left, lapis, "a variable");
+                return Err(finish(err, &expr.left, lapis, None));
+            };
+            if matches!(
+                ty,
+                "a wave" | "a sequencer" | "an atomic table" | "a function"
+            ) {
+                let err = EvalError::new(&expr.left, ErrorKind::Unassignable(ty));
+                return Err(err.suggest(format!("use `let {ident} = ...;` to replace it")));
             }
+            // errors in the right side are reported here, pointing at it
+            let right = &*expr.right;
+            let r = reassign(ident, ty, right, lapis);
+            or_report(r, right, Some(ty), lapis, buffer);
         }
         Expr::Index(left) => {
-            if let Some(k) = nth_path_ident(&left.expr, 0)
-                && let Some(index) = eval_usize(&left.index, lapis)
-                && let Some(right) = eval_float_f32(&expr.right, lapis)
-                && let Some(vec) = lapis.data.vmap.get_mut(&k)
-                && let Some(v) = vec.get_mut(index)
-            {
-                *v = right;
+            let k = nth_path_ident(&left.expr, 0).unwrap_or_default();
+            if !lapis.data.vmap.contains_key(&k) {
+                let err = path_error(&left.expr, lapis, "an array");
+                return Err(finish(err, &left.expr, lapis, Some("an array")));
             }
+            let index = eval_usize(&left.index, lapis)
+                .map_err(|e| finish(e, &left.index, lapis, Some("a float")))?;
+            let right = eval_float_f32(&expr.right, lapis)
+                .map_err(|e| finish(e, &expr.right, lapis, Some("a float")))?;
+            let vec = lapis.data.vmap.get_mut(&k).unwrap();
+            let len = vec.len();
+            let Some(v) = vec.get_mut(index) else {
+                let kind = ErrorKind::OutOfRange {
+                    what: "index",
+                    index,
+                    len,
+                };
+                return Err(EvalError::new(left, kind));
+            };
+            *v = right;
         }
         Expr::Lit(left) => {
             if let Lit::Str(left) = &left.lit {
-                if let Some(b) = eval_bool(&expr.right, lapis) {
+                if let Ok(b) = eval_bool(&expr.right, lapis) {
                     match left.value().as_str() {
                         "keys" => lapis.data.keys_active = b,
                         "quiet" => lapis.data.quiet = b,
                         "keys_repeat" => lapis.data.keys_repeat = b,
                         _ => {}
                     }
-                } else if let Some(right) = eval_string(&expr.right, lapis)
-                    && let Some(shortcut) = parse_shortcut(left.value())
-                {
+                } else if let Some(binding) = parse_midi(&left.value()) {
+                    lapis.data.midi.remove(&binding);
+                    if let Ok(shared) = eval_shared(&expr.right, lapis) {
+                        lapis
+                            .data
+                            .midi
+                            .insert(binding, RemoteAction::Shared(shared));
+                    } else if let Ok(code) = eval_string(&expr.right, lapis)
+                        && !code.is_empty()
+                    {
+                        lapis.data.midi.insert(binding, RemoteAction::Code(code));
+                    }
+                } else if let Some(addr) = left.value().strip_prefix("osc ") {
+                    let addr = addr.trim().to_string();
+                    lapis.data.osc.remove(&addr);
+                    if let Ok(shared) = eval_shared(&expr.right, lapis) {
+                        lapis.data.osc.insert(addr, RemoteAction::Shared(shared));
+                    } else if let Ok(code) = eval_string(&expr.right, lapis)
+                        && !code.is_empty()
+                    {
+                        lapis.data.osc.insert(addr, RemoteAction::Code(code));
+                    }
+                } else if let Some(shortcut) = parse_shortcut(left.value()) {
+                    let right = eval_string(&expr.right, lapis)
+                        .map_err(|e| finish(e, &expr.right, lapis, Some("a string")))?;
                     lapis.data.keys.remove(&shortcut);
                     let key = shortcut.1.name();
                     let code = right.replace("@", key);
@@ -330,47 +490,82 @@ fn eval_assign(expr: &ExprAssign, lapis: &mut Lapis) {
         }
         _ => {}
     }
+    Ok(())
+}
+
+/// give an existing variable (of type `ty`) a new value
+fn reassign(ident: String, ty: &str, right: &Expr, lapis: &mut Lapis) -> EvalResult<()> {
+    match ty {
+        "a float" => {
+            let f = eval_float(right, lapis)?;
+            lapis.data.fmap.insert(ident, f);
+        }
+        "a net" => {
+            let g = eval_net(right, lapis)?;
+            lapis.data.gmap.insert(ident, g);
+        }
+        "an array" => {
+            let a = eval_vec(right, lapis)?;
+            lapis.data.vmap.insert(ident, a);
+        }
+        "a node id" => {
+            let id = eval_nodeid(right, lapis)?;
+            lapis.data.idmap.insert(ident, id);
+        }
+        "a bool" => {
+            let b = eval_bool(right, lapis)?;
+            lapis.data.bmap.insert(ident, b);
+        }
+        "a shared" => {
+            let s = eval_shared(right, lapis)?;
+            lapis.data.insert_shared(ident, s);
+        }
+        "a source" => {
+            let s = eval_source(right, lapis)?;
+            lapis.data.srcmap.insert(ident, s);
+        }
+        "an event id" => {
+            let event = eval_eventid(right, lapis)?;
+            lapis.data.eventmap.insert(ident, event);
+        }
+        "a timer" => {
+            let timer = eval_timer(right, lapis)?;
+            lapis.data.timer_map.insert(ident, timer);
+        }
+        "an entity" => {
+            let entity = eval_entity(right, lapis)?;
+            lapis.data.entitymap.insert(ident, entity);
+        }
+        "a string" => {
+            let string = eval_string(right, lapis)?;
+            lapis.data.string_map.insert(ident, string);
+        }
+        _ => return Err(EvalError::invalid()),
+    }
+    Ok(())
 }
 
-fn eval_for_loop(expr: &ExprForLoop, lapis: &mut Lapis, buffer: &mut String) {
+fn eval_for_loop(expr: &ExprForLoop, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    let mut flow = Flow::Next;
     if let Some(ident) = pat_ident(&expr.pat) {
-        let bounds = range_bounds(&expr.expr, lapis);
-        let arr = eval_vec(&expr.expr, lapis);
+        let items = with_expanded(&expr.expr, lapis, buffer, |e, lapis, buffer| {
+            let r = loop_items(e, lapis);
+            or_report(r, e, Some("a range or an array"), lapis, buffer)
+        });
+        let Some(items) = items else {
+            return flow;
+        };
         let tmp = lapis.data.fmap.remove(&ident);
-        if let Some((r0, r1)) = bounds {
-            'main_loop: for i in r0..r1 {
-                lapis.data.fmap.insert(ident.clone(), i as f64);
-                for stmt in &expr.body.stmts {
-                    let s = eval_stmt(stmt.clone(), lapis);
-                    buffer.push_str(&s);
-                    // NOTE amy.. you've out lazied yourself (proud of you)
-                    if buffer.ends_with("#B") {
-                        buffer.pop();
-                        buffer.pop();
-                        break 'main_loop;
-                    } else if buffer.ends_with("#C") {
-                        buffer.pop();
-                        buffer.pop();
-                        continue 'main_loop;
-                    }
-                }
+        for i in items {
+            if !loop_tick(lapis, buffer) {
+                flow = Flow::Halt;
+                break;
             }
-        } else if let Some(arr) = arr {
-            'main_loop: for i in arr {
-                lapis.data.fmap.insert(ident.clone(), i as f64);
-                for stmt in &expr.body.stmts {
-                    let s = eval_stmt(stmt.clone(), lapis);
-                    buffer.push_str(&s);
-                    if buffer.ends_with("#B") {
-                        buffer.pop();
-                        buffer.pop();
-                        break 'main_loop;
-                    } else if buffer.ends_with("#C") {
-                        buffer.pop();
-                        buffer.pop();
-                        continue 'main_loop;
-                    }
-                }
+            lapis.data.fmap.insert(ident.clone(), i);
+            let body = eval_stmts(&expr.body.stmts, lapis, buffer);
+            if let Some(f) = loop_control(body, &expr.label) {
+                flow = f;
+                break;
             }
         }
         if let Some(old) = tmp {
@@ -379,24 +574,113 @@ fn eval_for_loop(expr: &ExprForLoop, lapis: &mut Lapis, buffer: &mut String) {
             lapis.data.fmap.remove(&ident);
         }
     }
+    flow
+}
+
+/// the values a `for` loop goes over
+fn loop_items(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Box<dyn Iterator<Item = f64>>> {
+    let range = match range_bounds(expr, lapis) {
+        Ok((r0, r1)) => return Ok(Box::new((r0..r1).map(|i| i as f64))),
+        Err(err) => err,
+    };
+    match eval_vec(expr, lapis) {
+        Ok(arr) => Ok(Box::new(arr.into_iter().map(|i| i as f64))),
+        Err(err) => Err(range.or(err)),
+    }
+}
+
+/// evaluate the condition of an `if` or `while`
+fn eval_cond(expr: &Expr, lapis: &mut Lapis, buffer: &mut String) -> Option<bool> {
+    let r = eval_bool(expr, lapis);
+    or_report(r, expr, Some("a bool"), lapis, buffer)
+}
+
+fn eval_while(expr: &ExprWhile, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    while let Some(true) = with_expanded(&expr.cond, lapis, buffer, eval_cond) {
+        if !loop_tick(lapis, buffer) {
+            return Flow::Halt;
+        }
+        let body = eval_stmts(&expr.body.stmts, lapis, buffer);
+        if let Some(f) = loop_control(body, &expr.label) {
+            return f;
+        }
+    }
+    Flow::Next
+}
+
+fn eval_loop(expr: &ExprLoop, lapis: &mut Lapis, buffer: &mut String) -> Flow {
+    loop {
+        if !loop_tick(lapis, buffer) {
+            return Flow::Halt;
+        }
+        let body = eval_stmts(&expr.body.stmts, lapis, buffer);
+        if let Some(f) = loop_control(body, &expr.label) {
+            return f;
+        }
+    }
+}
+
+/// count an iteration, returns false (and reports it) once the limit is reached
+fn loop_tick(lapis: &mut Lapis, buffer: &mut String) -> bool {
+    let limit = if lapis.data.hook {
+        HOOK_LOOP_LIMIT
+    } else {
+        LOOP_LIMIT
+    };
+    lapis.data.loop_iterations += 1;
+    if lapis.data.loop_iterations > limit {
+        buffer.push_str(&format!(
+            "\n// error: loop limit ({limit} iterations) reached"
+        ));
+        return false;
+    }
+    true
+}
+
+/// decide what a loop does with the flow of its body
+/// None means keep looping, Some(flow) means stop and pass flow up
+fn loop_control(flow: Flow, label: &Option<Label>) -> Option<Flow> {
+    let ours = |l: &Option<String>| match (l, label) {
+        (None, _) => true,
+        (Some(l), Some(label)) => *l == label.name.ident.to_string(),
+        (Some(_), None) => false,
+    };
+    match flow {
+        Flow::Next => None,
+        Flow::Continue(l) if ours(&l) => None,
+        Flow::Break(l) if ours(&l) => Some(Flow::Next),
+        flow => Some(flow),
+    }
 }
 
 // TODO move this somewhere?
-fn gravity_commands(expr: &ExprCall, lapis: &mut Lapis) -> Option<()> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn gravity_commands(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<()> {
+    let func = nth_path_ident(&expr.func, 0).or_invalid()?;
     if func == "gravity" {
-        let x = eval_float_f32(expr.args.first()?, lapis)?;
-        let y = eval_float_f32(expr.args.get(1)?, lapis)?;
+        let x = eval_float_f32(arg(expr, 0)?, lapis)?;
+        let y = eval_float_f32(arg(expr, 1)?, lapis)?;
         lapis.commands.insert_resource(Gravity(Vec2::new(x, y)));
     } else if func == "attraction" {
-        let a = eval_float_f32(expr.args.first()?, lapis)?;
+        let a = eval_float_f32(arg(expr, 0)?, lapis)?;
         lapis.commands.insert_resource(AttractionFactor(a));
+    } else if func == "attraction_theta" {
+        let t = eval_float_f32(arg(expr, 0)?, lapis)?;
+        lapis.commands.insert_resource(AttractionTheta(t.max(0.)));
+    } else {
+        return Err(EvalError::unknown_function());
     }
-    None
+    Ok(())
 }
 
-fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> Option<()> {
-    let func = nth_path_ident(&expr.func, 0)?;
+/// a port number
+fn eval_port(expr: &Expr, lapis: &Lapis) -> EvalResult<u16> {
+    let port = eval_usize(expr, lapis)?;
+    let err = || EvalError::new(expr, ErrorKind::Invalid).suggest("ports go up to 65535");
+    u16::try_from(port).map_err(|_| err())
+}
+
+fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> EvalResult<()> {
+    let func = nth_path_ident(&expr.func, 0).or_invalid()?;
     match func.as_str() {
         "list_in_devices" => {
             let list = list_in_devices().trim_end().replace('\n', "\n//");
@@ -408,11 +692,11 @@ fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> Op
         }
         "set_in_device" => {
             // underscores will evaluate to None
-            let host = eval_usize(expr.args.first()?, lapis);
-            let device = eval_usize(expr.args.get(1)?, lapis);
-            let channels = eval_usize(expr.args.get(2)?, lapis).map(|x| x as u16);
-            let sr = eval_usize(expr.args.get(3)?, lapis).map(|x| x as u32);
-            let buffer = eval_usize(expr.args.get(4)?, lapis).map(|x| x as u32);
+            let host = eval_usize(arg(expr, 0)?, lapis).ok();
+            let device = eval_usize(arg(expr, 1)?, lapis).ok();
+            let channels = eval_usize(arg(expr, 2)?, lapis).ok().map(|x| x as u16);
+            let sr = eval_usize(arg(expr, 3)?, lapis).ok().map(|x| x as u32);
+            let buffer = eval_usize(arg(expr, 4)?, lapis).ok().map(|x| x as u32);
             lapis.commands.trigger(SetInDevice {
                 host,
                 device,
@@ -422,11 +706,11 @@ fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> Op
             });
         }
         "set_out_device" => {
-            let host = eval_usize(expr.args.first()?, lapis);
-            let device = eval_usize(expr.args.get(1)?, lapis);
-            let channels = eval_usize(expr.args.get(2)?, lapis).map(|x| x as u16);
-            let sr = eval_usize(expr.args.get(3)?, lapis).map(|x| x as u32);
-            let buffer = eval_usize(expr.args.get(4)?, lapis).map(|x| x as u32);
+            let host = eval_usize(arg(expr, 0)?, lapis).ok();
+            let device = eval_usize(arg(expr, 1)?, lapis).ok();
+            let channels = eval_usize(arg(expr, 2)?, lapis).ok().map(|x| x as u16);
+            let sr = eval_usize(arg(expr, 3)?, lapis).ok().map(|x| x as u32);
+            let buffer = eval_usize(arg(expr, 4)?, lapis).ok().map(|x| x as u32);
             lapis.commands.trigger(SetOutDevice {
                 host,
                 device,
@@ -435,73 +719,181 @@ fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> Op
                 buffer,
             });
         }
+        "list_midi_devices" => {
+            let list = list_midi_devices().trim_end().replace('\n', "\n//");
+            buffer.push_str(&format!("\n//{list}"));
+        }
+        "set_midi_device" => {
+            let device = eval_usize(arg(expr, 0)?, lapis).ok();
+            lapis.commands.trigger(SetMidiDevice(device));
+        }
+        "drop_midi_device" => lapis.commands.trigger(DropMidiDevice),
+        "osc_listen" => {
+            let port = eval_port(arg(expr, 0)?, lapis)?;
+            // only local messages unless another host is given (e.g. "0.0.0.0")
+            let host = match expr.args.get(1) {
+                Some(host) => eval_string(host, lapis)?,
+                None => String::from("127.0.0.1"),
+            };
+            lapis.commands.trigger(OscListen { host, port });
+        }
+        "osc_stop" => lapis.commands.trigger(OscStop),
+        "osc_send" => {
+            let host = eval_string(arg(expr, 0)?, lapis)?;
+            let port = eval_port(arg(expr, 1)?, lapis)?;
+            let addr = eval_string(arg(expr, 2)?, lapis)?;
+            let mut args = Vec::new();
+            for arg in expr.args.iter().skip(3) {
+                let mut err = None;
+                if let Some(f) = attempt(eval_float(arg, lapis), &mut err) {
+                    args.push(OscArg::Float(f as f32));
+                } else if let Some(b) = attempt(eval_bool(arg, lapis), &mut err) {
+                    args.push(OscArg::Bool(b));
+                } else if let Some(s) = attempt(eval_string(arg, lapis), &mut err) {
+                    args.push(OscArg::Str(s));
+                } else {
+                    let err = err.unwrap_or_else(EvalError::invalid);
+                    return Err(err
+                        .at(arg)
+                        .suggest("osc arguments are floats, bools or strings"));
+                }
+            }
+            if let Err(err) = osc_send(&host, port, &addr, &args) {
+                buffer.push_str(&format!("\n// error: couldn't send osc: {err}"));
+            }
+        }
         "drop_in_stream" => lapis.commands.trigger(DropInStream),
         "drop_out_stream" => lapis.commands.trigger(DropOutStream),
+        "tempo" => {
+            let bpm = eval_float(arg(expr, 0)?, lapis)?;
+            if !bpm.is_finite() || bpm <= 0. {
+                let err = EvalError::new(arg(expr, 0)?, ErrorKind::Invalid);
+                return Err(err.suggest("the tempo has to be positive"));
+            }
+            lapis.data.timers.tempo = bpm;
+        }
+        "cancel_timers" => lapis.data.timers.clear(),
         "sleep" => {
-            let d = eval_float(expr.args.first()?, lapis)?;
-            let d = Duration::try_from_secs_f64(d).ok()?;
+            let d = eval_float(arg(expr, 0)?, lapis)?;
+            let Ok(d) = Duration::try_from_secs_f64(d) else {
+                let err = EvalError::new(arg(expr, 0)?, ErrorKind::Invalid);
+                return Err(err.suggest("the duration can't be negative"));
+            };
             thread::sleep(d);
         }
         "panic" => panic!(),
+        "quit" => {
+            lapis.commands.send_event(AppExit::Success);
+        }
         "eval" => {
-            let code = eval_string(expr.args.first()?, lapis)?;
+            let code = eval_string(arg(expr, 0)?, lapis)?;
             lapis.eval(&code);
         }
         "quiet_eval" => {
-            let code = eval_string(expr.args.first()?, lapis)?;
+            let code = eval_string(arg(expr, 0)?, lapis)?;
             lapis.quiet_eval(&code);
         }
+        "render_scene" => {
+            let seconds = eval_float(arg(expr, 0)?, lapis)?;
+            let path = eval_string(arg(expr, 1)?, lapis)?;
+            if !seconds.is_finite() || seconds <= 0. {
+                let err = EvalError::new(arg(expr, 0)?, ErrorKind::Invalid);
+                return Err(err.suggest("the duration has to be positive"));
+            }
+            if lapis.scene_render.is_some() {
+                buffer.push_str("\n// error: a render is already running");
+            } else if lapis.time.is_paused() {
+                // virtual time drives the render, it would never end
+                buffer.push_str("\n// error: time is paused (`time.resume();` first)");
+            } else {
+                let render = SceneRender::new(
+                    seconds,
+                    path,
+                    &mut lapis.audio_out,
+                    &mut lapis.out_stream_config,
+                    &mut lapis.voice_bus,
+                );
+                lapis.commands.insert_resource(render);
+            }
+        }
+        "record_start" => {
+            let path = eval_string(arg(expr, 0)?, lapis)?;
+            let float = match expr.args.get(1) {
+                Some(bits) => eval_float(bits, lapis)? == 32.,
+                None => false,
+            };
+            lapis.commands.trigger(RecordStart { path, float });
+        }
+        "record_stop" => lapis.commands.trigger(RecordStop),
+        "save_scene" => {
+            let path = eval_string(arg(expr, 0)?, lapis)?;
+            lapis.commands.trigger(SaveScene(path));
+        }
+        "load_scene" => {
+            let path = eval_string(arg(expr, 0)?, lapis)?;
+            lapis.commands.trigger(LoadScene(path));
+        }
+        "undo" => lapis.commands.trigger(Undo),
+        "redo" => lapis.commands.trigger(Redo),
         "clear_keys" => {
             lapis.clear_keys();
         }
+        "clear_midi" => {
+            lapis.clear_midi();
+        }
+        "clear_osc" => {
+            lapis.clear_osc();
+        }
         "clear_maps" => {
             lapis.clear_maps();
         }
         "clear" => {
             lapis.clear_keys();
+            lapis.clear_midi();
+            lapis.clear_osc();
             lapis.clear_maps();
         }
         #[cfg(feature = "plot")]
         "plot" => {
             use plotters::prelude::*;
-            let net = eval_net(expr.args.first()?, lapis)?;
-            let seconds = eval_float_f32(expr.args.get(1)?, lapis).unwrap_or(1.);
-            let sr = eval_float_f32(expr.args.get(2)?, lapis).unwrap_or(44100.);
-            let ymin = eval_float_f32(expr.args.get(3)?, lapis).unwrap_or(-1.);
-            let ymax = eval_float_f32(expr.args.get(4)?, lapis).unwrap_or(1.);
+            let net = eval_net(arg(expr, 0)?, lapis)?;
+            let seconds = eval_float_f32(arg(expr, 1)?, lapis).unwrap_or(1.);
+            let sr = eval_float_f32(arg(expr, 2)?, lapis).unwrap_or(44100.);
+            let ymin = eval_float_f32(arg(expr, 3)?, lapis).unwrap_or(-1.);
+            let ymax = eval_float_f32(arg(expr, 4)?, lapis).unwrap_or(1.);
             let mut hue = 0.;
             if let Some(arg) = expr.args.get(5)
-                && let Some(v) = eval_float(arg, lapis)
+                && let Ok(v) = eval_float(arg, lapis)
             {
                 hue = v;
             }
             let mut path = String::from("plot.png");
             let (mut w, mut h) = (1280, 640);
             if let Some(arg) = expr.args.get(6)
-                && let Some(v) = eval_string(arg, lapis)
+                && let Ok(v) = eval_string(arg, lapis)
             {
                 path = format!("{}.png", v);
             }
             if let Some(arg) = expr.args.get(7)
-                && let Some(v) = eval_usize(arg, lapis)
+                && let Ok(v) = eval_usize(arg, lapis)
             {
                 w = v as u32;
             }
             if let Some(arg) = expr.args.get(8)
-                && let Some(v) = eval_usize(arg, lapis)
+                && let Ok(v) = eval_usize(arg, lapis)
             {
                 h = v as u32;
             }
             let root = BitMapBackend::new(&path, (w, h)).into_drawing_area();
-            root.fill(&WHITE).ok()?;
+            root.fill(&WHITE).or_invalid()?;
             let mut chart = ChartBuilder::on(&root)
                 .margin(5)
                 .x_label_area_size(30)
                 .y_label_area_size(30)
                 .build_cartesian_2d(0f32..seconds, ymin..ymax)
-                .ok()?;
+                .or_invalid()?;
 
-            chart.configure_mesh().draw().ok()?;
+            chart.configure_mesh().draw().or_invalid()?;
 
             let samps = (seconds * sr) as usize;
             let ins = net.inputs();
@@ -524,10 +916,10 @@ fn function_calls(expr: &ExprCall, lapis: &mut Lapis, buffer: &mut String) -> Op
                         )
                         .point_size(1),
                     )
-                    .ok()?;
+                    .or_invalid()?;
             }
         }
-        _ => {}
+        _ => return gravity_commands(expr, lapis),
     }
-    None
+    Ok(())
 }
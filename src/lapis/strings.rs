@@ -1,57 +1,61 @@
-use super::{Lapis, floats::*, helpers::*, ints::*};
+use super::{Lapis, errors::*, floats::*, helpers::*, ints::*};
 use syn::*;
 
-pub fn eval_string(expr: &Expr, lapis: &Lapis) -> Option<String> {
+pub fn eval_string(expr: &Expr, lapis: &Lapis) -> EvalResult<String> {
     match expr {
-        Expr::Call(expr) => call_string(expr, lapis),
+        Expr::Call(call) => call_string(call, lapis).map_err(|e| e.within(call)),
         Expr::Lit(expr) => lit_string(&expr.lit),
-        Expr::Path(expr) => path_string(&expr.path, lapis),
-        _ => None,
+        Expr::Path(_) => path_string(expr, lapis),
+        _ => Err(EvalError::mismatch("a string")),
     }
+    .map_err(|e| e.at(expr))
 }
-fn lit_string(expr: &Lit) -> Option<String> {
+fn lit_string(expr: &Lit) -> EvalResult<String> {
     if let Lit::Str(expr) = expr {
-        return Some(expr.value());
+        return Ok(expr.value());
     }
-    None
+    Err(EvalError::mismatch("a string"))
 }
-fn path_string(expr: &Path, lapis: &Lapis) -> Option<String> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.string_map.get(&k).cloned()
+fn path_string(expr: &Expr, lapis: &Lapis) -> EvalResult<String> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a string")?;
+    match lapis.data.string_map.get(&k) {
+        Some(s) => Ok(s.clone()),
+        None => Err(path_error(expr, lapis, "a string")),
+    }
 }
-fn call_string(expr: &ExprCall, lapis: &Lapis) -> Option<String> {
-    let func = nth_path_ident(&expr.func, 0)?;
+fn call_string(expr: &ExprCall, lapis: &Lapis) -> EvalResult<String> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("a string")?;
     match func.as_str() {
         "file" => {
-            let path = eval_string(expr.args.first()?, lapis)?;
-            std::fs::read_to_string(path).ok()
+            let path = eval_string(arg(expr, 0)?, lapis)?;
+            std::fs::read_to_string(path).or_invalid()
         }
         "replace" => {
-            let string = eval_string(expr.args.first()?, lapis)?;
-            let from = eval_string(expr.args.get(1)?, lapis)?;
-            let to = eval_string(expr.args.get(2)?, lapis)?;
-            Some(string.replace(&from, &to))
+            let string = eval_string(arg(expr, 0)?, lapis)?;
+            let from = eval_string(arg(expr, 1)?, lapis)?;
+            let to = eval_string(arg(expr, 2)?, lapis)?;
+            Ok(string.replace(&from, &to))
         }
         "replacen" => {
-            let string = eval_string(expr.args.first()?, lapis)?;
-            let from = eval_string(expr.args.get(1)?, lapis)?;
-            let to = eval_string(expr.args.get(2)?, lapis)?;
-            let n = eval_usize(expr.args.get(3)?, lapis)?;
-            Some(string.replacen(&from, &to, n))
+            let string = eval_string(arg(expr, 0)?, lapis)?;
+            let from = eval_string(arg(expr, 1)?, lapis)?;
+            let to = eval_string(arg(expr, 2)?, lapis)?;
+            let n = eval_usize(arg(expr, 3)?, lapis)?;
+            Ok(string.replacen(&from, &to, n))
         }
         "format" => {
-            let mut string = eval_string(expr.args.first()?, lapis)?;
+            let mut string = eval_string(arg(expr, 0)?, lapis)?;
             let mut iter = expr.args.iter();
             iter.next();
             for arg in iter {
-                if let Some(f) = eval_float(arg, lapis) {
+                if let Ok(f) = eval_float(arg, lapis) {
                     string = string.replacen("$", &format!("{f}"), 1);
-                } else if let Some(s) = eval_string(arg, lapis) {
+                } else if let Ok(s) = eval_string(arg, lapis) {
                     string = string.replacen("$", &s, 1);
                 }
             }
-            Some(string)
+            Ok(string)
         }
-        _ => None,
+        _ => Err(EvalError::unknown_function()),
     }
 }
@@ -0,0 +1,247 @@
+use super::{Lapis, errors::*, floats::*, helpers::*, parse_input, strings::*};
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
+use syn::*;
+
+/// a handle to scheduled code (see `Timers`)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct TimerId(u64);
+
+#[derive(Clone, Copy, Debug)]
+enum Interval {
+    Seconds(f64),
+    /// at `Timers::tempo`
+    Beats(f64),
+}
+
+struct Timer {
+    code: String,
+    interval: Interval,
+    /// until it fires (in the interval's unit)
+    left: f64,
+    repeat: bool,
+}
+
+/// code scheduled with `after`/`every` (and their `_beats` versions)
+/// driven by virtual time, so pausing time pauses the timers
+pub struct Timers {
+    /// beats per minute
+    pub tempo: f64,
+    next: u64,
+    /// ordered by creation, so timers that fire on the same frame run in that order
+    active: BTreeMap<TimerId, Timer>,
+}
+
+/// the parsed code of active timers, parsed once when they're created
+/// (syn trees aren't `Send`, so this is a non-send resource)
+#[derive(Default)]
+pub struct TimerCache(HashMap<TimerId, Rc<Stmt>>);
+
+impl Default for Timers {
+    fn default() -> Self {
+        Timers {
+            tempo: 120.,
+            next: 0,
+            active: BTreeMap::new(),
+        }
+    }
+}
+
+impl Timers {
+    fn add(&mut self, code: String, interval: Interval, repeat: bool) -> TimerId {
+        let id = TimerId(self.next);
+        self.next += 1;
+        let left = match interval {
+            Interval::Seconds(x) | Interval::Beats(x) => x,
+        };
+        let timer = Timer {
+            code,
+            interval,
+            left,
+            repeat,
+        };
+        self.active.insert(id, timer);
+        id
+    }
+
+    pub fn cancel(&mut self, id: TimerId) {
+        self.active.remove(&id);
+    }
+
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// whether the timer hasn't fired (or been cancelled) yet
+    pub fn is_active(&self, id: TimerId) -> bool {
+        self.active.contains_key(&id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// move time forward (in seconds) and return the timers that fired (and their code)
+    /// repeating timers fire at most once per call, but keep their phase
+    fn advance(&mut self, dt: f64) -> Vec<(TimerId, String)> {
+        let beats = dt * self.tempo / 60.;
+        let mut due = Vec::new();
+        self.active.retain(|id, timer| {
+            let (step, len) = match timer.interval {
+                Interval::Seconds(x) => (dt, x),
+                Interval::Beats(x) => (beats, x),
+            };
+            timer.left -= step;
+            if timer.left > 0. {
+                return true;
+            }
+            due.push((*id, timer.code.clone()));
+            if timer.repeat {
+                timer.left += len * ((-timer.left / len).floor() + 1.);
+            }
+            timer.repeat
+        });
+        due
+    }
+}
+
+/// evaluate the code of the timers that are due
+pub fn run_timers(mut lapis: Lapis) {
+    let dt = lapis.time.delta_secs_f64();
+    if dt == 0. || lapis.data.timers.is_empty() {
+        lapis.timer_cache.0.clear();
+        return;
+    }
+    for (id, code) in lapis.data.timers.advance(dt) {
+        let stmt = lapis.timer_cache.0.get(&id).cloned();
+        lapis.as_hook(|lapis| match (stmt, lapis.data.quiet) {
+            (Some(stmt), true) => lapis.quiet_eval_parsed(&stmt),
+            (Some(stmt), false) => lapis.eval_parsed(&code, &stmt),
+            (None, true) => {}
+            // this reports the parse error
+            (None, false) => lapis.eval(&code),
+        });
+    }
+    // forget the code of the timers that are done or were cancelled
+    let timers = &lapis.data.timers;
+    lapis.timer_cache.0.retain(|id, _| timers.is_active(*id));
+}
+
+pub fn eval_timer(expr: &Expr, lapis: &mut Lapis) -> EvalResult<TimerId> {
+    match expr {
+        Expr::Call(call) => call_timer(call, lapis).map_err(|e| e.within(call)),
+        Expr::Path(_) => path_timer(expr, lapis),
+        _ => Err(EvalError::mismatch("a timer")),
+    }
+    .map_err(|e| e.at(expr))
+}
+
+fn call_timer(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<TimerId> {
+    let func = nth_path_ident(&expr.func, 0).or_mismatch("a timer")?;
+    let (beats, repeat) = match func.as_str() {
+        "after" => (false, false),
+        "every" => (false, true),
+        "after_beats" => (true, false),
+        "every_beats" => (true, true),
+        _ => return Err(EvalError::unknown_function()),
+    };
+    let time = eval_float(arg(expr, 0)?, lapis)?;
+    let code = eval_string(arg(expr, 1)?, lapis)?;
+    let interval = if beats {
+        Interval::Beats(time)
+    } else {
+        Interval::Seconds(time)
+    };
+    // a repeating timer with no interval would fire every frame forever
+    if !time.is_finite() || time < 0. || (repeat && time == 0.) {
+        let help = if repeat {
+            "the interval has to be positive"
+        } else {
+            "the delay can't be negative"
+        };
+        return Err(EvalError::new(arg(expr, 0)?, ErrorKind::Invalid).suggest(help));
+    }
+    let stmt = parse_input(&code).ok();
+    let id = lapis.data.timers.add(code, interval, repeat);
+    if let Some(stmt) = stmt {
+        lapis.timer_cache.0.insert(id, Rc::new(stmt));
+    }
+    Ok(id)
+}
+
+pub fn path_timer(expr: &Expr, lapis: &Lapis) -> EvalResult<TimerId> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a timer")?;
+    match lapis.data.timer_map.get(&k) {
+        Some(id) => Ok(*id),
+        None => Err(path_error(expr, lapis, "a timer")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fired(timers: &mut Timers, dt: f64) -> Vec<String> {
+        timers
+            .advance(dt)
+            .into_iter()
+            .map(|(_, code)| code)
+            .collect()
+    }
+
+    #[test]
+    fn one_shot_and_repeat() {
+        let mut timers = Timers::default();
+        let once = timers.add("a".into(), Interval::Seconds(1.), false);
+        let every = timers.add("b".into(), Interval::Seconds(1.), true);
+        assert!(fired(&mut timers, 0.5).is_empty());
+        // in creation order
+        assert_eq!(fired(&mut timers, 0.5), ["a", "b"]);
+        assert!(!timers.is_active(once));
+        assert!(timers.is_active(every));
+        assert_eq!(fired(&mut timers, 1.), ["b"]);
+        timers.cancel(every);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn keeps_phase() {
+        let mut timers = Timers::default();
+        timers.add("a".into(), Interval::Seconds(1.), true);
+        assert!(fired(&mut timers, 0.75).is_empty());
+        // late by half a second, so the next one is due in half a second
+        assert_eq!(fired(&mut timers, 0.75), ["a"]);
+        assert!(fired(&mut timers, 0.25).is_empty());
+        assert_eq!(fired(&mut timers, 0.25), ["a"]);
+    }
+
+    #[test]
+    fn once_per_call() {
+        let mut timers = Timers::default();
+        timers.add("a".into(), Interval::Seconds(1.), true);
+        timers.add("b".into(), Interval::Seconds(0.), false);
+        assert_eq!(fired(&mut timers, 3.5), ["a", "b"]);
+        // the missed ones are skipped, but the phase is kept
+        assert_eq!(fired(&mut timers, 0.5), ["a"]);
+    }
+
+    #[test]
+    fn beats() {
+        let mut timers = Timers::default();
+        timers.add("a".into(), Interval::Beats(1.), true);
+        // 120 bpm is 2 beats a second
+        assert!(fired(&mut timers, 0.25).is_empty());
+        assert_eq!(fired(&mut timers, 0.25), ["a"]);
+        timers.tempo = 60.;
+        assert!(fired(&mut timers, 0.5).is_empty());
+        assert_eq!(fired(&mut timers, 0.5), ["a"]);
+        timers.clear();
+        let seconds = timers.add("s".into(), Interval::Seconds(1.), false);
+        timers.tempo = 240.;
+        timers.add("b".into(), Interval::Beats(2.), false);
+        assert_eq!(fired(&mut timers, 0.5), ["b"]);
+        assert!(timers.is_active(seconds));
+    }
+}
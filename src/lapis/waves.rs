@@ -1,121 +1,103 @@
-use super::{Lapis, arrays::*, floats::*, helpers::*, ints::*, nets::*, strings::*};
+use super::{Lapis, arrays::*, errors::*, floats::*, helpers::*, ints::*, nets::*, strings::*};
 use fundsp::hacker::*;
 use std::sync::Arc;
 use syn::*;
 
-pub fn eval_wave(expr: &Expr, lapis: &mut Lapis) -> Option<Arc<Wave>> {
+pub fn eval_wave(expr: &Expr, lapis: &mut Lapis) -> EvalResult<Arc<Wave>> {
     match expr {
-        Expr::Call(expr) => call_wave(expr, lapis),
-        Expr::MethodCall(expr) => method_wave(expr, lapis),
-        Expr::Path(expr) => path_wave(&expr.path, lapis),
-        _ => None,
+        Expr::Call(call) => call_wave(call, lapis).map_err(|e| e.within(call)),
+        Expr::MethodCall(call) => method_wave(call, lapis).map_err(|e| e.within(call)),
+        Expr::Path(_) => path_wave(expr, lapis),
+        _ => Err(EvalError::mismatch("a wave")),
     }
+    .map_err(|e| e.at(expr))
 }
 
-fn call_wave(expr: &ExprCall, lapis: &mut Lapis) -> Option<Arc<Wave>> {
-    let seg0 = nth_path_ident(&expr.func, 0)?;
-    let seg1 = nth_path_ident(&expr.func, 1)?;
+fn call_wave(expr: &ExprCall, lapis: &mut Lapis) -> EvalResult<Arc<Wave>> {
+    let seg0 = nth_path_ident(&expr.func, 0).or_mismatch("a wave")?;
+    let seg1 = nth_path_ident(&expr.func, 1).or_mismatch("a wave")?;
     if seg0 == "Arc" && (seg1 == "new" || seg1 == "clone") {
-        return eval_wave(expr.args.first()?, lapis);
+        return eval_wave(arg(expr, 0)?, lapis);
     }
     if seg0 != "Wave" {
-        return None;
+        return Err(EvalError::unknown_function());
     }
     match seg1.as_str() {
         "new" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let chans = eval_usize(arg0, lapis)?;
-            let sr = eval_float(arg1, lapis)?;
-            Some(Arc::new(Wave::new(chans, sr)))
+            let chans = eval_usize(arg(expr, 0)?, lapis)?;
+            let sr = eval_float(arg(expr, 1)?, lapis)?;
+            Ok(Arc::new(Wave::new(chans, sr)))
         }
         "with_capacity" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let chans = eval_usize(arg0, lapis)?;
-            let sr = eval_float(arg1, lapis)?;
-            let cap = eval_usize(arg2, lapis)?;
-            Some(Arc::new(Wave::with_capacity(chans, sr, cap)))
+            let chans = eval_usize(arg(expr, 0)?, lapis)?;
+            let sr = eval_float(arg(expr, 1)?, lapis)?;
+            let cap = eval_usize(arg(expr, 2)?, lapis)?;
+            Ok(Arc::new(Wave::with_capacity(chans, sr, cap)))
         }
         "zero" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let chans = eval_usize(arg0, lapis)?;
-            let sr = eval_float(arg1, lapis)?;
-            let dur = eval_float(arg2, lapis)?;
-            Some(Arc::new(Wave::zero(chans, sr, dur)))
+            let chans = eval_usize(arg(expr, 0)?, lapis)?;
+            let sr = eval_float(arg(expr, 1)?, lapis)?;
+            let dur = eval_float(arg(expr, 2)?, lapis)?;
+            Ok(Arc::new(Wave::zero(chans, sr, dur)))
         }
         "from_samples" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let sr = eval_float(arg0, lapis)?;
-            let samps = eval_vec(arg1, lapis)?;
-            Some(Arc::new(Wave::from_samples(sr, &samps)))
+            let sr = eval_float(arg(expr, 0)?, lapis)?;
+            let samps = eval_vec(arg(expr, 1)?, lapis)?;
+            Ok(Arc::new(Wave::from_samples(sr, &samps)))
         }
-        "render" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let sr = eval_float(arg0, lapis)?;
-            let dur = eval_float(arg1, lapis)?;
-            let mut net = eval_net(arg2, lapis)?;
-            if net.inputs() == 0 && net.outputs() > 0 && dur >= 0.0 {
-                Some(Arc::new(Wave::render(sr, dur, &mut net)))
-            } else {
-                None
+        "render" | "render_latency" => {
+            let sr = eval_float(arg(expr, 0)?, lapis)?;
+            let dur = eval_float(arg(expr, 1)?, lapis)?;
+            let mut net = eval_net(arg(expr, 2)?, lapis)?;
+            if net.inputs() != 0 || net.outputs() == 0 {
+                return Err(EvalError::invalid().suggest("the net needs 0 inputs and some outputs"));
             }
-        }
-        "render_latency" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let sr = eval_float(arg0, lapis)?;
-            let dur = eval_float(arg1, lapis)?;
-            let mut net = eval_net(arg2, lapis)?;
-            if net.inputs() == 0 && net.outputs() > 0 && dur >= 0.0 {
-                Some(Arc::new(Wave::render_latency(sr, dur, &mut net)))
+            if dur < 0. {
+                return Err(EvalError::invalid().suggest("the duration can't be negative"));
+            }
+            if seg1 == "render" {
+                Ok(Arc::new(Wave::render(sr, dur, &mut net)))
             } else {
-                None
+                Ok(Arc::new(Wave::render_latency(sr, dur, &mut net)))
             }
         }
         "load" => {
-            let path = eval_string(expr.args.first()?, lapis)?;
-            Some(Arc::new(Wave::load(path).ok()?))
+            let path = eval_string(arg(expr, 0)?, lapis)?;
+            match Wave::load(path) {
+                Ok(wave) => Ok(Arc::new(wave)),
+                Err(e) => Err(EvalError::invalid().suggest(format!("{e}"))),
+            }
         }
-        _ => None,
+        _ => Err(EvalError::unknown_function()),
     }
 }
 
-fn method_wave(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<Arc<Wave>> {
+/// the wave a method is called on (or the error if the receiver isn't one)
+fn wave_receiver(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<Arc<Wave>> {
+    eval_wave(&expr.receiver, lapis).map_err(|e| EvalError::unknown_method().or(e))
+}
+
+fn method_wave(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<Arc<Wave>> {
     match expr.method.to_string().as_str() {
-        "filter" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let dur = eval_float(arg0, lapis)?;
-            let mut node = eval_net(arg1, lapis)?;
-            let wave = eval_wave(&expr.receiver, lapis)?;
-            if node.inputs() == wave.channels() && node.outputs() > 0 && dur >= 0.0 {
-                Some(Arc::new(wave.filter(dur, &mut node)))
-            } else {
-                None
+        method @ ("filter" | "filter_latency") => {
+            let wave = wave_receiver(expr, lapis)?;
+            let dur = eval_float(arg(expr, 0)?, lapis)?;
+            let mut node = eval_net(arg(expr, 1)?, lapis)?;
+            if node.inputs() != wave.channels() || node.outputs() == 0 {
+                let help = format!("the net needs {} inputs and some outputs", wave.channels());
+                return Err(EvalError::invalid().suggest(help));
             }
-        }
-        "filter_latency" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let dur = eval_float(arg0, lapis)?;
-            let mut node = eval_net(arg1, lapis)?;
-            let wave = eval_wave(&expr.receiver, lapis)?;
-            if node.inputs() == wave.channels() && node.outputs() > 0 && dur >= 0.0 {
-                Some(Arc::new(wave.filter_latency(dur, &mut node)))
+            if dur < 0. {
+                return Err(EvalError::invalid().suggest("the duration can't be negative"));
+            }
+            if method == "filter" {
+                Ok(Arc::new(wave.filter(dur, &mut node)))
             } else {
-                None
+                Ok(Arc::new(wave.filter_latency(dur, &mut node)))
             }
         }
-        "clone" => eval_wave(&expr.receiver, lapis),
-        _ => None,
+        "clone" => wave_receiver(expr, lapis),
+        _ => Err(EvalError::unknown_method()),
     }
 }
 
@@ -129,200 +111,218 @@ fn arc_mut(arc: &mut Arc<Wave>, safe: bool) -> &mut Wave {
     }
 }
 
-pub fn wave_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> Option<()> {
+pub fn wave_methods(expr: &ExprMethodCall, lapis: &mut Lapis) -> EvalResult<()> {
     let mut s = expr.method.to_string();
     let mut safe = true;
     if let Some(stripped) = s.strip_prefix("unsafe_") {
         s = stripped.to_string();
         safe = false;
     }
+    if !matches!(
+        s.as_str(),
+        "set_sample_rate"
+            | "push_channel"
+            | "insert_channel"
+            | "mix_channel"
+            | "set"
+            | "mix"
+            | "push"
+            | "resize"
+            | "normalize"
+            | "fade_in"
+            | "fade_out"
+            | "fade"
+            | "save_wav16"
+            | "save_wav32"
+            | "remove_channel"
+            | "append"
+            | "retain"
+            | "amplify"
+    ) {
+        return Err(EvalError::unknown_method());
+    }
+    let k = nth_path_ident(&expr.receiver, 0).or_mismatch("a wave")?;
+    if !lapis.data.wmap.contains_key(&k) {
+        return Err(EvalError::unknown_method().or(path_error(&expr.receiver, lapis, "a wave")));
+    }
     match s.as_str() {
         "set_sample_rate" => {
-            let arg0 = expr.args.first()?;
-            let sr = eval_float(arg0, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
+            let sr = eval_float(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
             arc_mut(wave, safe).set_sample_rate(sr);
         }
         "push_channel" => {
-            let arg = expr.args.first()?;
-            let samps = eval_vec(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if wave.channels() == 0 || wave.len() == samps.len() {
-                arc_mut(wave, safe).push_channel(&samps);
-            }
+            let samps = eval_vec(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            check_len(wave, samps.len())?;
+            arc_mut(wave, safe).push_channel(&samps);
         }
         "insert_channel" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let chan = eval_usize(arg0, lapis)?;
-            let samps = eval_vec(arg1, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if chan <= wave.channels() && (wave.channels() == 0 || wave.len() == samps.len()) {
-                arc_mut(wave, safe).insert_channel(chan, &samps);
-            }
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            let samps = eval_vec(arg(expr, 1)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            check_channel(expr, wave, chan, wave.channels() + 1)?;
+            check_len(wave, samps.len())?;
+            arc_mut(wave, safe).insert_channel(chan, &samps);
         }
         "mix_channel" => {
-            let chan = eval_usize(expr.args.first()?, lapis)?;
-            let offset = eval_isize(expr.args.get(1)?, lapis)?;
-            let samps = eval_vec(expr.args.get(2)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if chan < wave.channels() {
-                arc_mut(wave, safe).mix_channel(chan, offset, &samps);
-            }
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            let offset = eval_isize(arg(expr, 1)?, lapis)?;
+            let samps = eval_vec(arg(expr, 2)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            check_channel(expr, wave, chan, wave.channels())?;
+            arc_mut(wave, safe).mix_channel(chan, offset, &samps);
         }
-        "set" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let chan = eval_usize(arg0, lapis)?;
-            let index = eval_usize(arg1, lapis)?;
-            let val = eval_float_f32(arg2, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if chan < wave.channels() && index < wave.len() {
-                arc_mut(wave, safe).set(chan, index, val);
+        "set" | "mix" => {
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            let index = eval_usize(arg(expr, 1)?, lapis)?;
+            let val = eval_float_f32(arg(expr, 2)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            check_channel(expr, wave, chan, wave.channels())?;
+            if index >= wave.len() {
+                let kind = ErrorKind::OutOfRange {
+                    what: "index",
+                    index,
+                    len: wave.len(),
+                };
+                return Err(EvalError::new(expr, kind));
             }
-        }
-        "mix" => {
-            let arg0 = expr.args.first()?;
-            let arg1 = expr.args.get(1)?;
-            let arg2 = expr.args.get(2)?;
-            let chan = eval_usize(arg0, lapis)?;
-            let index = eval_usize(arg1, lapis)?;
-            let val = eval_float_f32(arg2, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if chan < wave.channels() && index < wave.len() {
+            if s == "set" {
+                arc_mut(wave, safe).set(chan, index, val);
+            } else {
                 arc_mut(wave, safe).mix(chan, index, val);
             }
         }
         "push" => {
-            let arg = expr.args.first()?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
+            let arg = arg(expr, 0)?;
             if let Expr::Tuple(expr) = arg {
                 let p = accumulate_args(&expr.elems, lapis);
-                let wave = lapis.data.wmap.get_mut(&k)?;
-                if p.len() == 1 || p.len() == wave.channels() {
-                    match p.len() {
-                        1 => arc_mut(wave, safe).push(p[0]),
-                        2 => arc_mut(wave, safe).push((p[0], p[1])),
-                        3 => arc_mut(wave, safe).push((p[0], p[1], p[2])),
-                        4 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3])),
-                        5 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4])),
-                        6 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4], p[5])),
-                        7 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4], p[5], p[6])),
-                        8 => arc_mut(wave, safe)
-                            .push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7])),
-                        9 => arc_mut(wave, safe)
-                            .push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], p[8])),
-                        10 => arc_mut(wave, safe)
-                            .push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], p[8], p[9])),
-                        _ => {}
+                let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+                if p.len() != 1 && p.len() != wave.channels() {
+                    let help = format!("the wave has {} channels", wave.channels());
+                    return Err(EvalError::new(arg, ErrorKind::Invalid).suggest(help));
+                }
+                match p.len() {
+                    1 => arc_mut(wave, safe).push(p[0]),
+                    2 => arc_mut(wave, safe).push((p[0], p[1])),
+                    3 => arc_mut(wave, safe).push((p[0], p[1], p[2])),
+                    4 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3])),
+                    5 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4])),
+                    6 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4], p[5])),
+                    7 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4], p[5], p[6])),
+                    8 => arc_mut(wave, safe).push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7])),
+                    9 => arc_mut(wave, safe)
+                        .push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], p[8])),
+                    10 => arc_mut(wave, safe)
+                        .push((p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7], p[8], p[9])),
+                    _ => {
+                        let err = EvalError::new(arg, ErrorKind::Invalid);
+                        return Err(err.suggest("at most 10 channels can be pushed"));
                     }
                 }
-            } else if let Some(val) = eval_float_f32(arg, lapis) {
-                let wave = lapis.data.wmap.get_mut(&k)?;
+            } else {
+                let val = eval_float_f32(arg, lapis)?;
+                let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
                 arc_mut(wave, safe).push(val);
             }
         }
         "resize" => {
-            let arg0 = expr.args.first()?;
-            let len = eval_usize(arg0, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if wave.channels() > 0 {
-                arc_mut(wave, safe).resize(len);
+            let len = eval_usize(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            if wave.channels() == 0 {
+                return Err(EvalError::invalid().suggest("the wave has no channels"));
             }
+            arc_mut(wave, safe).resize(len);
         }
         "normalize" => {
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
             arc_mut(wave, safe).normalize();
         }
-        "fade_in" => {
-            let arg = expr.args.first()?;
-            let time = eval_float(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if time <= wave.duration() {
-                arc_mut(wave, safe).fade_in(time);
+        "fade_in" | "fade_out" | "fade" => {
+            let time = eval_float(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            if time > wave.duration() {
+                let help = format!("the wave is {} seconds long", wave.duration());
+                return Err(EvalError::invalid().suggest(help));
             }
-        }
-        "fade_out" => {
-            let arg = expr.args.first()?;
-            let time = eval_float(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if time <= wave.duration() {
-                arc_mut(wave, safe).fade_out(time);
+            match s.as_str() {
+                "fade_in" => arc_mut(wave, safe).fade_in(time),
+                "fade_out" => arc_mut(wave, safe).fade_out(time),
+                _ => arc_mut(wave, safe).fade(time),
             }
         }
-        "fade" => {
-            let arg = expr.args.first()?;
-            let time = eval_float(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if time <= wave.duration() {
-                arc_mut(wave, safe).fade(time);
+        "save_wav16" | "save_wav32" => {
+            let name = eval_string(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get(&k).or_invalid()?;
+            if wave.channels() == 0 {
+                return Err(EvalError::invalid().suggest("the wave has no channels"));
             }
-        }
-        "save_wav16" => {
-            let name = eval_string(expr.args.first()?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get(&k)?;
-            if wave.channels() > 0 {
-                let _ = wave.save_wav16(name);
-            }
-        }
-        "save_wav32" => {
-            let name = eval_string(expr.args.first()?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get(&k)?;
-            if wave.channels() > 0 {
-                let _ = wave.save_wav32(name);
+            let saved = if s == "save_wav16" {
+                wave.save_wav16(name)
+            } else {
+                wave.save_wav32(name)
+            };
+            if let Err(e) = saved {
+                return Err(EvalError::invalid().suggest(format!("{e}")));
             }
         }
         "remove_channel" => {
-            let arg = expr.args.first()?;
-            let chan = eval_usize(arg, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if chan < wave.channels() {
-                arc_mut(wave, safe).remove_channel(chan);
-            }
+            let chan = eval_usize(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            check_channel(expr, wave, chan, wave.channels())?;
+            arc_mut(wave, safe).remove_channel(chan);
         }
         "append" => {
-            let arg = expr.args.first()?;
-            let src = lapis.data.wmap.get(&nth_path_ident(arg, 0)?)?.clone();
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
-            if wave.channels() == src.channels() {
-                arc_mut(wave, safe).append(&src);
+            let src = eval_wave(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
+            if wave.channels() != src.channels() {
+                let help = format!("the wave has {} channels", wave.channels());
+                return Err(EvalError::invalid().suggest(help));
             }
+            arc_mut(wave, safe).append(&src);
         }
         "retain" => {
-            let start = eval_isize(expr.args.first()?, lapis)?;
-            let length = eval_usize(expr.args.get(1)?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
+            let start = eval_isize(arg(expr, 0)?, lapis)?;
+            let length = eval_usize(arg(expr, 1)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
             arc_mut(wave, safe).retain(start, length);
         }
-        "amplify" => {
-            let amp = eval_float_f32(expr.args.first()?, lapis)?;
-            let k = nth_path_ident(&expr.receiver, 0)?;
-            let wave = lapis.data.wmap.get_mut(&k)?;
+        _ => {
+            let amp = eval_float_f32(arg(expr, 0)?, lapis)?;
+            let wave = lapis.data.wmap.get_mut(&k).or_invalid()?;
             arc_mut(wave, safe).amplify(amp);
         }
-        _ => {}
     }
-    None
+    Ok(())
 }
 
-fn path_wave(expr: &Path, lapis: &Lapis) -> Option<Arc<Wave>> {
-    let k = expr.segments.first()?.ident.to_string();
-    lapis.data.wmap.get(&k).cloned()
+/// channels past `len` are out of range
+fn check_channel(expr: &ExprMethodCall, wave: &Wave, chan: usize, len: usize) -> EvalResult<()> {
+    if chan < len {
+        return Ok(());
+    }
+    let kind = ErrorKind::OutOfRange {
+        what: "channel",
+        index: chan,
+        len: wave.channels(),
+    };
+    Err(EvalError::new(expr, kind))
+}
+
+/// channels added to a wave that has some have to be as long as them
+fn check_len(wave: &Wave, len: usize) -> EvalResult<()> {
+    if wave.channels() == 0 || wave.len() == len {
+        Ok(())
+    } else {
+        let help = format!("the wave is {} samples long", wave.len());
+        Err(EvalError::invalid().suggest(help))
+    }
+}
+
+fn path_wave(expr: &Expr, lapis: &Lapis) -> EvalResult<Arc<Wave>> {
+    let k = nth_path_ident(expr, 0).or_mismatch("a wave")?;
+    match lapis.data.wmap.get(&k) {
+        Some(wave) => Ok(wave.clone()),
+        None => Err(path_error(expr, lapis, "a wave")),
+    }
 }
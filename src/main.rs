@@ -15,19 +15,32 @@ use std::time::Duration;
 
 mod audio;
 mod config;
+mod headless;
+mod history;
 mod interaction;
 mod joints;
 mod lapis;
+mod midi;
 mod objects;
+mod osc;
+mod scene;
+mod shapes;
 mod ui;
+mod voices;
 
-use config::ConfigPlugin;
-use {interaction::*, joints::*, lapis::*, objects::*, ui::*};
+use config::{Config, ConfigPlugin};
+use headless::HeadlessPlugin;
+use {interaction::*, joints::*, lapis::*, objects::*, scene::*, ui::*};
 
-fn main() {
+fn main() -> AppExit {
     let _ = GLOBAL_ERROR_HANDLER.set(error);
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
+    let config = Config::load();
+    let headless = config.headless;
+    let mut app = App::new();
+    if headless {
+        app.add_plugins(HeadlessPlugin);
+    } else {
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: String::from("bgawk!"),
                 ..default()
@@ -39,23 +52,35 @@ fn main() {
             unfocused_mode: UpdateMode::reactive_low_power(Duration::from_secs_f64(1. / 60.)),
         })
         .add_plugins(PanCamPlugin)
-        .add_plugins(audio::AudioPlugin)
         .add_plugins(InteractPlugin)
+        .add_plugins(UiPlugin)
+        .add_plugins(PhysicsDebugPlugin::default())
+        .add_systems(Startup, setup);
+    }
+    let exit = app
+        .add_plugins(audio::AudioPlugin { headless })
+        .add_plugins(voices::VoicesPlugin)
+        .add_plugins(midi::MidiPlugin)
+        .add_plugins(osc::OscPlugin)
         .add_plugins(ObjectsPlugin)
         .add_plugins(JointsPlugin)
-        .add_plugins(UiPlugin)
         .add_plugins(LapisPlugin)
+        .add_plugins(ScenePlugin)
+        .add_plugins(history::HistoryPlugin)
         .add_plugins(PhysicsPlugins::default().with_length_unit(100.))
-        .add_plugins(PhysicsDebugPlugin::default())
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(Gravity::ZERO)
         .insert_resource(SleepingThreshold {
             linear: -1.,
             angular: -1.,
         })
-        .add_systems(Startup, setup)
-        .add_plugins(ConfigPlugin)
+        .add_plugins(ConfigPlugin(config))
         .run();
+    if headless {
+        // the lapis output doesn't end with a newline
+        println!();
+    }
+    exit
 }
 
 fn setup(mut commands: Commands, mut config_store: ResMut<GizmoConfigStore>) {
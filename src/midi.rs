@@ -0,0 +1,172 @@
+use crate::lapis::Lapis;
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, bounded};
+use fundsp::hacker::Shared;
+use midir::{MidiInput, MidiInputConnection};
+
+pub struct MidiPlugin;
+
+impl Plugin for MidiPlugin {
+    fn build(&self, app: &mut App) {
+        let (_, r) = bounded(1);
+        app.insert_resource(MidiReceiver(r))
+            .insert_non_send_resource(MidiConnection(None))
+            .add_observer(set_midi_device)
+            .add_observer(drop_midi_device)
+            .add_systems(Update, midi_bindings);
+    }
+}
+
+/// raw messages from the current midi input (status, data1, data2)
+#[derive(Resource, Deref)]
+pub struct MidiReceiver(pub Receiver<[u8; 3]>);
+
+struct MidiConnection(Option<MidiInputConnection<()>>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MidiKind {
+    Note,
+    Cc,
+}
+
+/// what a midi binding listens to (the value is 0..1)
+/// `channel: None` matches any channel, `release` is note off
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MidiBinding {
+    pub kind: MidiKind,
+    pub number: u8,
+    pub channel: Option<u8>,
+    pub release: bool,
+}
+
+/// what happens when a bound midi/osc message arrives
+/// the value is set on the shared, or replaces `@` in the code
+#[derive(Clone)]
+pub enum RemoteAction {
+    Shared(Shared),
+    Code(String),
+}
+
+/// trigger this event to connect to a midi input port (ending the current connection)
+/// None connects to the first port
+/// use `list_midi_devices` to get a list of port indexes
+#[derive(Event, Debug)]
+pub struct SetMidiDevice(pub Option<usize>);
+
+#[derive(Event)]
+pub struct DropMidiDevice;
+
+/// parse a binding like "midi cc 1 ch 0" or "!midi note 60"
+/// (without "ch" any channel matches, `!` means note off)
+pub fn parse_midi(k: &str) -> Option<MidiBinding> {
+    let release = k.starts_with('!');
+    let mut words = k.trim_start_matches('!').split_whitespace();
+    if words.next()? != "midi" {
+        return None;
+    }
+    let kind = match words.next()? {
+        "note" => MidiKind::Note,
+        "cc" => MidiKind::Cc,
+        _ => return None,
+    };
+    if release && kind == MidiKind::Cc {
+        return None;
+    }
+    let number = words.next()?.parse::<u8>().ok().filter(|n| *n < 128)?;
+    let channel = match words.next() {
+        Some("ch") => Some(words.next()?.parse::<u8>().ok().filter(|c| *c < 16)?),
+        Some(_) => return None,
+        None => None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    Some(MidiBinding {
+        kind,
+        number,
+        channel,
+        release,
+    })
+}
+
+/// get a list of midi input ports
+pub fn list_midi_devices() -> String {
+    let mut s = String::from("midi input devices:\n");
+    if let Ok(midi_in) = MidiInput::new("bgawk") {
+        for (i, port) in midi_in.ports().iter().enumerate() {
+            let name = midi_in.port_name(port).unwrap_or_default();
+            s.push_str(&format!("    {i}: {name}\n"));
+        }
+    }
+    s
+}
+
+fn set_midi_device(
+    trig: Trigger<SetMidiDevice>,
+    mut connection: NonSendMut<MidiConnection>,
+    mut receiver: ResMut<MidiReceiver>,
+) -> Result {
+    let midi_in = MidiInput::new("bgawk")?;
+    let ports = midi_in.ports();
+    let port = ports
+        .get(trig.event().0.unwrap_or(0))
+        .ok_or("couldn't find that midi device")?;
+    let (s, r) = bounded(1024);
+    let conn = midi_in
+        .connect(
+            port,
+            "bgawk-in",
+            move |_, message, _| {
+                let mut msg = [0; 3];
+                for (m, x) in msg.iter_mut().zip(message) {
+                    *m = *x;
+                }
+                let _ = s.try_send(msg);
+            },
+            (),
+        )
+        .map_err(|e| e.to_string())?;
+    connection.0 = Some(conn);
+    receiver.0 = r;
+    Ok(())
+}
+
+fn drop_midi_device(_: Trigger<DropMidiDevice>, mut connection: NonSendMut<MidiConnection>) {
+    connection.0 = None;
+}
+
+fn midi_bindings(mut lapis: Lapis, receiver: Res<MidiReceiver>) {
+    for [status, number, value] in receiver.try_iter() {
+        let channel = status & 0x0f;
+        let (kind, release) = match status & 0xf0 {
+            // note on with 0 velocity is a note off
+            0x90 => (MidiKind::Note, value == 0),
+            0x80 => (MidiKind::Note, true),
+            0xb0 => (MidiKind::Cc, false),
+            _ => continue,
+        };
+        let value = value as f32 / 127.;
+        for channel in [Some(channel), None] {
+            let binding = MidiBinding {
+                kind,
+                number,
+                channel,
+                release,
+            };
+            match lapis.data.midi.get(&binding).cloned() {
+                Some(RemoteAction::Shared(shared)) => shared.set(value),
+                Some(RemoteAction::Code(code)) => {
+                    let code = code.replace('@', &value.to_string());
+                    lapis.as_hook(|lapis| {
+                        if lapis.data.quiet {
+                            lapis.quiet_eval(&code);
+                        } else {
+                            lapis.eval(&code);
+                        }
+                    });
+                }
+                None => {}
+            }
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{
+    history::History,
     interaction::*,
-    lapis::{Lapis, floats::eval_float_f32},
+    joints::{JointFilter, JointProperty, joint_force},
+    lapis::{Lapis, LapisData, floats::eval_float_f32, parse_tokens},
+    shapes::Shape,
 };
 use avian2d::prelude::*;
-use bevy::{prelude::*, sprite::AlphaMode2d};
-use std::collections::VecDeque;
-use syn::{Expr, parse_str};
+use bevy::{gizmos::GizmoPlugin, prelude::*, sprite::AlphaMode2d};
+use fundsp::hacker::Shared;
+use proc_macro2::{Group, Ident, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet, VecDeque};
+use syn::{Expr, Stmt, parse_str};
 
 pub struct ObjectsPlugin;
 
@@ -14,35 +19,105 @@ impl Plugin for ObjectsPlugin {
         app.add_systems(
             Update,
             spawn
+                .after(update_cursor_info)
                 .run_if(resource_equals(EguiFocused(false)))
                 .run_if(resource_equals(Mode::Draw)),
         )
         .add_systems(PhysicsSchedule, attract.in_set(PhysicsStepSet::Last))
-        .add_systems(Update, eval_collisions)
-        .add_systems(PostUpdate, sync_links)
-        .add_systems(Update, update_tail)
+        .init_resource::<StepVelocities>()
+        .add_systems(
+            PhysicsSchedule,
+            record_velocities.in_set(PhysicsStepSet::First),
+        )
+        .init_non_send_resource::<CodeCache>()
+        .init_non_send_resource::<ScriptCache>()
+        .add_systems(
+            Update,
+            (compile_code, eval_collisions, compile_scripts, run_scripts).chain(),
+        )
+        .add_systems(
+            FixedPostUpdate,
+            (eval_contacts, run_step_scripts)
+                .chain()
+                .after(PhysicsSet::StepSimulation),
+        )
+        .init_non_send_resource::<LinkExprs>()
+        .add_systems(PostUpdate, (compile_links, sync_links).chain())
         .insert_resource(AttractionFactor(0.01))
+        .insert_resource(AttractionTheta(0.))
+        .init_resource::<Stroke>()
         .add_observer(set_property)
         .add_observer(insert_defaults);
+        // tails are only drawn (headless mode has no gizmos)
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, update_tail);
+        }
     }
 }
 
+/// (collision started code, collision ended code, while colliding code)
+/// the parsed versions are kept in `CodeCache`
 #[derive(Component, Reflect, Default)]
 #[reflect(Component)]
-pub struct Code(pub String, pub String);
+pub struct Code(pub String, pub String, pub String);
+
+/// parsed `Code` of every entity, only rebuilt when the text changes
+/// (syn trees aren't Send, so this can't live in the component itself)
+#[derive(Default)]
+pub struct CodeCache(HashMap<Entity, (CompiledCode, CompiledCode, CompiledCode)>);
+
+pub struct CompiledCode {
+    source: String,
+    /// None if parsing failed or the code needs textual substitution
+    stmt: Option<Stmt>,
+}
+
+impl CompiledCode {
+    /// `placeholders` are the ones this code gets (any others are left as they are)
+    fn new(source: &str, placeholders: &[&str]) -> Self {
+        let stmt = source
+            .parse::<TokenStream>()
+            .ok()
+            .and_then(|stream| parameterize(stream, placeholders))
+            .and_then(|stream| parse_tokens(stream).ok());
+        CompiledCode {
+            source: source.to_string(),
+            stmt,
+        }
+    }
+}
 
 #[derive(Component, Reflect, Default)]
 #[reflect(Component)]
 pub struct Links(pub String);
 
-#[derive(Component, Reflect)]
+/// code an object evaluates every frame, or every `steps` physics steps (if not 0)
+/// `$id` is the object, `$age` is the seconds since it got its script
+#[derive(Component, Reflect, Default, Clone)]
 #[reflect(Component)]
-pub struct Sides(pub u32);
+pub struct Script {
+    pub code: String,
+    pub steps: u32,
+}
+
+/// parsed `Script`s and their ages
+#[derive(Default)]
+pub struct ScriptCache(HashMap<Entity, (CompiledCode, f32)>);
+
+/// the outline being drawn with the freehand tool (world coordinates)
+#[derive(Resource, Default)]
+pub struct Stroke(pub Vec<Vec2>);
 
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 pub struct AttractionFactor(pub f32);
 
+/// accuracy of the barnes-hut approximation of attraction
+/// 0 computes every pair exactly, higher values are faster but less accurate
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct AttractionTheta(pub f32);
+
 #[derive(Component, Default)]
 pub struct Tail {
     pub len: usize,
@@ -82,31 +157,107 @@ fn spawn(
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     settings: Res<DrawSettings>,
     egui_focused: Res<EguiFocused>,
+    mut stroke: ResMut<Stroke>,
+    mut history: ResMut<History>,
 ) {
-    if !keyboard_input.pressed(KeyCode::Space)
+    if settings.shape == DrawShape::Freehand {
+        if mouse_button_input.just_pressed(MouseButton::Left) {
+            stroke.0.clear();
+        }
+        if mouse_button_input.pressed(MouseButton::Left)
+            && !keyboard_input.pressed(KeyCode::Space)
+            && stroke.0.last().is_none_or(|p| p.distance(cursor.f) > 4.)
+        {
+            stroke.0.push(cursor.f);
+        }
+    }
+    let released = !keyboard_input.pressed(KeyCode::Space)
         && mouse_button_input.just_released(MouseButton::Left)
         // avoid spawning when dragging outside of egui
-        && !egui_focused.is_changed()
-    {
-        let r = cursor.f.distance(cursor.i).max(1.);
+        && !egui_focused.is_changed();
+    // walls are built a click at a time, enter finishes them
+    let finished = match settings.shape {
+        DrawShape::Wall => {
+            if released {
+                stroke.0.push(cursor.f);
+            }
+            if keyboard_input.just_pressed(KeyCode::Backspace) {
+                stroke.0.pop();
+            } else if keyboard_input.just_pressed(KeyCode::Escape) {
+                stroke.0.clear();
+            }
+            keyboard_input.just_pressed(KeyCode::Enter)
+        }
+        _ => released,
+    };
+    if finished {
+        // (shape, position, scale)
+        let (shape, translation, scale) = match settings.shape {
+            DrawShape::Regular => {
+                let r = cursor.f.distance(cursor.i).max(1.);
+                (Shape::Regular(settings.sides), cursor.i, Vec2::splat(r))
+            }
+            DrawShape::Rectangle => {
+                let half = ((cursor.f - cursor.i) / 2.).abs().max(Vec2::ONE);
+                (Shape::Rectangle, (cursor.i + cursor.f) / 2., half)
+            }
+            DrawShape::Capsule => {
+                // fit the dragged box, the width is the diameter
+                let size = (cursor.f - cursor.i).abs();
+                let r = (size.x / 2.).max(1.);
+                let len = (size.y - size.x).max(0.) / r;
+                (
+                    Shape::Capsule(len),
+                    (cursor.i + cursor.f) / 2.,
+                    Vec2::splat(r),
+                )
+            }
+            DrawShape::Freehand => {
+                let points = std::mem::take(&mut stroke.0);
+                let Some((shape, center)) = Shape::polygon(&points) else {
+                    return;
+                };
+                (shape, center, Vec2::ONE)
+            }
+            DrawShape::Wall => {
+                let points = std::mem::take(&mut stroke.0);
+                let Some((shape, center)) = Shape::wall(&points) else {
+                    return;
+                };
+                (shape, center, Vec2::ONE)
+            }
+        };
+        // walls are always static
+        let rigid_body = match shape {
+            Shape::Wall(_) => RigidBody::Static,
+            _ => settings.rigid_body,
+        };
+        let r = match &shape {
+            Shape::Polygon(v) => v.iter().map(|p| p.length()).fold(1., f32::max),
+            _ => scale.max_element(),
+        };
         let material = ColorMaterial {
             color: Srgba::from_u8_array(settings.color).into(),
             alpha_mode: AlphaMode2d::Blend,
             ..default()
         };
-        let mesh_handle = meshes.add(RegularPolygon::new(1., settings.sides));
+        let mesh_handle = meshes.add(shape.mesh());
         let mat_handle = materials.add(material);
         let layer = 1 << settings.collision_layer;
         let mut e = commands.spawn((
             Mesh2d(mesh_handle),
             MeshMaterial2d(mat_handle),
-            settings.rigid_body,
+            rigid_body,
             Links(settings.links.clone()),
-            Code(settings.code.0.clone(), settings.code.1.clone()),
+            Code(
+                settings.code.0.clone(),
+                settings.code.1.clone(),
+                settings.code.2.clone(),
+            ),
             Mass(r * r * r),
             AngularInertia(r * r * r),
             CenterOfMass(settings.center_of_mass),
-            Collider::regular_polygon(1., settings.sides),
+            shape.collider(),
             CollisionLayers::from_bits(layer, layer),
             (
                 CollisionEventsEnabled,
@@ -116,11 +267,11 @@ fn spawn(
                 Friction::new(settings.friction),
             ),
             Transform {
-                translation: cursor.i.extend(0.),
-                scale: Vec3::new(r, r, 1.),
+                translation: translation.extend(0.),
+                scale: scale.extend(1.),
                 ..default()
             },
-            Sides(settings.sides),
+            shape,
             Tail {
                 len: settings.tail,
                 ..default()
@@ -136,6 +287,7 @@ fn spawn(
         if settings.custom_inertia {
             e.insert(AngularInertia(settings.inertia));
         }
+        history.spawned(e.id());
     }
 }
 
@@ -143,10 +295,15 @@ fn attract(
     layers: Query<(Entity, &CollisionLayers)>,
     mut query: Query<(&Mass, &Position, &mut LinearVelocity)>,
     factor: Res<AttractionFactor>,
+    theta: Res<AttractionTheta>,
 ) {
     if !factor.0.is_normal() {
         return;
     }
+    if theta.0 > 0. {
+        attract_barnes_hut(&layers, &mut query, factor.0, theta.0);
+        return;
+    }
     let mut combinations = layers.iter_combinations();
     while let Some([(e1, l1), (e2, l2)]) = combinations.fetch_next() {
         if l1 == l2 {
@@ -164,339 +321,911 @@ fn attract(
     }
 }
 
-fn replace(code: &str, e1: Entity, e2: Entity) -> String {
-    code.replace("$id", &format!("{}", e1.to_bits()))
-        .replace("$other", &format!("{}", e2.to_bits()))
+fn attract_barnes_hut(
+    layers: &Query<(Entity, &CollisionLayers)>,
+    query: &mut Query<(&Mass, &Position, &mut LinearVelocity)>,
+    factor: f32,
+    theta: f32,
+) {
+    // only objects with identical layers attract each other
+    let mut groups: HashMap<(u32, u32), Vec<(Entity, Vec2, f32)>> = HashMap::new();
+    for (e, l) in layers.iter() {
+        if let Ok((m, p, _)) = query.get(e) {
+            let key = (l.memberships.0, l.filters.0);
+            groups.entry(key).or_default().push((e, p.0, m.0));
+        }
+    }
+    for group in groups.values() {
+        if group.len() < 2 {
+            continue;
+        }
+        let points: Vec<(Vec2, f32)> = group.iter().map(|(_, p, m)| (*p, *m)).collect();
+        let tree = QuadTree::new(&points);
+        for (i, (e, p, _)) in group.iter().enumerate() {
+            let mut acc = Vec2::ZERO;
+            tree.accumulate(0, i, *p, theta * theta, &points, &mut acc);
+            if let Ok((_, _, mut v)) = query.get_mut(*e) {
+                v.0 += acc * factor;
+            }
+        }
+    }
 }
 
-fn eval_collisions(
-    code: Query<&Code>,
-    mut lapis: Lapis,
-    mut started: EventReader<CollisionStarted>,
-    mut ended: EventReader<CollisionEnded>,
+/// past this depth bodies share a leaf (coincident points)
+const QUADTREE_MAX_DEPTH: usize = 24;
+
+struct QuadNode {
+    center: Vec2,
+    half: f32,
+    mass: f32,
+    /// center of mass
+    com: Vec2,
+    /// index of the first of 4 consecutive children
+    children: Option<usize>,
+    /// indexes of the bodies in this node (leaves only)
+    bodies: Vec<usize>,
+}
+
+impl QuadNode {
+    fn new(center: Vec2, half: f32) -> Self {
+        QuadNode {
+            center,
+            half,
+            mass: 0.,
+            com: Vec2::ZERO,
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    fn quadrant(&self, p: Vec2) -> usize {
+        (p.x >= self.center.x) as usize + 2 * (p.y >= self.center.y) as usize
+    }
+}
+
+/// barnes-hut quadtree of point masses
+struct QuadTree(Vec<QuadNode>);
+
+impl QuadTree {
+    fn new(points: &[(Vec2, f32)]) -> Self {
+        let mut min = Vec2::splat(f32::INFINITY);
+        let mut max = Vec2::splat(f32::NEG_INFINITY);
+        for (p, _) in points {
+            min = min.min(*p);
+            max = max.max(*p);
+        }
+        let half = (max - min).max_element() / 2. + 1.;
+        let mut tree = QuadTree(vec![QuadNode::new((min + max) / 2., half)]);
+        for i in 0..points.len() {
+            tree.insert(0, i, points, 0);
+        }
+        for node in &mut tree.0 {
+            if node.mass != 0. {
+                node.com /= node.mass;
+            }
+        }
+        tree
+    }
+
+    fn insert(&mut self, n: usize, i: usize, points: &[(Vec2, f32)], depth: usize) {
+        let (p, m) = points[i];
+        self.0[n].mass += m;
+        self.0[n].com += p * m;
+        if let Some(c) = self.0[n].children {
+            let q = self.0[n].quadrant(p);
+            self.insert(c + q, i, points, depth + 1);
+        } else if self.0[n].bodies.is_empty() || depth >= QUADTREE_MAX_DEPTH {
+            self.0[n].bodies.push(i);
+        } else {
+            // split this leaf and push its bodies down
+            let c = self.0.len();
+            let (center, half) = (self.0[n].center, self.0[n].half / 2.);
+            for q in 0..4 {
+                let x = if q & 1 == 1 { half } else { -half };
+                let y = if q & 2 == 2 { half } else { -half };
+                self.0.push(QuadNode::new(center + Vec2::new(x, y), half));
+            }
+            self.0[n].children = Some(c);
+            for j in std::mem::take(&mut self.0[n].bodies) {
+                let q = self.0[n].quadrant(points[j].0);
+                self.insert(c + q, j, points, depth + 1);
+            }
+            let q = self.0[n].quadrant(p);
+            self.insert(c + q, i, points, depth + 1);
+        }
+    }
+
+    /// add the attraction of everything in node `n` on body `i` (at `p`) to `acc`
+    fn accumulate(
+        &self,
+        n: usize,
+        i: usize,
+        p: Vec2,
+        theta_squared: f32,
+        points: &[(Vec2, f32)],
+        acc: &mut Vec2,
+    ) {
+        let node = &self.0[n];
+        if node.mass == 0. {
+            return;
+        }
+        let Some(c) = node.children else {
+            for &j in &node.bodies {
+                let (pj, mj) = points[j];
+                let r = p.distance_squared(pj);
+                if j != i && r > 1. {
+                    *acc += (pj - p) * mj / r;
+                }
+            }
+            return;
+        };
+        let r = p.distance_squared(node.com);
+        let size = node.half * 2.;
+        let contains = (p - node.center).abs().max_element() <= node.half;
+        // far enough to be treated as a single mass
+        if !contains && size * size < theta_squared * r {
+            if r > 1. {
+                *acc += (node.com - p) * node.mass / r;
+            }
+        } else {
+            for q in 0..4 {
+                self.accumulate(c + q, i, p, theta_squared, points, acc);
+            }
+        }
+    }
+}
+
+/// the placeholders of collision code (`$id`, `$other`, then the contact floats)
+const CONTACT_PLACEHOLDERS: &[&str] = &[
+    "id", "other", "impulse", "speed", "x", "y", "nx", "ny", "duration", "slide",
+];
+
+/// the placeholders of scripts
+const SCRIPT_PLACEHOLDERS: &[&str] = &["id", "age"];
+
+/// what collision code knows about the contact (all 0 when there's none,
+/// like when a collision ends)
+#[derive(Clone, Copy, Default)]
+struct Contact {
+    /// total normal impulse
+    impulse: f32,
+    /// relative velocity along the normal (before the hit was resolved)
+    speed: f32,
+    /// contact point
+    x: f32,
+    y: f32,
+    /// normal, pointing from this object to the other
+    nx: f32,
+    ny: f32,
+    /// seconds since the objects started touching (only in while colliding code)
+    duration: f32,
+    /// relative velocity along the surface
+    slide: f32,
+}
+
+/// the velocities of bodies at the start of the last physics step
+/// (after the solver, colliding objects aren't approaching anymore)
+#[derive(Resource, Default)]
+struct StepVelocities(HashMap<Entity, (Vec2, f32)>);
+
+fn record_velocities(
+    mut velocities: ResMut<StepVelocities>,
+    query: Query<(Entity, &LinearVelocity, &AngularVelocity)>,
 ) {
-    if lapis.data.quiet {
-        for CollisionStarted(e1, e2) in started.read() {
-            if let Ok(c) = code.get(*e1) {
-                lapis.quiet_eval(&replace(&c.0, *e1, *e2));
+    velocities.0.clear();
+    velocities
+        .0
+        .extend(query.iter().map(|(e, v, w)| (e, (v.0, w.0))));
+}
+
+impl Contact {
+    /// the contact between two colliders, as seen from e1
+    fn new(
+        e1: Entity,
+        e2: Entity,
+        collisions: &Collisions,
+        velocities: &StepVelocities,
+        lapis: &Lapis,
+    ) -> Self {
+        let Some(pair) = collisions.get(e1, e2) else {
+            return Contact::default();
+        };
+        let mut impulse = 0.;
+        let mut point = Vec2::ZERO;
+        let mut normal = Vec2::ZERO;
+        let mut n = 0;
+        for manifold in &pair.manifolds {
+            for p in &manifold.points {
+                impulse += p.normal_impulse;
+                point += p.point;
+                normal += manifold.normal;
+                n += 1;
+            }
+        }
+        if n == 0 {
+            return Contact::default();
+        }
+        point /= n as f32;
+        // manifold normals point from collider1 to collider2
+        if pair.collider1 != e1 {
+            normal = -normal;
+        }
+        let normal = normal.normalize_or_zero();
+        let velocity = |e: Entity| {
+            let (v, w) = velocities.0.get(&e).copied().unwrap_or_else(|| {
+                let v = lapis.lin_velocity_query.get(e).map_or(Vec2::ZERO, |v| v.0);
+                let w = lapis.ang_velocity_query.get(e).map_or(0., |w| w.0);
+                (v, w)
+            });
+            let center = lapis
+                .trans_query
+                .get(e)
+                .map_or(point, |t| t.translation.xy());
+            v + (point - center).perp() * w
+        };
+        let relative = velocity(e2) - velocity(e1);
+        let along = relative.dot(normal);
+        Contact {
+            impulse,
+            speed: along.abs(),
+            x: point.x,
+            y: point.y,
+            nx: normal.x,
+            ny: normal.y,
+            duration: 0.,
+            slide: (relative - normal * along).length(),
+        }
+    }
+
+    /// the same contact as seen from the other object
+    fn flipped(self) -> Self {
+        Contact {
+            nx: -self.nx,
+            ny: -self.ny,
+            ..self
+        }
+    }
+
+    fn floats(&self) -> [(&'static str, f32); 8] {
+        [
+            ("impulse", self.impulse),
+            ("speed", self.speed),
+            ("x", self.x),
+            ("y", self.y),
+            ("nx", self.nx),
+            ("ny", self.ny),
+            ("duration", self.duration),
+            ("slide", self.slide),
+        ]
+    }
+}
+
+fn replace(code: &str, e1: Entity, e2: Entity, contact: &Contact) -> String {
+    let mut code = code
+        .replace("$id", &format!("{}", e1.to_bits()))
+        .replace("$other", &format!("{}", e2.to_bits()));
+    for (k, v) in contact.floats() {
+        code = code.replace(&format!("${k}"), &format!("({v})"));
+    }
+    code
+}
+
+/// turn the placeholders into `__id`, `__other`, `__impulse`... variables
+/// returns None if they appear inside of a string literal, since those
+/// can only be handled with textual substitution (`replace`)
+fn parameterize(stream: TokenStream, placeholders: &[&str]) -> Option<TokenStream> {
+    let mut out = Vec::new();
+    let mut iter = stream.into_iter().peekable();
+    while let Some(tt) = iter.next() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '$' => {
+                if let Some(TokenTree::Ident(i)) = iter.peek()
+                    && placeholders.contains(&i.to_string().as_str())
+                {
+                    let ident = Ident::new(&format!("__{i}"), i.span());
+                    iter.next();
+                    out.push(TokenTree::Ident(ident));
+                } else {
+                    out.push(TokenTree::Punct(p));
+                }
             }
-            if let Ok(c) = code.get(*e2) {
-                lapis.quiet_eval(&replace(&c.0, *e2, *e1));
+            TokenTree::Group(g) => {
+                let mut group = Group::new(g.delimiter(), parameterize(g.stream(), placeholders)?);
+                group.set_span(g.span());
+                out.push(TokenTree::Group(group));
             }
+            TokenTree::Literal(l) => {
+                let s = l.to_string();
+                if placeholders.iter().any(|k| s.contains(&format!("${k}"))) {
+                    return None;
+                }
+                out.push(TokenTree::Literal(l));
+            }
+            tt => out.push(tt),
         }
-        for CollisionEnded(e1, e2) in ended.read() {
-            if let Ok(c) = code.get(*e1) {
-                lapis.quiet_eval(&replace(&c.1, *e1, *e2));
+    }
+    Some(out.into_iter().collect())
+}
+
+fn compile_code(
+    code_query: Query<(Entity, &Code), Changed<Code>>,
+    mut removed: RemovedComponents<Code>,
+    mut cache: NonSendMut<CodeCache>,
+) {
+    for e in removed.read() {
+        cache.0.remove(&e);
+    }
+    for (e, code) in code_query.iter() {
+        if let Some((i, f, c)) = cache.0.get_mut(&e) {
+            if i.source != code.0 {
+                *i = CompiledCode::new(&code.0, CONTACT_PLACEHOLDERS);
             }
-            if let Ok(c) = code.get(*e2) {
-                lapis.quiet_eval(&replace(&c.1, *e2, *e1));
+            if f.source != code.1 {
+                *f = CompiledCode::new(&code.1, CONTACT_PLACEHOLDERS);
             }
+            if c.source != code.2 {
+                *c = CompiledCode::new(&code.2, CONTACT_PLACEHOLDERS);
+            }
+        } else {
+            let compiled = (
+                CompiledCode::new(&code.0, CONTACT_PLACEHOLDERS),
+                CompiledCode::new(&code.1, CONTACT_PLACEHOLDERS),
+                CompiledCode::new(&code.2, CONTACT_PLACEHOLDERS),
+            );
+            cache.0.insert(e, compiled);
         }
-    } else {
-        for CollisionStarted(e1, e2) in started.read() {
-            if let Ok(c) = code.get(*e1) {
-                lapis.eval(&replace(&c.0, *e1, *e2));
+    }
+}
+
+fn compile_scripts(
+    script_query: Query<(Entity, &Script), Changed<Script>>,
+    mut removed: RemovedComponents<Script>,
+    mut cache: NonSendMut<ScriptCache>,
+) {
+    for e in removed.read() {
+        cache.0.remove(&e);
+    }
+    for (e, script) in script_query.iter() {
+        match cache.0.get_mut(&e) {
+            Some((c, _)) if c.source == script.code => {}
+            Some((c, _)) => *c = CompiledCode::new(&script.code, SCRIPT_PLACEHOLDERS),
+            None => {
+                cache.0.insert(
+                    e,
+                    (CompiledCode::new(&script.code, SCRIPT_PLACEHOLDERS), 0.),
+                );
             }
-            if let Ok(c) = code.get(*e2) {
-                lapis.eval(&replace(&c.0, *e2, *e1));
+        }
+    }
+}
+
+/// scripts are always evaluated quietly (like the update code)
+fn eval_script(code: &CompiledCode, e: Entity, age: f32, lapis: &mut Lapis) {
+    if code.source.trim().is_empty() {
+        return;
+    }
+    let Some(stmt) = &code.stmt else {
+        let input = code
+            .source
+            .replace("$id", &format!("{}", e.to_bits()))
+            .replace("$age", &format!("({age})"));
+        lapis.as_hook(|lapis| lapis.quiet_eval(&input));
+        return;
+    };
+    let id = lapis.data.entitymap.insert(String::from("__id"), e);
+    let old_age = lapis.data.fmap.insert(String::from("__age"), age as f64);
+    lapis.as_hook(|lapis| lapis.quiet_eval_parsed(stmt));
+    if let Some(id) = id {
+        lapis.data.entitymap.insert(String::from("__id"), id);
+    } else {
+        lapis.data.entitymap.remove("__id");
+    }
+    if let Some(age) = old_age {
+        lapis.data.fmap.insert(String::from("__age"), age);
+    } else {
+        lapis.data.fmap.remove("__age");
+    }
+}
+
+/// age all scripts and evaluate the ones that run every frame
+fn run_scripts(mut cache: NonSendMut<ScriptCache>, mut lapis: Lapis) {
+    // like the update code, nothing runs while time is paused
+    if lapis.time.is_paused() {
+        return;
+    }
+    let dt = lapis.time.delta_secs();
+    for (e, (code, age)) in cache.0.iter_mut() {
+        *age += dt;
+        if lapis.script_query.get(*e).is_ok_and(|s| s.steps == 0) {
+            eval_script(code, *e, *age, &mut lapis);
+        }
+    }
+}
+
+fn run_step_scripts(cache: NonSend<ScriptCache>, mut lapis: Lapis, mut step: Local<u32>) {
+    *step = step.wrapping_add(1);
+    for (e, (code, age)) in cache.0.iter() {
+        let steps = lapis.script_query.get(*e).map_or(0, |s| s.steps);
+        if steps > 0 && *step % steps == 0 {
+            eval_script(code, *e, *age, &mut lapis);
+        }
+    }
+}
+
+fn eval_code(code: &CompiledCode, e1: Entity, e2: Entity, contact: &Contact, lapis: &mut Lapis) {
+    let Some(stmt) = &code.stmt else {
+        // this also reports parse errors (when not quiet)
+        let input = replace(&code.source, e1, e2, contact);
+        lapis.as_hook(|lapis| {
+            if lapis.data.quiet {
+                lapis.quiet_eval(&input);
+            } else {
+                lapis.eval(&input);
             }
+        });
+        return;
+    };
+    let id = lapis.data.entitymap.insert(String::from("__id"), e1);
+    let other = lapis.data.entitymap.insert(String::from("__other"), e2);
+    let floats: Vec<(String, Option<f64>)> = contact
+        .floats()
+        .into_iter()
+        .map(|(k, v)| {
+            let k = format!("__{k}");
+            let old = lapis.data.fmap.insert(k.clone(), v as f64);
+            (k, old)
+        })
+        .collect();
+    lapis.as_hook(|lapis| {
+        if lapis.data.quiet {
+            lapis.quiet_eval_parsed(stmt);
+        } else {
+            lapis.eval_parsed(&replace(&code.source, e1, e2, contact), stmt);
+        }
+    });
+    for (k, v) in [("__id", id), ("__other", other)] {
+        if let Some(v) = v {
+            lapis.data.entitymap.insert(String::from(k), v);
+        } else {
+            lapis.data.entitymap.remove(k);
+        }
+    }
+    for (k, v) in floats {
+        if let Some(v) = v {
+            lapis.data.fmap.insert(k, v);
+        } else {
+            lapis.data.fmap.remove(&k);
+        }
+    }
+}
+
+fn eval_collisions(
+    cache: NonSend<CodeCache>,
+    mut lapis: Lapis,
+    collisions: Collisions,
+    velocities: Res<StepVelocities>,
+    mut started: EventReader<CollisionStarted>,
+    mut ended: EventReader<CollisionEnded>,
+) {
+    for CollisionStarted(e1, e2) in started.read() {
+        let (c1, c2) = (cache.0.get(e1), cache.0.get(e2));
+        if c1.is_none() && c2.is_none() {
+            continue;
+        }
+        let contact = Contact::new(*e1, *e2, &collisions, &velocities, &lapis);
+        if let Some((c, _, _)) = c1 {
+            eval_code(c, *e1, *e2, &contact, &mut lapis);
+        }
+        if let Some((c, _, _)) = c2 {
+            eval_code(c, *e2, *e1, &contact.flipped(), &mut lapis);
+        }
+    }
+    for CollisionEnded(e1, e2) in ended.read() {
+        let contact = Contact::default();
+        if let Some((_, c, _)) = cache.0.get(e1) {
+            eval_code(c, *e1, *e2, &contact, &mut lapis);
+        }
+        if let Some((_, c, _)) = cache.0.get(e2) {
+            eval_code(c, *e2, *e1, &contact, &mut lapis);
+        }
+    }
+}
+
+/// evaluate the while colliding code of touching objects (every physics step)
+fn eval_contacts(
+    cache: NonSend<CodeCache>,
+    mut lapis: Lapis,
+    collisions: Collisions,
+    velocities: Res<StepVelocities>,
+    time: Res<Time>,
+    mut started: Local<HashMap<(Entity, Entity), f64>>,
+) {
+    let now = time.elapsed_secs_f64();
+    let code = |e: Entity| {
+        cache
+            .0
+            .get(&e)
+            .map(|(_, _, c)| c)
+            .filter(|c| !c.source.trim().is_empty())
+    };
+    let touching: HashSet<(Entity, Entity)> = collisions
+        .iter()
+        .filter(|pair| pair.is_touching())
+        .map(|pair| (pair.collider1, pair.collider2))
+        .collect();
+    started.retain(|pair, _| touching.contains(pair));
+    for (e1, e2) in touching {
+        let start = *started.entry((e1, e2)).or_insert(now);
+        let (c1, c2) = (code(e1), code(e2));
+        if c1.is_none() && c2.is_none() {
+            continue;
+        }
+        let mut contact = Contact::new(e1, e2, &collisions, &velocities, &lapis);
+        contact.duration = (now - start) as f32;
+        if let Some(c) = c1 {
+            eval_code(c, e1, e2, &contact, &mut lapis);
+        }
+        if let Some(c) = c2 {
+            eval_code(c, e2, e1, &contact.flipped(), &mut lapis);
+        }
+    }
+}
+
+/// a property that can be linked to a variable (see `LINKS_TOOLTIP`)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PropertyKind {
+    X,
+    Y,
+    Z,
+    Rx,
+    Ry,
+    Rot,
+    Mass,
+    Vx,
+    Vy,
+    Va,
+    Vm,
+    Vp,
+    Restitution,
+    LinDamp,
+    AngDamp,
+    Inertia,
+    H,
+    S,
+    L,
+    A,
+    Sides,
+    Cmx,
+    Cmy,
+    Friction,
+    Tail,
+    Layer,
+    Dynamic,
+    Sensor,
+    // joint motors
+    MotorVelocity,
+    MotorPosition,
+    MotorMax,
+    MotorStiffness,
+    MotorDamping,
+    // joint force and torque (read only)
+    Force,
+    Torque,
+}
+
+impl PropertyKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "x" => Some(PropertyKind::X),
+            "y" => Some(PropertyKind::Y),
+            "z" => Some(PropertyKind::Z),
+            "rx" => Some(PropertyKind::Rx),
+            "ry" => Some(PropertyKind::Ry),
+            "rot" => Some(PropertyKind::Rot),
+            "mass" => Some(PropertyKind::Mass),
+            "vx" => Some(PropertyKind::Vx),
+            "vy" => Some(PropertyKind::Vy),
+            "va" => Some(PropertyKind::Va),
+            "vm" => Some(PropertyKind::Vm),
+            "vp" => Some(PropertyKind::Vp),
+            "restitution" => Some(PropertyKind::Restitution),
+            "lindamp" => Some(PropertyKind::LinDamp),
+            "angdamp" => Some(PropertyKind::AngDamp),
+            "inertia" => Some(PropertyKind::Inertia),
+            "h" => Some(PropertyKind::H),
+            "s" => Some(PropertyKind::S),
+            "l" => Some(PropertyKind::L),
+            "a" => Some(PropertyKind::A),
+            "sides" => Some(PropertyKind::Sides),
+            "cmx" => Some(PropertyKind::Cmx),
+            "cmy" => Some(PropertyKind::Cmy),
+            "friction" => Some(PropertyKind::Friction),
+            "tail" => Some(PropertyKind::Tail),
+            "layer" => Some(PropertyKind::Layer),
+            "dynamic" => Some(PropertyKind::Dynamic),
+            "sensor" => Some(PropertyKind::Sensor),
+            "motor_velocity" => Some(PropertyKind::MotorVelocity),
+            "motor_position" => Some(PropertyKind::MotorPosition),
+            "motor_max" => Some(PropertyKind::MotorMax),
+            "motor_stiffness" => Some(PropertyKind::MotorStiffness),
+            "motor_damping" => Some(PropertyKind::MotorDamping),
+            "force" => Some(PropertyKind::Force),
+            "torque" => Some(PropertyKind::Torque),
+            _ => None,
         }
-        for CollisionEnded(e1, e2) in ended.read() {
-            if let Ok(c) = code.get(*e1) {
-                lapis.eval(&replace(&c.1, *e1, *e2));
+    }
+
+    /// read the value of this property from an entity
+    pub fn get(self, e: Entity, lapis: &Lapis) -> Option<f32> {
+        let hsla = || -> Option<Hsla> {
+            let mat_id = lapis.material_ids.get(e).ok()?;
+            Some(lapis.materials.get(mat_id)?.color.into())
+        };
+        match self {
+            PropertyKind::X => Some(lapis.trans_query.get(e).ok()?.translation.x),
+            PropertyKind::Y => Some(lapis.trans_query.get(e).ok()?.translation.y),
+            PropertyKind::Z => Some(lapis.trans_query.get(e).ok()?.translation.z),
+            PropertyKind::Rx => Some(lapis.trans_query.get(e).ok()?.scale.x),
+            PropertyKind::Ry => Some(lapis.trans_query.get(e).ok()?.scale.y),
+            PropertyKind::Rot => {
+                let trans = lapis.trans_query.get(e).ok()?;
+                Some(trans.rotation.to_euler(EulerRot::XYZ).2)
             }
-            if let Ok(c) = code.get(*e2) {
-                lapis.eval(&replace(&c.1, *e2, *e1));
+            PropertyKind::Mass => Some(lapis.mass_query.get(e).ok()?.0),
+            PropertyKind::Vx => Some(lapis.lin_velocity_query.get(e).ok()?.x),
+            PropertyKind::Vy => Some(lapis.lin_velocity_query.get(e).ok()?.y),
+            PropertyKind::Va => Some(lapis.ang_velocity_query.get(e).ok()?.0),
+            PropertyKind::Vm => {
+                let v = lapis.lin_velocity_query.get(e).ok()?;
+                Some(v.x.hypot(v.y))
             }
+            PropertyKind::Vp => {
+                let v = lapis.lin_velocity_query.get(e).ok()?;
+                Some(v.y.atan2(v.x))
+            }
+            PropertyKind::Restitution => Some(lapis.restitution_query.get(e).ok()?.coefficient),
+            PropertyKind::LinDamp => Some(lapis.lin_damp_query.get(e).ok()?.0),
+            PropertyKind::AngDamp => Some(lapis.ang_damp_query.get(e).ok()?.0),
+            PropertyKind::Inertia => Some(lapis.inertia_query.get(e).ok()?.0),
+            PropertyKind::H => Some(hsla()?.hue),
+            PropertyKind::S => Some(hsla()?.saturation),
+            PropertyKind::L => Some(hsla()?.lightness),
+            PropertyKind::A => Some(hsla()?.alpha),
+            PropertyKind::Sides => Some(lapis.shape_query.get(e).ok()?.sides()? as f32),
+            PropertyKind::Cmx => Some(lapis.cm_query.get(e).ok()?.0.x),
+            PropertyKind::Cmy => Some(lapis.cm_query.get(e).ok()?.0.y),
+            PropertyKind::Friction => Some(lapis.friction_query.get(e).ok()?.dynamic_coefficient),
+            PropertyKind::Tail => Some(lapis.tail_query.get(e).ok()?.len as f32),
+            PropertyKind::Layer => {
+                let l = lapis.layer_query.get(e).ok()?.memberships.0;
+                Some(l.ilog2() as f32)
+            }
+            PropertyKind::Dynamic => {
+                let body = lapis.body_query.get(e).ok()?;
+                Some((*body == RigidBody::Dynamic).into())
+            }
+            PropertyKind::Sensor => Some(lapis.sensor_query.contains(e).into()),
+            PropertyKind::MotorVelocity => Some(lapis.motor_query.get(e).ok()?.target_velocity),
+            PropertyKind::MotorPosition => Some(lapis.motor_query.get(e).ok()?.target_position),
+            PropertyKind::MotorMax => Some(lapis.motor_query.get(e).ok()?.max_force),
+            PropertyKind::MotorStiffness => Some(lapis.motor_query.get(e).ok()?.stiffness),
+            PropertyKind::MotorDamping => Some(lapis.motor_query.get(e).ok()?.damping),
+            PropertyKind::Force => Some(joint_force(e, lapis)?.0),
+            PropertyKind::Torque => Some(joint_force(e, lapis)?.1),
         }
     }
+
+    /// set this property of an entity (through the `Property` observer)
+    pub fn set(self, e: Entity, f: f32, lapis: &mut Lapis) {
+        let property = match self {
+            PropertyKind::X => Property::X(f),
+            PropertyKind::Y => Property::Y(f),
+            PropertyKind::Z => Property::Z(f),
+            PropertyKind::Rx => Property::Rx(f),
+            PropertyKind::Ry => Property::Ry(f),
+            PropertyKind::Rot => Property::Rot(f),
+            PropertyKind::Mass => Property::Mass(f),
+            PropertyKind::Vx => Property::Vx(f),
+            PropertyKind::Vy => Property::Vy(f),
+            PropertyKind::Va => Property::Va(f),
+            PropertyKind::Vm | PropertyKind::Vp => {
+                let Ok(v) = lapis.lin_velocity_query.get(e) else {
+                    return;
+                };
+                let (m, p) = if self == PropertyKind::Vm {
+                    (f, v.y.atan2(v.x))
+                } else {
+                    (v.x.hypot(v.y), f)
+                };
+                lapis.commands.trigger_targets(Property::Vx(m * p.cos()), e);
+                lapis.commands.trigger_targets(Property::Vy(m * p.sin()), e);
+                return;
+            }
+            PropertyKind::Restitution => Property::Restitution(f),
+            PropertyKind::LinDamp => Property::LinDamp(f),
+            PropertyKind::AngDamp => Property::AngDamp(f),
+            PropertyKind::Inertia => Property::Inertia(f),
+            PropertyKind::H => Property::H(f),
+            PropertyKind::S => Property::S(f),
+            PropertyKind::L => Property::L(f),
+            PropertyKind::A => Property::A(f),
+            PropertyKind::Sides => Property::Sides(f as u32),
+            PropertyKind::Cmx => Property::Cmx(f),
+            PropertyKind::Cmy => Property::Cmy(f),
+            PropertyKind::Friction => Property::Friction(f),
+            PropertyKind::Tail => Property::Tail(f as usize),
+            PropertyKind::Layer => Property::Layer(f as u32),
+            PropertyKind::Dynamic => Property::Dynamic(f > 0.),
+            PropertyKind::Sensor => Property::Sensor(f > 0.),
+            PropertyKind::MotorVelocity
+            | PropertyKind::MotorPosition
+            | PropertyKind::MotorMax
+            | PropertyKind::MotorStiffness
+            | PropertyKind::MotorDamping => {
+                let property = match self {
+                    PropertyKind::MotorVelocity => JointProperty::MotorVelocity(f),
+                    PropertyKind::MotorPosition => JointProperty::MotorPosition(f),
+                    PropertyKind::MotorMax => JointProperty::MotorMax(f),
+                    PropertyKind::MotorStiffness => JointProperty::MotorStiffness(f),
+                    _ => JointProperty::MotorDamping(f),
+                };
+                lapis.commands.trigger_targets(property, e);
+                return;
+            }
+            PropertyKind::Force | PropertyKind::Torque => return,
+        };
+        lapis.commands.trigger_targets(property, e);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LinkDir {
+    /// `property > var`
+    Out,
+    /// `property < var` or `property = expr`
+    In,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkBinding {
+    pub property: PropertyKind,
+    pub dir: LinkDir,
+    pub var: String,
+    /// resolved lazily, so links can name variables that don't exist yet
+    pub shared: Option<Shared>,
+    /// the `LapisData::smap_version` this was resolved at
+    pub version: u64,
 }
 
-fn sync_links(links_query: Query<(Entity, &Links)>, mut lapis: Lapis) {
-    for (e, Links(links)) in links_query.iter() {
-        for link in links.lines() {
+/// the compiled form of `Links`, rebuilt when the text changes
+#[derive(Component, Default)]
+pub struct LinkBindings {
+    pub source: String,
+    pub bindings: Vec<LinkBinding>,
+    pub errors: Vec<String>,
+}
+
+/// parsed float expressions of the links (for `In` links that aren't shared vars)
+/// indexed like `LinkBindings::bindings`
+#[derive(Default)]
+pub struct LinkExprs(HashMap<Entity, Vec<Option<Expr>>>);
+
+fn compile_links(
+    mut commands: Commands,
+    mut links_query: Query<(Entity, &Links, Option<&mut LinkBindings>), Changed<Links>>,
+    mut removed: RemovedComponents<Links>,
+    mut exprs: NonSendMut<LinkExprs>,
+    mut lapis_data: ResMut<LapisData>,
+    selected_query: Query<(), With<Selected>>,
+    egui_focused: Res<EguiFocused>,
+) {
+    for e in removed.read() {
+        exprs.0.remove(&e);
+    }
+    for (e, Links(links), bindings) in links_query.iter_mut() {
+        if let Some(bindings) = &bindings
+            && bindings.source == *links
+        {
+            continue;
+        }
+        let mut compiled = Vec::new();
+        let mut parsed = Vec::new();
+        let mut errors = Vec::new();
+        for (n, line) in links.lines().enumerate() {
             // links are in the form "property > var" or "property < var"
-            let mut link = link.split_ascii_whitespace();
+            let mut link = line.split_ascii_whitespace();
             let s0 = link.next();
             let s1 = link.next();
             let s2 = link.next();
-            let (Some(property), Some(dir), Some(var)) = (s0, s1, s2) else {
-                continue;
-            };
-            if let Some(var) = lapis.data.smap.get(var) {
-                let cmd = &mut lapis.commands;
-                match property {
-                    "x" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::X(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.trans_query.get(e).unwrap().translation.x);
-                        }
-                    }
-                    "y" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Y(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.trans_query.get(e).unwrap().translation.y);
-                        }
-                    }
-                    "z" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Z(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.trans_query.get(e).unwrap().translation.z);
-                        }
-                    }
-                    "rx" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Rx(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.trans_query.get(e).unwrap().scale.x);
-                        }
-                    }
-                    "ry" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Ry(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.trans_query.get(e).unwrap().scale.y);
-                        }
-                    }
-                    "rot" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Rot(var.value()), e);
-                        } else if dir == ">" {
-                            let trans = lapis.trans_query.get(e).unwrap();
-                            let rot = trans.rotation.to_euler(EulerRot::XYZ).2;
-                            var.set(rot);
-                        }
-                    }
-                    "mass" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Mass(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.mass_query.get_mut(e).unwrap().0);
-                        }
-                    }
-                    "vx" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Vx(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.lin_velocity_query.get(e).unwrap().x);
-                        }
-                    }
-                    "vy" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Vy(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.lin_velocity_query.get(e).unwrap().y);
-                        }
-                    }
-                    "va" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Va(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.ang_velocity_query.get(e).unwrap().0);
-                        }
-                    }
-                    "vm" => {
-                        let v = lapis.lin_velocity_query.get(e).unwrap();
-                        if dir == "<" {
-                            let m = var.value();
-                            let p = v.y.atan2(v.x);
-                            cmd.trigger_targets(Property::Vx(m * p.cos()), e);
-                            cmd.trigger_targets(Property::Vy(m * p.sin()), e);
-                        } else {
-                            var.set(v.x.hypot(v.y));
-                        }
-                    }
-                    "vp" => {
-                        let v = lapis.lin_velocity_query.get(e).unwrap();
-                        if dir == "<" {
-                            let m = v.x.hypot(v.y);
-                            let p = var.value();
-                            cmd.trigger_targets(Property::Vx(m * p.cos()), e);
-                            cmd.trigger_targets(Property::Vy(m * p.sin()), e);
-                        } else {
-                            var.set(v.y.atan2(v.x));
-                        }
-                    }
-                    "restitution" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Restitution(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.restitution_query.get(e).unwrap().coefficient);
-                        }
-                    }
-                    "lindamp" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::LinDamp(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.lin_damp_query.get(e).unwrap().0);
-                        }
-                    }
-                    "angdamp" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::AngDamp(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.ang_damp_query.get(e).unwrap().0);
-                        }
-                    }
-                    "inertia" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Inertia(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.inertia_query.get(e).unwrap().0);
-                        }
-                    }
-                    "h" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::H(var.value()), e);
-                        } else if dir == ">" {
-                            let mat_id = lapis.material_ids.get(e).unwrap();
-                            let mat = lapis.materials.get(mat_id).unwrap();
-                            let hsla: Hsla = mat.color.into();
-                            var.set(hsla.hue);
-                        }
-                    }
-                    "s" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::S(var.value()), e);
-                        } else if dir == ">" {
-                            let mat_id = lapis.material_ids.get(e).unwrap();
-                            let mat = lapis.materials.get(mat_id).unwrap();
-                            let hsla: Hsla = mat.color.into();
-                            var.set(hsla.saturation);
-                        }
-                    }
-                    "l" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::L(var.value()), e);
-                        } else if dir == ">" {
-                            let mat_id = lapis.material_ids.get(e).unwrap();
-                            let mat = lapis.materials.get(mat_id).unwrap();
-                            let hsla: Hsla = mat.color.into();
-                            var.set(hsla.lightness);
-                        }
-                    }
-                    "a" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::A(var.value()), e);
-                        } else if dir == ">" {
-                            let mat_id = lapis.material_ids.get(e).unwrap();
-                            let mat = lapis.materials.get(mat_id).unwrap();
-                            let hsla: Hsla = mat.color.into();
-                            var.set(hsla.alpha);
-                        }
-                    }
-                    "sides" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Sides(var.value() as u32), e);
-                        } else if dir == ">" {
-                            var.set(lapis.sides_query.get(e).unwrap().0 as f32);
-                        }
-                    }
-                    "cmx" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Cmx(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.cm_query.get(e).unwrap().0.x);
+            let error = match (s0, s1, s2) {
+                (None, _, _) => continue,
+                (Some(property), Some(dir), Some(var)) => {
+                    let kind = PropertyKind::parse(property);
+                    let link_dir = match dir {
+                        ">" => Some(LinkDir::Out),
+                        "<" | "=" => Some(LinkDir::In),
+                        _ => None,
+                    };
+                    let expr = parse_str::<Expr>(var).ok();
+                    let is_var = parse_str::<syn::Ident>(var).is_ok();
+                    match (kind, link_dir) {
+                        (None, _) => format!("unknown property \"{property}\""),
+                        (_, None) => format!("expected `<`, `>`, or `=`, found \"{dir}\""),
+                        (_, Some(LinkDir::Out)) if !is_var => {
+                            format!("\"{var}\" isn't a variable name")
                         }
-                    }
-                    "cmy" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Cmy(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.cm_query.get(e).unwrap().0.y);
+                        (_, Some(LinkDir::In)) if expr.is_none() => {
+                            format!("couldn't parse \"{var}\"")
                         }
-                    }
-                    "friction" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Friction(var.value()), e);
-                        } else if dir == ">" {
-                            var.set(lapis.friction_query.get(e).unwrap().dynamic_coefficient);
+                        (Some(property), Some(dir)) => {
+                            compiled.push(LinkBinding {
+                                property,
+                                dir,
+                                var: var.to_string(),
+                                shared: None,
+                                // force resolving on first sync
+                                version: u64::MAX,
+                            });
+                            parsed.push(expr);
+                            continue;
                         }
                     }
-                    "tail" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Tail(var.value() as usize), e);
-                        } else if dir == ">" {
-                            var.set(lapis.tail_query.get(e).unwrap().len as f32);
-                        }
-                    }
-                    "layer" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Layer(var.value() as u32), e);
-                        } else if dir == ">" {
-                            let l = lapis.layer_query.get(e).unwrap().memberships.0;
-                            var.set(l.ilog2() as f32);
-                        }
-                    }
-                    "dynamic" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Dynamic(var.value() > 0.), e);
-                        } else if dir == ">" {
-                            let body = lapis.body_query.get(e).unwrap();
-                            var.set((*body == RigidBody::Dynamic).into());
-                        }
-                    }
-                    "sensor" => {
-                        if dir == "<" {
-                            cmd.trigger_targets(Property::Sensor(var.value() > 0.), e);
-                        } else if dir == ">" {
-                            var.set(lapis.sensor_query.contains(e).into());
+                }
+                _ => String::from("expected \"property > var\" or \"property < var\""),
+            };
+            errors.push(format!("line {}: {error}", n + 1));
+        }
+        // don't flood the output while links are being typed in the ui
+        // (errors of the selected entity are shown there)
+        if !(egui_focused.0 && selected_query.contains(e)) {
+            for error in &errors {
+                lapis_data
+                    .buffer
+                    .push_str(&format!("\n// error: links of {e} {error}"));
+            }
+        }
+        exprs.0.insert(e, parsed);
+        if let Some(mut bindings) = bindings {
+            bindings.source = links.clone();
+            bindings.bindings = compiled;
+            bindings.errors = errors;
+        } else {
+            commands.entity(e).insert(LinkBindings {
+                source: links.clone(),
+                bindings: compiled,
+                errors,
+            });
+        }
+    }
+}
+
+fn sync_links(
+    mut links_query: Query<(Entity, &mut LinkBindings)>,
+    exprs: NonSend<LinkExprs>,
+    mut lapis: Lapis,
+) {
+    let version = lapis.data.smap_version;
+    for (e, mut bindings) in links_query.iter_mut() {
+        for (i, link) in bindings.bindings.iter_mut().enumerate() {
+            if link.version != version {
+                link.shared = lapis.data.smap.get(&link.var).cloned();
+                link.version = version;
+            }
+            if let Some(var) = &link.shared {
+                match link.dir {
+                    LinkDir::In => link.property.set(e, var.value(), &mut lapis),
+                    LinkDir::Out => {
+                        if let Some(f) = link.property.get(e, &lapis) {
+                            var.set(f);
                         }
                     }
-                    _ => {}
                 }
             // assign a float expression
-            } else if (dir == "<" || dir == "=")
-                && let Ok(expr) = parse_str::<Expr>(var)
-                && let Some(f) = eval_float_f32(&expr, &lapis)
+            } else if link.dir == LinkDir::In
+                && let Some(Some(expr)) = exprs.0.get(&e).and_then(|v| v.get(i))
+                && let Ok(f) = eval_float_f32(expr, &lapis)
             {
-                let cmd = &mut lapis.commands;
-                match property {
-                    "x" => cmd.trigger_targets(Property::X(f), e),
-                    "y" => cmd.trigger_targets(Property::Y(f), e),
-                    "z" => cmd.trigger_targets(Property::Z(f), e),
-                    "rx" => cmd.trigger_targets(Property::Rx(f), e),
-                    "ry" => cmd.trigger_targets(Property::Ry(f), e),
-                    "rot" => cmd.trigger_targets(Property::Rot(f), e),
-                    "mass" => cmd.trigger_targets(Property::Mass(f), e),
-                    "vx" => cmd.trigger_targets(Property::Vx(f), e),
-                    "vy" => cmd.trigger_targets(Property::Vy(f), e),
-                    "va" => cmd.trigger_targets(Property::Va(f), e),
-                    "vm" => {
-                        let v = lapis.lin_velocity_query.get(e).unwrap();
-                        let m = f;
-                        let p = v.y.atan2(v.x);
-                        cmd.trigger_targets(Property::Vx(m * p.cos()), e);
-                        cmd.trigger_targets(Property::Vy(m * p.sin()), e);
-                    }
-                    "vp" => {
-                        let v = lapis.lin_velocity_query.get(e).unwrap();
-                        let m = v.x.hypot(v.y);
-                        let p = f;
-                        cmd.trigger_targets(Property::Vx(m * p.cos()), e);
-                        cmd.trigger_targets(Property::Vy(m * p.sin()), e);
-                    }
-                    "restitution" => cmd.trigger_targets(Property::Restitution(f), e),
-                    "lindamp" => cmd.trigger_targets(Property::LinDamp(f), e),
-                    "angdamp" => cmd.trigger_targets(Property::AngDamp(f), e),
-                    "inertia" => cmd.trigger_targets(Property::Inertia(f), e),
-                    "h" => cmd.trigger_targets(Property::H(f), e),
-                    "s" => cmd.trigger_targets(Property::S(f), e),
-                    "l" => cmd.trigger_targets(Property::L(f), e),
-                    "a" => cmd.trigger_targets(Property::A(f), e),
-                    "sides" => cmd.trigger_targets(Property::Sides(f as u32), e),
-                    "cmx" => cmd.trigger_targets(Property::Cmx(f), e),
-                    "cmy" => cmd.trigger_targets(Property::Cmy(f), e),
-                    "friction" => cmd.trigger_targets(Property::Friction(f), e),
-                    "tail" => cmd.trigger_targets(Property::Tail(f as usize), e),
-                    "layer" => cmd.trigger_targets(Property::Layer(f as u32), e),
-                    "dynamic" => cmd.trigger_targets(Property::Dynamic(f > 0.), e),
-                    "sensor" => cmd.trigger_targets(Property::Sensor(f > 0.), e),
-                    _ => {}
-                }
+                link.property.set(e, f, &mut lapis);
             }
         }
     }
@@ -525,6 +1254,7 @@ pub enum Property {
     L(f32),
     A(f32),
     Sides(u32),
+    Shape(Shape),
     Cmx(f32),
     Cmy(f32),
     Friction(f32),
@@ -535,6 +1265,9 @@ pub enum Property {
     Links(String),
     CodeI(String),
     CodeF(String),
+    CodeC(String),
+    CodeU(String),
+    CodeUSteps(u32),
 }
 
 pub fn set_property(
@@ -549,7 +1282,9 @@ pub fn set_property(
     mut cm_query: Query<&mut CenterOfMass>,
     mut tail_query: Query<&mut Tail>,
     mut code_query: Query<&mut Code>,
+    mut script_query: Query<&mut Script>,
     selected_query: Query<Entity, With<Selected>>,
+    joints: Query<(), JointFilter>,
 ) {
     let e = trig.target();
     // methods applied to PLACEHOLDER affect the selected entities
@@ -667,15 +1402,16 @@ pub fn set_property(
             }
         }
         Property::Sides(val) => {
-            let val = val.clamp(3, 512);
+            let shape = Shape::Regular(val.clamp(3, 512));
+            commands.trigger_targets(Property::Shape(shape), e);
+        }
+        Property::Shape(ref shape) => {
             if let Ok(mesh_id) = mesh_ids.get(e) {
                 // hack to make sure the new mesh is in VisibleEntities (selectable)
                 trans_query.get_mut(e).unwrap().set_changed();
                 let mesh = meshes.get_mut(mesh_id).unwrap();
-                *mesh = RegularPolygon::new(1., val).into();
-                commands
-                    .entity(e)
-                    .insert((Sides(val), Collider::regular_polygon(1., val)));
+                *mesh = shape.mesh();
+                commands.entity(e).insert((shape.collider(), shape.clone()));
             }
         }
         Property::Cmx(val) => {
@@ -725,7 +1461,8 @@ pub fn set_property(
             }
         }
         Property::Links(ref val) => {
-            if trans_query.contains(e) {
+            // joints can have links too (for their motors and forces)
+            if trans_query.contains(e) || joints.contains(e) {
                 commands.entity(e).insert(Links(val.clone()));
             }
         }
@@ -739,6 +1476,31 @@ pub fn set_property(
                 c.1 = val.clone();
             }
         }
+        Property::CodeC(ref val) => {
+            if let Ok(mut c) = code_query.get_mut(e) {
+                c.2 = val.clone();
+            }
+        }
+        Property::CodeU(ref val) => {
+            if let Ok(mut s) = script_query.get_mut(e) {
+                s.code = val.clone();
+            } else if trans_query.contains(e) {
+                commands.entity(e).insert(Script {
+                    code: val.clone(),
+                    steps: 0,
+                });
+            }
+        }
+        Property::CodeUSteps(val) => {
+            if let Ok(mut s) = script_query.get_mut(e) {
+                s.steps = val;
+            } else if trans_query.contains(e) {
+                commands.entity(e).insert(Script {
+                    code: String::new(),
+                    steps: val,
+                });
+            }
+        }
     }
 }
 
@@ -759,7 +1521,8 @@ pub fn insert_defaults(
         alpha_mode: AlphaMode2d::Blend,
         ..default()
     };
-    let mesh_handle = meshes.add(RegularPolygon::new(1., settings.sides));
+    let shape = Shape::Regular(settings.sides);
+    let mesh_handle = meshes.add(shape.mesh());
     let mat_handle = materials.add(material);
     let layer = 1 << settings.collision_layer;
     commands.entity(e).insert((
@@ -767,11 +1530,15 @@ pub fn insert_defaults(
         MeshMaterial2d(mat_handle),
         settings.rigid_body,
         Links(settings.links.clone()),
-        Code(settings.code.0.clone(), settings.code.1.clone()),
+        Code(
+            settings.code.0.clone(),
+            settings.code.1.clone(),
+            settings.code.2.clone(),
+        ),
         Mass(r * r * r),
         AngularInertia(r * r * r),
         CenterOfMass(settings.center_of_mass),
-        Collider::regular_polygon(1., settings.sides),
+        shape.collider(),
         CollisionLayers::from_bits(layer, layer),
         (
             CollisionEventsEnabled,
@@ -781,7 +1548,7 @@ pub fn insert_defaults(
             Friction::new(settings.friction),
         ),
         Transform::from_scale(Vec3::new(r, r, 1.)),
-        Sides(settings.sides),
+        shape,
         Tail {
             len: settings.tail,
             ..default()
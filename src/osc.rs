@@ -0,0 +1,437 @@
+use crate::{
+    lapis::{Lapis, LapisData},
+    midi::RemoteAction,
+};
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender, bounded};
+use std::{
+    net::UdpSocket,
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+pub struct OscPlugin;
+
+impl Plugin for OscPlugin {
+    fn build(&self, app: &mut App) {
+        let (_, r) = bounded(1);
+        app.insert_resource(OscListener {
+            running: None,
+            receiver: r,
+        })
+        .add_observer(osc_listen)
+        .add_observer(osc_stop)
+        .add_systems(Update, osc_messages);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Long(i64),
+    Double(f64),
+    Bool(bool),
+    Nil,
+    Blob(Vec<u8>),
+}
+
+impl OscArg {
+    pub fn float(&self) -> Option<f32> {
+        match self {
+            OscArg::Int(x) => Some(*x as f32),
+            OscArg::Float(x) => Some(*x),
+            OscArg::Long(x) => Some(*x as f32),
+            OscArg::Double(x) => Some(*x as f32),
+            OscArg::Bool(x) => Some(*x as u8 as f32),
+            _ => None,
+        }
+    }
+
+    /// the argument as lapis code (None for nil and blobs)
+    fn literal(&self) -> Option<String> {
+        match self {
+            OscArg::Str(s) => Some(format!("{s:?}")),
+            OscArg::Bool(b) => Some(b.to_string()),
+            x => x.float().map(|x| x.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    pub addr: String,
+    pub args: Vec<OscArg>,
+}
+
+/// the running listener thread (if any) and the messages it received
+#[derive(Resource)]
+pub struct OscListener {
+    running: Option<Running>,
+    receiver: Receiver<OscMessage>,
+}
+
+/// a listener thread and where it listens
+struct Running {
+    host: String,
+    port: u16,
+    /// cleared to end the thread
+    on: Arc<AtomicBool>,
+    /// gives back the socket once the thread ends
+    thread: JoinHandle<UdpSocket>,
+}
+
+impl Running {
+    /// start listening on a socket (the messages go to the returned receiver)
+    fn spawn(socket: UdpSocket, host: String, port: u16) -> (Self, Receiver<OscMessage>) {
+        let on = Arc::new(AtomicBool::new(true));
+        let (s, r) = bounded(1024);
+        let thread_on = on.clone();
+        let thread = std::thread::spawn(move || listen(socket, s, thread_on));
+        let running = Running {
+            host,
+            port,
+            on,
+            thread,
+        };
+        (running, r)
+    }
+
+    /// end the thread and wait for it (it takes up to the socket's read timeout)
+    fn stop(self) -> Option<UdpSocket> {
+        self.on.store(false, Ordering::Relaxed);
+        self.thread.join().ok()
+    }
+}
+
+/// trigger this to listen for osc messages on a udp port (ending the current listener)
+#[derive(Event, Debug)]
+pub struct OscListen {
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Event)]
+pub struct OscStop;
+
+/// the current listener keeps going if the new socket can't be bound
+fn osc_listen(
+    trig: Trigger<OscListen>,
+    mut listener: ResMut<OscListener>,
+    mut lapis_data: ResMut<LapisData>,
+) {
+    let event = trig.event();
+    if let Some(running) = &listener.running
+        && running.host == event.host
+        && running.port == event.port
+    {
+        return;
+    }
+    // the old thread has to let go of its socket, or the port might still be taken
+    let old = listener
+        .running
+        .take()
+        .map(|r| (r.host.clone(), r.port, r.stop()));
+    let bound = UdpSocket::bind((event.host.as_str(), event.port)).and_then(|socket| {
+        // so the thread can notice it's been stopped
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        Ok(socket)
+    });
+    let (running, receiver) = match (bound, old) {
+        (Ok(socket), _) => Running::spawn(socket, event.host.clone(), event.port),
+        (Err(err), old) => {
+            lapis_data.buffer.push_str(&format!(
+                "\n// error: couldn't listen on {}:{}: {err}",
+                event.host, event.port
+            ));
+            let Some((host, port, Some(socket))) = old else {
+                return;
+            };
+            Running::spawn(socket, host, port)
+        }
+    };
+    listener.running = Some(running);
+    listener.receiver = receiver;
+}
+
+fn osc_stop(_: Trigger<OscStop>, mut listener: ResMut<OscListener>) {
+    if let Some(running) = listener.running.take() {
+        running.stop();
+    }
+}
+
+fn listen(socket: UdpSocket, sender: Sender<OscMessage>, on: Arc<AtomicBool>) -> UdpSocket {
+    let mut buf = vec![0; 65536];
+    let mut messages = Vec::new();
+    while on.load(Ordering::Relaxed) {
+        if let Ok((len, _)) = socket.recv_from(&mut buf) {
+            decode_packet(&buf[..len], &mut messages, 0);
+            for msg in messages.drain(..) {
+                let _ = sender.try_send(msg);
+            }
+        }
+    }
+    socket
+}
+
+/// `/shared/name f` sets a shared, and bound addresses (`"osc /addr" = ...`) run their action
+/// (arbitrary code is never taken from the network, only code bound here)
+fn osc_messages(mut lapis: Lapis, listener: Res<OscListener>) {
+    for msg in listener.receiver.try_iter() {
+        let first = msg.args.first();
+        if let Some(name) = msg.addr.strip_prefix("/shared/")
+            && let Some(shared) = lapis.data.smap.get(name)
+            && let Some(x) = first.and_then(OscArg::float)
+        {
+            shared.set(x);
+        }
+        match lapis.data.osc.get(&msg.addr).cloned() {
+            Some(RemoteAction::Shared(shared)) => {
+                if let Some(x) = first.and_then(OscArg::float) {
+                    shared.set(x);
+                }
+            }
+            Some(RemoteAction::Code(code)) => {
+                let code = match first.and_then(OscArg::literal) {
+                    Some(x) => code.replace('@', &x),
+                    None => code,
+                };
+                lapis.as_hook(|lapis| {
+                    if lapis.data.quiet {
+                        lapis.quiet_eval(&code);
+                    } else {
+                        lapis.eval(&code);
+                    }
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+/// send a message from an unbound udp socket (shared by all sends)
+pub fn osc_send(host: &str, port: u16, addr: &str, args: &[OscArg]) -> std::io::Result<()> {
+    static SOCKET: OnceLock<Option<UdpSocket>> = OnceLock::new();
+    let socket = SOCKET
+        .get_or_init(|| UdpSocket::bind("0.0.0.0:0").ok())
+        .as_ref()
+        .ok_or(std::io::ErrorKind::AddrNotAvailable)?;
+    socket.send_to(&encode_message(addr, args), (host, port))?;
+    Ok(())
+}
+
+pub fn encode_message(addr: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_string(&mut buf, addr);
+    let mut tags = String::from(",");
+    for arg in args {
+        tags.push(match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::Str(_) => 's',
+            OscArg::Long(_) => 'h',
+            OscArg::Double(_) => 'd',
+            OscArg::Bool(true) => 'T',
+            OscArg::Bool(false) => 'F',
+            OscArg::Nil => 'N',
+            OscArg::Blob(_) => 'b',
+        });
+    }
+    write_string(&mut buf, &tags);
+    for arg in args {
+        match arg {
+            OscArg::Int(x) => buf.extend(x.to_be_bytes()),
+            OscArg::Float(x) => buf.extend(x.to_be_bytes()),
+            OscArg::Str(s) => write_string(&mut buf, s),
+            OscArg::Long(x) => buf.extend(x.to_be_bytes()),
+            OscArg::Double(x) => buf.extend(x.to_be_bytes()),
+            OscArg::Blob(b) => {
+                buf.extend((b.len() as i32).to_be_bytes());
+                buf.extend(b);
+                pad(&mut buf);
+            }
+            OscArg::Bool(_) | OscArg::Nil => {}
+        }
+    }
+    buf
+}
+
+/// null terminated and padded to 4 bytes
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend(s.as_bytes());
+    buf.push(0);
+    pad(buf);
+}
+
+fn pad(buf: &mut Vec<u8>) {
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+/// decode a message or a bundle (of messages or bundles) into `out`
+/// anything malformed is skipped
+pub fn decode_packet(buf: &[u8], out: &mut Vec<OscMessage>, depth: usize) {
+    if depth > 8 {
+        return;
+    }
+    if buf.starts_with(b"#bundle\0") {
+        // skip the time tag, elements are (size, contents)
+        let mut pos = 16;
+        while let Some(size) = read_bytes::<4>(buf, &mut pos) {
+            let size = i32::from_be_bytes(size) as usize;
+            let Some(element) = buf.get(pos..pos.saturating_add(size)) else {
+                return;
+            };
+            decode_packet(element, out, depth + 1);
+            pos += size;
+        }
+    } else if let Some(msg) = decode_message(buf) {
+        out.push(msg);
+    }
+}
+
+fn decode_message(buf: &[u8]) -> Option<OscMessage> {
+    let mut pos = 0;
+    let addr = read_string(buf, &mut pos)?;
+    if !addr.starts_with('/') {
+        return None;
+    }
+    let mut args = Vec::new();
+    // a missing type tag string means no arguments
+    let tags = read_string(buf, &mut pos).unwrap_or_default();
+    for tag in tags.chars().skip_while(|c| *c == ',') {
+        let arg = match tag {
+            'i' => OscArg::Int(i32::from_be_bytes(read_bytes(buf, &mut pos)?)),
+            'f' => OscArg::Float(f32::from_be_bytes(read_bytes(buf, &mut pos)?)),
+            's' | 'S' => OscArg::Str(read_string(buf, &mut pos)?),
+            'h' => OscArg::Long(i64::from_be_bytes(read_bytes(buf, &mut pos)?)),
+            'd' => OscArg::Double(f64::from_be_bytes(read_bytes(buf, &mut pos)?)),
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'N' | 'I' => OscArg::Nil,
+            'b' => {
+                let len = i32::from_be_bytes(read_bytes(buf, &mut pos)?) as usize;
+                let blob = buf.get(pos..pos.checked_add(len)?)?.to_vec();
+                pos += (len + 3) & !3;
+                OscArg::Blob(blob)
+            }
+            // can't know the size of unknown types
+            _ => return None,
+        };
+        args.push(arg);
+    }
+    Some(OscMessage { addr, args })
+}
+
+fn read_string(buf: &[u8], pos: &mut usize) -> Option<String> {
+    let rest = buf.get(*pos..)?;
+    let len = rest.iter().position(|b| *b == 0)?;
+    let s = String::from_utf8(rest[..len].to_vec()).ok()?;
+    // the null and the padding
+    *pos += (len + 4) & !3;
+    Some(s)
+}
+
+fn read_bytes<const N: usize>(buf: &[u8], pos: &mut usize) -> Option<[u8; N]> {
+    let bytes = buf.get(*pos..pos.checked_add(N)?)?.try_into().ok()?;
+    *pos += N;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(buf: &[u8]) -> Vec<OscMessage> {
+        let mut out = Vec::new();
+        decode_packet(buf, &mut out, 0);
+        out
+    }
+
+    fn bundle(elements: &[&[u8]]) -> Vec<u8> {
+        let mut buf = b"#bundle\0".to_vec();
+        buf.extend(1u64.to_be_bytes());
+        for e in elements.iter().copied() {
+            buf.extend((e.len() as i32).to_be_bytes());
+            buf.extend(e);
+        }
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let args = vec![
+            OscArg::Int(-3),
+            OscArg::Float(0.5),
+            OscArg::Str("abc".into()),
+            OscArg::Long(1 << 40),
+            OscArg::Double(-2.25),
+            OscArg::Bool(true),
+            OscArg::Bool(false),
+            OscArg::Nil,
+            OscArg::Blob(vec![1, 2, 3, 4, 5]),
+            OscArg::Str("abcd".into()),
+        ];
+        let buf = encode_message("/a/b", &args);
+        assert_eq!(buf.len() % 4, 0);
+        let msg = OscMessage {
+            addr: "/a/b".into(),
+            args,
+        };
+        assert_eq!(decode(&buf), vec![msg]);
+        assert_eq!(decode(&encode_message("/x", &[]))[0].args, vec![]);
+    }
+
+    #[test]
+    fn bundles() {
+        let a = encode_message("/a", &[OscArg::Float(1.)]);
+        let b = encode_message("/b", &[OscArg::Str("s".into())]);
+        let nested = bundle(&[&b]);
+        let msgs = decode(&bundle(&[&a, &nested]));
+        let addrs: Vec<_> = msgs.iter().map(|m| m.addr.as_str()).collect();
+        assert_eq!(addrs, ["/a", "/b"]);
+        // an element that claims more bytes than there are stops the bundle
+        let mut buf = bundle(&[&b]);
+        buf.extend(100i32.to_be_bytes());
+        buf.extend([0; 8]);
+        assert_eq!(decode(&buf).len(), 1);
+        // too deep
+        let mut deep = encode_message("/d", &[]);
+        for _ in 0..10 {
+            deep = bundle(&[&deep]);
+        }
+        assert!(decode(&deep).is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let buf = encode_message("/a", &[OscArg::Double(1.), OscArg::Str("abc".into())]);
+        // (an address alone is a message with no arguments)
+        for len in (0..3).chain(8..buf.len()) {
+            assert!(decode(&buf[..len]).is_empty(), "truncated to {len}");
+        }
+        // not an address
+        assert!(decode(&encode_message("a", &[])).is_empty());
+        // unknown type tag
+        let mut buf = encode_message("/a", &[OscArg::Int(1)]);
+        buf[4..8].copy_from_slice(b",x\0\0");
+        assert!(decode(&buf).is_empty());
+        // negative blob length
+        for len in [-1i32, -5, i32::MIN] {
+            let mut buf = encode_message("/a", &[OscArg::Blob(vec![0; 4])]);
+            buf[8..12].copy_from_slice(&len.to_be_bytes());
+            assert!(decode(&buf).is_empty());
+        }
+        // negative bundle element size
+        let mut buf = bundle(&[]);
+        buf.extend((-4i32).to_be_bytes());
+        buf.extend(encode_message("/a", &[]));
+        assert!(decode(&buf).is_empty());
+    }
+}
@@ -0,0 +1,738 @@
+use crate::{
+    history::History,
+    interaction::*,
+    joints::{Breakable, JointFilter, JointMotor},
+    lapis::{Lapis, LapisData, functions::LapisFn, helpers::*},
+    objects::*,
+    shapes::Shape,
+};
+use avian2d::prelude::*;
+use bevy::{prelude::*, sprite::AlphaMode2d};
+use fundsp::hacker::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+/// bump this when the format changes in a way older files can't be read
+pub const SCENE_VERSION: u32 = 1;
+
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(save_scene).add_observer(load_scene);
+    }
+}
+
+/// trigger this event to write the whole scene to a ron file
+#[derive(Event)]
+pub struct SaveScene(pub String);
+
+/// trigger this event to replace the current scene with the one in a ron file
+#[derive(Event)]
+pub struct LoadScene(pub String);
+
+#[derive(Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    pub gravity: Vec2,
+    pub attraction: f32,
+    #[serde(default)]
+    pub attraction_theta: f32,
+    pub draw_settings: DrawSettings,
+    pub joint_settings: JointSettings,
+    pub bodies: Vec<BodyData>,
+    pub joints: Vec<JointData>,
+    pub vars: Vars,
+}
+
+/// a snapshot of an object
+/// `id` is the entity bits at the time of saving, used to rebuild references
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct BodyData {
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub rx: f32,
+    pub ry: f32,
+    pub rot: f32,
+    pub mass: f32,
+    pub inertia: f32,
+    pub vx: f32,
+    pub vy: f32,
+    pub va: f32,
+    pub restitution: f32,
+    pub lindamp: f32,
+    pub angdamp: f32,
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+    pub a: f32,
+    pub sides: u32,
+    /// older scenes only have `sides` (regular polygons)
+    #[serde(default)]
+    pub shape: Option<Shape>,
+    pub cmx: f32,
+    pub cmy: f32,
+    pub friction: f32,
+    pub tail: usize,
+    pub memberships: u32,
+    pub filters: u32,
+    pub rigid_body: RigidBody,
+    pub sensor: bool,
+    pub links: String,
+    pub code_i: String,
+    pub code_f: String,
+    #[serde(default)]
+    pub code_c: String,
+    #[serde(default)]
+    pub code_u: String,
+    #[serde(default)]
+    pub code_u_steps: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub enum JointData {
+    Fixed {
+        id: u64,
+        entity1: u64,
+        entity2: u64,
+        compliance: f32,
+        anchor1: Vec2,
+        anchor2: Vec2,
+        #[serde(default)]
+        breakable: Option<Breakable>,
+    },
+    Distance {
+        id: u64,
+        entity1: u64,
+        entity2: u64,
+        compliance: f32,
+        anchor1: Vec2,
+        anchor2: Vec2,
+        limits: Option<(f32, f32)>,
+        rest: f32,
+        #[serde(default)]
+        breakable: Option<Breakable>,
+    },
+    Prismatic {
+        id: u64,
+        entity1: u64,
+        entity2: u64,
+        compliance: f32,
+        anchor1: Vec2,
+        anchor2: Vec2,
+        limits: Option<(f32, f32)>,
+        free_axis: Vec2,
+        #[serde(default)]
+        motor: Option<JointMotor>,
+        #[serde(default)]
+        breakable: Option<Breakable>,
+    },
+    Revolute {
+        id: u64,
+        entity1: u64,
+        entity2: u64,
+        compliance: f32,
+        anchor1: Vec2,
+        anchor2: Vec2,
+        limits: Option<(f32, f32)>,
+        #[serde(default)]
+        motor: Option<JointMotor>,
+        #[serde(default)]
+        breakable: Option<Breakable>,
+    },
+}
+
+/// the lapis variables that can be written to a file
+/// (nets, waves, sequencers, node ids, sources, and event ids are skipped)
+#[derive(Serialize, Deserialize, Default)]
+pub struct Vars {
+    pub floats: BTreeMap<String, f64>,
+    pub vecs: BTreeMap<String, Vec<f32>>,
+    pub bools: BTreeMap<String, bool>,
+    pub shared: BTreeMap<String, f32>,
+    pub entities: BTreeMap<String, u64>,
+    pub atomic_tables: BTreeMap<String, Vec<f32>>,
+    pub strings: BTreeMap<String, String>,
+    pub keys: BTreeMap<String, String>,
+    /// (parameters, body)
+    #[serde(default)]
+    pub functions: BTreeMap<String, (Vec<String>, String)>,
+}
+
+pub fn capture_body(
+    e: Entity,
+    lapis: &Lapis,
+    links_query: &Query<&Links>,
+    code_query: &Query<&Code>,
+) -> Option<BodyData> {
+    let t = lapis.trans_query.get(e).ok()?;
+    let linv = lapis.lin_velocity_query.get(e).ok()?;
+    let mat_id = lapis.material_ids.get(e).ok()?;
+    let hsla: Hsla = lapis.materials.get(mat_id)?.color.into();
+    let cm = lapis.cm_query.get(e).ok()?;
+    let layers = lapis.layer_query.get(e).ok()?;
+    let code = code_query.get(e).ok()?;
+    let shape = lapis.shape_query.get(e).ok()?;
+    let script = lapis.script_query.get(e).ok().cloned().unwrap_or_default();
+    Some(BodyData {
+        id: e.to_bits(),
+        x: t.translation.x,
+        y: t.translation.y,
+        z: t.translation.z,
+        rx: t.scale.x,
+        ry: t.scale.y,
+        rot: t.rotation.to_euler(EulerRot::XYZ).2,
+        mass: lapis.mass_query.get(e).ok()?.0,
+        inertia: lapis.inertia_query.get(e).ok()?.0,
+        vx: linv.x,
+        vy: linv.y,
+        va: lapis.ang_velocity_query.get(e).ok()?.0,
+        restitution: lapis.restitution_query.get(e).ok()?.coefficient,
+        lindamp: lapis.lin_damp_query.get(e).ok()?.0,
+        angdamp: lapis.ang_damp_query.get(e).ok()?.0,
+        h: hsla.hue,
+        s: hsla.saturation,
+        l: hsla.lightness,
+        a: hsla.alpha,
+        sides: shape.sides().unwrap_or(3),
+        shape: Some(shape.clone()),
+        cmx: cm.x,
+        cmy: cm.y,
+        friction: lapis.friction_query.get(e).ok()?.dynamic_coefficient,
+        tail: lapis.tail_query.get(e).ok()?.len,
+        memberships: layers.memberships.0,
+        filters: layers.filters.0,
+        rigid_body: *lapis.body_query.get(e).ok()?,
+        sensor: lapis.sensor_query.contains(e),
+        links: links_query.get(e).ok()?.0.clone(),
+        code_i: code.0.clone(),
+        code_f: code.1.clone(),
+        code_c: code.2.clone(),
+        code_u: script.code,
+        code_u_steps: script.steps,
+    })
+}
+
+pub fn capture_joint(e: Entity, lapis: &Lapis) -> Option<JointData> {
+    if let Ok(j) = lapis.fixed_query.get(e) {
+        Some(JointData::Fixed {
+            id: e.to_bits(),
+            entity1: j.entity1.to_bits(),
+            entity2: j.entity2.to_bits(),
+            compliance: j.compliance,
+            anchor1: j.local_anchor1,
+            anchor2: j.local_anchor2,
+            breakable: lapis.breakable_query.get(e).ok().cloned(),
+        })
+    } else if let Ok(j) = lapis.distance_query.get(e) {
+        Some(JointData::Distance {
+            id: e.to_bits(),
+            entity1: j.entity1.to_bits(),
+            entity2: j.entity2.to_bits(),
+            compliance: j.compliance,
+            anchor1: j.local_anchor1,
+            anchor2: j.local_anchor2,
+            limits: j.length_limits.map(|l| (l.min, l.max)),
+            rest: j.rest_length,
+            breakable: lapis.breakable_query.get(e).ok().cloned(),
+        })
+    } else if let Ok(j) = lapis.prismatic_query.get(e) {
+        Some(JointData::Prismatic {
+            id: e.to_bits(),
+            entity1: j.entity1.to_bits(),
+            entity2: j.entity2.to_bits(),
+            compliance: j.compliance,
+            anchor1: j.local_anchor1,
+            anchor2: j.local_anchor2,
+            limits: j.free_axis_limits.map(|l| (l.min, l.max)),
+            free_axis: j.free_axis,
+            motor: lapis.motor_query.get(e).ok().copied(),
+            breakable: lapis.breakable_query.get(e).ok().cloned(),
+        })
+    } else if let Ok(j) = lapis.revolute_query.get(e) {
+        Some(JointData::Revolute {
+            id: e.to_bits(),
+            entity1: j.entity1.to_bits(),
+            entity2: j.entity2.to_bits(),
+            compliance: j.compliance,
+            anchor1: j.local_anchor1,
+            anchor2: j.local_anchor2,
+            limits: j.angle_limit.map(|l| (l.min, l.max)),
+            motor: lapis.motor_query.get(e).ok().copied(),
+            breakable: lapis.breakable_query.get(e).ok().cloned(),
+        })
+    } else {
+        None
+    }
+}
+
+pub fn spawn_body(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    body: &BodyData,
+) -> Entity {
+    let mut e = commands.spawn_empty();
+    insert_body(&mut e, meshes, materials, body);
+    e.id()
+}
+
+/// give an entity all the components of an object (replacing what it has)
+pub fn insert_body(
+    e: &mut EntityCommands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    body: &BodyData,
+) {
+    let shape = match &body.shape {
+        Some(shape) => shape.clone(),
+        None => Shape::Regular(body.sides.clamp(3, 512)),
+    };
+    let material = ColorMaterial {
+        color: Hsla::new(body.h, body.s, body.l, body.a).into(),
+        alpha_mode: AlphaMode2d::Blend,
+        ..default()
+    };
+    e.insert((
+        Mesh2d(meshes.add(shape.mesh())),
+        MeshMaterial2d(materials.add(material)),
+        body.rigid_body,
+        Links(body.links.clone()),
+        Code(
+            body.code_i.clone(),
+            body.code_f.clone(),
+            body.code_c.clone(),
+        ),
+        Mass(body.mass),
+        AngularInertia(body.inertia),
+        CenterOfMass(Vec2::new(body.cmx, body.cmy)),
+        shape.collider(),
+        CollisionLayers::from_bits(body.memberships, body.filters),
+        (
+            CollisionEventsEnabled,
+            LinearDamping(body.lindamp),
+            AngularDamping(body.angdamp),
+            Restitution::new(body.restitution),
+            Friction::new(body.friction),
+            LinearVelocity(Vec2::new(body.vx, body.vy)),
+            AngularVelocity(body.va),
+        ),
+        Transform {
+            translation: Vec3::new(body.x, body.y, body.z),
+            rotation: Quat::from_rotation_z(body.rot),
+            scale: Vec3::new(body.rx, body.ry, 1.),
+        },
+        shape,
+        Tail {
+            len: body.tail,
+            ..default()
+        },
+        SleepingDisabled,
+    ));
+    if body.sensor {
+        e.insert(Sensor);
+    } else {
+        e.remove::<Sensor>();
+    }
+    if body.code_u.is_empty() && body.code_u_steps == 0 {
+        e.remove::<Script>();
+    } else {
+        e.insert(Script {
+            code: body.code_u.clone(),
+            steps: body.code_u_steps,
+        });
+    }
+}
+
+/// spawn a joint, mapping the saved entity bits to live entities
+/// returns None if either of the connected objects is missing
+pub fn spawn_joint_data(
+    commands: &mut Commands,
+    joint: &JointData,
+    map: &HashMap<u64, Entity>,
+) -> Option<Entity> {
+    let (entity1, entity2) = joint.bodies();
+    if !map.contains_key(&entity1) || !map.contains_key(&entity2) {
+        return None;
+    }
+    let mut e = commands.spawn_empty();
+    insert_joint_data(&mut e, joint, map);
+    Some(e.id())
+}
+
+/// give an entity a joint (replacing any joint it has)
+/// returns None if either of the connected objects is missing
+pub fn insert_joint_data(
+    e: &mut EntityCommands,
+    joint: &JointData,
+    map: &HashMap<u64, Entity>,
+) -> Option<()> {
+    let (entity1, entity2) = joint.bodies();
+    let (entity1, entity2) = (*map.get(&entity1)?, *map.get(&entity2)?);
+    e.remove::<(
+        FixedJoint,
+        DistanceJoint,
+        PrismaticJoint,
+        RevoluteJoint,
+        JointMotor,
+        Breakable,
+    )>();
+    match joint {
+        JointData::Fixed {
+            compliance,
+            anchor1,
+            anchor2,
+            ..
+        } => {
+            e.insert(
+                FixedJoint::new(entity1, entity2)
+                    .with_compliance(*compliance)
+                    .with_local_anchor_1(*anchor1)
+                    .with_local_anchor_2(*anchor2),
+            );
+        }
+        JointData::Distance {
+            compliance,
+            anchor1,
+            anchor2,
+            limits,
+            rest,
+            ..
+        } => {
+            let mut j = DistanceJoint::new(entity1, entity2)
+                .with_compliance(*compliance)
+                .with_local_anchor_1(*anchor1)
+                .with_local_anchor_2(*anchor2)
+                .with_rest_length(*rest);
+            if let Some((min, max)) = limits {
+                j = j.with_limits(*min, *max);
+            }
+            e.insert(j);
+        }
+        JointData::Prismatic {
+            compliance,
+            anchor1,
+            anchor2,
+            limits,
+            free_axis,
+            ..
+        } => {
+            let mut j = PrismaticJoint::new(entity1, entity2)
+                .with_compliance(*compliance)
+                .with_local_anchor_1(*anchor1)
+                .with_local_anchor_2(*anchor2)
+                .with_free_axis(*free_axis);
+            if let Some((min, max)) = limits {
+                j = j.with_limits(*min, *max);
+            }
+            e.insert(j);
+        }
+        JointData::Revolute {
+            compliance,
+            anchor1,
+            anchor2,
+            limits,
+            ..
+        } => {
+            let mut j = RevoluteJoint::new(entity1, entity2)
+                .with_compliance(*compliance)
+                .with_local_anchor_1(*anchor1)
+                .with_local_anchor_2(*anchor2);
+            if let Some((min, max)) = limits {
+                j = j.with_angle_limits(*min, *max);
+            }
+            e.insert(j);
+        }
+    }
+    if let JointData::Prismatic { motor, .. } | JointData::Revolute { motor, .. } = joint
+        && let Some(motor) = motor
+    {
+        e.insert(*motor);
+    }
+    if let Some(breakable) = joint.breakable() {
+        e.insert(breakable.clone());
+    }
+    Some(())
+}
+
+impl JointData {
+    pub fn id(&self) -> u64 {
+        match self {
+            JointData::Fixed { id, .. }
+            | JointData::Distance { id, .. }
+            | JointData::Prismatic { id, .. }
+            | JointData::Revolute { id, .. } => *id,
+        }
+    }
+
+    /// the saved bits of the connected objects
+    pub fn bodies(&self) -> (u64, u64) {
+        match self {
+            JointData::Fixed {
+                entity1, entity2, ..
+            }
+            | JointData::Distance {
+                entity1, entity2, ..
+            }
+            | JointData::Prismatic {
+                entity1, entity2, ..
+            }
+            | JointData::Revolute {
+                entity1, entity2, ..
+            } => (*entity1, *entity2),
+        }
+    }
+
+    pub fn breakable(&self) -> Option<&Breakable> {
+        match self {
+            JointData::Fixed { breakable, .. }
+            | JointData::Distance { breakable, .. }
+            | JointData::Prismatic { breakable, .. }
+            | JointData::Revolute { breakable, .. } => breakable.as_ref(),
+        }
+    }
+
+    pub fn compliance(&self) -> f32 {
+        match self {
+            JointData::Fixed { compliance, .. }
+            | JointData::Distance { compliance, .. }
+            | JointData::Prismatic { compliance, .. }
+            | JointData::Revolute { compliance, .. } => *compliance,
+        }
+    }
+
+    pub fn anchors(&self) -> (Vec2, Vec2) {
+        match self {
+            JointData::Fixed {
+                anchor1, anchor2, ..
+            }
+            | JointData::Distance {
+                anchor1, anchor2, ..
+            }
+            | JointData::Prismatic {
+                anchor1, anchor2, ..
+            }
+            | JointData::Revolute {
+                anchor1, anchor2, ..
+            } => (*anchor1, *anchor2),
+        }
+    }
+
+    /// (min, max) of distance, prismatic, and revolute joints
+    pub fn limits(&self) -> Option<(f32, f32)> {
+        match self {
+            JointData::Fixed { .. } => None,
+            JointData::Distance { limits, .. }
+            | JointData::Prismatic { limits, .. }
+            | JointData::Revolute { limits, .. } => *limits,
+        }
+    }
+
+    /// the motor of a prismatic or revolute joint (the default if it has none yet)
+    pub fn motor(&self) -> Option<JointMotor> {
+        match self {
+            JointData::Prismatic { motor, .. } | JointData::Revolute { motor, .. } => {
+                Some(motor.unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn capture_vars(data: &LapisData) -> Vars {
+    let mut vars = Vars::default();
+    for (k, v) in &data.fmap {
+        vars.floats.insert(k.clone(), *v);
+    }
+    for (k, v) in &data.vmap {
+        vars.vecs.insert(k.clone(), v.clone());
+    }
+    for (k, v) in &data.bmap {
+        vars.bools.insert(k.clone(), *v);
+    }
+    for (k, v) in &data.smap {
+        vars.shared.insert(k.clone(), v.value());
+    }
+    for (k, v) in &data.entitymap {
+        vars.entities.insert(k.clone(), v.to_bits());
+    }
+    for (k, v) in &data.atomic_table_map {
+        let table = (0..v.len()).map(|i| v.at(i)).collect();
+        vars.atomic_tables.insert(k.clone(), table);
+    }
+    for (k, v) in &data.string_map {
+        vars.strings.insert(k.clone(), v.clone());
+    }
+    for (k, v) in &data.keys {
+        vars.keys.insert(shortcut_string(k), v.clone());
+    }
+    for (k, v) in &data.fnmap {
+        vars.functions
+            .insert(k.clone(), (v.params.clone(), v.body.clone()));
+    }
+    vars
+}
+
+fn restore_vars(data: &mut LapisData, vars: Vars, map: &HashMap<u64, Entity>) {
+    for (k, v) in vars.floats {
+        data.drop(&k);
+        data.fmap.insert(k, v);
+    }
+    for (k, v) in vars.vecs {
+        data.drop(&k);
+        data.vmap.insert(k, v);
+    }
+    for (k, v) in vars.bools {
+        data.drop(&k);
+        data.bmap.insert(k, v);
+    }
+    for (k, v) in vars.shared {
+        data.drop(&k);
+        data.insert_shared(k, shared(v));
+    }
+    for (k, v) in vars.entities {
+        // references to things that weren't saved would point at random entities
+        if let Some(e) = map.get(&v) {
+            data.drop(&k);
+            data.entitymap.insert(k, *e);
+        }
+    }
+    for (k, v) in vars.atomic_tables {
+        if v.len().is_power_of_two() {
+            data.drop(&k);
+            data.atomic_table_map
+                .insert(k, Arc::new(AtomicTable::new(&v)));
+        }
+    }
+    for (k, v) in vars.strings {
+        data.drop(&k);
+        data.string_map.insert(k, v);
+    }
+    for (k, (params, body)) in vars.functions {
+        data.drop(&k);
+        data.fnmap.insert(k, LapisFn { params, body });
+    }
+    for (k, v) in vars.keys {
+        if let Some(shortcut) = parse_shortcut(k) {
+            data.keys.insert(shortcut, v);
+        }
+    }
+}
+
+fn save_scene(
+    trig: Trigger<SaveScene>,
+    mut lapis: Lapis,
+    bodies: Query<Entity, With<RigidBody>>,
+    links_query: Query<&Links>,
+    code_query: Query<&Code>,
+    joints: Query<Entity, JointFilter>,
+    gravity: Res<Gravity>,
+    (attraction_factor, attraction_theta): (Res<AttractionFactor>, Res<AttractionTheta>),
+    draw_settings: Res<DrawSettings>,
+    joint_settings: Res<JointSettings>,
+) {
+    let path = &trig.event().0;
+    let scene = Scene {
+        version: SCENE_VERSION,
+        gravity: gravity.0,
+        attraction: attraction_factor.0,
+        attraction_theta: attraction_theta.0,
+        draw_settings: draw_settings.clone(),
+        joint_settings: joint_settings.clone(),
+        bodies: bodies
+            .iter()
+            .filter_map(|e| capture_body(e, &lapis, &links_query, &code_query))
+            .collect(),
+        joints: joints
+            .iter()
+            .filter_map(|e| capture_joint(e, &lapis))
+            .collect(),
+        vars: capture_vars(&lapis.data),
+    };
+    let pretty = ron::ser::PrettyConfig::default();
+    let result = ron::ser::to_string_pretty(&scene, pretty)
+        .map_err(|e| e.to_string())
+        .and_then(|s| std::fs::write(path, s).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => lapis.data.buffer.push_str(&format!(
+            "\n// saved {} objects and {} joints to {path}",
+            scene.bodies.len(),
+            scene.joints.len()
+        )),
+        Err(err) => lapis
+            .data
+            .buffer
+            .push_str(&format!("\n// error: couldn't save scene: {err}")),
+    }
+}
+
+fn load_scene(
+    trig: Trigger<LoadScene>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut lapis_data: ResMut<LapisData>,
+    bodies: Query<Entity, With<RigidBody>>,
+    joints: Query<Entity, JointFilter>,
+    mut gravity: ResMut<Gravity>,
+    (mut attraction_factor, mut attraction_theta): (
+        ResMut<AttractionFactor>,
+        ResMut<AttractionTheta>,
+    ),
+    mut draw_settings: ResMut<DrawSettings>,
+    mut joint_settings: ResMut<JointSettings>,
+    mut history: ResMut<History>,
+) {
+    let path = &trig.event().0;
+    let scene = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| ron::from_str::<Scene>(&s).map_err(|e| e.to_string()));
+    let scene = match scene {
+        Ok(scene) if scene.version <= SCENE_VERSION => scene,
+        Ok(scene) => {
+            lapis_data.buffer.push_str(&format!(
+                "\n// error: scene version {} is newer than supported ({SCENE_VERSION})",
+                scene.version
+            ));
+            return;
+        }
+        Err(err) => {
+            lapis_data
+                .buffer
+                .push_str(&format!("\n// error: couldn't load scene: {err}"));
+            return;
+        }
+    };
+    for e in joints.iter().chain(bodies.iter()) {
+        commands.entity(e).despawn();
+    }
+    // the history refers to the despawned entities
+    history.clear();
+    gravity.0 = scene.gravity;
+    attraction_factor.0 = scene.attraction;
+    attraction_theta.0 = scene.attraction_theta;
+    *draw_settings = scene.draw_settings;
+    *joint_settings = scene.joint_settings;
+    // saved entity bits -> new entities
+    let mut map = HashMap::new();
+    for body in &scene.bodies {
+        let e = spawn_body(&mut commands, &mut meshes, &mut materials, body);
+        map.insert(body.id, e);
+    }
+    for joint in &scene.joints {
+        if let Some(e) = spawn_joint_data(&mut commands, joint, &map) {
+            map.insert(joint.id(), e);
+        }
+    }
+    restore_vars(&mut lapis_data, scene.vars, &map);
+    lapis_data.buffer.push_str(&format!(
+        "\n// loaded {} objects and {} joints from {path}",
+        scene.bodies.len(),
+        scene.joints.len()
+    ));
+}
@@ -0,0 +1,437 @@
+use avian2d::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+};
+use serde::{Deserialize, Serialize};
+
+/// more than this and freehand outlines are thinned out
+const MAX_VERTICES: usize = 256;
+
+/// half the thickness of the drawn line of walls (the collider has none)
+const WALL_WIDTH: f32 = 1.5;
+
+/// the outline of an object, in local units (the transform's scale is its size)
+/// the mesh and collider are both built from this
+#[derive(Component, Reflect, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum Shape {
+    /// a regular polygon with radius 1 and this many sides
+    Regular(u32),
+    /// a 2x2 square (so rx and ry are the half width and half height)
+    Rectangle,
+    /// a capsule with radius 1 and a straight part this long (along y)
+    Capsule(f32),
+    /// a simple polygon (convex or concave), counter-clockwise
+    /// build these with `Shape::polygon`
+    Polygon(Vec<Vec2>),
+    /// an open chain of line segments (a wall), build these with `Shape::wall`
+    Wall(Vec<Vec2>),
+}
+
+impl Shape {
+    /// a polygon from an outline (in any winding order)
+    /// returns the shape (centered on its centroid) and that centroid,
+    /// or None if there aren't enough distinct points to enclose an area.
+    /// self-intersecting outlines are replaced with their convex hull
+    pub fn polygon(points: &[Vec2]) -> Option<(Shape, Vec2)> {
+        let mut outline: Vec<Vec2> = Vec::new();
+        for p in points {
+            if p.is_finite() && outline.last().is_none_or(|l| l.distance_squared(*p) > 1e-6) {
+                outline.push(*p);
+            }
+        }
+        while outline.len() > 1 && outline[0].distance_squared(outline[outline.len() - 1]) <= 1e-6 {
+            outline.pop();
+        }
+        if outline.len() > MAX_VERTICES {
+            let step = outline.len() as f32 / MAX_VERTICES as f32;
+            outline = (0..MAX_VERTICES)
+                .map(|i| outline[(i as f32 * step) as usize])
+                .collect();
+        }
+        if self_intersects(&outline) {
+            outline = convex_hull(&outline);
+        }
+        let area = signed_area(&outline);
+        if outline.len() < 3 || area.abs() < 1e-3 {
+            return None;
+        }
+        if area < 0. {
+            outline.reverse();
+        }
+        let center = centroid(&outline);
+        let local = outline.into_iter().map(|p| p - center).collect();
+        Some((Shape::Polygon(local), center))
+    }
+
+    /// a chain of segments through these points
+    /// returns the shape (centered on its bounding box) and that center,
+    /// or None if there are fewer than 2 distinct points
+    pub fn wall(points: &[Vec2]) -> Option<(Shape, Vec2)> {
+        let mut chain: Vec<Vec2> = Vec::new();
+        for p in points {
+            if p.is_finite() && chain.last().is_none_or(|l| l.distance_squared(*p) > 1e-6) {
+                chain.push(*p);
+            }
+        }
+        chain.truncate(MAX_VERTICES);
+        if chain.len() < 2 {
+            return None;
+        }
+        let (min, max) = bounds(&chain);
+        let center = (min + max) / 2.;
+        let local = chain.into_iter().map(|p| p - center).collect();
+        Some((Shape::Wall(local), center))
+    }
+
+    /// number of sides for regular polygons, rectangles, and polygons
+    pub fn sides(&self) -> Option<u32> {
+        match self {
+            Shape::Regular(n) => Some(*n),
+            Shape::Rectangle => Some(4),
+            Shape::Capsule(_) => None,
+            Shape::Polygon(v) => Some(v.len() as u32),
+            Shape::Wall(_) => None,
+        }
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        match self {
+            Shape::Regular(n) => RegularPolygon::new(1., (*n).clamp(3, 512)).into(),
+            Shape::Rectangle => Rectangle::new(2., 2.).into(),
+            Shape::Capsule(len) => Capsule2d::new(1., len.max(0.)).into(),
+            Shape::Polygon(v) => polygon_mesh(v),
+            Shape::Wall(v) => wall_mesh(v),
+        }
+    }
+
+    pub fn collider(&self) -> Collider {
+        match self {
+            Shape::Regular(n) => Collider::regular_polygon(1., (*n).clamp(3, 512)),
+            Shape::Rectangle => Collider::rectangle(2., 2.),
+            Shape::Capsule(len) => Collider::capsule(1., len.max(0.)),
+            Shape::Polygon(v) if is_convex(v) => {
+                Collider::convex_hull(v.clone()).unwrap_or_else(|| Collider::circle(1.))
+            }
+            Shape::Polygon(v) => {
+                let n = v.len() as u32;
+                let indices = (0..n).map(|i| [i, (i + 1) % n]).collect();
+                Collider::convex_decomposition(v.clone(), indices)
+            }
+            Shape::Wall(v) if v.len() == 2 => Collider::segment(v[0], v[1]),
+            Shape::Wall(v) => Collider::polyline(v.clone(), None),
+        }
+    }
+
+    /// the lapis method that gives an object this shape
+    pub fn method(&self) -> String {
+        match self {
+            Shape::Regular(n) => format!("sides({n})"),
+            Shape::Rectangle => String::from("rectangle()"),
+            Shape::Capsule(len) => format!("capsule({len})"),
+            Shape::Polygon(v) => {
+                let coords: Vec<String> = v.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                format!("poly([{}])", coords.join(","))
+            }
+            Shape::Wall(v) => {
+                let coords: Vec<String> = v.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                format!("wall([{}])", coords.join(","))
+            }
+        }
+    }
+}
+
+fn polygon_mesh(points: &[Vec2]) -> Mesh {
+    let positions: Vec<[f32; 3]> = points.iter().map(|p| [p.x, p.y, 0.]).collect();
+    let normals = vec![[0., 0., 1.]; points.len()];
+    let (min, max) = bounds(points);
+    let size = (max - min).max(Vec2::splat(f32::EPSILON));
+    let uvs: Vec<[f32; 2]> = points
+        .iter()
+        .map(|p| {
+            let uv = (*p - min) / size;
+            [uv.x, 1. - uv.y]
+        })
+        .collect();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(triangulate(points)))
+}
+
+/// a quad for each segment, extended past the ends so corners don't show gaps
+fn wall_mesh(points: &[Vec2]) -> Mesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for pair in points.windows(2) {
+        let dir = (pair[1] - pair[0]).normalize_or_zero() * WALL_WIDTH;
+        let (a, b) = (pair[0] - dir, pair[1] + dir);
+        let n = dir.perp();
+        let i = positions.len() as u32;
+        for p in [a - n, b - n, b + n, a + n] {
+            positions.push([p.x, p.y, 0.]);
+        }
+        indices.extend([i, i + 1, i + 2, i, i + 2, i + 3]);
+    }
+    let normals = vec![[0., 0., 1.]; positions.len()];
+    let uvs = vec![[0., 0.]; positions.len()];
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+fn bounds(points: &[Vec2]) -> (Vec2, Vec2) {
+    points.iter().fold(
+        (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+        |(min, max), p| (min.min(*p), max.max(*p)),
+    )
+}
+
+/// ear clipping of a simple counter-clockwise polygon
+fn triangulate(points: &[Vec2]) -> Vec<u32> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::new();
+    'clip: while remaining.len() > 3 {
+        let n = remaining.len();
+        for i in 0..n {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (pa, pb, pc) = (points[a], points[b], points[c]);
+            // reflex (or flat) corners aren't ears
+            if (pb - pa).perp_dot(pc - pb) <= 0. {
+                continue;
+            }
+            let blocked = remaining
+                .iter()
+                .any(|&j| j != a && j != b && j != c && in_triangle(points[j], pa, pb, pc));
+            if !blocked {
+                triangles.extend([a as u32, b as u32, c as u32]);
+                remaining.remove(i);
+                continue 'clip;
+            }
+        }
+        // no ears left (only possible with degenerate outlines), fan the rest
+        break;
+    }
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.extend([remaining[0], remaining[i], remaining[i + 1]].map(|x| x as u32));
+    }
+    triangles
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(p - a);
+    let d2 = (c - b).perp_dot(p - b);
+    let d3 = (a - c).perp_dot(p - c);
+    d1 >= 0. && d2 >= 0. && d3 >= 0.
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].perp_dot(points[(i + 1) % n]))
+        .sum::<f32>()
+        / 2.
+}
+
+fn centroid(points: &[Vec2]) -> Vec2 {
+    let n = points.len();
+    let area = signed_area(points);
+    let mut c = Vec2::ZERO;
+    for i in 0..n {
+        let (p, q) = (points[i], points[(i + 1) % n]);
+        c += (p + q) * p.perp_dot(q);
+    }
+    c / (6. * area)
+}
+
+/// counter-clockwise with no reflex corners
+fn is_convex(points: &[Vec2]) -> bool {
+    let n = points.len();
+    (0..n).all(|i| {
+        let (a, b, c) = (points[i], points[(i + 1) % n], points[(i + 2) % n]);
+        (b - a).perp_dot(c - b) >= 0.
+    })
+}
+
+fn self_intersects(points: &[Vec2]) -> bool {
+    let n = points.len();
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        // skip the neighbors (they share a vertex)
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let (c, d) = (points[j], points[(j + 1) % n]);
+            if segments_cross(a, b, c, d) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn segments_cross(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let d1 = (b - a).perp_dot(c - a);
+    let d2 = (b - a).perp_dot(d - a);
+    let d3 = (d - c).perp_dot(a - c);
+    let d4 = (d - c).perp_dot(b - c);
+    d1 * d2 < 0. && d3 * d4 < 0.
+}
+
+/// monotone chain, counter-clockwise
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let half = |points: &mut dyn Iterator<Item = &Vec2>| {
+        let mut chain: Vec<Vec2> = Vec::new();
+        for p in points {
+            while let [.., a, b] = chain[..]
+                && (b - a).perp_dot(*p - b) <= 0.
+            {
+                chain.pop();
+            }
+            chain.push(*p);
+        }
+        // the last point starts the other half
+        chain.pop();
+        chain
+    };
+    let mut hull = half(&mut sorted.iter());
+    hull.extend(half(&mut sorted.iter().rev()));
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(shape: Option<(Shape, Vec2)>) -> Vec<Vec2> {
+        match shape {
+            Some((Shape::Polygon(v), _)) => v,
+            other => panic!("not a polygon: {other:?}"),
+        }
+    }
+
+    /// the summed area of the triangles
+    fn mesh_area(points: &[Vec2], indices: &[u32]) -> f32 {
+        indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| points[t[i] as usize]);
+                (b - a).perp_dot(c - a) / 2.
+            })
+            .sum()
+    }
+
+    const SQUARE: [Vec2; 4] = [
+        Vec2::new(0., 0.),
+        Vec2::new(2., 0.),
+        Vec2::new(2., 2.),
+        Vec2::new(0., 2.),
+    ];
+
+    #[test]
+    fn winding() {
+        let mut clockwise = SQUARE;
+        clockwise.reverse();
+        for points in [SQUARE, clockwise] {
+            let (shape, center) = Shape::polygon(&points).unwrap();
+            assert_eq!(center, Vec2::ONE);
+            let v = outline(Some((shape, center)));
+            assert_eq!(v.len(), 4);
+            assert!((signed_area(&v) - 4.).abs() < 1e-5);
+            assert!(is_convex(&v));
+        }
+    }
+
+    #[test]
+    fn duplicates_and_collinear() {
+        let points = [
+            SQUARE[0],
+            SQUARE[0],
+            Vec2::new(1., 0.),
+            SQUARE[1],
+            SQUARE[2],
+            SQUARE[2],
+            SQUARE[3],
+            // closing the outline
+            SQUARE[0],
+        ];
+        let v = outline(Shape::polygon(&points));
+        // repeats are dropped, the point on the edge stays
+        assert_eq!(v.len(), 5);
+        let indices = triangulate(&v);
+        assert_eq!(indices.len() / 3, v.len() - 2);
+        assert!((mesh_area(&v, &indices) - 4.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn concave() {
+        // an L and a 5 pointed star
+        let l = [
+            Vec2::new(0., 0.),
+            Vec2::new(3., 0.),
+            Vec2::new(3., 1.),
+            Vec2::new(1., 1.),
+            Vec2::new(1., 3.),
+            Vec2::new(0., 3.),
+        ];
+        let star: Vec<Vec2> = (0..10)
+            .map(|i| {
+                let r = if i % 2 == 0 { 1. } else { 0.4 };
+                Vec2::from_angle(i as f32 * std::f32::consts::TAU / 10.) * r
+            })
+            .collect();
+        for points in [&l[..], &star] {
+            let v = outline(Shape::polygon(points));
+            assert_eq!(v.len(), points.len());
+            assert!(!is_convex(&v));
+            let indices = triangulate(&v);
+            assert_eq!(indices.len() / 3, v.len() - 2);
+            assert!((mesh_area(&v, &indices) - signed_area(&v)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn self_intersecting_becomes_hull() {
+        // a bow tie
+        let points = [SQUARE[0], SQUARE[2], SQUARE[1], SQUARE[3]];
+        assert!(self_intersects(&points));
+        let v = outline(Shape::polygon(&points));
+        assert_eq!(v.len(), 4);
+        assert!(!self_intersects(&v));
+        assert!(is_convex(&v));
+        assert!((signed_area(&v) - 4.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn degenerate() {
+        let line = [Vec2::ZERO, Vec2::X, Vec2::new(2., 0.), Vec2::new(3., 0.)];
+        let nan = [Vec2::ZERO, Vec2::X, Vec2::NAN];
+        assert!(Shape::polygon(&[]).is_none());
+        assert!(Shape::polygon(&[Vec2::ONE; 5]).is_none());
+        assert!(Shape::polygon(&SQUARE[..2]).is_none());
+        assert!(Shape::polygon(&line).is_none());
+        assert!(Shape::polygon(&nan).is_none());
+        assert!(Shape::polygon(&[Vec2::ZERO, Vec2::X, Vec2::new(1., 1e-5)]).is_none());
+    }
+}
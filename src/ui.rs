@@ -1,4 +1,12 @@
-use crate::{interaction::*, lapis::*, objects::*};
+use crate::{
+    audio::*,
+    history::History,
+    interaction::*,
+    joints::{JointFilter, JointProperty},
+    lapis::*,
+    objects::*,
+    scene::*,
+};
 use avian2d::prelude::*;
 use bevy::{
     app::{App, Plugin},
@@ -11,7 +19,8 @@ use bevy::{
 };
 use bevy_egui::{EguiContexts, EguiPlugin, EguiPrimaryContextPass, egui, egui::*};
 use egui_extras::syntax_highlighting::*;
-use std::sync::Arc;
+use fundsp::hacker::AudioUnit;
+use std::{collections::HashMap, ops::RangeInclusive, sync::Arc};
 
 pub struct UiPlugin;
 
@@ -22,6 +31,8 @@ impl Plugin for UiPlugin {
             .insert_resource(ScaleFactor(1.))
             .insert_resource(FontSizes(12., 8.))
             .init_resource::<UpdateCode>()
+            .insert_resource(ScenePath(String::from("scene.ron")))
+            .insert_resource(RecordPath(String::from("take.wav"), false))
             .add_systems(Update, toggle_help)
             .add_systems(EguiPrimaryContextPass, egui_ui);
     }
@@ -30,13 +41,20 @@ impl Plugin for UiPlugin {
 #[derive(Resource, Default)]
 struct UpdateCode(String);
 
+#[derive(Resource)]
+struct ScenePath(String);
+
+/// (path, 32-bit float?)
+#[derive(Resource)]
+struct RecordPath(String, bool);
+
 #[derive(Resource)]
 pub struct FontSizes(pub f32, pub f32);
 
 #[derive(Resource, Default)]
 struct InsertComponents {
     links: String,
-    code: (String, String),
+    code: (String, String, String),
 }
 
 #[derive(Resource)]
@@ -55,10 +73,13 @@ fn egui_ui(
     mut lapis: Lapis,
     mut draw: ResMut<DrawSettings>,
     mut gravity: ResMut<Gravity>,
-    mut selected: Query<(&mut Code, &mut Links), With<Selected>>,
+    mut selected: Query<(&mut Code, &mut Links, Option<&LinkBindings>), With<Selected>>,
     mut update_code: ResMut<UpdateCode>,
     mut mode: ResMut<Mode>,
-    mut attraction_factor: ResMut<AttractionFactor>,
+    (mut attraction_factor, mut attraction_theta): (
+        ResMut<AttractionFactor>,
+        ResMut<AttractionTheta>,
+    ),
     mut joint: ResMut<JointSettings>,
     mut insert: ResMut<InsertComponents>,
     cursor: Res<CursorInfo>,
@@ -69,7 +90,13 @@ fn egui_ui(
         ResMut<ClearColor>,
     ),
     (mut bloom, mut tonemapping): (Query<&mut Bloom>, Query<&mut Tonemapping>),
-    mut font_sizes: ResMut<FontSizes>,
+    (mut font_sizes, mut scene_path, mut record_path, recording): (
+        ResMut<FontSizes>,
+        ResMut<ScenePath>,
+        ResMut<RecordPath>,
+        Option<Res<Recording>>,
+    ),
+    joint_query: Query<Entity, JointFilter>,
 ) {
     let Ok(ctx) = contexts.ctx_mut() else { return };
     let theme = CodeTheme::dark(12.);
@@ -104,7 +131,7 @@ fn egui_ui(
         });
     }
     if !lapis.time.is_paused() {
-        lapis.quiet_eval(&update_code.0);
+        lapis.as_hook(|lapis| lapis.quiet_eval(&update_code.0));
     }
     egui::Window::new("mode")
         .default_width(270.)
@@ -119,6 +146,17 @@ fn egui_ui(
             });
             ui.separator();
             if *mode == Mode::Draw {
+                ui.horizontal(|ui| {
+                    ui.label("shape");
+                    ui.selectable_value(&mut draw.shape, DrawShape::Regular, "Regular");
+                    ui.selectable_value(&mut draw.shape, DrawShape::Rectangle, "Rectangle");
+                    ui.selectable_value(&mut draw.shape, DrawShape::Capsule, "Capsule");
+                    ui.selectable_value(&mut draw.shape, DrawShape::Freehand, "Freehand")
+                        .on_hover_text("trace an outline, it can be concave");
+                    ui.selectable_value(&mut draw.shape, DrawShape::Wall, "Wall").on_hover_text(
+                        "click to add points, enter to finish\nbackspace removes the last point, escape clears",
+                    );
+                });
                 Grid::new("draw_grid").show(ui, |ui| {
                     ui.label("rigid body");
                     ui.horizontal(|ui| {
@@ -129,9 +167,11 @@ fn egui_ui(
                     ui.label("collision layer");
                     ui.add(DragValue::new(&mut draw.collision_layer).range(0..=31));
                     ui.end_row();
-                    ui.label("sides");
-                    ui.add(DragValue::new(&mut draw.sides).range(3..=512));
-                    ui.end_row();
+                    if draw.shape == DrawShape::Regular {
+                        ui.label("sides");
+                        ui.add(DragValue::new(&mut draw.sides).range(3..=512));
+                        ui.end_row();
+                    }
                     ui.label("color");
                     ui.color_edit_button_srgba_unmultiplied(&mut draw.color);
                     ui.end_row();
@@ -172,6 +212,7 @@ fn egui_ui(
                     links_line(ui, &mut draw.links);
                     code_line_i(ui, &mut draw.code.0, &mut layouter);
                     code_line_f(ui, &mut draw.code.1, &mut layouter);
+                    code_line_c(ui, &mut draw.code.2, &mut layouter);
                 });
             } else if *mode == Mode::Edit {
                 if lapis.time.is_paused() {
@@ -191,6 +232,57 @@ fn egui_ui(
                     ui.label("attraction");
                     ui.add(DragValue::new(&mut attraction_factor.0).speed(0.01))
                         .on_hover_text("how much objects gravitate towards each other");
+                    ui.end_row();
+                    ui.label("theta");
+                    ui.add(
+                        DragValue::new(&mut attraction_theta.0)
+                            .range(0.0..=2.0)
+                            .speed(0.01),
+                    )
+                    .on_hover_text(
+                        "accuracy of attraction\n0 = exact (slow with many objects)\nhigher = faster approximation (0.5 is a good start)",
+                    );
+                });
+                ui.collapsing("scene", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("path");
+                        ui.text_edit_singleline(&mut scene_path.0);
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("save").clicked() {
+                            lapis.commands.trigger(SaveScene(scene_path.0.clone()));
+                        }
+                        if ui
+                            .button("load")
+                            .on_hover_text("replaces all objects, joints, and settings")
+                            .clicked()
+                        {
+                            lapis.commands.trigger(LoadScene(scene_path.0.clone()));
+                        }
+                    });
+                });
+                ui.collapsing("record", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("path");
+                        ui.text_edit_singleline(&mut record_path.0);
+                    });
+                    ui.horizontal(|ui| {
+                        if recording.is_some() {
+                            if ui.button("stop").clicked() {
+                                lapis.commands.trigger(RecordStop);
+                            }
+                        } else if ui
+                            .button("record")
+                            .on_hover_text("record the output stream to a wav file")
+                            .clicked()
+                        {
+                            lapis.commands.trigger(RecordStart {
+                                path: record_path.0.clone(),
+                                float: record_path.1,
+                            });
+                        }
+                        ui.toggle_value(&mut record_path.1, "32-bit float?");
+                    });
                 });
                 ui.collapsing("ui settings", |ui| {
                     Grid::new("ui_settings_grid").show(ui, |ui| {
@@ -333,18 +425,25 @@ fn egui_ui(
                 match n {
                     0 => {}
                     1 => {
-                        let (mut code, mut links) = selected.single_mut().unwrap();
+                        let (mut code, mut links, bindings) = selected.single_mut().unwrap();
                         ScrollArea::vertical().show(ui, |ui| {
                             links_line(ui, &mut links.0);
+                            if let Some(bindings) = bindings {
+                                for error in &bindings.errors {
+                                    ui.colored_label(Color32::LIGHT_RED, error);
+                                }
+                            }
                             code_line_i(ui, &mut code.0, &mut layouter);
                             code_line_f(ui, &mut code.1, &mut layouter);
+                            code_line_c(ui, &mut code.2, &mut layouter);
                         });
                     }
                     _ => {
                         if ui.button("apply to selected").clicked() {
-                            for (mut code, mut links) in selected.iter_mut() {
+                            for (mut code, mut links, _) in selected.iter_mut() {
                                 code.0 = insert.code.0.clone();
                                 code.1 = insert.code.1.clone();
+                                code.2 = insert.code.2.clone();
                                 links.0 = insert.links.clone();
                             }
                         }
@@ -352,6 +451,7 @@ fn egui_ui(
                             links_line(ui, &mut insert.links);
                             code_line_i(ui, &mut insert.code.0, &mut layouter);
                             code_line_f(ui, &mut insert.code.1, &mut layouter);
+                            code_line_c(ui, &mut insert.code.2, &mut layouter);
                         });
                     }
                 }
@@ -446,8 +546,10 @@ fn egui_ui(
                     .on_hover_text("enable key repeat events");
             });
             let theme = CodeTheme::dark(font_sizes.1);
+            let size = font_sizes.1;
             let mut layouter = |ui: &Ui, string: &dyn TextBuffer, wrap_width: f32| {
-                let mut layout_job = highlight(ui.ctx(), ui.style(), &theme, string.as_str(), "rs");
+                let mut layout_job =
+                    highlight_output(ui.ctx(), ui.style(), &theme, string.as_str(), size);
                 layout_job.wrap.max_width = wrap_width;
                 ui.fonts(|f| f.layout_job(layout_job))
             };
@@ -509,6 +611,14 @@ fn egui_ui(
                 });
             });
         });
+    if *mode == Mode::Edit && !lapis.selected_query.is_empty() {
+        egui::Window::new("inspector")
+            .pivot(Align2::CENTER_TOP)
+            .default_pos([w / 2., 15.])
+            .show(ctx, |ui| {
+                inspector_window_function(ui, &mut lapis, &joint_query);
+            });
+    }
     egui::Window::new("info")
         .default_open(false)
         .pivot(Align2::LEFT_BOTTOM)
@@ -527,8 +637,21 @@ fn egui_ui(
                 if ui.button("about").clicked() {
                     lapis.data.about = !lapis.data.about;
                 }
+                if ui
+                    .button("vars")
+                    .on_hover_text("browse lapis variables")
+                    .clicked()
+                {
+                    lapis.data.vars = !lapis.data.vars;
+                }
             });
         });
+    // the window borrows all of lapis
+    let mut vars = lapis.data.vars;
+    egui::Window::new("variables")
+        .open(&mut vars)
+        .show(ctx, |ui| variables_window_function(ui, &mut lapis));
+    lapis.data.vars = vars;
     egui::Window::new("about")
         .open(&mut lapis.data.about)
         .show(ctx, about_window_function);
@@ -569,7 +692,12 @@ fn code_line_i(
             "evaluated when this object starts colliding with another\n
 these placeholders will be substituted:
 $id for this entity's id
-$other for the other entity's id",
+$other for the other entity's id
+$impulse for the total normal impulse
+$speed for the relative speed along the normal (as they hit)
+$x, $y for the contact point
+$nx, $ny for the normal (pointing to the other)
+$slide for the sliding speed (along the surface)",
         );
     });
 }
@@ -593,9 +721,537 @@ fn code_line_f(
             "evaluated when this object stops colliding with another\n
 these placeholders will be substituted:
 $id for this entity's id
-$other for the other entity's id",
+$other for the other entity's id
+(the contact placeholders are all 0 here)",
+        );
+    });
+}
+
+fn code_line_c(
+    ui: &mut Ui,
+    buffer: &mut String,
+    layouter: &mut dyn FnMut(&Ui, &dyn TextBuffer, f32) -> Arc<Galley>,
+) {
+    ui.horizontal(|ui| {
+        ui.label("code_c");
+        ui.add(
+            TextEdit::multiline(buffer)
+                .hint_text("while colliding")
+                .code_editor()
+                .desired_rows(1)
+                .desired_width(f32::INFINITY)
+                .layouter(layouter),
+        )
+        .on_hover_text(
+            "evaluated every physics step while this object touches another\n
+these placeholders will be substituted:
+$id for this entity's id
+$other for the other entity's id
+$impulse, $speed, $x, $y, $nx, $ny, $slide (like in code_i)
+$duration for the seconds since they started touching",
+        );
+    });
+}
+
+/// (label, property, drag speed, range) of the object fields in the inspector
+const OBJECT_FIELDS: &[(&str, PropertyKind, f64, RangeInclusive<f32>)] = &[
+    ("x", PropertyKind::X, 1., f32::NEG_INFINITY..=f32::INFINITY),
+    ("y", PropertyKind::Y, 1., f32::NEG_INFINITY..=f32::INFINITY),
+    ("z", PropertyKind::Z, 0.1, f32::NEG_INFINITY..=f32::INFINITY),
+    (
+        "rx",
+        PropertyKind::Rx,
+        0.1,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "ry",
+        PropertyKind::Ry,
+        0.1,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "rot",
+        PropertyKind::Rot,
+        0.01,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    ("mass", PropertyKind::Mass, 1., 0.0..=f32::INFINITY),
+    ("inertia", PropertyKind::Inertia, 1., 0.0..=f32::INFINITY),
+    (
+        "vx",
+        PropertyKind::Vx,
+        1.,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "vy",
+        PropertyKind::Vy,
+        1.,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "va",
+        PropertyKind::Va,
+        0.01,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "restitution",
+        PropertyKind::Restitution,
+        0.01,
+        0.0..=f32::INFINITY,
+    ),
+    (
+        "friction",
+        PropertyKind::Friction,
+        0.01,
+        0.0..=f32::INFINITY,
+    ),
+    (
+        "linear damping",
+        PropertyKind::LinDamp,
+        0.01,
+        0.0..=f32::INFINITY,
+    ),
+    (
+        "angular damping",
+        PropertyKind::AngDamp,
+        0.01,
+        0.0..=f32::INFINITY,
+    ),
+    (
+        "center of mass x",
+        PropertyKind::Cmx,
+        0.1,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    (
+        "center of mass y",
+        PropertyKind::Cmy,
+        0.1,
+        f32::NEG_INFINITY..=f32::INFINITY,
+    ),
+    ("hue", PropertyKind::H, 1., 0.0..=360.),
+    ("saturation", PropertyKind::S, 0.01, 0.0..=1.),
+    ("lightness", PropertyKind::L, 0.01, 0.0..=1.),
+    ("alpha", PropertyKind::A, 0.01, 0.0..=1.),
+    ("sides", PropertyKind::Sides, 0.1, 3.0..=512.),
+    ("tail", PropertyKind::Tail, 1., 0.0..=36000.),
+    ("collision layer", PropertyKind::Layer, 0.1, 0.0..=31.),
+];
+
+type JointGet = fn(&JointData) -> Option<f32>;
+type JointSet = fn(&JointData, f32) -> JointProperty;
+
+/// (label, read from a joint, the property that sets it) of the joint fields in the inspector
+/// fields of pairs keep the other value of each joint
+const JOINT_FIELDS: &[(&str, JointGet, JointSet)] = &[
+    (
+        "compliance",
+        |j| Some(j.compliance()),
+        |_, v| JointProperty::Compliance(v),
+    ),
+    (
+        "anchor1 x",
+        |j| Some(j.anchors().0.x),
+        |j, v| JointProperty::Anchor1(v, j.anchors().0.y),
+    ),
+    (
+        "anchor1 y",
+        |j| Some(j.anchors().0.y),
+        |j, v| JointProperty::Anchor1(j.anchors().0.x, v),
+    ),
+    (
+        "anchor2 x",
+        |j| Some(j.anchors().1.x),
+        |j, v| JointProperty::Anchor2(v, j.anchors().1.y),
+    ),
+    (
+        "anchor2 y",
+        |j| Some(j.anchors().1.y),
+        |j, v| JointProperty::Anchor2(j.anchors().1.x, v),
+    ),
+    (
+        "min",
+        |j| Some(j.limits()?.0),
+        |j, v| JointProperty::Limits(v, j.limits().unwrap_or_default().1),
+    ),
+    (
+        "max",
+        |j| Some(j.limits()?.1),
+        |j, v| JointProperty::Limits(j.limits().unwrap_or_default().0, v),
+    ),
+    (
+        "rest",
+        |j| match j {
+            JointData::Distance { rest, .. } => Some(*rest),
+            _ => None,
+        },
+        |_, v| JointProperty::Rest(v),
+    ),
+    (
+        "free axis x",
+        |j| match j {
+            JointData::Prismatic { free_axis, .. } => Some(free_axis.x),
+            _ => None,
+        },
+        |j, v| match j {
+            JointData::Prismatic { free_axis, .. } => JointProperty::FreeAxis(v, free_axis.y),
+            _ => JointProperty::FreeAxis(v, 0.),
+        },
+    ),
+    (
+        "free axis y",
+        |j| match j {
+            JointData::Prismatic { free_axis, .. } => Some(free_axis.y),
+            _ => None,
+        },
+        |j, v| match j {
+            JointData::Prismatic { free_axis, .. } => JointProperty::FreeAxis(free_axis.x, v),
+            _ => JointProperty::FreeAxis(0., v),
+        },
+    ),
+    (
+        "motor velocity",
+        |j| Some(j.motor()?.target_velocity),
+        |_, v| JointProperty::MotorVelocity(v),
+    ),
+    (
+        "motor position",
+        |j| Some(j.motor()?.target_position),
+        |_, v| JointProperty::MotorPosition(v),
+    ),
+    (
+        "motor max",
+        |j| Some(j.motor()?.max_force),
+        |_, v| JointProperty::MotorMax(v),
+    ),
+    (
+        "motor stiffness",
+        |j| Some(j.motor()?.stiffness),
+        |_, v| JointProperty::MotorStiffness(v),
+    ),
+    (
+        "motor damping",
+        |j| Some(j.motor()?.damping),
+        |_, v| JointProperty::MotorDamping(v),
+    ),
+    (
+        "break force",
+        |j| Some(j.breakable().map_or(0., |b| b.force)),
+        |_, v| JointProperty::BreakForce(v),
+    ),
+    (
+        "break torque",
+        |j| Some(j.breakable().map_or(0., |b| b.torque)),
+        |_, v| JointProperty::BreakTorque(v),
+    ),
+];
+
+/// editable fields of the selected objects and the joints between them
+fn inspector_window_function(
+    ui: &mut Ui,
+    lapis: &mut Lapis,
+    joint_query: &Query<Entity, JointFilter>,
+) {
+    let objects: Vec<Entity> = lapis.selected_query.iter().collect();
+    let joints: Vec<(Entity, JointData)> = joint_query
+        .iter()
+        .filter_map(|e| Some((e, capture_joint(e, lapis)?)))
+        .filter(|(_, j)| {
+            let (e1, e2) = j.bodies();
+            objects.iter().any(|e| e.to_bits() == e1) && objects.iter().any(|e| e.to_bits() == e2)
+        })
+        .collect();
+    ScrollArea::vertical().show(ui, |ui| {
+        Grid::new("inspector_grid").show(ui, |ui| {
+            for (label, kind, speed, range) in OBJECT_FIELDS {
+                let values: Vec<f32> = objects.iter().filter_map(|e| kind.get(*e, lapis)).collect();
+                if values.is_empty() {
+                    continue;
+                }
+                ui.label(*label);
+                if let Some(v) = mixed_drag(ui, &values, *speed, range.clone(), &mut lapis.history)
+                {
+                    // (sides only applies to objects that have sides)
+                    for e in &objects {
+                        if kind.get(*e, lapis).is_some() {
+                            lapis.history.touch(&mut lapis.commands, *e);
+                            kind.set(*e, v, lapis);
+                        }
+                    }
+                }
+                ui.end_row();
+            }
+            for (label, kind) in [
+                ("dynamic", PropertyKind::Dynamic),
+                ("sensor", PropertyKind::Sensor),
+            ] {
+                let values: Vec<bool> = objects
+                    .iter()
+                    .filter_map(|e| Some(kind.get(*e, lapis)? > 0.))
+                    .collect();
+                let Some(&first) = values.first() else {
+                    continue;
+                };
+                let mut checked = first;
+                let mixed = values.iter().any(|b| *b != first);
+                ui.label(label);
+                if ui
+                    .add(Checkbox::without_text(&mut checked).indeterminate(mixed))
+                    .changed()
+                {
+                    for e in &objects {
+                        lapis.history.touch(&mut lapis.commands, *e);
+                        kind.set(*e, f32::from(checked), lapis);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if joints.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.label(format!("joints: {}", joints.len()));
+        Grid::new("inspector_joint_grid").show(ui, |ui| {
+            for (label, get, set) in JOINT_FIELDS {
+                let values: Vec<f32> = joints.iter().filter_map(|(_, j)| get(j)).collect();
+                if values.is_empty() {
+                    continue;
+                }
+                ui.label(*label);
+                let range = f32::NEG_INFINITY..=f32::INFINITY;
+                if let Some(v) = mixed_drag(ui, &values, 0.01, range, &mut lapis.history) {
+                    for (e, j) in &joints {
+                        if get(j).is_some() {
+                            lapis.history.touch(&mut lapis.commands, *e);
+                            lapis.commands.trigger_targets(set(j, v), *e);
+                        }
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// a drag value for a field of several entities ("mixed" if their values differ)
+/// returns the new value when it's changed. a whole drag is one undoable edit
+/// (the history lets go of it when the mouse is released, even if this is gone by then)
+fn mixed_drag(
+    ui: &mut Ui,
+    values: &[f32],
+    speed: f64,
+    range: RangeInclusive<f32>,
+    history: &mut History,
+) -> Option<f32> {
+    let mut value = values[0];
+    let mixed = values.iter().any(|v| *v != value);
+    let mut drag = DragValue::new(&mut value).speed(speed).range(range);
+    if mixed {
+        drag = drag.custom_formatter(|_, _| String::from("mixed"));
+    }
+    let response = ui.add(drag);
+    if response.drag_started() {
+        history.hold = true;
+    } else if response.drag_stopped() {
+        history.hold = false;
+    }
+    response.changed().then_some(value)
+}
+
+/// every lapis variable grouped by type with its current value
+/// floats, bools, strings, and shared values can be edited in place
+fn variables_window_function(ui: &mut Ui, lapis: &mut Lapis) {
+    let mut dropped = None;
+    let mut drop_button = |ui: &mut Ui, k: &str| {
+        if ui.small_button("x").on_hover_text("drop").clicked() {
+            dropped = Some(k.to_string());
+        }
+    };
+    let data = &mut lapis.data;
+    let commands = &mut lapis.commands;
+    ScrollArea::vertical().show(ui, |ui| {
+        ui.collapsing(format!("floats ({})", data.fmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.fmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.add(DragValue::new(v).speed(0.01));
+                });
+            }
+        });
+        ui.collapsing(format!("shared ({})", data.smap.len()), |ui| {
+            for (k, v) in sorted(&mut data.smap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    let mut value = v.value();
+                    if ui.add(DragValue::new(&mut value).speed(0.01)).changed() {
+                        v.set(value);
+                    }
+                });
+            }
+        });
+        ui.collapsing(format!("bools ({})", data.bmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.bmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.checkbox(v, k.as_str());
+                });
+            }
+        });
+        ui.collapsing(format!("strings ({})", data.string_map.len()), |ui| {
+            for (k, v) in sorted(&mut data.string_map) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.text_edit_singleline(v);
+                });
+            }
+        });
+        ui.collapsing(format!("arrays ({})", data.vmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.vmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    let mut values: Vec<String> = v.iter().take(8).map(f32::to_string).collect();
+                    if v.len() > 8 {
+                        values.push(String::from("..."));
+                    }
+                    ui.label(format!("[{}]", values.join(", ")))
+                        .on_hover_text(format!("length: {}", v.len()));
+                });
+            }
+        });
+        ui.collapsing(format!("nets ({})", data.gmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.gmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!(
+                        "{} nodes, {} ins, {} outs",
+                        v.size(),
+                        v.inputs(),
+                        v.outputs()
+                    ));
+                });
+            }
+        });
+        ui.collapsing(format!("waves ({})", data.wmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.wmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!(
+                        "{} channels, {:.3} seconds, {} Hz",
+                        v.channels(),
+                        v.duration(),
+                        v.sample_rate()
+                    ));
+                });
+            }
+        });
+        ui.collapsing(format!("sequencers ({})", data.seqmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.seqmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!("{} outs", v.outputs()));
+                });
+            }
+        });
+        ui.collapsing(format!("entities ({})", data.entitymap.len()), |ui| {
+            for (k, v) in sorted(&mut data.entitymap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    let state = if commands.get_entity(*v).is_ok() {
+                        "alive"
+                    } else {
+                        "despawned"
+                    };
+                    ui.label(format!("{} ({state})", v.to_bits()));
+                });
+            }
+        });
+        ui.collapsing(
+            format!("atomic tables ({})", data.atomic_table_map.len()),
+            |ui| {
+                for (k, v) in sorted(&mut data.atomic_table_map) {
+                    ui.horizontal(|ui| {
+                        drop_button(ui, k);
+                        ui.label(k.as_str());
+                        ui.label(format!("{} values", v.len()));
+                    });
+                }
+            },
         );
+        ui.collapsing(format!("node ids ({})", data.idmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.idmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!("{v:?}"));
+                });
+            }
+        });
+        ui.collapsing(format!("event ids ({})", data.eventmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.eventmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!("{v:?}"));
+                });
+            }
+        });
+        ui.collapsing(format!("sources ({})", data.srcmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.srcmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!("{v:?}"));
+                });
+            }
+        });
+        ui.collapsing(format!("functions ({})", data.fnmap.len()), |ui| {
+            for (k, v) in sorted(&mut data.fnmap) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(format!("{k}({})", v.params.join(", ")))
+                        .on_hover_text(v.source(k));
+                });
+            }
+        });
+        ui.collapsing(format!("timers ({})", data.timer_map.len()), |ui| {
+            for (k, v) in sorted(&mut data.timer_map) {
+                ui.horizontal(|ui| {
+                    drop_button(ui, k);
+                    ui.label(k.as_str());
+                    ui.label(format!("{v:?}"));
+                    if data.timers.is_active(*v) {
+                        if ui.small_button("cancel").clicked() {
+                            data.timers.cancel(*v);
+                        }
+                    } else {
+                        ui.label("(done)");
+                    }
+                });
+            }
+        });
     });
+    if let Some(k) = dropped {
+        lapis.drop(&k);
+    }
+}
+
+/// the entries of a map ordered by name (so the list doesn't shuffle)
+fn sorted<V>(map: &mut HashMap<String, V>) -> Vec<(&String, &mut V)> {
+    let mut entries: Vec<_> = map.iter_mut().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries
 }
 
 fn about_window_function(ui: &mut Ui) {
@@ -663,6 +1319,10 @@ fn help_window_function(ui: &mut Ui) {
 - hold the right mouse button while one object is selected
   to track it with the camera
 - press ctrl+c to copy selected objects/joints as commands
+- press ctrl+z to undo and ctrl+shift+z to redo (drawing,
+  deleting, dragging, and edits typed in the input)
+- the vars button in the info window lists every lapis
+  variable (floats, bools, strings, and shareds are editable)
 - in edit mode:
     - press ctrl+a to select all objects
     - when selecting objects, hold shift to add to selection
@@ -670,14 +1330,24 @@ fn help_window_function(ui: &mut Ui) {
     - press delete to delete selected objects
     - press shift+delete to delete any joints connected to
       selected objects
+    - the inspector window edits the selected objects and
+      the joints between them (\"mixed\" when their values differ)
     - if you don't need objects to gravitate towards each
       other set the attraction to zero. this will disable
-      that system allowing much better performance",
+      that system allowing much better performance
+    - with many objects, set the attraction theta above
+      zero (e.g. 0.5) to approximate attraction faster",
         );
         ui.strong("lapis additions:");
         ui.collapsing("entity creation/deletion", |ui| {
             ui.label("- to spawn an object with values from draw settings:");
             ui.code("spawn(r); // r is radius");
+            ui.label("- to spawn a polygon from its outline (x, y pairs):");
+            ui.code("spawn_poly([0,0, 100,0, 100,100, 50,40, 0,100]);");
+            ui.label("(it's centered on its centroid, concave outlines work)");
+            ui.label("- to spawn a static wall through points (a line or a polyline):");
+            ui.code("spawn_wall([-300,200, -300,-200, 300,-200, 300,200]);");
+            ui.label("(walls have collision code, restitution, friction, etc. like any object)");
             ui.label("- to create a joint between 2 points:");
             ui.label("(those points must intersect 2 objects)");
             ui.code("joint(x1, y1, x2, y2);");
@@ -725,7 +1395,11 @@ let joint = joint(0,0,200,0);",
 - entity.s(f)  // saturation
 - entity.l(f)  // lightness 
 - entity.a(f)  // alpha
-- entity.sides(f)
+- entity.sides(f)  // regular polygon
+- entity.rectangle()  // rx and ry are half width/height
+- entity.capsule(f)  // straight length (relative to radius)
+- entity.poly(arr)  // outline [x0,y0, x1,y1, ...]
+- entity.wall(arr)  // line segments through [x0,y0, x1,y1, ...]
 - entity.cmx(f)  // x center of mass
 - entity.cmy(f)
 - entity.friction(f)
@@ -739,6 +1413,19 @@ let joint = joint(0,0,200,0);",
 - entity.links(str)  // links text
 - entity.code_i(str) // collision start code
 - entity.code_f(str) // collision end
+- entity.code_c(str) // while colliding (every physics step)
+// evaluated every frame ($id is the object, $age the seconds
+// since it got this code), or every f physics steps if f > 0
+// (not while time is paused)
+- entity.code_u(str)
+- entity.code_u_steps(f)
+
+// a sound that plays from the object (net has 1 output)
+// panned and quieter the farther it is from the camera
+// its inputs (up to 3) get the doppler factor, vx, and vy
+- entity.voice(net)
+- entity.voice_gain(f)
+- entity.no_voice()
 
 // despawns joints connected to this object
 - entity.disjoint()
@@ -773,6 +1460,8 @@ let joint = joint(0,0,200,0);",
 - entity.friction
 - entity.tail
 - entity.layer
+- entity.voice_gain
+- entity.code_u_steps
 - entity.dynamic // bool
 - entity.sensor // bool",
             );
@@ -794,7 +1483,27 @@ let joint = joint(0,0,200,0);",
 - entity.rest(f)
 
 // prismatic joint free axis
-- entity.free_axis(f, f)",
+- entity.free_axis(f, f)
+
+// motors (revolute and prismatic joints)
+// angle (or position along the axis) and its velocity
+- entity.motor_velocity(f)  // target velocity
+- entity.motor_position(f)  // target position
+- entity.motor_max(f)  // max torque (or force)
+- entity.motor_stiffness(f)  // 0 for a velocity motor
+- entity.motor_damping(f)  // how fast velocity is matched
+
+// a motor following a shared var
+- entity.links(\"motor_velocity < speed\")
+
+// breaking (0 = never), the joint despawns when its
+// force (or torque) goes over the threshold
+- entity.break_force(f)
+- entity.break_torque(f)
+// (revolute joints only have torque at their angle limits,
+// without min/max it's always 0. distance joints have none)
+// evaluated when it breaks ($id is the joint)
+- entity.on_break(str)",
             );
         });
         ui.collapsing("joint fields", |ui| {
@@ -810,6 +1519,15 @@ let joint = joint(0,0,200,0);",
 - entity.rest
 - entity.axis_x
 - entity.axis_y
+- entity.motor_velocity
+- entity.motor_position
+- entity.motor_max
+- entity.motor_stiffness
+- entity.motor_damping
+- entity.force  // applied in the last step
+- entity.torque  // (revolute: only at the angle limits)
+- entity.break_force
+- entity.break_torque
 ",
             );
         });
@@ -840,12 +1558,207 @@ time.elapsed_wrapped();
             ui.label("change gravity and attraction factor:");
             ui.code(
                 "gravity(0, -980);
-attraction(0.5);",
+attraction(0.5);
+// 0 = exact, >0 = faster approximation
+attraction_theta(0.5);",
+            );
+            ui.label("save/load the whole scene (objects, joints, settings, and variables):");
+            ui.code(
+                "save_scene(\"scene.ron\");
+load_scene(\"scene.ron\");",
+            );
+            ui.label("(nets, waves, sequencers, and sources aren't saved)");
+            ui.label("undo/redo the last edit (loading a scene clears the history):");
+            ui.code(
+                "undo();
+redo();",
+            );
+            ui.label("(undo puts back the whole state of the edited objects)");
+            ui.label("render the next 10 seconds of output (and physics) to a file:");
+            ui.code("render_scene(10, \"out.wav\");");
+            ui.label("(this runs one physics step per frame, as fast as possible)");
+            ui.label("(time has to be running, and can't be paused until it's done)");
+            ui.label("(the output device can't be changed during a render)");
+            ui.label("record the output stream (16-bit, or 32-bit float):");
+            ui.code(
+                "record_start(\"take.wav\");
+record_start(\"take.wav\", 32);
+record_stop();",
+            );
+            ui.label("evaluate code later, or repeatedly (driven by virtual time):");
+            ui.code(
+                "let t = after(2, \"x = 1;\");
+let t = every(0.5, \"x += 1;\");
+t.cancel();
+t.is_active();
+cancel_timers();",
+            );
+            ui.label("or in beats (at a tempo in bpm, 120 by default):");
+            ui.code(
+                "tempo(90);
+let t = every_beats(1, \"kick();\");
+let t = after_beats(4, \"drop();\");
+let bpm = tempo();",
+            );
+            ui.label("(unlike sleep(), these don't block, and pausing time pauses them)");
+            ui.label("exit the app (ends a headless run early):");
+            ui.code("quit();");
+        });
+        ui.collapsing("midi", |ui| {
+            ui.label("connect to a midi input (_ for the first one):");
+            ui.code(
+                "list_midi_devices();
+set_midi_device(0);
+drop_midi_device();",
+            );
+            ui.label("bind a cc or note to a shared (set to the value, 0..1):");
+            ui.code(
+                "let knob = shared(0);
+\"midi cc 1 ch 0\" = knob;",
+            );
+            ui.label("or to code (@ is replaced with the value):");
+            ui.code(
+                "\"midi note 60\" = \"e.x(@ * 100);\";
+// ! is note off
+\"!midi note 60\" = \"e.x(0);\";
+// unbind
+\"midi cc 1 ch 0\" = \"\";
+clear_midi();",
+            );
+            ui.label("(without ch, any channel matches)");
+        });
+        ui.collapsing("osc", |ui| {
+            ui.label("listen on a udp port (local only, unless a host is given):");
+            ui.code(
+                "osc_listen(9000);
+osc_listen(9000, \"127.0.0.1\");
+osc_stop();",
+            );
+            ui.label("incoming messages:");
+            ui.code("// /shared/knob 0.5 sets the shared called knob");
+            ui.label("bind an address to a shared or to code (@ is the first argument):");
+            ui.code(
+                "\"osc /fader/1\" = knob;
+\"osc /spawn\" = \"let e = spawn(@);\";
+\"osc /spawn\" = \"\";
+clear_osc();",
+            );
+            ui.label("send (floats, bools, and strings), e.g. in collision code:");
+            ui.code("osc_send(\"127.0.0.1\", 57120, \"/hit\", e.x, e.y);");
+        });
+        ui.collapsing("loops", |ui| {
+            ui.label("besides for loops, there's while and loop:");
+            ui.code(
+                "let i = 0;
+while i < 10 { i += 1; }
+loop {
+    i -= 1;
+    if i < 0 { break; }
+}",
+            );
+            ui.label("labels work with break and continue:");
+            ui.code(
+                "'outer: for i in 0..10 {
+    for j in 0..10 {
+        if i * j > 20 { break 'outer; }
+    }
+}",
+            );
+            ui.label("a single evaluation can run at most 1000000");
+            ui.label("loop iterations (so runaway loops don't hang)");
+            ui.label("code that runs on its own (collision code, scripts, timers,");
+            ui.label("keys, midi and osc bindings, update code) can run at most 10000");
+        });
+        ui.collapsing("functions", |ui| {
+            ui.label("define functions (parameters don't need types):");
+            ui.code(
+                "fn ball(x, y, r) {
+    let e = spawn(r);
+    e.x(x).y(y);
+    e
+}
+fn twice(n) { return n * 2; }",
+            );
+            ui.label("call them anywhere an expression goes:");
+            ui.code("let b = ball(0, twice(50), 20);");
+            ui.label("- arguments are bound to the parameter names for the call");
+            ui.label("  (any variables with those names are restored after)");
+            ui.label("- the last expression (without `;`) or `return` gives the value");
+            ui.label("- evaluate a function's name to see its definition");
+            ui.label("- functions are dropped like variables: `f.drop();`");
+        });
+        ui.collapsing("match", |ui| {
+            ui.label("match on floats, strings, or bools:");
+            ui.code(
+                "match $other.layer {
+    0 => { a.set(1); }
+    1 | 2 => { a.set(2); }
+    3..=5 => { a.set(3); }
+    _ => {}
+}",
+            );
+            ui.label("match and if/else give values too:");
+            ui.code(
+                "let f = match x { 0 => 220., _ => 440. };
+let s = match name { \"kick\" => 1, \"snare\" => 2, _ => 0 };
+let g = if x > 0 { 1 } else if x < 0 { -1 } else { 0 };",
             );
+            ui.label("- ranges can be open: `..0`, `10..`");
+            ui.label("- a name binds the value for the arm, guards use `if`:");
+            ui.code("match x { n if n > 100 => { y = n; } _ => {} }");
+            ui.label("- only the first arm that matches is evaluated");
+        });
+        ui.collapsing("errors", |ui| {
+            ui.label("errors quote the line they happened on and");
+            ui.label("underline the code that caused them:");
+            ui.code(
+                "// error: unknown variable `fre`
+// | 1: let f = fre * 2;
+// |            ^^^
+// help: did you mean `freq`?",
+            );
+            ui.label("net variables used directly in a failed `a op b` are put back, not consumed");
         });
     });
 }
 
+/// highlight the output like code, except error messages which are red
+fn highlight_output(
+    ctx: &Context,
+    style: &Style,
+    theme: &CodeTheme,
+    text: &str,
+    size: f32,
+) -> text::LayoutJob {
+    let mut job = text::LayoutJob::default();
+    let error = TextFormat::simple(FontId::monospace(size), Color32::LIGHT_RED);
+    let flush = |code: &mut String, job: &mut text::LayoutJob| {
+        if !code.is_empty() {
+            let highlighted = highlight(ctx, style, theme, code, "rs");
+            for section in &highlighted.sections {
+                let text = &highlighted.text[section.byte_range.clone()];
+                job.append(text, section.leading_space, section.format.clone());
+            }
+            code.clear();
+        }
+    };
+    let mut code = String::new();
+    for line in text.split_inclusive('\n') {
+        let line_start = line.trim_start_matches('\n');
+        if line_start.starts_with("// error")
+            || line_start.starts_with("// | ")
+            || line_start.starts_with("// help: ")
+        {
+            flush(&mut code, &mut job);
+            job.append(line, 0., error.clone());
+        } else {
+            code.push_str(line);
+        }
+    }
+    flush(&mut code, &mut job);
+    job
+}
+
 const LINKS_TOOLTIP: &str = "link a property of this entity to a shared var\n
 every line should follow the form:
 property > variable
@@ -856,6 +1769,8 @@ to set the property to the variable's value\n
 note: float expressions also work in assignment
 e.g. \"mass < 5\", \"y < sin(s.value())\", or \"rot = PI*3\"
 (no spaces)\n
+variables that don't exist yet can be linked,
+they take effect once they're created\n
 properties list:
 x / y / z
 h / s / l / a (hue saturation lightness alpha)
@@ -875,4 +1790,10 @@ cmx / cmy (center of mass)
 friction
 layer
 dynamic (>0 means true)
-sensor (same)";
+sensor (same)\n
+joints can have links too (set with joint.links(\"...\"))
+force / torque (read only, e.g. \"force > tension\")
+(a revolute joint's torque is 0 unless it's at its angle limits)
+motors (revolute/prismatic):
+motor_velocity / motor_position
+motor_max / motor_stiffness / motor_damping";
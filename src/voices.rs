@@ -0,0 +1,235 @@
+use crate::interaction::Selected;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use fundsp::hacker::*;
+
+/// in pixels per second (the length unit is 100 pixels per meter)
+const SPEED_OF_SOUND: f32 = 34300.;
+
+/// voices closer than this (in pixels) to the listener play at full gain
+const REF_DISTANCE: f32 = 200.;
+
+pub struct VoicesPlugin;
+
+impl Plugin for VoicesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(set_voice_property)
+            .add_observer(remove_voice)
+            .add_systems(Update, (attach_voices, update_voices).chain());
+    }
+}
+
+/// a sound that plays from an object, mixed into the output through `VoiceBus`
+/// `net` has 1 output and up to 3 inputs, fed with the doppler factor, vx, and vy
+/// it's panned and attenuated by its position relative to the camera
+#[derive(Component, Clone)]
+pub struct Voice {
+    pub net: Net,
+    pub gain: f32,
+    doppler: Shared,
+    vx: Shared,
+    vy: Shared,
+    /// gain times distance attenuation
+    level: Shared,
+    pan: Shared,
+}
+
+impl Voice {
+    pub fn new(net: Net, gain: f32) -> Self {
+        Voice {
+            net,
+            gain,
+            doppler: shared(1.),
+            vx: shared(0.),
+            vy: shared(0.),
+            level: shared(0.),
+            pan: shared(0.),
+        }
+    }
+
+    /// whether a net can be a voice
+    pub fn fits(net: &Net) -> bool {
+        net.outputs() == 1 && net.inputs() <= 3
+    }
+
+    /// the node added to the bus, it passes its inputs through and adds the voice
+    fn unit(&self, channels: usize) -> Net {
+        let mut controls = Net::new(0, 0);
+        for s in [&self.doppler, &self.vx, &self.vy]
+            .into_iter()
+            .take(self.net.inputs())
+        {
+            controls = controls | Net::wrap(Box::new(var(s)));
+        }
+        let mono = controls >> self.net.clone() >> Net::wrap(Box::new(pass() * var(&self.level)));
+        let out = if channels == 1 {
+            mono
+        } else {
+            let pan = Net::wrap(Box::new(var(&self.pan)));
+            let stereo = (mono | pan) >> Net::wrap(Box::new(panner()));
+            if channels > 2 {
+                stereo | Net::scalar(channels - 2, 0.)
+            } else {
+                stereo
+            }
+        };
+        let mut thru = Net::new(channels, channels);
+        for i in 0..channels {
+            thru.pass_through(i, i);
+        }
+        thru + out
+    }
+}
+
+/// the mix of all voices, played along with `AudioOutput`
+/// voices are chained after a silent root node, each adding itself to the signal
+/// a new bus is made for every output stream (and scene render), voices
+/// that aren't on the current bus are added to it in `attach_voices`
+#[derive(Resource)]
+pub struct VoiceBus {
+    net: Net,
+    root: NodeId,
+    channels: usize,
+    sr: f64,
+    nodes: Vec<(Entity, NodeId)>,
+}
+
+impl VoiceBus {
+    pub fn new(channels: usize, sr: f64) -> Self {
+        let mut net = Net::new(0, channels);
+        let root = net.push(Box::new(Net::scalar(channels, 0.)));
+        net.pipe_output(root);
+        net.set_sample_rate(sr);
+        net.allocate();
+        VoiceBus {
+            net,
+            root,
+            channels,
+            sr,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// the backend to play (this can only be called once per bus)
+    pub fn backend(&mut self) -> NetBackend {
+        self.net.backend()
+    }
+
+    /// remove all voices from the bus
+    pub fn silence(&mut self) {
+        for (_, id) in std::mem::take(&mut self.nodes) {
+            self.net.remove_link(id);
+        }
+        self.commit();
+    }
+
+    fn add(&mut self, e: Entity, mut unit: Net) {
+        unit.set_sample_rate(self.sr);
+        unit.allocate();
+        let last = self.nodes.last().map_or(self.root, |(_, id)| *id);
+        let id = self.net.push(Box::new(unit));
+        self.net.pipe_all(last, id);
+        self.net.pipe_output(id);
+        self.nodes.push((e, id));
+        self.commit();
+    }
+
+    /// `remove_link` connects the node's inputs to wherever its outputs went
+    fn remove(&mut self, e: Entity) {
+        if let Some(i) = self.nodes.iter().position(|(x, _)| *x == e) {
+            let (_, id) = self.nodes.remove(i);
+            self.net.remove_link(id);
+            self.commit();
+        }
+    }
+
+    fn commit(&mut self) {
+        if self.net.has_backend() {
+            self.net.commit();
+        }
+    }
+}
+
+#[derive(Event, Clone)]
+pub enum VoiceProperty {
+    Net(Net),
+    Gain(f32),
+    Remove,
+}
+
+fn set_voice_property(
+    trig: Trigger<VoiceProperty>,
+    mut commands: Commands,
+    mut voices: Query<&mut Voice>,
+    selected_query: Query<Entity, With<Selected>>,
+    bodies: Query<(), With<RigidBody>>,
+) {
+    let e = trig.target();
+    // methods applied to PLACEHOLDER affect the selected entities
+    if e == Entity::PLACEHOLDER {
+        let targets: Vec<Entity> = selected_query.iter().collect();
+        if !targets.is_empty() {
+            commands.trigger_targets(trig.event().clone(), targets);
+        }
+        return;
+    }
+    if !bodies.contains(e) {
+        return;
+    }
+    match trig.event() {
+        VoiceProperty::Net(net) => {
+            let gain = voices.get(e).map_or(1., |v| v.gain);
+            // replacing the component takes the old voice off the bus
+            commands.entity(e).insert(Voice::new(net.clone(), gain));
+        }
+        VoiceProperty::Gain(val) => {
+            if let Ok(mut voice) = voices.get_mut(e) {
+                voice.gain = *val;
+            }
+        }
+        VoiceProperty::Remove => {
+            commands.entity(e).remove::<Voice>();
+        }
+    }
+}
+
+fn remove_voice(trig: Trigger<OnReplace, Voice>, mut bus: ResMut<VoiceBus>) {
+    bus.remove(trig.target());
+}
+
+fn attach_voices(voices: Query<(Entity, &Voice)>, mut bus: ResMut<VoiceBus>) {
+    for (e, voice) in voices.iter() {
+        if !bus.nodes.iter().any(|(x, _)| *x == e) {
+            let unit = voice.unit(bus.channels);
+            bus.add(e, unit);
+        }
+    }
+}
+
+/// the listener is the camera (or the origin when there's none)
+fn update_voices(
+    voices: Query<(&Voice, &Transform, Option<&LinearVelocity>)>,
+    camera: Query<&Transform, With<Camera>>,
+) {
+    let listener = camera.single().map_or(Vec2::ZERO, |t| t.translation.xy());
+    for (voice, t, v) in voices.iter() {
+        let offset = t.translation.xy() - listener;
+        let distance = offset.length();
+        let v = v.map_or(Vec2::ZERO, |v| v.0);
+        // speed towards the listener
+        let approach = if distance > 0. {
+            -v.dot(offset / distance)
+        } else {
+            0.
+        };
+        let approach = approach.min(SPEED_OF_SOUND * 0.9);
+        voice
+            .doppler
+            .set(SPEED_OF_SOUND / (SPEED_OF_SOUND - approach));
+        voice.vx.set(v.x);
+        voice.vy.set(v.y);
+        let far = distance.max(REF_DISTANCE);
+        voice.level.set(voice.gain * REF_DISTANCE / far);
+        voice.pan.set(offset.x / far);
+    }
+}